@@ -65,10 +65,10 @@ quis lacus at, gravida maximus elit. Duis tristique, nisl nullam.
     "#;
 
     b.iter(|| {
-        format!("{}", escape(string_long, Html).unwrap());
-        format!("{}", escape(string_short, Html).unwrap());
-        format!("{}", escape(empty, Html).unwrap());
-        format!("{}", escape(no_escape, Html).unwrap());
-        format!("{}", escape(no_escape_long, Html).unwrap());
+        let _: String = format!("{}", escape(string_long, Html).unwrap());
+        let _: String = format!("{}", escape(string_short, Html).unwrap());
+        let _: String = format!("{}", escape(empty, Html).unwrap());
+        let _: String = format!("{}", escape(no_escape, Html).unwrap());
+        let _: String = format!("{}", escape(no_escape_long, Html).unwrap());
     });
 }
@@ -14,7 +14,7 @@ fn functions(c: &mut Criterion) {
 fn escape_json(b: &mut criterion::Bencher<'_>) {
     b.iter(|| {
         for &s in STRINGS {
-            format!("{}", json(s).unwrap());
+            let _: String = format!("{}", json(s).unwrap());
         }
     });
 }
@@ -22,7 +22,7 @@ fn escape_json(b: &mut criterion::Bencher<'_>) {
 fn escape_json_pretty(b: &mut criterion::Bencher<'_>) {
     b.iter(|| {
         for &s in STRINGS {
-            format!("{}", json_pretty(s, 2).unwrap());
+            let _: String = format!("{}", json_pretty(s, 2).unwrap());
         }
     });
 }
@@ -30,7 +30,7 @@ fn escape_json_pretty(b: &mut criterion::Bencher<'_>) {
 fn escape_json_for_html(b: &mut criterion::Bencher<'_>) {
     b.iter(|| {
         for &s in STRINGS {
-            format!("{}", escape(json(s).unwrap(), Html).unwrap());
+            let _: String = format!("{}", escape(json(s).unwrap(), Html).unwrap());
         }
     });
 }
@@ -38,7 +38,7 @@ fn escape_json_for_html(b: &mut criterion::Bencher<'_>) {
 fn escape_json_for_html_pretty(b: &mut criterion::Bencher<'_>) {
     b.iter(|| {
         for &s in STRINGS {
-            format!("{}", escape(json_pretty(s, 2).unwrap(), Html).unwrap(),);
+            let _: String = format!("{}", escape(json_pretty(s, 2).unwrap(), Html).unwrap(),);
         }
     });
 }
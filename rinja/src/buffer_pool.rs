@@ -0,0 +1,210 @@
+//! Reuse of render buffers across renderings, used by the web framework integrations
+//!
+//! [`Template::render()`] allocates a new buffer for every rendering, which grows, i.e. gets
+//! reallocated, whenever [`SIZE_HINT`][Template::SIZE_HINT] was too small. [`render()`] renders
+//! into a buffer that is taken from a pool per thread instead, and hands out the buffer itself,
+//! which returns to the pool of the thread that drops it. So the buffers have to grow only a few
+//! times over the lifetime of a thread, and the result is neither copied nor reallocated.
+
+use core::ops::Deref;
+use core::{fmt, mem};
+use std::cell::RefCell;
+use std::string::String;
+use std::thread_local;
+use std::vec::Vec;
+
+use crate::{Result, Template};
+
+/// Buffers with a larger capacity are dropped after their use instead of being kept
+pub const MAX_RETAINED_CAPACITY: usize = 1 << 20;
+
+/// At most this many unused buffers are kept per thread
+pub const MAX_RETAINED_BUFFERS: usize = 16;
+
+thread_local! {
+    static POOL: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Renders the template into a buffer of the pool of the current thread
+///
+/// The returned buffer goes back into the pool when it is dropped, so pass it on to e.g. a
+/// response body instead of copying it.
+pub fn render<T: Template + ?Sized>(tmpl: &T) -> Result<PooledBuffer> {
+    let buffer = POOL.with(|pool| pool.borrow_mut().pop()).unwrap_or_default();
+    let mut buffer = PooledBuffer(buffer);
+    crate::observer::observe::<T, _>(|| {
+        let _ = buffer.0.try_reserve(T::SIZE_HINT);
+        tmpl.render_into(&mut buffer.0)?;
+        Ok(buffer)
+    })
+}
+
+/// A rendered template, see [`render()`]
+///
+/// The buffer returns to the pool of the current thread when it is dropped.
+pub struct PooledBuffer(String);
+
+impl PooledBuffer {
+    /// The rendered template
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Takes the buffer out of the pool
+    #[inline]
+    pub fn into_string(mut self) -> String {
+        mem::take(&mut self.0)
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        let mut buffer = mem::take(&mut self.0);
+        if buffer.capacity() == 0 || buffer.capacity() > MAX_RETAINED_CAPACITY {
+            return;
+        }
+        buffer.clear();
+        // The pool of the thread may already be gone if the buffer is dropped at its exit.
+        let _ = POOL.try_with(|pool| {
+            if let Ok(mut pool) = pool.try_borrow_mut() {
+                if pool.len() < MAX_RETAINED_BUFFERS {
+                    pool.push(buffer);
+                }
+            }
+        });
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for PooledBuffer {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for PooledBuffer {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl fmt::Debug for PooledBuffer {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for PooledBuffer {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<PooledBuffer> for String {
+    #[inline]
+    fn from(buffer: PooledBuffer) -> Self {
+        buffer.into_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Test(usize);
+
+    impl Template for Test {
+        fn render_into<W: fmt::Write + ?Sized>(&self, writer: &mut W) -> Result<()> {
+            for _ in 0..self.0 {
+                writer.write_str("test")?;
+            }
+            Ok(())
+        }
+
+        const EXTENSION: Option<&'static str> = Some("txt");
+
+        const SIZE_HINT: usize = 4;
+
+        const MIME_TYPE: &'static str = "text/plain; charset=utf-8";
+    }
+
+    impl fmt::Display for Test {
+        #[inline]
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.render_into(f).map_err(|_| fmt::Error {})
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let rendered = render(&Test(100)).unwrap();
+        assert_eq!(rendered.as_str(), "test".repeat(100));
+        let capacity = rendered.0.capacity();
+        assert!(capacity >= 400);
+        drop(rendered);
+
+        // The buffer is reused.
+        let rendered = render(&Test(2)).unwrap();
+        assert_eq!(rendered.as_str(), "testtest");
+        assert_eq!(rendered.0.capacity(), capacity);
+
+        // A buffer that is in use is not handed out again.
+        let other = render(&Test(1)).unwrap();
+        assert_eq!(other.as_str(), "test");
+        drop((rendered, other));
+        assert_eq!(POOL.with(|pool| pool.borrow().len()), 2);
+
+        // A buffer that was taken out of the pool does not return.
+        let rendered = render(&Test(1)).unwrap().into_string();
+        assert_eq!(rendered, "test");
+        assert_eq!(POOL.with(|pool| pool.borrow().len()), 1);
+
+        // Too large buffers are not kept.
+        POOL.with(|pool| pool.borrow_mut().clear());
+        let rendered = render(&Test(MAX_RETAINED_CAPACITY / 4 + 1)).unwrap();
+        assert_eq!(rendered.len(), MAX_RETAINED_CAPACITY + 4);
+        drop(rendered);
+        assert!(POOL.with(|pool| pool.borrow().is_empty()));
+    }
+
+    #[test]
+    fn test_render_nested() {
+        struct Outer;
+
+        impl Template for Outer {
+            fn render_into<W: fmt::Write + ?Sized>(&self, writer: &mut W) -> Result<()> {
+                writer.write_str(&render(&Test(1))?)?;
+                writer.write_str("!")?;
+                Ok(())
+            }
+
+            const EXTENSION: Option<&'static str> = Some("txt");
+
+            const SIZE_HINT: usize = 5;
+
+            const MIME_TYPE: &'static str = "text/plain; charset=utf-8";
+        }
+
+        impl fmt::Display for Outer {
+            #[inline]
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.render_into(f).map_err(|_| fmt::Error {})
+            }
+        }
+
+        assert_eq!(render(&Outer).unwrap().as_str(), "test!");
+    }
+}
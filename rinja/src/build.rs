@@ -0,0 +1,114 @@
+//! Helpers for build scripts, to share templates between crates
+//!
+//! A library crate can ship templates, e.g. macros or base templates, that other crates
+//! `{% import %}`, `{% include %}` or `{% extends %}`. The library needs a [`links`] key in its
+//! `Cargo.toml`, and exports its template folder in its `build.rs`:
+//!
+//! ```no_run
+//! // build.rs of the crate with `links = "my-ui"`
+//! rinja::build::export_templates("templates");
+//! ```
+//!
+//! A crate that depends on the library forwards the exported folders to its own templates:
+//!
+//! ```no_run
+//! // build.rs of the crate that uses the templates of `my-ui`
+//! rinja::build::import_templates();
+//! ```
+//!
+//! Then the folder is available as the environment variable `DEP_MY_UI_RINJA_TEMPLATES`, which
+//! can be used in the `dirs` of its `rinja.toml`:
+//!
+//! ```toml
+//! [general]
+//! dirs = ["templates", "$DEP_MY_UI_RINJA_TEMPLATES"]
+//! ```
+//!
+//! [`links`]: https://doc.rust-lang.org/cargo/reference/build-scripts.html#the-links-manifest-key
+
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::string::String;
+use std::vec::Vec;
+
+/// The suffix of the environment variables that contain exported template folders
+pub const ENV_SUFFIX: &str = "_RINJA_TEMPLATES";
+
+/// Exports the template folder `dir` of the current crate to crates that depend on it
+///
+/// `dir` is relative to the crate root. This function must be called in the build script of a
+/// crate that has a `links` key in its `Cargo.toml`.
+pub fn export_templates(dir: impl AsRef<Path>) {
+    let root = env::var_os("CARGO_MANIFEST_DIR").expect("not called in a build script");
+    write_export(&mut io::stdout().lock(), &PathBuf::from(root).join(dir))
+        .expect("could not write to stdout");
+}
+
+/// Makes the template folders that were exported by dependencies available to `rinja.toml`
+///
+/// This function must be called in the build script of the crate that uses the templates.
+pub fn import_templates() {
+    write_imports(&mut io::stdout().lock(), env::vars_os()).expect("could not write to stdout");
+}
+
+fn write_export(out: &mut impl Write, dir: &Path) -> io::Result<()> {
+    let Some(dir) = dir.to_str() else {
+        panic!("the template folder {dir:?} is not valid UTF-8");
+    };
+    writeln!(out, "cargo:rinja_templates={dir}")
+}
+
+fn write_imports(
+    out: &mut impl Write,
+    vars: impl IntoIterator<Item = (OsString, OsString)>,
+) -> io::Result<()> {
+    let mut vars: Vec<(String, String)> = vars
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(name, _)| name.starts_with("DEP_") && name.ends_with(ENV_SUFFIX))
+        .collect();
+    vars.sort();
+    for (name, value) in vars {
+        writeln!(out, "cargo:rustc-env={name}={value}")?;
+        writeln!(out, "cargo:rerun-if-env-changed={name}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::ToOwned;
+
+    use super::*;
+
+    #[test]
+    fn test_export_and_import() {
+        let mut out = Vec::new();
+        write_export(&mut out, Path::new("/src/ui/templates")).unwrap();
+        assert_eq!(out, b"cargo:rinja_templates=/src/ui/templates\n");
+
+        let vars = [
+            ("PATH", "/bin"),
+            ("DEP_UI_RINJA_TEMPLATES", "/src/ui/templates"),
+            ("DEP_UI_INCLUDE", "/src/ui/include"),
+            ("DEP_ICONS_RINJA_TEMPLATES", "/src/icons/templates"),
+        ];
+        let mut out = Vec::new();
+        write_imports(
+            &mut out,
+            vars.map(|(name, value)| (name.to_owned().into(), value.to_owned().into())),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+cargo:rustc-env=DEP_ICONS_RINJA_TEMPLATES=/src/icons/templates
+cargo:rerun-if-env-changed=DEP_ICONS_RINJA_TEMPLATES
+cargo:rustc-env=DEP_UI_RINJA_TEMPLATES=/src/ui/templates
+cargo:rerun-if-env-changed=DEP_UI_RINJA_TEMPLATES
+",
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Support for cached fragments, i.e. `{% cache %}` blocks
+//!
+//! A template that uses `{% cache key %}` blocks has to implement [`RenderCache`]. The content of
+//! a block is only rendered if the cache has no entry for its key, and is stored in the cache
+//! afterwards. Usually the implementation delegates to a cache that is shared between
+//! renderings, e.g. an [`LruCache`] if the feature `"lru-cache"` is enabled.
+//!
+//! ```
+//! # use std::cell::RefCell;
+//! # use std::collections::HashMap;
+//! # use rinja::Template;
+//! # use rinja::cache::RenderCache;
+//! #[derive(Template)]
+//! #[template(
+//!     source = "{% cache lang %}{{ self.expensive() }}{% endcache %}",
+//!     ext = "txt"
+//! )]
+//! struct Navigation<'a> {
+//!     lang: &'a str,
+//!     cache: &'a RefCell<HashMap<String, String>>,
+//! }
+//!
+//! impl Navigation<'_> {
+//!     fn expensive(&self) -> String {
+//!         format!("navigation ({}, {} cached)", self.lang, self.cache.borrow().len())
+//!     }
+//! }
+//!
+//! impl RenderCache for Navigation<'_> {
+//!     fn get(&self, key: &str) -> Option<String> {
+//!         self.cache.borrow().get(key).cloned()
+//!     }
+//!
+//!     fn insert(&self, key: String, value: String) {
+//!         self.cache.borrow_mut().insert(key, value);
+//!     }
+//! }
+//!
+//! let cache = RefCell::new(HashMap::new());
+//! let en = Navigation { lang: "en", cache: &cache };
+//! assert_eq!(en.to_string(), "navigation (en, 0 cached)");
+//! // the second rendering uses the cached content
+//! assert_eq!(en.to_string(), "navigation (en, 0 cached)");
+//! let de = Navigation { lang: "de", cache: &cache };
+//! assert_eq!(de.to_string(), "navigation (de, 1 cached)");
+//! ```
+
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::sync::Arc;
+
+#[cfg(feature = "lru-cache")]
+pub use self::lru::LruCache;
+
+/// Stores the rendered content of `{% cache %}` blocks
+pub trait RenderCache {
+    /// Returns the content that was stored for `key`, if any
+    ///
+    /// The content is already escaped, and is written into the output as is.
+    fn get(&self, key: &str) -> Option<String>;
+
+    /// Stores the rendered `value` of a block for `key`
+    ///
+    /// The key consists of the location of the block in the templates, and of the key
+    /// expression of the block, so different blocks don't share their content.
+    fn insert(&self, key: String, value: String);
+}
+
+macro_rules! impl_for_ref {
+    ($($ty:ty)*) => { $(
+        impl<T: RenderCache + ?Sized> RenderCache for $ty {
+            #[inline]
+            fn get(&self, key: &str) -> Option<String> {
+                T::get(self, key)
+            }
+
+            #[inline]
+            fn insert(&self, key: String, value: String) {
+                T::insert(self, key, value)
+            }
+        }
+    )* };
+}
+
+impl_for_ref!(&T &mut T Box<T> Rc<T> Arc<T>);
+
+#[cfg(feature = "lru-cache")]
+mod lru {
+    use std::collections::HashMap;
+    use std::sync::{Mutex, PoisonError};
+
+    use super::*;
+
+    /// An in-memory [`RenderCache`] that keeps the most recently used entries
+    ///
+    /// It can be shared between threads, e.g. in a `static` or in the state of a web server.
+    /// If the cache is full, the entry that was least recently used is dropped.
+    #[derive(Debug)]
+    pub struct LruCache {
+        capacity: usize,
+        inner: Mutex<Inner>,
+    }
+
+    #[derive(Debug, Default)]
+    struct Inner {
+        // the content, and the time of its last use
+        entries: HashMap<String, (String, u64)>,
+        time: u64,
+    }
+
+    impl LruCache {
+        /// Creates a cache that keeps at most `capacity` entries
+        pub fn new(capacity: usize) -> Self {
+            Self {
+                capacity,
+                inner: Mutex::default(),
+            }
+        }
+
+        /// Drops all entries, e.g. after the data the templates render has changed
+        pub fn clear(&self) {
+            self.inner
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entries
+                .clear();
+        }
+    }
+
+    impl RenderCache for LruCache {
+        fn get(&self, key: &str) -> Option<String> {
+            let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+            inner.time += 1;
+            let time = inner.time;
+            let (value, last_use) = inner.entries.get_mut(key)?;
+            *last_use = time;
+            Some(value.clone())
+        }
+
+        fn insert(&self, key: String, value: String) {
+            if self.capacity == 0 {
+                return;
+            }
+            let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+            inner.time += 1;
+            let time = inner.time;
+            if inner.entries.len() >= self.capacity && !inner.entries.contains_key(&key) {
+                // The caches are small, so a linear search is fast enough.
+                let oldest = inner
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, (_, last_use))| *last_use)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    inner.entries.remove(&oldest);
+                }
+            }
+            inner.entries.insert(key, (value, time));
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use alloc::borrow::ToOwned;
+
+        use super::*;
+
+        #[test]
+        fn test_lru_cache() {
+            let cache = LruCache::new(2);
+            cache.insert("a".to_owned(), "1".to_owned());
+            cache.insert("b".to_owned(), "2".to_owned());
+            assert_eq!(cache.get("a").as_deref(), Some("1"));
+
+            // `b` was used least recently
+            cache.insert("c".to_owned(), "3".to_owned());
+            assert_eq!(cache.get("b"), None);
+            assert_eq!(cache.get("a").as_deref(), Some("1"));
+            assert_eq!(cache.get("c").as_deref(), Some("3"));
+
+            // replacing an entry does not drop another one
+            cache.insert("c".to_owned(), "4".to_owned());
+            assert_eq!(cache.get("a").as_deref(), Some("1"));
+            assert_eq!(cache.get("c").as_deref(), Some("4"));
+
+            cache.clear();
+            assert_eq!(cache.get("a"), None);
+
+            let cache = LruCache::new(0);
+            cache.insert("a".to_owned(), "1".to_owned());
+            assert_eq!(cache.get("a"), None);
+        }
+    }
+}
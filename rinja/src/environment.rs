@@ -0,0 +1,1169 @@
+//! Runtime template engine, enabled by the feature `runtime`
+//!
+//! An [`Environment`] holds templates that are only known at runtime, e.g. user-editable themes,
+//! and renders them with a context that implements [`Serialize`]. The templates are parsed by the
+//! same parser as the derived templates, but only a subset of the template language is
+//! understood, see [`Environment`].
+
+use core::fmt;
+use core::mem;
+use std::borrow::Cow;
+use std::boxed::Box;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::string::{String, ToString};
+use std::sync::Arc;
+use std::vec::Vec;
+use std::{format, vec};
+
+use parser::node::{
+    BlockDef, FilterBlock, If, Include, Let, Lit, Loop, TemplatePath, Whitespace, Ws,
+};
+use parser::{Expr, Filter, Node, Parsed, Syntax, Target, WithSpan};
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+
+use crate::filters::{self, Escaper};
+use crate::whitespace::WsState;
+use crate::{Error, Result};
+
+/// Templates that are loaded, parsed and rendered at runtime
+///
+/// Templates are added from strings with [`Environment::add_template()`], or from the files in a
+/// directory with [`Environment::add_dir()`], and rendered by name with
+/// [`Environment::render()`]. The context of the template is any value that serializes to a map,
+/// e.g. a `struct` that derives [`Serialize`], and its fields are the variables of the template.
+///
+/// ```
+/// # use rinja::Environment;
+/// #[derive(serde::Serialize)]
+/// struct Greeting<'a> {
+///     name: &'a str,
+///     items: &'a [&'a str],
+/// }
+///
+/// let mut env = Environment::new();
+/// env.add_template(
+///     "hello.html",
+///     "Hello, {{ name }}!{% for item in items %} {{ loop.index }}. {{ item|upper }}{% endfor %}",
+/// )?;
+/// let ctx = Greeting { name: "<world>", items: &["tea", "cake"] };
+/// assert_eq!(
+///     env.render("hello.html", &ctx)?,
+///     "Hello, &#60;world&#62;! 1. TEA 2. CAKE",
+/// );
+/// # Ok::<(), rinja::Error>(())
+/// ```
+///
+/// Like for derived templates, the escaper is selected by the extension of the template name,
+/// e.g. `.html` templates are HTML escaped, and `.txt` templates are not escaped at all.
+///
+/// Text, comments, expressions, `{% if %}`, `{% for %}` (with `{% break %}`, `{% continue %}`
+/// and `{% else %}`), `{% let %}`, `{% include %}`, `{% extends %}`, `{% block %}` (with
+/// `super()`), `{% filter %}` and `{% raw %}` are supported. Expressions can use literals, the
+/// variables of the context, fields, indexing, the operators and the methods `len()` and
+/// `is_empty()`. The filters `abs`, `capfirst`, `capitalize`, `center`, `default`, `escape`,
+/// `indent`, `join`, `json`, `ljust`, `lower`, `rjust`, `safe`, `title`, `trim`, `truncate`,
+/// `upper` and `wordcount` are available. Unlike in derived templates, any value can be used as
+/// a condition: `false`, `null`, `0`, and empty strings, arrays and maps are false.
+///
+/// Using anything else, or an undefined variable, makes the rendering fail with an
+/// [`Error::Runtime`].
+#[derive(Debug, Default)]
+pub struct Environment {
+    templates: HashMap<String, Parsed>,
+}
+
+impl Environment {
+    /// Creates an environment without any templates
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an environment with all templates in `dir`, see [`Environment::add_dir()`]
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let mut env = Self::new();
+        env.add_dir(dir)?;
+        Ok(env)
+    }
+
+    /// Parses `source` and adds it as the template `name`, replacing a template of the same name
+    pub fn add_template(
+        &mut self,
+        name: impl Into<String>,
+        source: impl Into<String>,
+    ) -> Result<()> {
+        self.insert(name.into(), source.into(), None)
+    }
+
+    /// Adds all files in `dir` and its subdirectories as templates
+    ///
+    /// The name of a template is its path relative to `dir`, with `/` as separator, e.g.
+    /// `"emails/welcome.html"`. Like for derived templates, one trailing newline is removed from
+    /// the files.
+    pub fn add_dir(&mut self, dir: impl AsRef<Path>) -> Result<()> {
+        self.add_dir_with_prefix(dir.as_ref(), "")
+    }
+
+    fn add_dir_with_prefix(&mut self, dir: &Path, prefix: &str) -> Result<()> {
+        for entry in fs::read_dir(dir).map_err(io_error)? {
+            let entry = entry.map_err(io_error)?;
+            let path = entry.path();
+            let file_name = entry.file_name();
+            let Some(file_name) = file_name.to_str() else {
+                continue;
+            };
+            let name = format!("{prefix}{file_name}");
+            if entry.file_type().map_err(io_error)?.is_dir() {
+                self.add_dir_with_prefix(&path, &format!("{name}/"))?;
+            } else {
+                let mut source = fs::read_to_string(&path).map_err(io_error)?;
+                if source.ends_with('\n') {
+                    let _ = source.pop();
+                }
+                self.insert(name, source, Some(Arc::from(path)))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, name: String, source: String, path: Option<Arc<Path>>) -> Result<()> {
+        let parsed = Parsed::new(Arc::from(source), path, &Syntax::default())
+            .map_err(|err| Error::Custom(Box::new(err)))?;
+        self.templates.insert(name, parsed);
+        Ok(())
+    }
+
+    /// Returns `true` if there is a template called `name`
+    #[inline]
+    pub fn contains_template(&self, name: &str) -> bool {
+        self.templates.contains_key(name)
+    }
+
+    /// Renders the template `name` with the variables in `context`
+    pub fn render(&self, name: &str, context: &impl Serialize) -> Result<String> {
+        let mut buf = String::new();
+        self.render_into(&mut buf, name, context)?;
+        Ok(buf)
+    }
+
+    /// Renders the template `name` with the variables in `context` into `writer`
+    pub fn render_into<W: fmt::Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        name: &str,
+        context: &impl Serialize,
+    ) -> Result<()> {
+        let globals = match serde_json::to_value(context)? {
+            Value::Object(globals) => globals,
+            Value::Null => Map::new(),
+            _ => return Err(runtime_error("the context must serialize to a map")),
+        };
+        let nodes = self
+            .nodes(name)
+            .ok_or_else(|| runtime_error(format!("unknown template {name:?}")))?;
+
+        let mut renderer = Renderer {
+            env: self,
+            globals: Some(&globals),
+            locals: Vec::new(),
+            escaper: Escaping::for_name(name),
+            blocks: HashMap::new(),
+            super_block: None,
+            filter_source: None,
+            depth: 0,
+            ws: WsState::new(Whitespace::Preserve),
+        };
+        let mut buf = String::new();
+        renderer.render_template(nodes, &mut buf)?;
+        writer.write_str(&buf)?;
+        Ok(())
+    }
+
+    fn nodes(&self, name: &str) -> Option<&[Node<'_>]> {
+        self.templates.get(name).map(Parsed::nodes)
+    }
+}
+
+fn io_error(err: std::io::Error) -> Error {
+    Error::Custom(Box::new(err))
+}
+
+fn runtime_error(msg: impl Into<String>) -> Error {
+    Error::Runtime(msg.into())
+}
+
+// The nesting of included and extended templates is limited to stop endless recursions.
+const MAX_DEPTH: usize = 64;
+
+// Ranges are collected into arrays, so their length is limited to not exhaust the memory.
+const MAX_RANGE_LEN: i64 = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Flow {
+    Next,
+    Break,
+    Continue,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Escaping {
+    Html,
+    HtmlAttr,
+    Js,
+    Json,
+    Shell,
+    Text,
+}
+
+impl Escaping {
+    fn for_name(name: &str) -> Self {
+        let ext = match name.rsplit_once('.') {
+            Some((_, ext)) if !ext.contains('/') => ext,
+            _ => "",
+        };
+        Self::for_extension(ext).unwrap_or(Self::Text)
+    }
+
+    // Uses the same extensions as the default escapers of derived templates, which are also the
+    // names that can be passed to the `escape` filter.
+    fn for_extension(ext: &str) -> Option<Self> {
+        Some(match ext {
+            "html" | "htm" | "j2" | "jinja" | "jinja2" | "svg" | "xml" => Self::Html,
+            "attr" => Self::HtmlAttr,
+            "js" => Self::Js,
+            "json" | "jsonl" => Self::Json,
+            "sh" => Self::Shell,
+            "md" | "none" | "txt" | "yml" | "" => Self::Text,
+            _ => return None,
+        })
+    }
+
+    fn write_escaped(self, out: &mut String, s: &str) -> fmt::Result {
+        match self {
+            Self::Html => filters::Html.write_escaped_str(out, s),
+            Self::HtmlAttr => filters::HtmlAttr.write_escaped_str(out, s),
+            Self::Js => filters::Js.write_escaped_str(out, s),
+            Self::Json => filters::Json.write_escaped_str(out, s),
+            Self::Shell => filters::Shell.write_escaped_str(out, s),
+            Self::Text => filters::Text.write_escaped_str(out, s),
+        }
+    }
+}
+
+// Mirrors the code generator, like the interpreter of the `hot-reload` feature.
+struct Renderer<'a> {
+    env: &'a Environment,
+    globals: Option<&'a Map<String, Value>>,
+    locals: Vec<(&'a str, Value)>,
+    escaper: Escaping,
+    // The definitions of every block, the one of the most derived template first
+    blocks: HashMap<&'a str, Vec<&'a BlockDef<'a>>>,
+    // The name and the index in `blocks` of the block that is rendered, for `super()`
+    super_block: Option<(&'a str, usize)>,
+    filter_source: Option<Value>,
+    depth: usize,
+    ws: WsState<'a>,
+}
+
+impl<'a> Renderer<'a> {
+    fn render_template(&mut self, mut nodes: &'a [Node<'a>], out: &mut String) -> Result<()> {
+        // Like in derived templates, only the blocks of a template that extends another one are
+        // rendered, in the place where the root template defines them.
+        loop {
+            self.collect_blocks(nodes);
+            let Some(path) = nodes.iter().find_map(|node| match node {
+                Node::Extends(extends) => Some(&extends.path),
+                _ => None,
+            }) else {
+                break;
+            };
+            self.enter()?;
+            let name = self.template_path(path)?;
+            nodes = self
+                .env
+                .nodes(&name)
+                .ok_or_else(|| Error::UnknownExtends(name.into_owned()))?;
+        }
+        self.handle(nodes, out)?;
+        self.ws.finish(|s| out.push_str(s));
+        Ok(())
+    }
+
+    fn collect_blocks(&mut self, nodes: &'a [Node<'a>]) {
+        for node in nodes {
+            if let Node::BlockDef(def) = node {
+                self.blocks.entry(def.name).or_default().push(def);
+                self.collect_blocks(&def.nodes);
+            }
+        }
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        self.depth += 1;
+        match self.depth > MAX_DEPTH {
+            true => Err(runtime_error(
+                "templates are included or extended too deeply",
+            )),
+            false => Ok(()),
+        }
+    }
+
+    fn handle(&mut self, nodes: &'a [Node<'a>], out: &mut String) -> Result<Flow> {
+        for node in nodes {
+            let flow = match node {
+                Node::Lit(lit) => {
+                    self.visit_lit(lit, out);
+                    Flow::Next
+                }
+                Node::Comment(comment) => {
+                    self.handle_ws(comment.ws, out);
+                    Flow::Next
+                }
+                Node::Expr(ws, expr) => {
+                    match &**expr {
+                        Expr::Call(callee, args)
+                            if matches!(***callee, Expr::Var("super")) && args.is_empty() =>
+                        {
+                            self.write_super(*ws, out)?;
+                        }
+                        _ => {
+                            self.handle_ws(*ws, out);
+                            self.write_expr(expr, out)?;
+                        }
+                    }
+                    Flow::Next
+                }
+                Node::Raw(raw) => {
+                    self.handle_ws(raw.ws1, out);
+                    self.visit_lit(&raw.lit, out);
+                    self.handle_ws(raw.ws2, out);
+                    Flow::Next
+                }
+                Node::Let(l) => {
+                    self.write_let(l, out)?;
+                    Flow::Next
+                }
+                Node::If(i) => self.write_if(i, out)?,
+                Node::Loop(l) => self.write_loop(l, out)?,
+                Node::Break(ws, cond) | Node::Continue(ws, cond) => {
+                    self.handle_ws(**ws, out);
+                    match cond {
+                        Some(cond) if !self.eval_bool(cond)? => Flow::Next,
+                        _ if matches!(node, Node::Break(..)) => Flow::Break,
+                        _ => Flow::Continue,
+                    }
+                }
+                Node::Include(i) => {
+                    self.write_include(i, out)?;
+                    Flow::Next
+                }
+                Node::Extends(_) => Flow::Next,
+                Node::BlockDef(def) => {
+                    self.write_block(def.name, def.ws1, def.ws2, out)?;
+                    Flow::Next
+                }
+                Node::Call(call) if call.name == "super" && call.scope.is_none() => {
+                    self.write_super(call.ws, out)?;
+                    Flow::Next
+                }
+                Node::FilterBlock(filter) => {
+                    self.write_filter_block(filter, out)?;
+                    Flow::Next
+                }
+                _ => {
+                    let tag = node
+                        .span()
+                        .split(|c: char| !c.is_alphanumeric() && c != '_')
+                        .find(|s| !s.is_empty())
+                        .unwrap_or_default();
+                    return Err(runtime_error(format!(
+                        "`{tag}` is not supported by `Environment`"
+                    )));
+                }
+            };
+            if flow != Flow::Next {
+                return Ok(flow);
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    // Renders `nodes` in a new scope.
+    fn handle_scoped(&mut self, nodes: &'a [Node<'a>], out: &mut String) -> Result<Flow> {
+        let scope = self.locals.len();
+        let flow = self.handle(nodes, out);
+        self.locals.truncate(scope);
+        flow
+    }
+
+    fn write_expr(&mut self, expr: &Expr<'_>, out: &mut String) -> Result<()> {
+        let (value, safe) = self.eval_safe(expr)?;
+        let text = display(&value);
+        match safe {
+            true => out.push_str(&text),
+            false => self.escaper.write_escaped(out, &text)?,
+        }
+        Ok(())
+    }
+
+    // Like `eval()`, but also returns if the value must not be escaped again, because it is the
+    // output of a filter like `escape` or `safe`.
+    fn eval_safe<'b>(&'b self, expr: &Expr<'_>) -> Result<(Cow<'b, Value>, bool)> {
+        match expr {
+            Expr::Filter(filter) => {
+                let (value, safe) = self.filter(filter)?;
+                Ok((Cow::Owned(value), safe))
+            }
+            _ => Ok((self.eval(expr)?, false)),
+        }
+    }
+
+    fn write_let(&mut self, l: &'a Let<'a>, out: &mut String) -> Result<()> {
+        self.handle_ws(l.ws, out);
+        let (Some(val), None) = (&l.val, &l.else_nodes) else {
+            return Err(runtime_error(
+                "`let` without a value, or with an `else` branch, is not supported by \
+                 `Environment`",
+            ));
+        };
+        let value = self.eval(val)?.into_owned();
+        self.bind(&l.var, value)
+    }
+
+    fn write_if(&mut self, i: &'a If<'a>, out: &mut String) -> Result<Flow> {
+        let mut taken = None;
+        for (index, cond) in i.branches.iter().enumerate() {
+            if index == 0 {
+                self.handle_ws(cond.ws, out);
+            }
+            match &cond.cond {
+                Some(test) if test.target.is_some() => {
+                    return Err(runtime_error("`if let` is not supported by `Environment`"));
+                }
+                Some(test) if !self.eval_bool(&test.expr)? => continue,
+                _ => {
+                    taken = Some(index);
+                    break;
+                }
+            }
+        }
+
+        let mut flow = Flow::Next;
+        if let Some(index) = taken {
+            let cond = &i.branches[index];
+            self.prepare_ws(cond.ws);
+            flow = self.handle_scoped(&cond.nodes, out)?;
+            let end_ws = i.branches.get(index + 1).map_or(i.ws, |next| next.ws);
+            self.flush_ws(end_ws, out);
+        }
+        self.prepare_ws(i.ws);
+        Ok(flow)
+    }
+
+    fn write_loop(&mut self, l: &'a WithSpan<'a, Loop<'a>>, out: &mut String) -> Result<Flow> {
+        self.handle_ws(l.ws1, out);
+        let items = match self.eval(&l.iter)?.into_owned() {
+            Value::Array(items) => items,
+            Value::Object(map) => map
+                .into_iter()
+                .map(|(key, value)| Value::Array(vec![Value::String(key), value]))
+                .collect(),
+            Value::String(s) => s.chars().map(|c| Value::String(c.to_string())).collect(),
+            value => {
+                return Err(runtime_error(format!(
+                    "cannot iterate over {}",
+                    type_name(&value)
+                )));
+            }
+        };
+        let items = match &l.cond {
+            Some(cond) => {
+                let mut filtered = Vec::with_capacity(items.len());
+                for item in items {
+                    let scope = self.locals.len();
+                    self.bind(&l.var, item.clone())?;
+                    let keep = self.eval_bool(cond)?;
+                    self.locals.truncate(scope);
+                    if keep {
+                        filtered.push(item);
+                    }
+                }
+                filtered
+            }
+            None => items,
+        };
+
+        let len = items.len();
+        for (index, item) in items.into_iter().enumerate() {
+            let scope = self.locals.len();
+            self.bind(&l.var, item)?;
+            let mut info = Map::new();
+            info.insert("index".into(), (index + 1).into());
+            info.insert("index0".into(), index.into());
+            info.insert("first".into(), (index == 0).into());
+            info.insert("last".into(), (index + 1 == len).into());
+            self.locals.push(("loop", Value::Object(info)));
+            self.prepare_ws(l.ws1);
+            let flow = self.handle(&l.body, out);
+            self.locals.truncate(scope);
+            self.flush_ws(l.ws2, out);
+            if flow? == Flow::Break {
+                break;
+            }
+        }
+        if len == 0 && !l.else_nodes.is_empty() {
+            self.prepare_ws(l.ws2);
+            self.handle_scoped(&l.else_nodes, out)?;
+            self.flush_ws(l.ws3, out);
+        }
+        self.prepare_ws(l.ws3);
+        Ok(Flow::Next)
+    }
+
+    fn write_include(&mut self, i: &'a Include<'a>, out: &mut String) -> Result<()> {
+        self.flush_ws(i.ws, out);
+        let name = self.template_path(&i.path)?;
+        let nodes = self
+            .env
+            .nodes(&name)
+            .ok_or_else(|| Error::UnknownInclude(name.into_owned()))?;
+
+        let mut vars = Vec::with_capacity(i.vars.len());
+        for (target, expr) in &i.vars {
+            vars.push((target, self.eval(expr)?.into_owned()));
+        }
+        let (locals, globals) = match i.only {
+            true => (mem::take(&mut self.locals), self.globals.take()),
+            false => (Vec::new(), self.globals),
+        };
+        let scope = self.locals.len();
+        let mut result = vars
+            .into_iter()
+            .try_for_each(|(target, value)| self.bind(target, value));
+        if result.is_ok() {
+            result = self.enter();
+        }
+        if result.is_ok() {
+            result = self.handle(nodes, out).map(|_| ());
+            self.depth -= 1;
+        }
+        self.locals.truncate(scope);
+        if i.only {
+            self.locals = locals;
+            self.globals = globals;
+        }
+        result?;
+        self.prepare_ws(i.ws);
+        Ok(())
+    }
+
+    fn write_block(&mut self, name: &'a str, ws1: Ws, ws2: Ws, out: &mut String) -> Result<()> {
+        self.write_block_at(name, 0, ws1, ws2, out)
+    }
+
+    // Renders the definition of the current block in the template that is extended.
+    fn write_super(&mut self, ws: Ws, out: &mut String) -> Result<()> {
+        let Some((name, index)) = self.super_block else {
+            return Err(runtime_error("cannot call `super()` outside of a block"));
+        };
+        self.write_block_at(name, index + 1, ws, ws, out)
+    }
+
+    // Renders the `index`th definition of the block `name`, where 0 is the most derived one.
+    fn write_block_at(
+        &mut self,
+        name: &'a str,
+        index: usize,
+        ws1: Ws,
+        ws2: Ws,
+        out: &mut String,
+    ) -> Result<()> {
+        self.flush_ws(ws1, out);
+        let Some(def) = self
+            .blocks
+            .get(name)
+            .and_then(|defs| defs.get(index))
+            .copied()
+        else {
+            return Err(runtime_error(format!(
+                "no `super()` block found for {name:?}"
+            )));
+        };
+        // Like in derived templates, the variables of the surrounding scope are not inherited.
+        let locals = mem::take(&mut self.locals);
+        let super_block = self.super_block.replace((name, index));
+        self.prepare_ws(def.ws1);
+        let result = self.handle(&def.nodes, out);
+        self.flush_ws(def.ws2, out);
+        self.super_block = super_block;
+        self.locals = locals;
+        result?;
+        self.prepare_ws(ws2);
+        Ok(())
+    }
+
+    fn write_filter_block(
+        &mut self,
+        filter: &'a WithSpan<'a, FilterBlock<'a>>,
+        out: &mut String,
+    ) -> Result<()> {
+        self.flush_ws(filter.ws1, out);
+        self.prepare_ws(filter.ws1);
+        let mut content = String::new();
+        self.handle_scoped(&filter.nodes, &mut content)?;
+        self.flush_ws(filter.ws2, &mut content);
+
+        let source = self.filter_source.replace(Value::String(content));
+        let filters = Expr::Filter(filter.filters.clone());
+        let result = self.write_expr(&filters, out);
+        self.filter_source = source;
+        result?;
+        self.prepare_ws(filter.ws2);
+        Ok(())
+    }
+
+    fn template_path(&self, path: &TemplatePath<'_>) -> Result<Cow<'a, str>> {
+        match path {
+            TemplatePath::Static(name) => Ok(Cow::Owned(unescape(name)?)),
+            TemplatePath::Dynamic(expr) => match self.eval(expr)?.into_owned() {
+                Value::String(name) => Ok(Cow::Owned(name)),
+                value => Err(runtime_error(format!(
+                    "the name of a template must be a string, not {}",
+                    type_name(&value)
+                ))),
+            },
+        }
+    }
+
+    fn bind(&mut self, target: &'a Target<'a>, value: Value) -> Result<()> {
+        match target {
+            Target::Name(name) => self.locals.push((name, value)),
+            Target::Placeholder(_) => {}
+            Target::Tuple(path, targets) if path.is_empty() => {
+                let Value::Array(items) = value else {
+                    return Err(runtime_error(format!(
+                        "cannot destructure {} as a tuple",
+                        type_name(&value)
+                    )));
+                };
+                if items.len() != targets.len() {
+                    return Err(runtime_error(format!(
+                        "cannot destructure {} items into {} variables",
+                        items.len(),
+                        targets.len()
+                    )));
+                }
+                for (target, item) in targets.iter().zip(items) {
+                    self.bind(target, item)?;
+                }
+            }
+            _ => {
+                return Err(runtime_error(
+                    "only names and tuples can be assigned by `Environment`",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn eval_bool(&self, expr: &Expr<'_>) -> Result<bool> {
+        Ok(truthy(&*self.eval(expr)?))
+    }
+
+    fn var(&self, name: &str) -> Result<&Value> {
+        self.lookup(name)
+            .ok_or_else(|| runtime_error(format!("undefined variable `{name}`")))
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Value> {
+        self.locals
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value)
+            .or_else(|| self.globals?.get(name))
+    }
+
+    // Evaluates the input of the filter `default`, which is `None` if it names an undefined
+    // variable or member. Any other error is returned.
+    fn eval_defined(&self, expr: &Expr<'_>) -> Result<Option<Cow<'_, Value>>> {
+        Ok(match expr {
+            Expr::Var(name) => self.lookup(name).map(Cow::Borrowed),
+            Expr::Attr(obj, key) => self
+                .eval_defined(obj)?
+                .and_then(|obj| get_member(obj, &Value::String((*key).into()))),
+            Expr::Index(obj, index) => {
+                let index = self.eval(index)?.into_owned();
+                self.eval_defined(obj)?
+                    .and_then(|obj| get_member(obj, &index))
+            }
+            Expr::Group(inner) => self.eval_defined(inner)?,
+            _ => Some(self.eval(expr)?),
+        })
+    }
+
+    fn eval(&self, expr: &Expr<'_>) -> Result<Cow<'_, Value>> {
+        Ok(match expr {
+            Expr::BoolLit(b) => Cow::Owned(Value::Bool(*b == "true")),
+            Expr::NumLit(s) => Cow::Owned(num_lit(s)?),
+            Expr::StrLit(s) | Expr::CharLit(s) => Cow::Owned(Value::String(unescape(s)?)),
+            Expr::Var(name) => Cow::Borrowed(self.var(name)?),
+            Expr::Attr(obj, key) => member(self.eval(obj)?, &Value::String((*key).into()))?,
+            Expr::Index(obj, index) => {
+                let index = self.eval(index)?.into_owned();
+                member(self.eval(obj)?, &index)?
+            }
+            Expr::Array(items) => {
+                let items = items.iter().map(|item| Ok(self.eval(item)?.into_owned()));
+                Cow::Owned(Value::Array(items.collect::<Result<_>>()?))
+            }
+            Expr::Map(entries) => {
+                let mut map = Map::new();
+                for (key, value) in entries {
+                    let Value::String(key) = self.eval(key)?.into_owned() else {
+                        return Err(runtime_error("the keys of a map must be strings"));
+                    };
+                    map.insert(key, self.eval(value)?.into_owned());
+                }
+                Cow::Owned(Value::Object(map))
+            }
+            Expr::Group(inner) => self.eval(inner)?,
+            Expr::Unary("!", inner) => Cow::Owned(Value::Bool(!self.eval_bool(inner)?)),
+            Expr::Unary("-", inner) => Cow::Owned(arithmetic("-", &0.into(), &*self.eval(inner)?)?),
+            Expr::BinOp(op, left, right) => Cow::Owned(self.binop(op, left, right)?),
+            Expr::Range(op, Some(start), Some(end)) => {
+                let (Some(start), Some(end)) =
+                    (self.eval(start)?.as_i64(), self.eval(end)?.as_i64())
+                else {
+                    return Err(runtime_error("the bounds of a range must be integers"));
+                };
+                let end = if *op == "..=" {
+                    end.saturating_add(1)
+                } else {
+                    end
+                };
+                if end.saturating_sub(start) > MAX_RANGE_LEN {
+                    return Err(runtime_error(format!(
+                        "ranges cannot be longer than {MAX_RANGE_LEN} items"
+                    )));
+                }
+                Cow::Owned(Value::Array((start..end).map(Value::from).collect()))
+            }
+            Expr::IfElse(cond, then, otherwise) => match self.eval_bool(cond)? {
+                true => self.eval(then)?,
+                false => self.eval(otherwise)?,
+            },
+            Expr::Call(callee, args) => match (&***callee, args.as_slice()) {
+                (Expr::Attr(obj, method @ ("len" | "is_empty")), []) => {
+                    let len = match &*self.eval(obj)? {
+                        Value::String(s) => s.len(),
+                        Value::Array(items) => items.len(),
+                        Value::Object(map) => map.len(),
+                        value => {
+                            return Err(runtime_error(format!(
+                                "{} has no method `{method}()`",
+                                type_name(value)
+                            )));
+                        }
+                    };
+                    Cow::Owned(match *method {
+                        "len" => len.into(),
+                        _ => (len == 0).into(),
+                    })
+                }
+                _ => {
+                    return Err(runtime_error(
+                        "only the methods `len()` and `is_empty()` can be called",
+                    ))
+                }
+            },
+            Expr::Filter(filter) => Cow::Owned(self.filter(filter)?.0),
+            Expr::FilterSource => match &self.filter_source {
+                Some(source) => Cow::Borrowed(source),
+                None => return Err(runtime_error("no filter block source")),
+            },
+            _ => return Err(runtime_error("unsupported expression")),
+        })
+    }
+
+    fn binop(&self, op: &str, left: &Expr<'_>, right: &Expr<'_>) -> Result<Value> {
+        match op {
+            "&&" => return Ok((self.eval_bool(left)? && self.eval_bool(right)?).into()),
+            "||" => return Ok((self.eval_bool(left)? || self.eval_bool(right)?).into()),
+            _ => {}
+        }
+        let left = self.eval(left)?;
+        let right = self.eval(right)?;
+        let (left, right) = (&*left, &*right);
+        Ok(match op {
+            "==" => equals(left, right).into(),
+            "!=" => (!equals(left, right)).into(),
+            "<" | "<=" | ">" | ">=" => {
+                let Some(ordering) = compare(left, right) else {
+                    return Err(runtime_error(format!(
+                        "cannot compare {} with {}",
+                        type_name(left),
+                        type_name(right)
+                    )));
+                };
+                match op {
+                    "<" => ordering.is_lt(),
+                    "<=" => ordering.is_le(),
+                    ">" => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                }
+                .into()
+            }
+            "in" => contains(right, left)?.into(),
+            "not in" => (!contains(right, left)?).into(),
+            _ => arithmetic(op, left, right)?,
+        })
+    }
+
+    // Returns the filtered value, and if it must not be escaped again.
+    fn filter(&self, filter: &Filter<'_>) -> Result<(Value, bool)> {
+        let Filter { name, arguments } = filter;
+        let Some((input, args)) = arguments.split_first() else {
+            return Err(runtime_error(format!("the filter `{name}` needs an input")));
+        };
+        if *name == "default" {
+            let fallback = args
+                .first()
+                .ok_or_else(|| runtime_error("the filter `default` needs a fallback value"))?;
+            let check_empty = match args.get(1) {
+                Some(check_empty) => self.eval_bool(check_empty)?,
+                None => false,
+            };
+            return match self.eval_defined(input)? {
+                Some(value) if !value.is_null() && (!check_empty || truthy(&value)) => {
+                    Ok((value.into_owned(), false))
+                }
+                _ => Ok((self.eval(fallback)?.into_owned(), false)),
+            };
+        }
+
+        let (value, input_safe) = self.eval_safe(input)?;
+        let args = args
+            .iter()
+            .map(|arg| self.eval(arg))
+            .collect::<Result<Vec<_>>>()?;
+        let text = || display(&value);
+        let usize_arg = |index: usize| {
+            args.get(index)
+                .and_then(|arg| arg.as_u64())
+                .and_then(|arg| usize::try_from(arg).ok())
+                .ok_or_else(|| {
+                    runtime_error(format!("the filter `{name}` needs a number argument"))
+                })
+        };
+        let escaper = match args.first() {
+            Some(arg) if matches!(*name, "e" | "escape") => {
+                let escaper = arg.as_str().and_then(Escaping::for_extension);
+                escaper.ok_or_else(|| {
+                    runtime_error(format!("invalid escaper {arg} for the filter `{name}`"))
+                })?
+            }
+            _ => self.escaper,
+        };
+        // Like in derived templates, changing the case of already escaped text, or trimming or
+        // indenting it, keeps it escaped.
+        let safe = match *name {
+            "e" | "escape" | "json" | "safe" => true,
+            "capfirst" | "capitalize" | "center" | "indent" | "lower" | "lowercase" | "title"
+            | "trim" | "upper" | "uppercase" => input_safe,
+            _ => false,
+        };
+        let value = match *name {
+            "abs" => return Ok((arithmetic("abs", &value, &0.into())?, false)),
+            "capfirst" => filters::capfirst(text())?.to_string(),
+            "capitalize" => filters::capitalize(text())?.to_string(),
+            "center" => filters::center(text(), usize_arg(0)?)?.to_string(),
+            "e" | "escape" => {
+                let mut escaped = String::new();
+                escaper.write_escaped(&mut escaped, &text())?;
+                escaped
+            }
+            "indent" => filters::indent(text(), usize_arg(0)?)?.to_string(),
+            "join" => {
+                let separator = args.first().map_or(Cow::Borrowed(""), |arg| display(arg));
+                let items = match &*value {
+                    Value::Array(items) => items.iter().map(display).collect(),
+                    _ => vec![text()],
+                };
+                filters::join(items, separator)?.to_string()
+            }
+            "json" => filters::json(&*value)?.to_string(),
+            "ljust" => filters::ljust(text(), usize_arg(0)?)?.to_string(),
+            "lower" | "lowercase" => filters::lower(text())?.to_string(),
+            "rjust" => filters::rjust(text(), usize_arg(0)?)?.to_string(),
+            "safe" => return Ok((value.into_owned(), safe)),
+            "title" => filters::title(text())?,
+            "trim" => filters::trim(text())?.to_string(),
+            "truncate" => filters::truncate(text(), usize_arg(0)?)?.to_string(),
+            "upper" | "uppercase" => filters::upper(text())?.to_string(),
+            "wordcount" => return Ok((filters::wordcount(text())?.into(), false)),
+            _ => {
+                return Err(runtime_error(format!(
+                    "the filter `{name}` is not supported by `Environment`"
+                )));
+            }
+        };
+        Ok((Value::String(value), safe))
+    }
+
+    fn visit_lit(&mut self, lit: &'a Lit<'a>, out: &mut String) {
+        self.ws.visit_lit(lit, |s| out.push_str(s));
+    }
+
+    fn handle_ws(&mut self, ws: Ws, out: &mut String) {
+        self.ws.handle_ws(ws, |s| out.push_str(s));
+    }
+
+    fn flush_ws(&mut self, ws: Ws, out: &mut String) {
+        self.ws.flush_ws(ws, |s| out.push_str(s));
+    }
+
+    fn prepare_ws(&mut self, ws: Ws) {
+        self.ws.prepare_ws(ws);
+    }
+}
+
+// Formats a value like it is written into the template.
+fn display(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::Null => Cow::Borrowed(""),
+        Value::String(s) => Cow::Borrowed(s),
+        Value::Bool(b) => Cow::Borrowed(if *b { "true" } else { "false" }),
+        Value::Number(n) => Cow::Owned(n.to_string()),
+        value => Cow::Owned(value.to_string()),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a bool",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "a map",
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+// Looks up a field of a map, or an item of an array or a tuple.
+fn member<'v>(value: Cow<'v, Value>, key: &Value) -> Result<Cow<'v, Value>> {
+    let type_name = type_name(&value);
+    get_member(value, key)
+        .ok_or_else(|| runtime_error(format!("{type_name} has no member {}", display(key))))
+}
+
+// Returns `None` if the value has no member `key`.
+fn get_member<'v>(value: Cow<'v, Value>, key: &Value) -> Option<Cow<'v, Value>> {
+    let found = match (&*value, key) {
+        (Value::Object(map), Value::String(key)) => map.get(key).is_some(),
+        (Value::Array(items), _) => index(key).is_some_and(|index| index < items.len()),
+        _ => false,
+    };
+    if !found {
+        return None;
+    }
+    Some(match value {
+        Cow::Borrowed(Value::Object(map)) => Cow::Borrowed(&map[key.as_str().unwrap_or_default()]),
+        Cow::Borrowed(Value::Array(items)) => Cow::Borrowed(&items[index(key).unwrap_or_default()]),
+        Cow::Owned(Value::Object(mut map)) => Cow::Owned(
+            map.remove(key.as_str().unwrap_or_default())
+                .unwrap_or_default(),
+        ),
+        Cow::Owned(Value::Array(mut items)) => {
+            Cow::Owned(items.swap_remove(index(key).unwrap_or_default()))
+        }
+        _ => unreachable!(),
+    })
+}
+
+// A tuple field like `pair.0` is looked up with a string key.
+fn index(key: &Value) -> Option<usize> {
+    match key {
+        Value::Number(n) => usize::try_from(n.as_u64()?).ok(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn equals(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => a == b,
+            _ => a.as_f64() == b.as_f64(),
+        },
+        _ => left == right,
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Option<core::cmp::Ordering> {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => match (a.as_i64(), b.as_i64()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        },
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn contains(container: &Value, item: &Value) -> Result<bool> {
+    Ok(match (container, item) {
+        (Value::Array(items), _) => items.iter().any(|i| equals(i, item)),
+        (Value::Object(map), Value::String(key)) => map.contains_key(key),
+        (Value::String(s), Value::String(part)) => s.contains(part.as_str()),
+        _ => {
+            return Err(runtime_error(format!(
+                "cannot look for {} in {}",
+                type_name(item),
+                type_name(container)
+            )));
+        }
+    })
+}
+
+// Integers stay integers, like in Rust, unless one of the operands is a float.
+fn arithmetic(op: &str, left: &Value, right: &Value) -> Result<Value> {
+    let error = || {
+        runtime_error(format!(
+            "cannot apply `{op}` to {} and {}",
+            type_name(left),
+            type_name(right)
+        ))
+    };
+    let (Value::Number(a), Value::Number(b)) = (left, right) else {
+        return Err(error());
+    };
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        let result = match op {
+            "+" => a.checked_add(b),
+            "-" => a.checked_sub(b),
+            "*" => a.checked_mul(b),
+            "/" => a.checked_div(b),
+            "%" => a.checked_rem(b),
+            "**" => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+            "abs" => a.checked_abs(),
+            _ => return Err(error()),
+        };
+        return result
+            .map(Value::from)
+            .ok_or_else(|| runtime_error(format!("`{op}` overflowed or divided by zero")));
+    }
+    let (Some(a), Some(b)) = (a.as_f64(), b.as_f64()) else {
+        return Err(error());
+    };
+    let result = match op {
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "/" => a / b,
+        "%" => a % b,
+        "**" => a.powf(b),
+        "abs" => a.abs(),
+        _ => return Err(error()),
+    };
+    Number::from_f64(result)
+        .map(Value::Number)
+        .ok_or_else(|| runtime_error(format!("the result of `{op}` is not a finite number")))
+}
+
+// Parses a number literal, which is written like in Rust.
+fn num_lit(lit: &str) -> Result<Value> {
+    const SUFFIXES: &[&str] = &[
+        "i128", "isize", "i16", "i32", "i64", "i8", "u128", "usize", "u16", "u32", "u64", "u8",
+        "f32", "f64",
+    ];
+
+    let error = || runtime_error(format!("invalid number literal {lit:?}"));
+    let (negative, digits) = match lit.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, lit),
+    };
+    let digits = digits.replace('_', "");
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x") => (16, &digits[2..]),
+        Some("0o") => (8, &digits[2..]),
+        Some("0b") => (2, &digits[2..]),
+        _ => (10, &digits[..]),
+    };
+    let suffix = SUFFIXES
+        .iter()
+        .find(|suffix| digits.ends_with(*suffix) && (radix != 16 || !suffix.starts_with('f')));
+    let digits = match suffix {
+        Some(suffix) => &digits[..digits.len() - suffix.len()],
+        None => digits,
+    };
+
+    let is_float = radix == 10
+        && (suffix.is_some_and(|suffix| suffix.starts_with('f'))
+            || digits.contains(['.', 'e', 'E']));
+    let value = if is_float {
+        let value: f64 = digits.parse().map_err(|_| error())?;
+        Value::Number(Number::from_f64(if negative { -value } else { value }).ok_or_else(error)?)
+    } else {
+        let value = i128::from_str_radix(digits, radix).map_err(|_| error())?;
+        let value = if negative { -value } else { value };
+        match (i64::try_from(value), u64::try_from(value)) {
+            (Ok(value), _) => value.into(),
+            (_, Ok(value)) => value.into(),
+            _ => return Err(error()),
+        }
+    };
+    Ok(value)
+}
+
+// Resolves the escape sequences of a string or char literal.
+fn unescape(s: &str) -> Result<String> {
+    let error = || runtime_error(format!("invalid escape sequence in {s:?}"));
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        let c = match chars.next().ok_or_else(error)? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            c @ ('\\' | '\'' | '"') => c,
+            'x' => {
+                let code = chars.as_str().get(..2).ok_or_else(error)?;
+                let code = u8::from_str_radix(code, 16).map_err(|_| error())?;
+                chars.nth(1);
+                char::from(code)
+            }
+            'u' => {
+                let rest = chars.as_str();
+                let end = rest.find('}').ok_or_else(error)?;
+                let code = rest.get(1..end).ok_or_else(error)?.replace('_', "");
+                let code = u32::from_str_radix(&code, 16).map_err(|_| error())?;
+                chars = rest[end + 1..].chars();
+                char::from_u32(code).ok_or_else(error)?
+            }
+            _ => return Err(error()),
+        };
+        result.push(c);
+    }
+    Ok(result)
+}
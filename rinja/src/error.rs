@@ -1,7 +1,10 @@
-use std::convert::Infallible;
-use std::fmt::{self, Display};
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::any::Any;
+use core::convert::Infallible;
+use core::fmt::{self, Display};
 
-pub type Result<I, E = Error> = std::result::Result<I, E>;
+pub type Result<I, E = Error> = core::result::Result<I, E>;
 
 /// rinja error type
 ///
@@ -9,6 +12,14 @@ pub type Result<I, E = Error> = std::result::Result<I, E>;
 ///
 /// If the feature `serde_json` is enabled an
 /// additional error variant `Json` is added.
+/// If the feature `runtime` is enabled an
+/// additional error variant `Runtime` is added.
+/// If the feature `tokio` is enabled an
+/// additional error variant `Io` is added.
+///
+/// With the feature `std`, the variant `Custom` holds a `std::error::Error`, so its `source()`
+/// chain and downcasting work. Without it, `Custom` holds any [`CustomError`], i.e. any
+/// `Debug + Display` type.
 ///
 /// # Why not `failure`/`error-chain`?
 ///
@@ -28,19 +39,140 @@ pub enum Error {
     /// formatting error
     Fmt,
     /// an error raised by using `?` in a template
+    #[cfg(feature = "std")]
     Custom(Box<dyn std::error::Error + Send + Sync>),
+    /// an error raised by using `?` in a template
+    #[cfg(not(feature = "std"))]
+    Custom(Box<dyn CustomError>),
+    /// the template selected by a dynamic `{% include %}` is not listed in
+    /// `#[template(includes = [...])]`
+    UnknownInclude(String),
+    /// the template selected by a dynamic `{% extends %}` is not listed in
+    /// `#[template(extends = [...])]`
+    UnknownExtends(String),
+    /// a template requested a value with `rinja::get_value()`, but no value was passed for the
+    /// key to `render_with_values()`
+    ValueMissing(String),
+    /// a template requested a value with `rinja::get_value()`, but the value that was passed for
+    /// the key has a different type
+    ValueType(String),
     /// json conversion error
     #[cfg(feature = "serde_json")]
     Json(serde_json::Error),
+    /// a template of an [`Environment`][crate::Environment] could not be rendered, e.g. because
+    /// it uses an undefined variable or a feature that is not supported at runtime
+    #[cfg(feature = "runtime")]
+    Runtime(String),
+    /// the writer of [`Template::render_into_async()`][crate::Template::render_into_async] failed
+    #[cfg(feature = "tokio")]
+    Io(std::io::Error),
+}
+
+/// Errors that can be raised by using `?` in a template
+///
+/// It is implemented for every type that implements `Debug`, `Display`, `Send` and `Sync`,
+/// e.g. every `std::error::Error + Send + Sync`, and `&'static str`. With the feature `std`,
+/// errors that do not implement `std::error::Error` are wrapped, so they can be stored in
+/// [`Error::Custom`].
+pub trait CustomError: fmt::Debug + Display + Send + Sync + 'static {
+    #[doc(hidden)]
+    fn as_any(&self) -> &(dyn Any + Send + Sync);
+
+    #[doc(hidden)]
+    fn into_any(self: Box<Self>) -> Box<dyn Any + Send + Sync>;
+}
+
+impl<T: fmt::Debug + Display + Send + Sync + 'static> CustomError for T {
+    #[inline]
+    fn as_any(&self) -> &(dyn Any + Send + Sync) {
+        self
+    }
+
+    #[inline]
+    fn into_any(self: Box<Self>) -> Box<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+impl dyn CustomError {
+    /// Returns `true` if the error is of type `E`
+    #[inline]
+    pub fn is<E: CustomError>(&self) -> bool {
+        self.as_any().is::<E>()
+    }
+
+    /// Returns a reference to the error if it is of type `E`
+    #[inline]
+    pub fn downcast_ref<E: CustomError>(&self) -> Option<&E> {
+        self.as_any().downcast_ref()
+    }
+
+    /// Returns the error if it is of type `E`, or else the original box
+    pub fn downcast<E: CustomError>(self: Box<Self>) -> Result<Box<E>, Box<Self>> {
+        match self.is::<E>() {
+            true => Ok(self
+                .into_any()
+                .downcast()
+                .unwrap_or_else(|_| unreachable!())),
+            false => Err(self),
+        }
+    }
+}
+
+impl Error {
+    /// Returns the error of type `E` that was raised by using `?` in a template,
+    /// or converts the error into an `E` otherwise
+    ///
+    /// Templates with an `error = "E"` attribute use this method to return their own error type
+    /// from `try_render()` and `try_render_into()`.
+    #[cfg(feature = "std")]
+    pub fn into_custom<E>(self) -> E
+    where
+        E: From<Error> + std::error::Error + Send + Sync + 'static,
+    {
+        match self {
+            Error::Custom(err) => match err.downcast::<E>() {
+                Ok(err) => *err,
+                Err(err) => E::from(Error::Custom(err)),
+            },
+            err => E::from(err),
+        }
+    }
+
+    /// Returns the error of type `E` that was raised by using `?` in a template,
+    /// or converts the error into an `E` otherwise
+    ///
+    /// Templates with an `error = "E"` attribute use this method to return their own error type
+    /// from `try_render()` and `try_render_into()`.
+    #[cfg(not(feature = "std"))]
+    pub fn into_custom<E>(self) -> E
+    where
+        E: From<Error> + CustomError,
+    {
+        match self {
+            Error::Custom(err) => match err.downcast::<E>() {
+                Ok(err) => *err,
+                Err(err) => E::from(Error::Custom(err)),
+            },
+            err => E::from(err),
+        }
+    }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match *self {
             Error::Fmt => None,
             Error::Custom(ref err) => Some(err.as_ref()),
+            Error::UnknownInclude(_) | Error::UnknownExtends(_) => None,
+            Error::ValueMissing(_) | Error::ValueType(_) => None,
             #[cfg(feature = "serde_json")]
             Error::Json(ref err) => Some(err),
+            #[cfg(feature = "runtime")]
+            Error::Runtime(_) => None,
+            #[cfg(feature = "tokio")]
+            Error::Io(ref err) => Some(err),
         }
     }
 }
@@ -50,8 +182,22 @@ impl Display for Error {
         match self {
             Error::Fmt => write!(formatter, "formatting error"),
             Error::Custom(err) => write!(formatter, "{err}"),
+            Error::UnknownInclude(path) => {
+                write!(formatter, "cannot include unknown template {path:?}")
+            }
+            Error::UnknownExtends(path) => {
+                write!(formatter, "cannot extend unknown template {path:?}")
+            }
+            Error::ValueMissing(key) => write!(formatter, "no value for key {key:?}"),
+            Error::ValueType(key) => {
+                write!(formatter, "the value for key {key:?} has another type")
+            }
             #[cfg(feature = "serde_json")]
             Error::Json(err) => write!(formatter, "json conversion error: {err}"),
+            #[cfg(feature = "runtime")]
+            Error::Runtime(msg) => write!(formatter, "{msg}"),
+            #[cfg(feature = "tokio")]
+            Error::Io(err) => write!(formatter, "I/O error: {err}"),
         }
     }
 }
@@ -78,11 +224,107 @@ impl From<Infallible> for Error {
     }
 }
 
+/// The error of `base ** exp` in a template if `base` is an integer, held by [`Error::Custom`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PowError {
+    /// `exp` was negative
+    NegativeExponent,
+    /// the result does not fit into the type of `base`
+    Overflow,
+}
+
+impl Display for PowError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            PowError::NegativeExponent => "an integer cannot be raised to a negative power",
+            PowError::Overflow => "integer power overflowed",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PowError {}
+
 #[cfg(test)]
 mod tests {
-    use super::Error;
+    use alloc::boxed::Box;
+    use core::fmt;
+
+    use super::{CustomError, Error};
 
     #[allow(dead_code)]
     trait AssertSendSyncStatic: Send + Sync + 'static {}
     impl AssertSendSyncStatic for Error {}
+
+    #[derive(Debug, PartialEq)]
+    enum TestError {
+        Own,
+        Rinja,
+    }
+
+    impl fmt::Display for TestError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("test error")
+        }
+    }
+
+    #[cfg(feature = "std")]
+    impl std::error::Error for TestError {}
+
+    impl From<Error> for TestError {
+        fn from(_: Error) -> Self {
+            TestError::Rinja
+        }
+    }
+
+    #[test]
+    fn test_into_custom() {
+        let err = Error::Custom(Box::new(TestError::Own));
+        assert_eq!(err.into_custom::<TestError>(), TestError::Own);
+        let err = Error::Custom(Box::new(fmt::Error));
+        assert_eq!(err.into_custom::<TestError>(), TestError::Rinja);
+        assert_eq!(Error::Fmt.into_custom::<TestError>(), TestError::Rinja);
+    }
+
+    #[test]
+    fn test_custom_downcast() {
+        let err: Box<dyn CustomError> = Box::new("msg");
+        assert!(err.is::<&str>());
+        assert_eq!(err.downcast_ref::<&str>(), Some(&"msg"));
+        assert!(err.downcast_ref::<fmt::Error>().is_none());
+        let err = err.downcast::<fmt::Error>().unwrap_err();
+        assert_eq!(*err.downcast::<&str>().unwrap(), "msg");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_custom_source() {
+        use alloc::string::ToString;
+        use std::error::Error as _;
+
+        #[derive(Debug)]
+        struct Outer(fmt::Error);
+
+        impl fmt::Display for Outer {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("outer")
+            }
+        }
+
+        impl std::error::Error for Outer {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let err = Error::Custom(Box::new(Outer(fmt::Error)));
+        let source = err.source().unwrap();
+        assert!(source.downcast_ref::<Outer>().is_some());
+        assert!(source.source().unwrap().downcast_ref::<fmt::Error>().is_some());
+
+        // `From` conversions into the payload work, too.
+        let err = Error::Custom("msg".into());
+        assert_eq!(err.source().unwrap().to_string(), "msg");
+    }
 }
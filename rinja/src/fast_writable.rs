@@ -0,0 +1,200 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::fmt;
+
+/// Types that can write themselves into the output without going through [`fmt::Display`]
+///
+/// Expressions in a template are written with [`FastWritable::write_into()`] if their type, after
+/// escaping, implements this trait, and with their `Display` implementation otherwise. Escaping
+/// still happens as usual: e.g. a `&str` that is rendered in an HTML template is passed through
+/// the HTML escaper straight into the output, without the detour through a [`fmt::Formatter`].
+///
+/// Implement it for your own types if they can produce their output cheaper than `Display` does.
+/// The output of both implementations must be the same.
+///
+/// ```rust
+/// # use std::fmt;
+/// # use rinja::FastWritable;
+/// struct Greeting;
+///
+/// impl fmt::Display for Greeting {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         f.write_str("Hello")
+///     }
+/// }
+///
+/// impl FastWritable for Greeting {
+///     fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+///         dest.write_str("Hello")
+///     }
+/// }
+///
+/// #[derive(rinja::Template)]
+/// #[template(source = "{{ greeting }}, world!", ext = "html")]
+/// struct Page {
+///     greeting: Greeting,
+/// }
+///
+/// assert_eq!(Page { greeting: Greeting }.to_string(), "Hello, world!");
+/// ```
+pub trait FastWritable {
+    /// Writes the value into `dest`
+    fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> fmt::Result;
+}
+
+macro_rules! impl_for_ref {
+    ($($ty:ty),* $(,)?) => { $(
+        impl<T: FastWritable + ?Sized> FastWritable for $ty {
+            #[inline]
+            fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+                T::write_into(self, dest)
+            }
+        }
+    )* };
+}
+
+impl_for_ref!(&T, &mut T, Box<T>, Rc<T>, Arc<T>);
+
+impl FastWritable for str {
+    #[inline]
+    fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+        dest.write_str(self)
+    }
+}
+
+impl FastWritable for String {
+    #[inline]
+    fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+        dest.write_str(self)
+    }
+}
+
+impl FastWritable for Cow<'_, str> {
+    #[inline]
+    fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+        dest.write_str(self)
+    }
+}
+
+impl FastWritable for char {
+    #[inline]
+    fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+        dest.write_char(*self)
+    }
+}
+
+impl FastWritable for bool {
+    #[inline]
+    fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+        dest.write_str(match self {
+            true => "true",
+            false => "false",
+        })
+    }
+}
+
+macro_rules! impl_for_int {
+    ($($ty:ty => $unsigned:ty),* $(,)?) => { $(
+        impl FastWritable for $ty {
+            #[allow(unused_comparisons)]
+            fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+                // Long enough for `i128::MIN`
+                let mut buf = [0u8; 40];
+                let mut pos = buf.len();
+                let negative = *self < 0;
+                let mut n = match negative {
+                    true => (*self as $unsigned).wrapping_neg(),
+                    false => *self as $unsigned,
+                };
+                loop {
+                    pos -= 1;
+                    buf[pos] = b'0' + (n % 10) as u8;
+                    n /= 10;
+                    if n == 0 {
+                        break;
+                    }
+                }
+                if negative {
+                    pos -= 1;
+                    buf[pos] = b'-';
+                }
+                // SAFETY: the buffer only contains ASCII digits and `-`
+                dest.write_str(unsafe { core::str::from_utf8_unchecked(&buf[pos..]) })
+            }
+        }
+    )* };
+}
+
+impl_for_int!(
+    u8 => u8, u16 => u16, u32 => u32, u64 => u64, u128 => u128, usize => usize,
+    i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize,
+);
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    fn fast<T: FastWritable + ?Sized>(value: &T) -> String {
+        let mut buf = String::new();
+        value.write_into(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_ints() {
+        for value in [0, 1, -1, 9, 10, -10, 1234567890, i64::MIN, i64::MAX] {
+            assert_eq!(fast(&value), value.to_string());
+        }
+        assert_eq!(fast(&u128::MAX), u128::MAX.to_string());
+        assert_eq!(fast(&i128::MIN), i128::MIN.to_string());
+        assert_eq!(fast(&i8::MIN), "-128");
+        assert_eq!(fast(&0u8), "0");
+    }
+
+    #[test]
+    fn test_prefers_fast_writable() {
+        use crate::helpers::{Writable, WriteWritable};
+
+        struct Both;
+
+        impl fmt::Display for Both {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("display")
+            }
+        }
+
+        impl FastWritable for Both {
+            fn write_into<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+                dest.write_str("fast")
+            }
+        }
+
+        struct OnlyDisplay;
+
+        impl fmt::Display for OnlyDisplay {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("display")
+            }
+        }
+
+        let mut buf = String::new();
+        (&&Writable(&Both)).rinja_write(&mut buf).unwrap();
+        buf.push(' ');
+        (&&Writable(&OnlyDisplay)).rinja_write(&mut buf).unwrap();
+        assert_eq!(buf, "fast display");
+    }
+
+    #[test]
+    fn test_others() {
+        assert_eq!(fast("a<b"), "a<b");
+        assert_eq!(fast(&Cow::Borrowed("x")), "x");
+        assert_eq!(fast(&Arc::new(String::from("y"))), "y");
+        assert_eq!(fast(&'ä'), "ä");
+        assert_eq!(fast(&true), "true");
+    }
+}
@@ -0,0 +1,305 @@
+use alloc::format;
+use alloc::string::String;
+use core::convert::Infallible;
+use core::fmt::Write;
+
+use crate::values::{get_value, Values};
+
+/// The key of the runtime value that selects the locale of the [`currency()`] filter
+pub const LOCALE_KEY: &str = "locale";
+
+/// Formats an amount of money, e.g. `{{ cents|currency("EUR") }}`
+///
+/// Integers are amounts in the minor unit of the currency, e.g. cents, floats are amounts in the
+/// major unit. The amount is rounded to the number of decimals of the currency, e.g. 2 for `"EUR"`
+/// and 0 for `"JPY"`. A currency code that is not known is used as its own symbol.
+///
+/// The separators and the position of the symbol depend on the locale, e.g. `"€1,234.50"` for
+/// `"en"`, `"1.234,50 €"` for `"de"`. The locale is the value `"locale"` (a `String` or a
+/// `&'static str`) that was passed to [`Template::render_with_values()`], or `"en"` if there is
+/// none.
+///
+/// [`Template::render_with_values()`]: crate::Template::render_with_values
+///
+/// ```
+/// # use rinja::Template;
+/// #[derive(Template)]
+/// #[template(source = r#"{{ cents|currency("EUR") }} {{ yen|currency("JPY") }}"#, ext = "html")]
+/// struct Price {
+///     cents: u64,
+///     yen: f64,
+/// }
+///
+/// let price = Price { cents: 123_450, yen: 980.4 };
+/// assert_eq!(price.to_string(), "€1,234.50 ¥980");
+/// let values = [("locale", "de-AT")];
+/// assert_eq!(
+///     price.render_with_values(&values).unwrap(),
+///     "1.234,50\u{a0}€ 980\u{a0}¥",
+/// );
+/// ```
+#[inline]
+pub fn currency(
+    value: impl CurrencyAmount,
+    code: &str,
+    values: &dyn Values,
+) -> Result<String, Infallible> {
+    let currency = Currency::new(code);
+    Ok(format_amount(value, currency, currency.decimals, values))
+}
+
+/// Formats an amount of money with `precision` decimals
+///
+/// See [`currency()`]. Integers are still amounts in the minor unit of the currency.
+#[inline]
+pub fn currency_precision(
+    value: impl CurrencyAmount,
+    code: &str,
+    precision: usize,
+    values: &dyn Values,
+) -> Result<String, Infallible> {
+    let currency = Currency::new(code);
+    // more decimals than `i128` can hold are meaningless
+    let precision = precision.min(MAX_PRECISION) as u32;
+    Ok(format_amount(value, currency, precision, values))
+}
+
+/// An amount of money that can be formatted by the [`currency()`] filter
+pub trait CurrencyAmount {
+    /// Returns the amount in units of `10^-precision` of the major unit, rounded half away from
+    /// zero
+    ///
+    /// `decimals` is the number of decimals of the currency, i.e. the size of its minor unit.
+    fn scaled_amount(&self, decimals: u32, precision: u32) -> i128;
+}
+
+impl<T: CurrencyAmount + ?Sized> CurrencyAmount for &T {
+    #[inline]
+    fn scaled_amount(&self, decimals: u32, precision: u32) -> i128 {
+        T::scaled_amount(self, decimals, precision)
+    }
+}
+
+macro_rules! impl_for_int {
+    ($($ty:ty)*) => { $(
+        impl CurrencyAmount for $ty {
+            fn scaled_amount(&self, decimals: u32, precision: u32) -> i128 {
+                let value = i128::try_from(*self).unwrap_or(i128::MAX);
+                match precision.checked_sub(decimals) {
+                    Some(more) => value.saturating_mul(pow10(more)),
+                    None => {
+                        let divisor = pow10(decimals - precision);
+                        let (quot, rem) = (value / divisor, value % divisor);
+                        match rem.unsigned_abs() * 2 >= divisor.unsigned_abs() {
+                            true => quot + value.signum(),
+                            false => quot,
+                        }
+                    }
+                }
+            }
+        }
+    )* };
+}
+
+impl_for_int!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+
+macro_rules! impl_for_float {
+    ($($ty:ty)*) => { $(
+        impl CurrencyAmount for $ty {
+            fn scaled_amount(&self, _: u32, precision: u32) -> i128 {
+                let value = f64::from(*self) * pow10(precision) as f64;
+                // `as` saturates, and there is no `f64::round()` in `core`
+                match value < 0.0 {
+                    true => (value - 0.5) as i128,
+                    false => (value + 0.5) as i128,
+                }
+            }
+        }
+    )* };
+}
+
+impl_for_float!(f32 f64);
+
+const MAX_PRECISION: usize = 30;
+
+fn pow10(exp: u32) -> i128 {
+    10_i128.checked_pow(exp).unwrap_or(i128::MAX)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Currency<'a> {
+    symbol: &'a str,
+    decimals: u32,
+}
+
+impl<'a> Currency<'a> {
+    fn new(code: &'a str) -> Self {
+        const CURRENCIES: &[(&str, &str, u32)] = &[
+            ("AUD", "A$", 2),
+            ("BRL", "R$", 2),
+            ("CAD", "CA$", 2),
+            ("CHF", "CHF", 2),
+            ("CNY", "CN¥", 2),
+            ("CZK", "Kč", 2),
+            ("DKK", "kr.", 2),
+            ("EUR", "€", 2),
+            ("GBP", "£", 2),
+            ("HKD", "HK$", 2),
+            ("HUF", "Ft", 2),
+            ("INR", "₹", 2),
+            ("JPY", "¥", 0),
+            ("KRW", "₩", 0),
+            ("MXN", "MX$", 2),
+            ("NOK", "kr", 2),
+            ("NZD", "NZ$", 2),
+            ("PLN", "zł", 2),
+            ("SEK", "kr", 2),
+            ("TRY", "₺", 2),
+            ("UAH", "₴", 2),
+            ("USD", "$", 2),
+        ];
+
+        match CURRENCIES
+            .iter()
+            .find(|(c, _, _)| c.eq_ignore_ascii_case(code))
+        {
+            Some(&(_, symbol, decimals)) => Self { symbol, decimals },
+            None => Self {
+                symbol: code,
+                decimals: 2,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Locale {
+    group: char,
+    decimal: char,
+    symbol_after: bool,
+}
+
+impl Locale {
+    fn from_values(values: &dyn Values) -> Self {
+        let locale = match get_value::<String>(values, LOCALE_KEY) {
+            Ok(locale) => locale.as_str(),
+            Err(_) => get_value::<&'static str>(values, LOCALE_KEY).map_or("en", |s| *s),
+        };
+        let language = locale.split(['-', '_']).next().unwrap_or_default();
+
+        const NBSP: char = '\u{a0}';
+        let (group, decimal, symbol_after) = match language.to_ascii_lowercase().as_str() {
+            "de" | "es" | "id" | "it" | "nl" | "pt" | "tr" | "da" | "el" | "hr" | "ro" => {
+                ('.', ',', true)
+            }
+            "cs" | "fi" | "fr" | "hu" | "nb" | "no" | "pl" | "ru" | "sk" | "sv" | "uk" => {
+                (NBSP, ',', true)
+            }
+            _ => (',', '.', false),
+        };
+        Self {
+            group,
+            decimal,
+            symbol_after,
+        }
+    }
+}
+
+fn format_amount(
+    value: impl CurrencyAmount,
+    currency: Currency<'_>,
+    precision: u32,
+    values: &dyn Values,
+) -> String {
+    let locale = Locale::from_values(values);
+    let amount = value.scaled_amount(currency.decimals, precision);
+
+    let mut digits = String::new();
+    let _ = write!(
+        digits,
+        "{:0>width$}",
+        amount.unsigned_abs(),
+        width = precision as usize + 1
+    );
+    let (int_part, frac_part) = digits.split_at(digits.len() - precision as usize);
+
+    let mut number = String::new();
+    for (i, c) in int_part.chars().enumerate() {
+        if i > 0 && (int_part.len() - i) % 3 == 0 {
+            number.push(locale.group);
+        }
+        number.push(c);
+    }
+    if !frac_part.is_empty() {
+        number.push(locale.decimal);
+        number.push_str(frac_part);
+    }
+
+    let sign = if amount < 0 { "-" } else { "" };
+    let symbol = currency.symbol;
+    if locale.symbol_after {
+        format!("{sign}{number}\u{a0}{symbol}")
+    } else if symbol.ends_with(|c: char| c.is_alphabetic()) {
+        format!("{sign}{symbol}\u{a0}{number}")
+    } else {
+        format!("{sign}{symbol}{number}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_currency() {
+        assert_eq!(currency(123_450_u64, "EUR", &()).unwrap(), "€1,234.50");
+        assert_eq!(currency(-5_i32, "usd", &()).unwrap(), "-$0.05");
+        assert_eq!(currency(0_u8, "USD", &()).unwrap(), "$0.00");
+        assert_eq!(currency(1_000_000_u32, "JPY", &()).unwrap(), "¥1,000,000");
+        let by_ref = &19.999_f64;
+        assert_eq!(currency(by_ref, "GBP", &()).unwrap(), "£20.00");
+        assert_eq!(currency(-1.005_f32, "GBP", &()).unwrap(), "-£1.00");
+        assert_eq!(currency(995_u32, "CHF", &()).unwrap(), "CHF\u{a0}9.95");
+        assert_eq!(currency(995_u32, "XYZ", &()).unwrap(), "XYZ\u{a0}9.95");
+
+        assert_eq!(
+            currency_precision(123_450_u64, "EUR", 0, &()).unwrap(),
+            "€1,235"
+        );
+        assert_eq!(
+            currency_precision(-150_i64, "EUR", 1, &()).unwrap(),
+            "-€1.5"
+        );
+        assert_eq!(
+            currency_precision(-155_i64, "EUR", 1, &()).unwrap(),
+            "-€1.6"
+        );
+        assert_eq!(currency_precision(5_u8, "JPY", 2, &()).unwrap(), "¥5.00");
+        assert_eq!(
+            currency_precision(2.5_f64, "USD", 4, &()).unwrap(),
+            "$2.5000"
+        );
+        assert!(
+            currency_precision(1_u8, "USD", usize::MAX, &())
+                .unwrap()
+                .len()
+                < 40
+        );
+
+        let values = [(LOCALE_KEY, String::from("de_DE"))];
+        assert_eq!(
+            currency(123_450_u64, "EUR", &values).unwrap(),
+            "1.234,50\u{a0}€"
+        );
+        let values = [(LOCALE_KEY, "fr")];
+        assert_eq!(
+            currency(-123_456_789_i64, "EUR", &values).unwrap(),
+            "-1\u{a0}234\u{a0}567,89\u{a0}€",
+        );
+        let values = [(LOCALE_KEY, "en-GB")];
+        assert_eq!(currency(123_450_u64, "EUR", &values).unwrap(), "€1,234.50");
+        // a locale of the wrong type is ignored
+        let values = [(LOCALE_KEY, 7_u8)];
+        assert_eq!(currency(123_450_u64, "EUR", &values).unwrap(), "€1,234.50");
+    }
+}
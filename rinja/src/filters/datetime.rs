@@ -0,0 +1,159 @@
+use alloc::string::String;
+
+use crate::{Error, Result};
+
+/// Formats a date and/or time value
+///
+/// The format string uses the `strftime` syntax, e.g. `"%Y-%m-%d %H:%M"`.
+/// The filter is implemented for the date and time types of the [`chrono`] crate
+/// (feature `"chrono"`) and the [`time`] crate (feature `"time"`).
+///
+/// [`chrono`]: https://docs.rs/chrono
+/// [`time`]: https://docs.rs/time
+///
+/// ```
+/// # #[cfg(feature = "chrono")]
+/// # {
+/// # use rinja::Template;
+/// #[derive(Template)]
+/// #[template(source = r#"<time>{{ created_at|date("%Y-%m-%d") }}</time>"#, ext = "html")]
+/// struct Post {
+///     created_at: chrono::NaiveDate,
+/// }
+///
+/// let created_at = chrono::NaiveDate::from_ymd_opt(2024, 7, 16).unwrap();
+/// assert_eq!(Post { created_at }.to_string(), "<time>2024-07-16</time>");
+/// # }
+/// ```
+#[inline]
+pub fn date<T: FormatDate>(value: T, format: &str) -> Result<String> {
+    value.format_date(format)
+}
+
+/// A date and/or time value that can be formatted by the [`date()`] filter
+pub trait FormatDate {
+    /// Formats the value according to the `strftime` style `format` string
+    fn format_date(&self, format: &str) -> Result<String>;
+}
+
+impl<T: FormatDate + ?Sized> FormatDate for &T {
+    #[inline]
+    fn format_date(&self, format: &str) -> Result<String> {
+        T::format_date(self, format)
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_impls {
+    use core::fmt;
+    use core::fmt::Write;
+
+    use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+
+    use super::*;
+
+    // `chrono` reports invalid format strings as `fmt::Error` when the value is displayed.
+    fn write_chrono(value: impl fmt::Display) -> Result<String> {
+        let mut dest = String::new();
+        write!(dest, "{value}").map_err(|_| Error::Fmt)?;
+        Ok(dest)
+    }
+
+    impl<Tz: TimeZone> FormatDate for DateTime<Tz>
+    where
+        Tz::Offset: fmt::Display,
+    {
+        fn format_date(&self, format: &str) -> Result<String> {
+            write_chrono(self.format(format))
+        }
+    }
+
+    impl FormatDate for NaiveDateTime {
+        fn format_date(&self, format: &str) -> Result<String> {
+            write_chrono(self.format(format))
+        }
+    }
+
+    impl FormatDate for NaiveDate {
+        fn format_date(&self, format: &str) -> Result<String> {
+            write_chrono(self.format(format))
+        }
+    }
+
+    impl FormatDate for NaiveTime {
+        fn format_date(&self, format: &str) -> Result<String> {
+            write_chrono(self.format(format))
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_impls {
+    use alloc::boxed::Box;
+
+    use time::format_description::parse_strftime_borrowed;
+    use time::{Date, OffsetDateTime, PrimitiveDateTime, Time};
+
+    use super::*;
+
+    macro_rules! impl_format_date {
+        ($($ty:ty)*) => { $(
+            impl FormatDate for $ty {
+                fn format_date(&self, format: &str) -> Result<String> {
+                    let items = parse_strftime_borrowed(format)
+                        .map_err(|err| Error::Custom(Box::new(err)))?;
+                    self.format(&items).map_err(|err| Error::Custom(Box::new(err)))
+                }
+            }
+        )* };
+    }
+
+    impl_format_date!(OffsetDateTime PrimitiveDateTime Date Time);
+}
+
+#[cfg(test)]
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_date_chrono() {
+        use chrono::{FixedOffset, NaiveDate, TimeZone};
+
+        let d = NaiveDate::from_ymd_opt(2024, 2, 9).unwrap();
+        assert_eq!(date(d, "%Y-%m-%d").unwrap(), "2024-02-09");
+        let by_ref = &d;
+        assert_eq!(date(by_ref, "%d.%m.%y").unwrap(), "09.02.24");
+
+        let dt = d.and_hms_opt(13, 5, 0).unwrap();
+        assert_eq!(date(dt, "%H:%M").unwrap(), "13:05");
+        let dt = FixedOffset::east_opt(3600)
+            .unwrap()
+            .from_local_datetime(&dt)
+            .unwrap();
+        assert_eq!(date(dt, "%F %T %:z").unwrap(), "2024-02-09 13:05:00 +01:00");
+
+        assert!(matches!(date(d, "%Q"), Err(Error::Fmt)));
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_date_time() {
+        use time::{Date, Month, Time, UtcOffset};
+
+        let d = Date::from_calendar_date(2024, Month::February, 9).unwrap();
+        assert_eq!(date(d, "%Y-%m-%d").unwrap(), "2024-02-09");
+        let by_ref = &d;
+        assert_eq!(date(by_ref, "%d.%m.%y").unwrap(), "09.02.24");
+
+        let dt = d.with_time(Time::from_hms(13, 5, 0).unwrap());
+        assert_eq!(date(dt, "%H:%M").unwrap(), "13:05");
+        let dt = dt.assume_offset(UtcOffset::from_hms(1, 0, 0).unwrap());
+        assert_eq!(date(dt, "%F %T %z").unwrap(), "2024-02-09 13:05:00 +0100");
+
+        assert!(matches!(date(d, "%Q"), Err(Error::Custom(_))));
+        // a date has no hour
+        assert!(matches!(date(d, "%H"), Err(Error::Custom(_))));
+    }
+}
@@ -0,0 +1,237 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+
+/// Substitutes a fallback for missing or empty values
+///
+/// `fallback` is used if `value` is `None`.
+/// If `check_empty` is `true`, then `fallback` is used for empty strings and
+/// empty collections, too, also if they are wrapped in `Some`. In a template, `check_empty` is an optional second argument,
+/// which defaults to `false`.
+///
+/// ```
+/// # use rinja::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{{ nickname|default("anonymous") }} {{ bio|default("-", true) }}"#,
+///     ext = "txt"
+/// )]
+/// struct Example<'a> {
+///     nickname: Option<&'a str>,
+///     bio: &'a str,
+/// }
+///
+/// assert_eq!(Example { nickname: None, bio: "" }.to_string(), "anonymous -");
+/// assert_eq!(Example { nickname: Some("rinja"), bio: "hi" }.to_string(), "rinja hi");
+/// ```
+#[inline]
+pub fn default<T, U>(
+    value: &T,
+    fallback: U,
+    check_empty: bool,
+) -> Result<Defaulted<&T::Value, U>, Infallible>
+where
+    T: DefaultFilterable + ?Sized,
+{
+    Ok(match value.default_value(check_empty) {
+        Some(value) => Defaulted::Value(value),
+        None => Defaulted::Fallback(fallback),
+    })
+}
+
+/// A value that can be replaced by the [`default()`] filter
+pub trait DefaultFilterable {
+    /// The type of the value that is used if no fallback is needed
+    type Value: ?Sized;
+
+    /// Returns `None` if the value is missing, or if `check_empty` is set and the value is empty
+    fn default_value(&self, check_empty: bool) -> Option<&Self::Value>;
+}
+
+impl<T: DefaultFilterable> DefaultFilterable for Option<T> {
+    type Value = T::Value;
+
+    #[inline]
+    fn default_value(&self, check_empty: bool) -> Option<&T::Value> {
+        self.as_ref()?.default_value(check_empty)
+    }
+}
+
+macro_rules! impl_for_scalar {
+    ($($ty:ty)*) => { $(
+        impl DefaultFilterable for $ty {
+            type Value = Self;
+
+            #[inline]
+            fn default_value(&self, _: bool) -> Option<&Self> {
+                Some(self)
+            }
+        }
+    )* };
+}
+
+impl_for_scalar!(bool char f32 f64 i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+
+macro_rules! impl_for_collection {
+    ($([$($generics:tt)*] $ty:ty;)*) => { $(
+        impl<$($generics)*> DefaultFilterable for $ty {
+            type Value = Self;
+
+            #[inline]
+            fn default_value(&self, check_empty: bool) -> Option<&Self> {
+                match check_empty && self.is_empty() {
+                    true => None,
+                    false => Some(self),
+                }
+            }
+        }
+    )* };
+}
+
+impl_for_collection! {
+    [] str;
+    [] String;
+    [] Cow<'_, str>;
+    [T] [T];
+    [T, const N: usize] [T; N];
+    [T] Vec<T>;
+    [T] VecDeque<T>;
+    [K, V] BTreeMap<K, V>;
+    [T] BTreeSet<T>;
+}
+
+#[cfg(feature = "std")]
+impl_for_collection! {
+    [K, V, S] HashMap<K, V, S>;
+    [T, S] HashSet<T, S>;
+}
+
+macro_rules! impl_for_ref {
+    ($($ty:ty)*) => { $(
+        impl<T: DefaultFilterable + ?Sized> DefaultFilterable for $ty {
+            type Value = T::Value;
+
+            #[inline]
+            fn default_value(&self, check_empty: bool) -> Option<&T::Value> {
+                T::default_value(self, check_empty)
+            }
+        }
+    )* };
+}
+
+impl_for_ref!(&T &mut T Box<T> Rc<T> Arc<T>);
+
+/// The result of the [`default()`] filter: either the input value, or the fallback
+///
+/// It implements [`Display`][fmt::Display] if both variants do,
+/// and [`IntoIterator`] if both variants yield the same items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Defaulted<V, F> {
+    /// The input value was present
+    Value(V),
+    /// The input value was missing, so the fallback is used
+    Fallback(F),
+}
+
+impl<V: fmt::Display, F: fmt::Display> fmt::Display for Defaulted<V, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Value(value) => value.fmt(f),
+            Self::Fallback(fallback) => fallback.fmt(f),
+        }
+    }
+}
+
+impl<V, F> IntoIterator for Defaulted<V, F>
+where
+    V: IntoIterator,
+    F: IntoIterator<Item = V::Item>,
+{
+    type Item = V::Item;
+    type IntoIter = DefaultedIter<V::IntoIter, F::IntoIter>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        DefaultedIter(match self {
+            Self::Value(value) => Defaulted::Value(value.into_iter()),
+            Self::Fallback(fallback) => Defaulted::Fallback(fallback.into_iter()),
+        })
+    }
+}
+
+/// The iterator returned by [`Defaulted::into_iter()`]
+#[derive(Debug, Clone)]
+pub struct DefaultedIter<V, F>(Defaulted<V, F>);
+
+impl<V, F> Iterator for DefaultedIter<V, F>
+where
+    V: Iterator,
+    F: Iterator<Item = V::Item>,
+{
+    type Item = V::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            Defaulted::Value(value) => value.next(),
+            Defaulted::Fallback(fallback) => fallback.next(),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            Defaulted::Value(value) => value.size_hint(),
+            Defaulted::Fallback(fallback) => fallback.size_hint(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_default() {
+        let some = Some("value");
+        let none = None::<&str>;
+        assert_eq!(default(&some, "fb", false).unwrap().to_string(), "value");
+        assert_eq!(default(&none, "fb", false).unwrap().to_string(), "fb");
+        assert_eq!(default(&none, "fb", true).unwrap().to_string(), "fb");
+        assert_eq!(default(&Some(""), "fb", false).unwrap().to_string(), "");
+        assert_eq!(default(&Some(""), "fb", true).unwrap().to_string(), "fb");
+        assert_eq!(default(&Some(0), "fb", true).unwrap().to_string(), "0");
+
+        assert_eq!(default("", "fb", false).unwrap().to_string(), "");
+        assert_eq!(default("", "fb", true).unwrap().to_string(), "fb");
+        assert_eq!(
+            default(&&String::new(), "fb", true).unwrap().to_string(),
+            "fb"
+        );
+        assert_eq!(
+            default(&Box::new("x"), "fb", true).unwrap().to_string(),
+            "x"
+        );
+    }
+
+    #[test]
+    fn test_default_iter() {
+        let empty = Vec::<u32>::new();
+        let full = vec![1, 2];
+        let collect = |d: Defaulted<_, _>| d.into_iter().copied().collect::<Vec<u32>>();
+        assert_eq!(collect(default(&empty, &[3], true).unwrap()), [3]);
+        assert!(collect(default(&empty, &[3], false).unwrap()).is_empty());
+        assert_eq!(collect(default(&full, &[3], true).unwrap()), [1, 2]);
+    }
+}
@@ -1,7 +1,12 @@
-use std::convert::Infallible;
-use std::fmt::{self, Display, Formatter, Write};
-use std::num::NonZeroU8;
-use std::str;
+use alloc::boxed::Box;
+#[cfg(test)]
+use alloc::string::ToString;
+use core::convert::Infallible;
+use core::fmt::{self, Display, Formatter, Write};
+use core::num::NonZeroU8;
+use core::{mem, str};
+
+use crate::FastWritable;
 
 /// Marks a string (or other `Display` type) as safe
 ///
@@ -41,21 +46,28 @@ pub struct EscapeDisplay<T, E>(T, E);
 impl<T: fmt::Display, E: Escaper> fmt::Display for EscapeDisplay<T, E> {
     #[inline]
     fn fmt(&self, fmt: &mut Formatter<'_>) -> fmt::Result {
-        struct EscapeWriter<W, E>(W, E);
+        write!(EscapeWriter(fmt, self.1), "{}", &self.0)
+    }
+}
 
-        impl<W: Write, E: Escaper> Write for EscapeWriter<W, E> {
-            #[inline]
-            fn write_str(&mut self, s: &str) -> fmt::Result {
-                self.1.write_escaped_str(&mut self.0, s)
-            }
+impl<T: FastWritable, E: Escaper> FastWritable for EscapeDisplay<T, E> {
+    #[inline]
+    fn write_into<W: Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+        self.0.write_into(&mut EscapeWriter(dest, self.1))
+    }
+}
 
-            #[inline]
-            fn write_char(&mut self, c: char) -> fmt::Result {
-                self.1.write_escaped_char(&mut self.0, c)
-            }
-        }
+struct EscapeWriter<W, E>(W, E);
 
-        write!(EscapeWriter(fmt, self.1), "{}", &self.0)
+impl<W: Write, E: Escaper> Write for EscapeWriter<W, E> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.1.write_escaped_str(&mut self.0, s)
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.1.write_escaped_char(&mut self.0, c)
     }
 }
 
@@ -77,55 +89,32 @@ pub struct Html;
 
 impl Escaper for Html {
     fn write_escaped_str<W: Write>(&self, mut fmt: W, string: &str) -> fmt::Result {
+        let bytes = string.as_bytes();
         let mut escaped_buf = *b"&#__;";
         let mut last = 0;
-
-        for (index, byte) in string.bytes().enumerate() {
-            const MIN_CHAR: u8 = b'"';
-            const MAX_CHAR: u8 = b'>';
-
-            struct Table {
-                _align: [usize; 0],
-                lookup: [Option<[NonZeroU8; 2]>; (MAX_CHAR - MIN_CHAR + 1) as usize],
-            }
-
-            const TABLE: Table = {
-                const fn n(c: u8) -> Option<[NonZeroU8; 2]> {
-                    let n0 = match NonZeroU8::new(c / 10 + b'0') {
-                        Some(n) => n,
-                        None => panic!(),
-                    };
-                    let n1 = match NonZeroU8::new(c % 10 + b'0') {
-                        Some(n) => n,
-                        None => panic!(),
-                    };
-                    Some([n0, n1])
+        let mut index = 0;
+
+        // Clean runs are skipped a whole word at a time, and only words that contain an
+        // escapable byte are looked at byte by byte.
+        while index < bytes.len() {
+            let end = match bytes.get(index..index + WORD_SIZE) {
+                Some(word) if !html_word_needs_escaping(word) => {
+                    index += WORD_SIZE;
+                    continue;
                 }
-
-                let mut table = Table {
-                    _align: [],
-                    lookup: [None; (MAX_CHAR - MIN_CHAR + 1) as usize],
-                };
-
-                table.lookup[(b'"' - MIN_CHAR) as usize] = n(b'"');
-                table.lookup[(b'&' - MIN_CHAR) as usize] = n(b'&');
-                table.lookup[(b'\'' - MIN_CHAR) as usize] = n(b'\'');
-                table.lookup[(b'<' - MIN_CHAR) as usize] = n(b'<');
-                table.lookup[(b'>' - MIN_CHAR) as usize] = n(b'>');
-                table
+                Some(_) => index + WORD_SIZE,
+                None => bytes.len(),
             };
-
-            let escaped = match byte {
-                MIN_CHAR..=MAX_CHAR => TABLE.lookup[(byte - MIN_CHAR) as usize],
-                _ => None,
-            };
-            if let Some(escaped) = escaped {
-                escaped_buf[2] = escaped[0].get();
-                escaped_buf[3] = escaped[1].get();
-                fmt.write_str(&string[last..index])?;
-                fmt.write_str(unsafe { std::str::from_utf8_unchecked(escaped_buf.as_slice()) })?;
-                last = index + 1;
+            for (index, &byte) in bytes.iter().enumerate().take(end).skip(index) {
+                if let Some(escaped) = html_escaped_byte(byte) {
+                    escaped_buf[2] = escaped[0].get();
+                    escaped_buf[3] = escaped[1].get();
+                    fmt.write_str(&string[last..index])?;
+                    fmt.write_str(unsafe { str::from_utf8_unchecked(escaped_buf.as_slice()) })?;
+                    last = index + 1;
+                }
             }
+            index = end;
         }
         fmt.write_str(&string[last..])
     }
@@ -142,6 +131,236 @@ impl Escaper for Html {
     }
 }
 
+const WORD_SIZE: usize = mem::size_of::<usize>();
+
+/// Tells if any byte of the `WORD_SIZE` long `word` needs to be escaped by [`Html`]
+#[inline]
+fn html_word_needs_escaping(word: &[u8]) -> bool {
+    const fn splat(byte: u8) -> usize {
+        usize::from_ne_bytes([byte; WORD_SIZE])
+    }
+
+    // `has_zero(x)` is non-zero iff any byte of `x` is zero, so `has_zero(word ^ splat(c))` is
+    // non-zero iff any byte of `word` is `c`.
+    #[inline]
+    fn has_zero(x: usize) -> usize {
+        x.wrapping_sub(splat(0x01)) & !x & splat(0x80)
+    }
+
+    let word = usize::from_ne_bytes(word.try_into().unwrap());
+    (has_zero(word ^ splat(b'"'))
+        | has_zero(word ^ splat(b'&'))
+        | has_zero(word ^ splat(b'\''))
+        | has_zero(word ^ splat(b'<'))
+        | has_zero(word ^ splat(b'>')))
+        != 0
+}
+
+/// The two decimal digits of the HTML entity `&#__;` of `byte`, if it needs to be escaped
+#[inline]
+fn html_escaped_byte(byte: u8) -> Option<[NonZeroU8; 2]> {
+    const MIN_CHAR: u8 = b'"';
+    const MAX_CHAR: u8 = b'>';
+
+    struct Table {
+        _align: [usize; 0],
+        lookup: [Option<[NonZeroU8; 2]>; (MAX_CHAR - MIN_CHAR + 1) as usize],
+    }
+
+    const TABLE: Table = {
+        const fn n(c: u8) -> Option<[NonZeroU8; 2]> {
+            let n0 = match NonZeroU8::new(c / 10 + b'0') {
+                Some(n) => n,
+                None => panic!(),
+            };
+            let n1 = match NonZeroU8::new(c % 10 + b'0') {
+                Some(n) => n,
+                None => panic!(),
+            };
+            Some([n0, n1])
+        }
+
+        let mut table = Table {
+            _align: [],
+            lookup: [None; (MAX_CHAR - MIN_CHAR + 1) as usize],
+        };
+
+        table.lookup[(b'"' - MIN_CHAR) as usize] = n(b'"');
+        table.lookup[(b'&' - MIN_CHAR) as usize] = n(b'&');
+        table.lookup[(b'\'' - MIN_CHAR) as usize] = n(b'\'');
+        table.lookup[(b'<' - MIN_CHAR) as usize] = n(b'<');
+        table.lookup[(b'>' - MIN_CHAR) as usize] = n(b'>');
+        table
+    };
+
+    match byte {
+        MIN_CHAR..=MAX_CHAR => TABLE.lookup[(byte - MIN_CHAR) as usize],
+        _ => None,
+    }
+}
+
+/// Escape characters in a safe way for HTML attribute values, even if they are not quoted
+///
+/// All ASCII characters except for letters, digits, `,`, `-`, `.` and `_` are replaced by their
+/// decimal HTML entity, e.g. ` ` => `&#32;`, `"` => `&#34;` and `=` => `&#61;`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HtmlAttr;
+
+impl Escaper for HtmlAttr {
+    fn write_escaped_str<W: Write>(&self, mut fmt: W, string: &str) -> fmt::Result {
+        let mut last = 0;
+        for (index, byte) in string.bytes().enumerate() {
+            if attr_needs_escaping(byte) {
+                fmt.write_str(&string[last..index])?;
+                write!(fmt, "&#{byte};")?;
+                last = index + 1;
+            }
+        }
+        fmt.write_str(&string[last..])
+    }
+
+    fn write_escaped_char<W: Write>(&self, mut fmt: W, c: char) -> fmt::Result {
+        match c.is_ascii() && attr_needs_escaping(c as u8) {
+            true => write!(fmt, "&#{};", c as u8),
+            false => fmt.write_char(c),
+        }
+    }
+}
+
+#[inline]
+fn attr_needs_escaping(byte: u8) -> bool {
+    byte.is_ascii()
+        && !matches!(byte, b'0'..=b'9' | b'A'..=b'Z' | b'a'..=b'z' | b',' | b'-' | b'.' | b'_')
+}
+
+/// Escape characters that would end or break a string literal in a JSON document
+///
+/// The output is meant to be used inside of a string literal, e.g. `"name": "{{ name }}"`.
+///
+/// * `"` => `\"`
+/// * `\` => `\\`
+/// * control characters => `\n`, `\r`, `\t`, `\b`, `\f`, or `\u00XX`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl Escaper for Json {
+    fn write_escaped_str<W: Write>(&self, mut fmt: W, string: &str) -> fmt::Result {
+        let mut last = 0;
+        for (index, byte) in string.bytes().enumerate() {
+            if let Some(escaped) = json_escape_byte(byte) {
+                fmt.write_str(&string[last..index])?;
+                escaped.write_to(&mut fmt)?;
+                last = index + 1;
+            }
+        }
+        fmt.write_str(&string[last..])
+    }
+
+    fn write_escaped_char<W: Write>(&self, mut fmt: W, c: char) -> fmt::Result {
+        match c.is_ascii().then(|| json_escape_byte(c as u8)).flatten() {
+            Some(escaped) => escaped.write_to(&mut fmt),
+            None => fmt.write_char(c),
+        }
+    }
+}
+
+enum JsonEscaped {
+    Short(&'static str),
+    Unicode(u8),
+}
+
+impl JsonEscaped {
+    fn write_to<W: Write>(&self, mut fmt: W) -> fmt::Result {
+        match *self {
+            Self::Short(s) => fmt.write_str(s),
+            Self::Unicode(byte) => write!(fmt, "\\u{byte:04x}"),
+        }
+    }
+}
+
+#[inline]
+fn json_escape_byte(byte: u8) -> Option<JsonEscaped> {
+    Some(JsonEscaped::Short(match byte {
+        b'"' => "\\\"",
+        b'\\' => "\\\\",
+        b'\n' => "\\n",
+        b'\r' => "\\r",
+        b'\t' => "\\t",
+        0x08 => "\\b",
+        0x0c => "\\f",
+        0..=0x1f => return Some(JsonEscaped::Unicode(byte)),
+        _ => return None,
+    }))
+}
+
+/// Escape characters in a safe way for string literals in JavaScript, e.g. in an inline `<script>`
+///
+/// All ASCII characters except for letters, digits, ` `, `,`, `.` and `_` are replaced by a
+/// hexadecimal escape sequence, e.g. `'` => `\x27` and `<` => `\x3c`, so the value can neither
+/// end the string literal, nor the `<script>` element. The line terminators U+2028 and U+2029
+/// are replaced by `\u2028` and `\u2029`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Js;
+
+impl Escaper for Js {
+    fn write_escaped_str<W: Write>(&self, mut fmt: W, string: &str) -> fmt::Result {
+        let mut last = 0;
+        for (index, c) in string.char_indices() {
+            if js_needs_escaping(c) {
+                fmt.write_str(&string[last..index])?;
+                write_js_escaped(&mut fmt, c)?;
+                last = index + c.len_utf8();
+            }
+        }
+        fmt.write_str(&string[last..])
+    }
+
+    fn write_escaped_char<W: Write>(&self, mut fmt: W, c: char) -> fmt::Result {
+        match js_needs_escaping(c) {
+            true => write_js_escaped(fmt, c),
+            false => fmt.write_char(c),
+        }
+    }
+}
+
+#[inline]
+fn js_needs_escaping(c: char) -> bool {
+    match c {
+        '0'..='9' | 'A'..='Z' | 'a'..='z' | ' ' | ',' | '.' | '_' => false,
+        '\u{2028}' | '\u{2029}' => true,
+        c => c.is_ascii(),
+    }
+}
+
+fn write_js_escaped<W: Write>(mut fmt: W, c: char) -> fmt::Result {
+    match c.is_ascii() {
+        true => write!(fmt, "\\x{:02x}", c as u8),
+        false => write!(fmt, "\\u{:04x}", c as u32),
+    }
+}
+
+/// Quote the input as a single argument of a POSIX shell command
+///
+/// The value is wrapped in single quotes, and every `'` is replaced by `'\''`,
+/// e.g. `it's` => `'it'\''s'`.
+/// If a value is written in several pieces, then every piece is quoted on its own.
+/// The shell concatenates adjacent quoted strings, so the result is still a single argument.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Shell;
+
+impl Escaper for Shell {
+    fn write_escaped_str<W: Write>(&self, mut fmt: W, string: &str) -> fmt::Result {
+        fmt.write_char('\'')?;
+        for (index, part) in string.split('\'').enumerate() {
+            if index > 0 {
+                fmt.write_str(r"'\''")?;
+            }
+            fmt.write_str(part)?;
+        }
+        fmt.write_char('\'')
+    }
+}
+
 /// Don't escape the input but return in verbatim
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Text;
@@ -369,6 +588,13 @@ const _: () = {
         }
     }
 
+    impl<T: fmt::Display + FastWritable> FastWritable for Safe<T> {
+        #[inline]
+        fn write_into<W: Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+            self.0.write_into(dest)
+        }
+    }
+
     macro_rules! add_ref {
         ($([$($tt:tt)*])*) => { $(
             impl<'a, T: fmt::Display, E: Escaper> AutoEscape
@@ -397,6 +623,13 @@ impl<T: fmt::Display> fmt::Display for Unsafe<T> {
     }
 }
 
+impl<T: fmt::Display + FastWritable> FastWritable for Unsafe<T> {
+    #[inline]
+    fn write_into<W: Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+        self.0.write_into(dest)
+    }
+}
+
 /// Like [`Safe`], but only for HTML output
 pub struct HtmlSafeOutput<T: fmt::Display>(pub T);
 
@@ -407,6 +640,13 @@ impl<T: fmt::Display> fmt::Display for HtmlSafeOutput<T> {
     }
 }
 
+impl<T: fmt::Display + FastWritable> FastWritable for HtmlSafeOutput<T> {
+    #[inline]
+    fn write_into<W: Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+        self.0.write_into(dest)
+    }
+}
+
 macro_rules! mark_html_safe {
     ($($ty:ty),* $(,)?) => {$(
         impl HtmlSafe for $ty {}
@@ -418,26 +658,29 @@ mark_html_safe! {
     f32, f64,
     i8, i16, i32, i64, i128, isize,
     u8, u16, u32, u64, u128, usize,
-    std::num::NonZeroI8, std::num::NonZeroI16, std::num::NonZeroI32,
-    std::num::NonZeroI64, std::num::NonZeroI128, std::num::NonZeroIsize,
-    std::num::NonZeroU8, std::num::NonZeroU16, std::num::NonZeroU32,
-    std::num::NonZeroU64, std::num::NonZeroU128, std::num::NonZeroUsize,
+    core::num::NonZeroI8, core::num::NonZeroI16, core::num::NonZeroI32,
+    core::num::NonZeroI64, core::num::NonZeroI128, core::num::NonZeroIsize,
+    core::num::NonZeroU8, core::num::NonZeroU16, core::num::NonZeroU32,
+    core::num::NonZeroU64, core::num::NonZeroU128, core::num::NonZeroUsize,
 }
 
 impl<T: HtmlSafe + ?Sized> HtmlSafe for Box<T> {}
-impl<T: HtmlSafe + ?Sized> HtmlSafe for std::cell::Ref<'_, T> {}
-impl<T: HtmlSafe + ?Sized> HtmlSafe for std::cell::RefMut<'_, T> {}
-impl<T: HtmlSafe + ?Sized> HtmlSafe for std::rc::Rc<T> {}
-impl<T: HtmlSafe + ?Sized> HtmlSafe for std::sync::Arc<T> {}
+impl<T: HtmlSafe + ?Sized> HtmlSafe for core::cell::Ref<'_, T> {}
+impl<T: HtmlSafe + ?Sized> HtmlSafe for core::cell::RefMut<'_, T> {}
+impl<T: HtmlSafe + ?Sized> HtmlSafe for alloc::rc::Rc<T> {}
+impl<T: HtmlSafe + ?Sized> HtmlSafe for alloc::sync::Arc<T> {}
+#[cfg(feature = "std")]
 impl<T: HtmlSafe + ?Sized> HtmlSafe for std::sync::MutexGuard<'_, T> {}
+#[cfg(feature = "std")]
 impl<T: HtmlSafe + ?Sized> HtmlSafe for std::sync::RwLockReadGuard<'_, T> {}
+#[cfg(feature = "std")]
 impl<T: HtmlSafe + ?Sized> HtmlSafe for std::sync::RwLockWriteGuard<'_, T> {}
-impl<T: HtmlSafe> HtmlSafe for std::num::Wrapping<T> {}
+impl<T: HtmlSafe> HtmlSafe for core::num::Wrapping<T> {}
 impl<T: fmt::Display> HtmlSafe for HtmlSafeOutput<T> {}
 
-impl<T> HtmlSafe for std::borrow::Cow<'_, T>
+impl<T> HtmlSafe for alloc::borrow::Cow<'_, T>
 where
-    T: HtmlSafe + std::borrow::ToOwned + ?Sized,
+    T: HtmlSafe + alloc::borrow::ToOwned + ?Sized,
     T::Owned: HtmlSafe,
 {
 }
@@ -449,6 +692,36 @@ fn test_escape() {
     assert_eq!(escape("bla&", Html).unwrap().to_string(), "bla&#38;");
     assert_eq!(escape("<foo", Html).unwrap().to_string(), "&#60;foo");
     assert_eq!(escape("bla&h", Html).unwrap().to_string(), "bla&#38;h");
+    assert_eq!(
+        escape("a long text without anything to escape", Html)
+            .unwrap()
+            .to_string(),
+        "a long text without anything to escape",
+    );
+    assert_eq!(
+        escape("größer als > kleiner als <", Html)
+            .unwrap()
+            .to_string(),
+        "größer als &#62; kleiner als &#60;",
+    );
+    // Escapable bytes at every position of a word, and in the remainder after the last word
+    for len in 0..40 {
+        for pos in 0..len {
+            for (c, entity) in [
+                ('"', "&#34;"),
+                ('&', "&#38;"),
+                ('\'', "&#39;"),
+                ('<', "&#60;"),
+                ('>', "&#62;"),
+            ] {
+                let mut input = "a".repeat(len);
+                let mut expected = input.clone();
+                input.replace_range(pos..=pos, &c.to_string());
+                expected.replace_range(pos..=pos, entity);
+                assert_eq!(escape(&input, Html).unwrap().to_string(), expected);
+            }
+        }
+    }
 
     assert_eq!(escape("", Text).unwrap().to_string(), "");
     assert_eq!(escape("<&>", Text).unwrap().to_string(), "<&>");
@@ -457,6 +730,81 @@ fn test_escape() {
     assert_eq!(escape("bla&h", Text).unwrap().to_string(), "bla&h");
 }
 
+#[test]
+fn test_escape_html_attr() {
+    assert_eq!(escape("", HtmlAttr).unwrap().to_string(), "");
+    assert_eq!(
+        escape("my-class_1.a,b", HtmlAttr).unwrap().to_string(),
+        "my-class_1.a,b"
+    );
+    assert_eq!(
+        escape("x onclick=alert(1)", HtmlAttr).unwrap().to_string(),
+        "x&#32;onclick&#61;alert&#40;1&#41;"
+    );
+    assert_eq!(
+        escape("\"'`<>&äö", HtmlAttr).unwrap().to_string(),
+        "&#34;&#39;&#96;&#60;&#62;&#38;äö"
+    );
+    assert_eq!(escape('=', HtmlAttr).unwrap().to_string(), "&#61;");
+    assert_eq!(escape('ä', HtmlAttr).unwrap().to_string(), "ä");
+}
+
+#[test]
+fn test_escape_js() {
+    assert_eq!(escape("", Js).unwrap().to_string(), "");
+    assert_eq!(
+        escape("Hello, world.", Js).unwrap().to_string(),
+        "Hello, world."
+    );
+    assert_eq!(
+        escape("</script><script>alert('x')", Js)
+            .unwrap()
+            .to_string(),
+        r"\x3c\x2fscript\x3e\x3cscript\x3ealert\x28\x27x\x27\x29"
+    );
+    assert_eq!(
+        escape("\"\\`${}\n&ä\u{2028}\u{2029}", Js)
+            .unwrap()
+            .to_string(),
+        r"\x22\x5c\x60\x24\x7b\x7d\x0a\x26ä\u2028\u2029"
+    );
+    assert_eq!(escape('\'', Js).unwrap().to_string(), r"\x27");
+    assert_eq!(escape('\u{2028}', Js).unwrap().to_string(), r"\u2028");
+}
+
+#[test]
+fn test_escape_json() {
+    assert_eq!(escape("", Json).unwrap().to_string(), "");
+    assert_eq!(escape("<&>'", Json).unwrap().to_string(), "<&>'");
+    assert_eq!(
+        escape("say \"hi\"\\", Json).unwrap().to_string(),
+        r#"say \"hi\"\\"#
+    );
+    assert_eq!(
+        escape("a\nb\r\tc\x08\x0c\x00\x1fä", Json)
+            .unwrap()
+            .to_string(),
+        r"a\nb\r\tc\b\f\u0000\u001fä"
+    );
+    assert_eq!(escape('"', Json).unwrap().to_string(), r#"\""#);
+    assert_eq!(escape('\x01', Json).unwrap().to_string(), r"\u0001");
+}
+
+#[test]
+fn test_escape_shell() {
+    assert_eq!(escape("", Shell).unwrap().to_string(), "''");
+    assert_eq!(escape("a b", Shell).unwrap().to_string(), "'a b'");
+    assert_eq!(
+        escape("$(rm -rf ~); `x` \"y\" \\", Shell)
+            .unwrap()
+            .to_string(),
+        r#"'$(rm -rf ~); `x` "y" \'"#
+    );
+    assert_eq!(escape("it's", Shell).unwrap().to_string(), r"'it'\''s'");
+    assert_eq!(escape("''", Shell).unwrap().to_string(), r"''\'''\'''");
+    assert_eq!(escape('\'', Shell).unwrap().to_string(), r"''\'''");
+}
+
 #[test]
 fn test_html_safe_marker() {
     struct Script1;
@@ -1,4 +1,7 @@
+use std::borrow::ToOwned;
+use std::boxed::Box;
 use std::convert::Infallible;
+use std::string::String;
 use std::{fmt, io, str};
 
 use serde::Serialize;
@@ -177,6 +180,9 @@ fn write(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
 
 #[cfg(test)]
 mod tests {
+    use std::string::ToString;
+    use std::vec;
+
     use super::*;
 
     #[test]
@@ -0,0 +1,137 @@
+use std::fmt;
+use std::string::String;
+use std::vec::Vec;
+
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+
+use super::{try_to_string, HtmlSafeOutput};
+
+/// Renders a CommonMark (markdown) text to HTML
+///
+/// HTML in the input is not passed through, but escaped. Links and images are only kept if their
+/// URL is relative, or uses the scheme `http`, `https` or `mailto`, otherwise only their text is
+/// rendered. So the filter can be used on untrusted input. The output is marked as safe, so it
+/// does not get escaped again.
+///
+/// ```
+/// # #[cfg(feature = "markdown")]
+/// # {
+/// # use rinja::Template;
+/// #[derive(Template)]
+/// #[template(source = "<div>{{ text|markdown }}</div>", ext = "html")]
+/// struct Article<'a> {
+///     text: &'a str,
+/// }
+///
+/// let text = "# Hello\n\nThis is *important* <script>alert(1)</script>";
+/// assert_eq!(
+///     Article { text }.to_string(),
+///     "<div><h1>Hello</h1>\n\
+///     <p>This is <em>important</em> &lt;script&gt;alert(1)&lt;/script&gt;</p>\n\
+///     </div>",
+/// );
+/// # }
+/// ```
+#[inline]
+pub fn markdown(s: impl fmt::Display) -> Result<HtmlSafeOutput<impl fmt::Display>, fmt::Error> {
+    fn markdown(s: &str) -> String {
+        // whether the enclosing links and images are rendered
+        let mut links = Vec::new();
+        let parser = Parser::new_ext(s, Options::empty()).filter_map(|event| match event {
+            Event::Html(html) | Event::InlineHtml(html) => Some(Event::Text(html)),
+            Event::Start(Tag::Link { ref dest_url, .. } | Tag::Image { ref dest_url, .. }) => {
+                let keep = is_safe_url(dest_url);
+                links.push(keep);
+                keep.then_some(event)
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                links.pop().unwrap_or(true).then_some(event)
+            }
+            event => Some(event),
+        });
+        let mut output = String::with_capacity(s.len() + s.len() / 4);
+        html::push_html(&mut output, parser);
+        output
+    }
+    Ok(HtmlSafeOutput(markdown(&try_to_string(s)?)))
+}
+
+/// Only relative URLs, and URLs with the scheme `http`, `https` or `mailto` are safe
+fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore leading spaces and control characters, and tabs and newlines anywhere.
+    let url = url.trim_start_matches(|c: char| c <= ' ');
+    let Some(colon) = url.find(':') else {
+        return true;
+    };
+    let scheme = &url[..colon];
+    if scheme.contains(['/', '?', '#']) {
+        // the colon is part of the path, query or fragment of a relative URL
+        return true;
+    }
+    let scheme: String = scheme
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+    ["http", "https", "mailto"]
+        .iter()
+        .any(|safe| scheme.eq_ignore_ascii_case(safe))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn test_markdown() {
+        assert_eq!(markdown("").unwrap().to_string(), "");
+        assert_eq!(
+            markdown("*a* & **b**").unwrap().to_string(),
+            "<p><em>a</em> &amp; <strong>b</strong></p>\n",
+        );
+        assert_eq!(
+            markdown("- x\n- [y](https://example.com/?a=1&b=2)")
+                .unwrap()
+                .to_string(),
+            "<ul>\n<li>x</li>\n<li><a href=\"https://example.com/?a=1&amp;b=2\">y</a></li>\n</ul>\n",
+        );
+    }
+
+    #[test]
+    fn test_markdown_escapes_html() {
+        assert_eq!(
+            markdown("<div onclick=\"x()\">\n\n*a*\n\n</div>")
+                .unwrap()
+                .to_string(),
+            "&lt;div onclick=\"x()\"&gt;\n<p><em>a</em></p>\n&lt;/div&gt;",
+        );
+        assert_eq!(
+            markdown("a <b>c</b>").unwrap().to_string(),
+            "<p>a &lt;b&gt;c&lt;/b&gt;</p>\n",
+        );
+    }
+
+    #[test]
+    fn test_markdown_unsafe_urls() {
+        assert_eq!(
+            markdown("[a](javascript:alert(1)) [b](JavaScript&#58;x) <javascript:y>")
+                .unwrap()
+                .to_string(),
+            "<p>a b javascript:y</p>\n",
+        );
+        assert_eq!(
+            markdown("![a](data:text/html,x) [![b](java&#9;script:x)](/c:d)")
+                .unwrap()
+                .to_string(),
+            "<p>a <a href=\"/c:d\">b</a></p>\n",
+        );
+        assert_eq!(
+            markdown("[a](mailto:a@b.c) [b](HTTPS://x) [c](d) [e](#f:g)")
+                .unwrap()
+                .to_string(),
+            "<p><a href=\"mailto:a@b.c\">a</a> <a href=\"HTTPS://x\">b</a> \
+             <a href=\"d\">c</a> <a href=\"#f:g\">e</a></p>\n",
+        );
+    }
+}
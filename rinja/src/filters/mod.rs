@@ -3,28 +3,47 @@
 //! Contains all the built-in filter functions for use in templates.
 //! You can define your own filters, as well.
 
+mod currency;
+#[cfg(any(feature = "chrono", feature = "time"))]
+mod datetime;
+mod default;
 mod escape;
 #[cfg(feature = "serde_json")]
 mod json;
-
-use std::cell::Cell;
-use std::convert::Infallible;
-use std::fmt::{self, Write};
-
+#[cfg(feature = "markdown")]
+mod markdown;
+#[cfg(feature = "reltime")]
+mod reltime;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::cell::Cell;
+use core::convert::Infallible;
+use core::fmt::{self, Write};
+
+pub use currency::{currency, currency_precision, CurrencyAmount, LOCALE_KEY};
+#[cfg(any(feature = "chrono", feature = "time"))]
+pub use datetime::{date, FormatDate};
+pub use default::{default, DefaultFilterable, Defaulted, DefaultedIter};
 pub use escape::{
-    e, escape, safe, AutoEscape, AutoEscaper, Escaper, Html, HtmlSafe, HtmlSafeOutput, MaybeSafe,
-    Safe, Text, Unsafe,
+    e, escape, safe, AutoEscape, AutoEscaper, Escaper, Html, HtmlAttr, HtmlSafe, HtmlSafeOutput,
+    Js, Json, MaybeSafe, Safe, Shell, Text, Unsafe,
 };
 #[cfg(feature = "humansize")]
 use humansize::{ISizeFormatter, ToF64, DECIMAL};
 #[cfg(feature = "serde_json")]
 pub use json::{json, json_pretty, AsIndent};
+#[cfg(feature = "markdown")]
+pub use markdown::markdown;
 #[cfg(feature = "num-traits")]
 use num_traits::{cast::NumCast, Signed};
 #[cfg(feature = "urlencode")]
 use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+#[cfg(feature = "reltime")]
+pub use reltime::{reltime, reltime_from, NowSource, RelTime};
 
-use crate::{Error, Result};
+use crate::Result;
 
 #[cfg(feature = "urlencode")]
 // Urlencode char encoding set. Only the characters in the unreserved set don't
@@ -361,7 +380,7 @@ pub fn into_f64<T>(number: T) -> Result<f64>
 where
     T: NumCast,
 {
-    number.to_f64().ok_or(Error::Fmt)
+    number.to_f64().ok_or(crate::Error::Fmt)
 }
 
 #[cfg(feature = "num-traits")]
@@ -370,7 +389,7 @@ pub fn into_isize<T>(number: T) -> Result<isize>
 where
     T: NumCast,
 {
-    number.to_isize().ok_or(Error::Fmt)
+    number.to_isize().ok_or(crate::Error::Fmt)
 }
 
 /// Joins iterable into a string separated by provided argument
@@ -418,6 +437,262 @@ where
     }
 }
 
+/// Collects an iterable into a sorted [`Vec`]
+///
+/// The sort is stable, i.e. it does not reorder equal elements.
+///
+/// ```
+/// # use rinja::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = "{% for n in numbers|sort %}{{ n }} {% endfor %}",
+///     ext = "txt"
+/// )]
+/// struct Example {
+///     numbers: Vec<u32>,
+/// }
+///
+/// assert_eq!(Example { numbers: vec![3, 1, 2] }.to_string(), "1 2 3 ");
+/// ```
+#[inline]
+pub fn sort<I>(input: I) -> Result<Vec<I::Item>, Infallible>
+where
+    I: IntoIterator,
+    I::Item: Ord,
+{
+    let mut items: Vec<_> = input.into_iter().collect();
+    items.sort();
+    Ok(items)
+}
+
+/// Collects an iterable into a [`Vec`], sorted with the comparator function `compare`
+///
+/// The sort is stable, i.e. it does not reorder equal elements.
+/// In a template, the filter takes the name of the attribute to sort by,
+/// e.g. `{% for user in users|sort_by("name") %}`, or a closure that returns the key,
+/// e.g. `{% for user in users|sort_by(|u| u.name.len()) %}`.
+///
+/// ```
+/// # use rinja::Template;
+/// struct User {
+///     name: &'static str,
+///     age: u32,
+/// }
+///
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{% for u in users|sort_by("age") %}{{ u.name }} {% endfor %}"#,
+///     ext = "txt"
+/// )]
+/// struct Example {
+///     users: Vec<User>,
+/// }
+///
+/// let users = vec![User { name: "b", age: 42 }, User { name: "a", age: 23 }];
+/// assert_eq!(Example { users }.to_string(), "a b ");
+/// ```
+#[inline]
+pub fn sort_by<I, F>(input: I, compare: F) -> Result<Vec<I::Item>, Infallible>
+where
+    I: IntoIterator,
+    F: FnMut(&I::Item, &I::Item) -> core::cmp::Ordering,
+{
+    let mut items: Vec<_> = input.into_iter().collect();
+    items.sort_by(compare);
+    Ok(items)
+}
+
+/// Collects the results of applying `f` to every item of an iterable into a [`Vec`]
+///
+/// In a template, the filter takes the name of the attribute to extract from each item,
+/// e.g. `{{ users|map("email")|join(", ") }}`, or a closure, e.g. `{{ users|map(|u| u.email) }}`.
+/// To access an attribute of a single value, use the `attr` filter, e.g. `{{ user|attr("email") }}`.
+///
+/// ```
+/// # use rinja::Template;
+/// struct User {
+///     name: &'static str,
+/// }
+///
+/// #[derive(Template)]
+/// #[template(source = r#"{{ users|map("name")|join(", ") }}"#, ext = "txt")]
+/// struct Example {
+///     users: Vec<User>,
+/// }
+///
+/// let users = vec![User { name: "a" }, User { name: "b" }];
+/// assert_eq!(Example { users }.to_string(), "a, b");
+/// ```
+#[inline]
+pub fn map<I, T, F>(input: I, f: F) -> Result<Vec<T>, Infallible>
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> T,
+{
+    Ok(input.into_iter().map(f).collect())
+}
+
+/// Groups the items of an iterable by the key returned by `key`
+///
+/// The result is a [`Vec`] of `(key, items)` pairs, sorted by the key.
+/// The items in each group keep their original order.
+/// In a template, the filter takes the name of the attribute to group by,
+/// e.g. `{% for (city, users) in users|group_by("city") %}`, or a closure that returns the key,
+/// e.g. `{% for (len, users) in users|group_by(|u| u.name.len()) %}`.
+///
+/// ```
+/// # use rinja::Template;
+/// struct User {
+///     name: &'static str,
+///     city: &'static str,
+/// }
+///
+/// #[derive(Template)]
+/// #[template(
+///     source = r#"{% for (city, users) in users|group_by("city") -%}
+///         {{ city }}: {% for u in users %}{{ u.name }} {% endfor %}
+///     {% endfor %}"#,
+///     ext = "txt"
+/// )]
+/// struct Example {
+///     users: Vec<User>,
+/// }
+///
+/// let users = vec![
+///     User { name: "a", city: "Paris" },
+///     User { name: "b", city: "Berlin" },
+///     User { name: "c", city: "Paris" },
+/// ];
+/// assert_eq!(
+///     Example { users }.to_string(),
+///     "Berlin: b \n    Paris: a c \n    ",
+/// );
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn group_by<I, K, F>(input: I, mut key: F) -> Result<Vec<(K, Vec<I::Item>)>, Infallible>
+where
+    I: IntoIterator,
+    K: Ord,
+    F: FnMut(&I::Item) -> K,
+{
+    let mut items: Vec<_> = input.into_iter().map(|item| (key(&item), item)).collect();
+    items.sort_by(|(l, _), (r, _)| l.cmp(r));
+
+    let mut groups: Vec<(K, Vec<I::Item>)> = Vec::new();
+    for (key, item) in items {
+        match groups.last_mut() {
+            Some((last, group)) if *last == key => group.push(item),
+            _ => groups.push((key, vec![item])),
+        }
+    }
+    Ok(groups)
+}
+
+/// Splits an iterable into chunks of `size` items
+///
+/// The last chunk may be shorter. Use [`batch_fill()`] to pad it.
+/// A `size` of `0` is treated as `1`.
+///
+/// ```
+/// # use rinja::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = "{% for row in numbers|batch(2) %}{{ row|join(\",\") }};{% endfor %}",
+///     ext = "txt"
+/// )]
+/// struct Example {
+///     numbers: Vec<u32>,
+/// }
+///
+/// assert_eq!(Example { numbers: vec![1, 2, 3] }.to_string(), "1,2;3;");
+/// ```
+pub fn batch<I: IntoIterator>(input: I, size: usize) -> Result<Vec<Vec<I::Item>>, Infallible> {
+    let size = size.max(1);
+    let mut iter = input.into_iter().peekable();
+    let mut chunks = Vec::new();
+    while iter.peek().is_some() {
+        chunks.push(iter.by_ref().take(size).collect());
+    }
+    Ok(chunks)
+}
+
+/// Splits an iterable into chunks of `size` items, padding the last chunk with `fill`
+///
+/// This is the implementation of `batch` if called with a second argument,
+/// e.g. `{% for row in cells|batch(3, "-") %}`. The padding items are [`Defaulted::Fallback`],
+/// all other items are [`Defaulted::Value`].
+/// The last chunk is never padded to more items than the input has.
+#[allow(clippy::type_complexity)]
+pub fn batch_fill<I: IntoIterator, F: Clone>(
+    input: I,
+    size: usize,
+    fill: F,
+) -> Result<Vec<Vec<Defaulted<I::Item, F>>>, Infallible> {
+    let items: Vec<_> = input.into_iter().map(Defaulted::Value).collect();
+    let size = size.min(items.len()).max(1);
+    let mut chunks = batch(items, size)?;
+    if let Some(last) = chunks.last_mut() {
+        last.resize_with(size, || Defaulted::Fallback(fill.clone()));
+    }
+    Ok(chunks)
+}
+
+/// Splits an iterable into `count` columns of roughly equal length
+///
+/// The first columns get one more item than the last columns if the items cannot be distributed
+/// evenly. Use [`slice_fill()`] to pad the shorter columns.
+/// A `count` of `0` is treated as `1`, and a `count` of `10_000` or more as `10_000`.
+///
+/// ```
+/// # use rinja::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = "{% for col in numbers|slice(2) %}{{ col|join(\",\") }};{% endfor %}",
+///     ext = "txt"
+/// )]
+/// struct Example {
+///     numbers: Vec<u32>,
+/// }
+///
+/// assert_eq!(Example { numbers: vec![1, 2, 3] }.to_string(), "1,2;3;");
+/// ```
+pub fn slice<I: IntoIterator>(input: I, count: usize) -> Result<Vec<Vec<I::Item>>, Infallible> {
+    let count = count.clamp(1, MAX_LEN);
+    let items: Vec<_> = input.into_iter().collect();
+    let (per_column, extra) = (items.len() / count, items.len() % count);
+    let mut iter = items.into_iter();
+    Ok((0..count)
+        .map(|column| {
+            let len = if column < extra {
+                per_column + 1
+            } else {
+                per_column
+            };
+            iter.by_ref().take(len).collect()
+        })
+        .collect())
+}
+
+/// Splits an iterable into `count` columns of roughly equal length, padding the shorter columns
+/// with `fill`
+///
+/// This is the implementation of `slice` if called with a second argument,
+/// e.g. `{% for col in cells|slice(3, "-") %}`. The padding items are [`Defaulted::Fallback`],
+/// all other items are [`Defaulted::Value`].
+#[allow(clippy::type_complexity)]
+pub fn slice_fill<I: IntoIterator, F: Clone>(
+    input: I,
+    count: usize,
+    fill: F,
+) -> Result<Vec<Vec<Defaulted<I::Item, F>>>, Infallible> {
+    let mut columns = slice(input.into_iter().map(Defaulted::Value), count)?;
+    let len = columns.first().map_or(0, Vec::len);
+    for column in &mut columns {
+        column.resize_with(len, || Defaulted::Fallback(fill.clone()));
+    }
+    Ok(columns)
+}
+
 #[cfg(feature = "num-traits")]
 /// Absolute value
 pub fn abs<T>(number: T) -> Result<T>
@@ -427,6 +702,22 @@ where
     Ok(number.abs())
 }
 
+/// Uppercase the first character of a value. All others are left unchanged.
+#[inline]
+pub fn capfirst(s: impl fmt::Display) -> Result<impl fmt::Display, fmt::Error> {
+    fn capfirst(s: String) -> Result<String, fmt::Error> {
+        match s.chars().next() {
+            Some(c) if !c.is_uppercase() => {
+                let mut replacement: String = c.to_uppercase().collect();
+                replacement.push_str(&s[c.len_utf8()..]);
+                Ok(replacement)
+            }
+            _ => Ok(s),
+        }
+    }
+    capfirst(try_to_string(s)?)
+}
+
 /// Capitalize a value. The first character will be uppercase, all others lowercase.
 #[inline]
 pub fn capitalize(s: impl fmt::Display) -> Result<impl fmt::Display, fmt::Error> {
@@ -464,6 +755,80 @@ impl<T: fmt::Display> fmt::Display for Center<T> {
     }
 }
 
+/// Left-justifies the value in a field of a given width, by padding it with spaces
+///
+/// Use [`ljust_fill()`] to pad it with another character.
+#[inline]
+pub fn ljust(src: impl fmt::Display, width: usize) -> Result<impl fmt::Display, Infallible> {
+    ljust_fill(src, width, ' ')
+}
+
+/// Left-justifies the value in a field of a given width, by padding it with `fill`
+#[inline]
+pub fn ljust_fill(
+    src: impl fmt::Display,
+    width: usize,
+    fill: char,
+) -> Result<impl fmt::Display, Infallible> {
+    Ok(Justify {
+        src,
+        width,
+        fill,
+        right: false,
+    })
+}
+
+/// Right-justifies the value in a field of a given width, by padding it with spaces
+///
+/// Use [`rjust_fill()`] to pad it with another character.
+#[inline]
+pub fn rjust(src: impl fmt::Display, width: usize) -> Result<impl fmt::Display, Infallible> {
+    rjust_fill(src, width, ' ')
+}
+
+/// Right-justifies the value in a field of a given width, by padding it with `fill`
+#[inline]
+pub fn rjust_fill(
+    src: impl fmt::Display,
+    width: usize,
+    fill: char,
+) -> Result<impl fmt::Display, Infallible> {
+    Ok(Justify {
+        src,
+        width,
+        fill,
+        right: true,
+    })
+}
+
+struct Justify<T> {
+    src: T,
+    width: usize,
+    fill: char,
+    right: bool,
+}
+
+impl<T: fmt::Display> fmt::Display for Justify<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.width >= MAX_LEN {
+            return write!(f, "{}", self.src);
+        }
+        // The width is counted in chars, like the width of `center()`.
+        let src = try_to_string(&self.src)?;
+        let padding = self.width.saturating_sub(src.chars().count());
+        if !self.right {
+            f.write_str(&src)?;
+        }
+        for _ in 0..padding {
+            f.write_char(self.fill)?;
+        }
+        if self.right {
+            f.write_str(&src)?;
+        }
+        Ok(())
+    }
+}
+
 /// Count the words in that string.
 #[inline]
 pub fn wordcount(s: impl fmt::Display) -> Result<usize, fmt::Error> {
@@ -510,6 +875,8 @@ fn try_to_string(s: impl fmt::Display) -> Result<String, fmt::Error> {
 
 #[cfg(test)]
 mod tests {
+    use alloc::string::ToString;
+
     use super::*;
 
     #[cfg(feature = "humansize")]
@@ -692,7 +1059,7 @@ mod tests {
         assert_eq!(into_isize(1.5_f64).unwrap(), 1_isize);
         assert_eq!(into_isize(-1.5_f64).unwrap(), -1_isize);
         match into_isize(f64::INFINITY) {
-            Err(Error::Fmt) => {}
+            Err(crate::Error::Fmt) => {}
             _ => panic!("Should return error of type Err(Error::Fmt)"),
         };
     }
@@ -732,6 +1099,97 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_sort() {
+        assert_eq!(sort([3, 1, 2]).unwrap(), [1, 2, 3]);
+        assert_eq!(sort(&["b", "c", "a"]).unwrap(), [&"a", &"b", &"c"]);
+        assert!(sort(Vec::<u8>::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let items = [(1, "b"), (0, "c"), (1, "a")];
+        assert_eq!(
+            sort_by(items, |a, b| a.0.cmp(&b.0)).unwrap(),
+            [(0, "c"), (1, "b"), (1, "a")],
+        );
+        assert_eq!(
+            sort_by(&items, |a, b| a.1.cmp(b.1)).unwrap(),
+            [&(1, "a"), &(1, "b"), &(0, "c")],
+        );
+    }
+
+    #[test]
+    fn test_map() {
+        let items = [(1, "b"), (0, "c")];
+        assert_eq!(map(items, |i| i.1).unwrap(), ["b", "c"]);
+        assert_eq!(map(&items, |i| &i.0).unwrap(), [&1, &0]);
+    }
+
+    #[test]
+    fn test_group_by() {
+        let items = [(1, "b"), (0, "c"), (1, "a")];
+        assert_eq!(
+            group_by(items, |i| i.0).unwrap(),
+            [(0, vec![(0, "c")]), (1, vec![(1, "b"), (1, "a")])],
+        );
+        assert_eq!(
+            group_by(&items, |i| i.1).unwrap(),
+            [
+                ("a", vec![&(1, "a")]),
+                ("b", vec![&(1, "b")]),
+                ("c", vec![&(0, "c")])
+            ],
+        );
+        assert!(group_by(Vec::<u8>::new(), |i| *i).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_batch() {
+        assert_eq!(batch(1..=5, 2).unwrap(), [vec![1, 2], vec![3, 4], vec![5]]);
+        assert_eq!(batch(1..=4, 2).unwrap(), [vec![1, 2], vec![3, 4]]);
+        assert_eq!(batch(1..=2, 0).unwrap(), [vec![1], vec![2]]);
+        assert!(batch(Vec::<u8>::new(), 2).unwrap().is_empty());
+
+        let v = Defaulted::Value;
+        let f = Defaulted::Fallback;
+        assert_eq!(
+            batch_fill(1..=3, 2, 0).unwrap(),
+            [vec![v(1), v(2)], vec![v(3), f(0)]],
+        );
+        assert_eq!(batch_fill(1..=2, 2, 0).unwrap(), [vec![v(1), v(2)]]);
+        assert_eq!(batch_fill(1..=2, usize::MAX, 0).unwrap(), [vec![v(1), v(2)]]);
+        assert!(batch_fill(Vec::<u8>::new(), usize::MAX, 0).unwrap().is_empty());
+        assert_eq!(batch(1..=2, usize::MAX).unwrap(), [vec![1, 2]]);
+    }
+
+    #[test]
+    fn test_slice() {
+        assert_eq!(
+            slice(1..=7, 3).unwrap(),
+            [vec![1, 2, 3], vec![4, 5], vec![6, 7]],
+        );
+        assert_eq!(slice(1..=4, 2).unwrap(), [vec![1, 2], vec![3, 4]]);
+        assert_eq!(slice(1..=2, 3).unwrap(), [vec![1], vec![2], vec![]]);
+        assert_eq!(slice(1..=2, 0).unwrap(), [vec![1, 2]]);
+        assert_eq!(slice(1..=2, usize::MAX).unwrap().len(), MAX_LEN);
+
+        let v = Defaulted::Value;
+        let f = Defaulted::Fallback;
+        assert_eq!(
+            slice_fill(1..=5, 3, 0).unwrap(),
+            [vec![v(1), v(2)], vec![v(3), v(4)], vec![v(5), f(0)]],
+        );
+        assert_eq!(
+            slice_fill(1..=4, 2, 0).unwrap(),
+            [vec![v(1), v(2)], vec![v(3), v(4)]],
+        );
+        let columns = slice_fill(1..=2, usize::MAX, 0).unwrap();
+        assert_eq!(columns.len(), MAX_LEN);
+        assert_eq!(columns[0], [v(1)]);
+        assert_eq!(columns[MAX_LEN - 1], [f(0)]);
+    }
+
     #[cfg(feature = "num-traits")]
     #[test]
     #[allow(clippy::float_cmp)]
@@ -744,6 +1202,17 @@ mod tests {
         assert_eq!(abs(-1.0_f64).unwrap(), 1.0_f64);
     }
 
+    #[test]
+    fn test_capfirst() {
+        assert_eq!(capfirst("foo").unwrap().to_string(), "Foo");
+        assert_eq!(capfirst("").unwrap().to_string(), "");
+        assert_eq!(capfirst("fOO bAR").unwrap().to_string(), "FOO bAR");
+        assert_eq!(capfirst("rust in NYC").unwrap().to_string(), "Rust in NYC");
+        assert_eq!(capfirst("Éa").unwrap().to_string(), "Éa");
+        assert_eq!(capfirst("ßa").unwrap().to_string(), "SSa");
+        assert_eq!(capfirst(" foo").unwrap().to_string(), " foo");
+    }
+
     #[test]
     fn test_capitalize() {
         assert_eq!(capitalize("foo").unwrap().to_string(), "Foo".to_string());
@@ -778,6 +1247,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_justify() {
+        assert_eq!(ljust("f", 3).unwrap().to_string(), "f  ");
+        assert_eq!(rjust("f", 3).unwrap().to_string(), "  f");
+        assert_eq!(ljust("foo", 1).unwrap().to_string(), "foo");
+        assert_eq!(rjust(42, 5).unwrap().to_string(), "   42");
+        assert_eq!(ljust_fill("äö", 4, '.').unwrap().to_string(), "äö..");
+        assert_eq!(rjust_fill(7, 3, '0').unwrap().to_string(), "007");
+        assert_eq!(rjust("foo", 111_669_149_696).unwrap().to_string(), "foo");
+    }
+
     #[test]
     fn test_wordcount() {
         assert_eq!(wordcount("").unwrap(), 0);
@@ -0,0 +1,191 @@
+use core::convert::Infallible;
+use core::fmt;
+use core::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Formats a point in time relative to now, e.g. `"3 minutes ago"` or `"in 2 days"`
+///
+/// The filter is implemented for [`SystemTime`], for the date and time types of the [`chrono`]
+/// crate (feature `"chrono"`) and the [`time`] crate (feature `"time"`), and for a [`Duration`],
+/// which is the time that has passed since an event. With an argument, e.g.
+/// `{{ created_at|reltime(clock) }}`, the current time is taken from a [`NowSource`] instead
+/// of the system clock, which makes the output predictable in tests.
+///
+/// [`chrono`]: https://docs.rs/chrono
+/// [`time`]: https://docs.rs/time
+///
+/// ```
+/// # use std::time::{Duration, SystemTime};
+/// # use rinja::Template;
+/// #[derive(Template)]
+/// #[template(source = "edited {{ edited_at|reltime(now) }}", ext = "html")]
+/// struct Comment {
+///     edited_at: SystemTime,
+///     now: SystemTime,
+/// }
+///
+/// let now = SystemTime::now();
+/// let edited_at = now - Duration::from_secs(3 * 60 + 10);
+/// assert_eq!(Comment { edited_at, now }.to_string(), "edited 3 minutes ago");
+/// ```
+#[inline]
+pub fn reltime<T: RelTime>(value: T) -> Result<impl fmt::Display, Infallible> {
+    reltime_from(value, SystemTime::now())
+}
+
+/// Formats a point in time relative to the time returned by `now`
+///
+/// See [`reltime()`].
+#[inline]
+pub fn reltime_from<T: RelTime, N: NowSource>(
+    value: T,
+    now: N,
+) -> Result<impl fmt::Display, Infallible> {
+    Ok(RelTimeFilter(value.secs_from(now.now())))
+}
+
+/// A point in time, or the time since an event, that can be formatted by the [`reltime()`]
+/// filter
+pub trait RelTime {
+    /// Returns how many seconds the value lies after `now`, or a negative number if it lies
+    /// before `now`
+    fn secs_from(&self, now: SystemTime) -> i64;
+}
+
+/// Tells the [`reltime()`] filter what time it is
+pub trait NowSource {
+    /// Returns the current time
+    fn now(&self) -> SystemTime;
+}
+
+impl NowSource for SystemTime {
+    #[inline]
+    fn now(&self) -> SystemTime {
+        *self
+    }
+}
+
+impl NowSource for fn() -> SystemTime {
+    #[inline]
+    fn now(&self) -> SystemTime {
+        self()
+    }
+}
+
+impl<T: NowSource + ?Sized> NowSource for &T {
+    #[inline]
+    fn now(&self) -> SystemTime {
+        T::now(self)
+    }
+}
+
+impl<T: RelTime + ?Sized> RelTime for &T {
+    #[inline]
+    fn secs_from(&self, now: SystemTime) -> i64 {
+        T::secs_from(self, now)
+    }
+}
+
+impl RelTime for SystemTime {
+    fn secs_from(&self, now: SystemTime) -> i64 {
+        unix_secs(*self).saturating_sub(unix_secs(now))
+    }
+}
+
+/// The time that has passed since an event, so the event lies in the past
+impl RelTime for Duration {
+    fn secs_from(&self, _: SystemTime) -> i64 {
+        i64::try_from(self.as_secs()).map_or(i64::MIN, |secs| -secs)
+    }
+}
+
+// Seconds since the Unix epoch, negative if `time` lies before it.
+fn unix_secs(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => i64::try_from(d.as_secs()).unwrap_or(i64::MAX),
+        Err(err) => i64::try_from(err.duration().as_secs()).map_or(i64::MIN, |secs| -secs),
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> RelTime for chrono::DateTime<Tz> {
+    fn secs_from(&self, now: SystemTime) -> i64 {
+        self.timestamp().saturating_sub(unix_secs(now))
+    }
+}
+
+#[cfg(feature = "time")]
+impl RelTime for time::OffsetDateTime {
+    fn secs_from(&self, now: SystemTime) -> i64 {
+        self.unix_timestamp().saturating_sub(unix_secs(now))
+    }
+}
+
+struct RelTimeFilter(i64);
+
+impl fmt::Display for RelTimeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: &[(u64, &str)] = &[
+            (365 * 24 * 60 * 60, "year"),
+            (30 * 24 * 60 * 60, "month"),
+            (24 * 60 * 60, "day"),
+            (60 * 60, "hour"),
+            (60, "minute"),
+            (1, "second"),
+        ];
+
+        let secs = self.0.unsigned_abs();
+        let Some((count, unit)) = UNITS
+            .iter()
+            .find(|(len, _)| secs >= *len)
+            .map(|(len, unit)| (secs / len, unit))
+        else {
+            return f.write_str("just now");
+        };
+        let plural = if count == 1 { "" } else { "s" };
+        match self.0 < 0 {
+            true => write!(f, "{count} {unit}{plural} ago"),
+            false => write!(f, "in {count} {unit}{plural}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn test_reltime() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let fmt = |secs: i64| {
+            let time = match secs < 0 {
+                true => now - Duration::from_secs(secs.unsigned_abs()),
+                false => now + Duration::from_secs(secs.unsigned_abs()),
+            };
+            reltime_from(time, now).unwrap().to_string()
+        };
+        assert_eq!(fmt(0), "just now");
+        assert_eq!(fmt(-1), "1 second ago");
+        assert_eq!(fmt(-59), "59 seconds ago");
+        assert_eq!(fmt(-60), "1 minute ago");
+        assert_eq!(fmt(-3 * 60 - 59), "3 minutes ago");
+        assert_eq!(fmt(2 * 60 * 60), "in 2 hours");
+        assert_eq!(fmt(-24 * 60 * 60), "1 day ago");
+        assert_eq!(fmt(45 * 24 * 60 * 60), "in 1 month");
+        assert_eq!(fmt(-800 * 24 * 60 * 60), "2 years ago");
+
+        let before_epoch = UNIX_EPOCH - Duration::from_secs(90);
+        assert_eq!(
+            reltime_from(before_epoch, UNIX_EPOCH).unwrap().to_string(),
+            "1 minute ago",
+        );
+        assert_eq!(
+            reltime_from(&Duration::from_secs(5), &now)
+                .unwrap()
+                .to_string(),
+            "5 seconds ago",
+        );
+    }
+}
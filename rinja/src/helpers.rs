@@ -1,8 +1,37 @@
 #![doc(hidden)]
 
-use std::cell::Cell;
-use std::fmt;
-use std::iter::{Enumerate, Peekable};
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cell::Cell;
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher, Hash};
+use core::iter::{Enumerate, Peekable};
+use core::ops::{Deref, Index};
+use core::{array, fmt, slice};
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+#[doc(hidden)]
+pub extern crate alloc;
+// Generated code reaches `std` through this path, so a `no_std` crate can derive `Template` even
+// if another crate enables the feature `std`.
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub extern crate std;
+#[cfg(feature = "tokio")]
+#[doc(hidden)]
+pub use tokio;
+#[cfg(feature = "tracing")]
+#[doc(hidden)]
+pub use tracing;
+
+use crate::{FastWritable, RenderChunks, Values};
 
 pub struct TemplateLoop<I>
 where
@@ -89,3 +118,614 @@ where
         Ok(())
     }
 }
+
+/// An error raised by using `?` in a template, converted into [`Error::Custom`] with
+/// [`IntoCustomError`]
+///
+/// [`Error::Custom`]: crate::Error::Custom
+pub struct CustomErr<E>(Cell<Option<E>>);
+
+impl<E> CustomErr<E> {
+    #[inline]
+    pub fn new(err: E) -> Self {
+        Self(Cell::new(Some(err)))
+    }
+
+    #[inline]
+    fn take(&self) -> E {
+        self.0.take().expect("the error was already converted")
+    }
+}
+
+/// Converts an error with the same autoref trick as [`crate::filters::AutoEscape`]
+///
+/// With the feature `std`, a `std::error::Error` is stored as is, so its `source()` chain and
+/// downcasting work. Other errors are wrapped into a [`DisplayError`].
+pub trait IntoCustomError {
+    fn rinja_into_error(&self) -> crate::Error;
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + Send + Sync + 'static> IntoCustomError for &CustomErr<E> {
+    #[inline]
+    fn rinja_into_error(&self) -> crate::Error {
+        crate::Error::Custom(alloc::boxed::Box::new(self.take()))
+    }
+}
+
+impl<E: crate::CustomError> IntoCustomError for CustomErr<E> {
+    #[inline]
+    fn rinja_into_error(&self) -> crate::Error {
+        #[cfg(feature = "std")]
+        let err = DisplayError(self.take());
+        #[cfg(not(feature = "std"))]
+        let err = self.take();
+        crate::Error::Custom(alloc::boxed::Box::new(err))
+    }
+}
+
+/// Wraps an error that only implements `Debug` and `Display` into a `std::error::Error`
+#[cfg(feature = "std")]
+pub struct DisplayError<E>(pub E);
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> fmt::Debug for DisplayError<E> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Display> fmt::Display for DisplayError<E> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug + fmt::Display> std::error::Error for DisplayError<E> {}
+
+/// Implemented by derived templates, renders the body of the template into a [`Sink`]
+pub trait RenderSink {
+    fn render_into_sink<W: fmt::Write + ?Sized>(
+        &self,
+        writer: &mut Sink<'_, W>,
+        values: &dyn Values,
+    ) -> crate::Result<()>;
+}
+
+/// [`Template::render_chunks()`][crate::Template::render_chunks] of derived templates
+#[inline]
+pub fn render_chunks<T: RenderSink + ?Sized>(tmpl: &T) -> RenderChunks<'static> {
+    RenderChunks::with_sink(tmpl)
+}
+
+/// The writer that the body of a derived template writes into
+///
+/// The static text of the template is written with the inherent [`Sink::write_str()`], which
+/// only accepts `&'static str`, so [`Template::render_chunks()`][crate::Template::render_chunks]
+/// can borrow it. Everything else is written through the [`fmt::Write`] implementation.
+pub struct Sink<'a, W: ?Sized> {
+    writer: &'a mut W,
+    write_static: Option<fn(&mut W, &'static str) -> fmt::Result>,
+}
+
+impl<'a, W: fmt::Write + ?Sized> Sink<'a, W> {
+    #[inline]
+    pub fn new(writer: &'a mut W) -> Self {
+        Self {
+            writer,
+            write_static: None,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn with_static(
+        writer: &'a mut W,
+        write_static: fn(&mut W, &'static str) -> fmt::Result,
+    ) -> Self {
+        Self {
+            writer,
+            write_static: Some(write_static),
+        }
+    }
+
+    /// Writes static text of the template
+    #[inline]
+    pub fn write_str(&mut self, s: &'static str) -> fmt::Result {
+        match self.write_static {
+            Some(write_static) => write_static(self.writer, s),
+            None => self.writer.write_str(s),
+        }
+    }
+}
+
+impl<W: fmt::Write + ?Sized> fmt::Write for Sink<'_, W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_str(s)
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.writer.write_char(c)
+    }
+
+    #[inline]
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        self.writer.write_fmt(args)
+    }
+}
+
+/// The value of a map literal `{key: value, …}` in a template
+///
+/// The entries are kept in their order, and looked up with a linear search, which is fast for the
+/// few entries a map literal has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapLiteral<K, V, const N: usize>(pub [(K, V); N]);
+
+impl<K, V, const N: usize> MapLiteral<K, V, N> {
+    pub fn get<Q: PartialEq + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.0
+            .iter()
+            .find_map(|(k, v)| (k.borrow() == key).then_some(v))
+    }
+
+    #[inline]
+    pub fn contains_key<Q: PartialEq + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.get(key).is_some()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, (K, V)> {
+        self.0.iter()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        N
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        N == 0
+    }
+}
+
+impl<K, V, Q, const N: usize> Index<&Q> for MapLiteral<K, V, N>
+where
+    K: Borrow<Q>,
+    Q: PartialEq + ?Sized,
+{
+    type Output = V;
+
+    /// Panics if there is no entry for `key`, like a `HashMap` does
+    #[track_caller]
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+impl<K, V, const N: usize> IntoIterator for MapLiteral<K, V, N> {
+    type Item = (K, V);
+    type IntoIter = array::IntoIter<(K, V), N>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, K, V, const N: usize> IntoIterator for &'a MapLiteral<K, V, N> {
+    type Item = &'a (K, V);
+    type IntoIter = slice::Iter<'a, (K, V)>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// Wraps the value of an expression for [`WriteWritable`]
+pub struct Writable<'a, T: ?Sized>(pub &'a T);
+
+/// Used by the generated code to write an expression with [`FastWritable`] if its type
+/// implements it, and with [`fmt::Display`] otherwise
+pub trait WriteWritable {
+    fn rinja_write<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> fmt::Result;
+}
+
+impl<T: FastWritable + ?Sized> WriteWritable for &Writable<'_, T> {
+    #[inline]
+    fn rinja_write<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+        self.0.write_into(dest)
+    }
+}
+
+impl<T: fmt::Display + ?Sized> WriteWritable for &&Writable<'_, T> {
+    #[inline]
+    fn rinja_write<W: fmt::Write + ?Sized>(&self, dest: &mut W) -> fmt::Result {
+        write!(dest, "{}", self.0)
+    }
+}
+
+/// Wraps the operands of `item in container` for [`ContainsItem`], as `Contains(container, item)`
+pub struct Contains<'a, C: ?Sized, T: ?Sized>(pub &'a C, pub &'a T);
+
+/// Used by the generated code to check if a container contains an item
+///
+/// If the item dereferences to a type the container can look up, e.g. a `&str` or a `String` in
+/// a `HashMap<String, _>`, the dereferenced item is used. Otherwise the item is used as it is,
+/// e.g. a `&str` in a `[&str]`.
+pub trait ContainsItem {
+    fn rinja_contains(&self) -> bool;
+}
+
+impl<C, T> ContainsItem for &Contains<'_, C, T>
+where
+    C: Container<T::Target> + ?Sized,
+    T: Deref + ?Sized,
+{
+    #[inline]
+    fn rinja_contains(&self) -> bool {
+        self.0.contains_item(self.1)
+    }
+}
+
+impl<C: Container<T> + ?Sized, T: ?Sized> ContainsItem for &&Contains<'_, C, T> {
+    #[inline]
+    fn rinja_contains(&self) -> bool {
+        self.0.contains_item(self.1)
+    }
+}
+
+/// A value that the `in` operator can look up items of type `Q` in
+pub trait Container<Q: ?Sized> {
+    fn contains_item(&self, item: &Q) -> bool;
+}
+
+impl<C: Container<Q> + ?Sized, Q: ?Sized> Container<Q> for &C {
+    #[inline]
+    fn contains_item(&self, item: &Q) -> bool {
+        C::contains_item(self, item)
+    }
+}
+
+impl<T: PartialEq<Q>, Q: ?Sized> Container<Q> for [T] {
+    #[inline]
+    fn contains_item(&self, item: &Q) -> bool {
+        self.iter().any(|elem| elem == item)
+    }
+}
+
+impl<T: PartialEq<Q>, Q: ?Sized, const N: usize> Container<Q> for [T; N] {
+    #[inline]
+    fn contains_item(&self, item: &Q) -> bool {
+        self.as_slice().contains_item(item)
+    }
+}
+
+impl<T: PartialEq<Q>, Q: ?Sized> Container<Q> for Vec<T> {
+    #[inline]
+    fn contains_item(&self, item: &Q) -> bool {
+        self.as_slice().contains_item(item)
+    }
+}
+
+impl<T: PartialEq<Q>, Q: ?Sized> Container<Q> for VecDeque<T> {
+    #[inline]
+    fn contains_item(&self, item: &Q) -> bool {
+        self.iter().any(|elem| elem == item)
+    }
+}
+
+/// A substring
+impl Container<str> for str {
+    #[inline]
+    fn contains_item(&self, item: &str) -> bool {
+        self.contains(item)
+    }
+}
+
+impl Container<char> for str {
+    #[inline]
+    fn contains_item(&self, item: &char) -> bool {
+        self.contains(*item)
+    }
+}
+
+impl<Q: ?Sized> Container<Q> for String
+where
+    str: Container<Q>,
+{
+    #[inline]
+    fn contains_item(&self, item: &Q) -> bool {
+        self.as_str().contains_item(item)
+    }
+}
+
+/// A key
+impl<K: Borrow<Q> + Ord, V, Q: Ord + ?Sized> Container<Q> for BTreeMap<K, V> {
+    #[inline]
+    fn contains_item(&self, item: &Q) -> bool {
+        self.contains_key(item)
+    }
+}
+
+impl<T: Borrow<Q> + Ord, Q: Ord + ?Sized> Container<Q> for BTreeSet<T> {
+    #[inline]
+    fn contains_item(&self, item: &Q) -> bool {
+        self.contains(item)
+    }
+}
+
+/// A key
+#[cfg(feature = "std")]
+impl<K, V, S, Q> Container<Q> for HashMap<K, V, S>
+where
+    K: Borrow<Q> + Eq + Hash,
+    S: BuildHasher,
+    Q: Eq + Hash + ?Sized,
+{
+    #[inline]
+    fn contains_item(&self, item: &Q) -> bool {
+        self.contains_key(item)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, S, Q> Container<Q> for HashSet<T, S>
+where
+    T: Borrow<Q> + Eq + Hash,
+    S: BuildHasher,
+    Q: Eq + Hash + ?Sized,
+{
+    #[inline]
+    fn contains_item(&self, item: &Q) -> bool {
+        self.contains(item)
+    }
+}
+
+/// A key
+impl<K: Borrow<Q>, V, Q: PartialEq + ?Sized, const N: usize> Container<Q> for MapLiteral<K, V, N> {
+    #[inline]
+    fn contains_item(&self, item: &Q) -> bool {
+        self.contains_key(item)
+    }
+}
+
+/// Used by the generated code for `base ** exp`
+///
+/// Integers are raised to an integer power with `checked_pow()`, a negative exponent or an
+/// overflow is reported as a [`PowError`][crate::PowError]. Floats are raised to an integer
+/// power with `powi()`, and to a float power with `powf()`.
+pub trait Pow<Exp> {
+    type Output;
+
+    fn rinja_pow(self, exp: Exp) -> crate::Result<Self::Output>;
+}
+
+impl<T: Pow<Exp> + Copy, Exp> Pow<Exp> for &T {
+    type Output = T::Output;
+
+    #[inline]
+    fn rinja_pow(self, exp: Exp) -> crate::Result<Self::Output> {
+        T::rinja_pow(*self, exp)
+    }
+}
+
+/// An exponent of an integer base in `base ** exp`
+pub trait IntExponent: Copy {
+    /// Fails if the exponent is negative or greater than `u32::MAX`
+    fn rinja_int_exp(self) -> Result<u32, crate::PowError>;
+}
+
+/// An exponent of a float base in `base ** exp`
+#[cfg(feature = "std")]
+pub trait FloatExponent: Copy {
+    fn rinja_pow_f32(self, base: f32) -> f32;
+    fn rinja_pow_f64(self, base: f64) -> f64;
+}
+
+impl<T: IntExponent> IntExponent for &T {
+    #[inline]
+    fn rinja_int_exp(self) -> Result<u32, crate::PowError> {
+        T::rinja_int_exp(*self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: FloatExponent> FloatExponent for &T {
+    #[inline]
+    fn rinja_pow_f32(self, base: f32) -> f32 {
+        T::rinja_pow_f32(*self, base)
+    }
+
+    #[inline]
+    fn rinja_pow_f64(self, base: f64) -> f64 {
+        T::rinja_pow_f64(*self, base)
+    }
+}
+
+macro_rules! impl_pow_int {
+    ($($ty:ty)*) => { $(
+        impl<E: IntExponent> Pow<E> for $ty {
+            type Output = $ty;
+
+            #[inline]
+            fn rinja_pow(self, exp: E) -> crate::Result<$ty> {
+                let err = match exp.rinja_int_exp() {
+                    Ok(exp) => match self.checked_pow(exp) {
+                        Some(value) => return Ok(value),
+                        None => crate::PowError::Overflow,
+                    },
+                    Err(err) => err,
+                };
+                Err(crate::Error::Custom(alloc::boxed::Box::new(err)))
+            }
+        }
+
+        impl IntExponent for $ty {
+            #[inline]
+            fn rinja_int_exp(self) -> Result<u32, crate::PowError> {
+                match u32::try_from(self) {
+                    Ok(exp) => Ok(exp),
+                    Err(_) if self > 0 => Err(crate::PowError::Overflow),
+                    Err(_) => Err(crate::PowError::NegativeExponent),
+                }
+            }
+        }
+
+        #[cfg(feature = "std")]
+        impl FloatExponent for $ty {
+            #[inline]
+            fn rinja_pow_f32(self, base: f32) -> f32 {
+                match i32::try_from(self) {
+                    Ok(exp) => base.powi(exp),
+                    Err(_) => base.powf(self as f32),
+                }
+            }
+
+            #[inline]
+            fn rinja_pow_f64(self, base: f64) -> f64 {
+                match i32::try_from(self) {
+                    Ok(exp) => base.powi(exp),
+                    Err(_) => base.powf(self as f64),
+                }
+            }
+        }
+    )* };
+}
+
+impl_pow_int!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+
+#[cfg(feature = "std")]
+macro_rules! impl_pow_float {
+    ($($ty:ty: $method:ident)*) => { $(
+        impl<E: FloatExponent> Pow<E> for $ty {
+            type Output = $ty;
+
+            #[inline]
+            fn rinja_pow(self, exp: E) -> crate::Result<$ty> {
+                Ok(exp.$method(self))
+            }
+        }
+
+        impl FloatExponent for $ty {
+            #[inline]
+            fn rinja_pow_f32(self, base: f32) -> f32 {
+                base.powf(self as f32)
+            }
+
+            #[inline]
+            fn rinja_pow_f64(self, base: f64) -> f64 {
+                base.powf(self as f64)
+            }
+        }
+    )* };
+}
+
+#[cfg(feature = "std")]
+impl_pow_float!(f32: rinja_pow_f32 f64: rinja_pow_f64);
+
+/// Adapter to render a template into an [`io::Write`] without an intermediate `String`
+#[cfg(feature = "std")]
+pub struct IoWriter<'a, W: io::Write + ?Sized> {
+    writer: &'a mut W,
+    err: Option<io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, W: io::Write + ?Sized> IoWriter<'a, W> {
+    #[inline]
+    pub fn new(writer: &'a mut W) -> Self {
+        Self { writer, err: None }
+    }
+
+    /// Prefers the original I/O error over the [`crate::Error::Fmt`] it caused
+    #[inline]
+    pub fn finish(self, result: crate::Result<()>) -> io::Result<()> {
+        match (result, self.err) {
+            (Ok(()), _) => Ok(()),
+            (Err(_), Some(err)) => Err(err),
+            (Err(err), None) => Err(io::Error::other(err)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write + ?Sized> fmt::Write for IoWriter<'_, W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_all(s.as_bytes()).map_err(|err| {
+            self.err = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+/// Returns `true` only the first time it is called with `id` in this process
+///
+/// Used by `{% once %}` blocks. The blocks of macros and included templates are generated at
+/// every call site, so a `static` in the generated code would not be shared between them.
+/// Instead, `id` identifies the block by the module of the template type, and the full path of
+/// the template file and its location in it.
+#[cfg(feature = "std")]
+pub fn first_render(id: &'static str) -> bool {
+    static RENDERED: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    RENDERED
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(id)
+}
+
+/// Writes the output of a template that was rendered so far into `writer`, and clears `buf`
+///
+/// Used by templates with `render_async = true` between their static text and expressions.
+#[cfg(feature = "tokio")]
+pub async fn write_async<W>(writer: &mut W, buf: &mut String) -> crate::Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin + ?Sized,
+{
+    let mut pos = 0;
+    while pos < buf.len() {
+        let written = core::future::poll_fn(|cx| {
+            core::pin::Pin::new(&mut *writer).poll_write(cx, &buf.as_bytes()[pos..])
+        })
+        .await
+        .map_err(crate::Error::Io)?;
+        if written == 0 {
+            return Err(crate::Error::Io(io::ErrorKind::WriteZero.into()));
+        }
+        pos += written;
+    }
+    buf.clear();
+    Ok(())
+}
+
+/// Writes the rest of the output of a template into `writer` and flushes it, unless rendering
+/// the template failed
+#[cfg(feature = "tokio")]
+pub async fn finish_async<W>(
+    writer: &mut W,
+    buf: &mut String,
+    result: crate::Result<()>,
+) -> crate::Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin + ?Sized,
+{
+    result?;
+    write_async(writer, buf).await?;
+    core::future::poll_fn(|cx| core::pin::Pin::new(&mut *writer).poll_flush(cx))
+        .await
+        .map_err(crate::Error::Io)
+}
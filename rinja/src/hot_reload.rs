@@ -0,0 +1,206 @@
+//! Runtime interpreter used by the `hot-reload` feature
+//!
+//! In debug builds, the derived `render_into()` first tries to render the template file as it
+//! currently is on disk. Only this subset of the template language is understood here:
+//!
+//! * plain text and comments, including whitespace control (`{%-`, `~%}`, …),
+//! * expressions that name a field of the template struct, e.g. `{{ name }}`,
+//! * the same expressions followed by the `safe` filter, e.g. `{{ name|safe }}`.
+//!
+//! If the template uses anything else, or if the file cannot be read, the compiled version of
+//! the template is rendered instead, and the installed [`RenderObserver`] is told why.
+//!
+//! [`RenderObserver`]: crate::observer::RenderObserver
+
+#![doc(hidden)]
+
+use std::boxed::Box;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::sync::Arc;
+use std::vec::Vec;
+use std::fmt;
+
+pub use parser::node::Whitespace;
+use parser::node::{Lit, Ws};
+pub use parser::Syntax;
+use parser::{Ast, Expr, Filter, Node};
+
+use crate::filters::{escape, Escaper, Html, HtmlSafe};
+use crate::whitespace::WsState;
+
+/// A field of the template struct, which may be referenced in the template
+pub struct Field<'a, T: ?Sized, E> {
+    value: &'a T,
+    escaper: E,
+}
+
+impl<'a, T: ?Sized, E> Field<'a, T, E> {
+    #[inline]
+    pub fn new(value: &'a T, escaper: E) -> Self {
+        Self { value, escaper }
+    }
+}
+
+/// The escaped and unescaped (for `|safe`) representation of a field
+#[derive(Clone, Copy)]
+pub struct FieldValue<'a> {
+    escaped: &'a dyn fmt::Display,
+    raw: &'a dyn fmt::Display,
+}
+
+/// Selects how a field is displayed with the same autoref trick as [`crate::filters::AutoEscape`]
+pub trait HotReloadField {
+    fn rinja_hot_reload_field(&self) -> Option<FieldValue<'_>>;
+}
+
+/// Don't escape HTML safe types
+impl<T: HtmlSafe> HotReloadField for &&Field<'_, T, Html> {
+    #[inline]
+    fn rinja_hot_reload_field(&self) -> Option<FieldValue<'_>> {
+        Some(FieldValue {
+            escaped: self.value,
+            raw: self.value,
+        })
+    }
+}
+
+/// Use the provided escaper
+impl<T: fmt::Display, E: Escaper> HotReloadField for &Field<'_, T, E> {
+    #[inline]
+    fn rinja_hot_reload_field(&self) -> Option<FieldValue<'_>> {
+        Some(FieldValue {
+            escaped: *self,
+            raw: self.value,
+        })
+    }
+}
+
+/// Fields that cannot be displayed cannot be used in a template
+impl<T: ?Sized, E> HotReloadField for Field<'_, T, E> {
+    #[inline]
+    fn rinja_hot_reload_field(&self) -> Option<FieldValue<'_>> {
+        None
+    }
+}
+
+impl<T: fmt::Display, E: Escaper> fmt::Display for Field<'_, T, E> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match escape(self.value, self.escaper) {
+            Ok(escaped) => write!(f, "{escaped}"),
+            Err(err) => match err {},
+        }
+    }
+}
+
+/// Renders the template file at `path` into `writer`
+///
+/// Returns `None` if the file could not be read, or if it uses features that are not supported
+/// by the interpreter. Nothing was written into `writer` in this case.
+pub fn render<W: fmt::Write + ?Sized>(
+    writer: &mut W,
+    path: &str,
+    syntax: &Syntax<'_>,
+    whitespace: Whitespace,
+    fields: &[(&str, Option<FieldValue<'_>>)],
+) -> Option<crate::Result<()>> {
+    let Ok(mut source) = read_to_string(path) else {
+        fall_back(path, "could not be read");
+        return None;
+    };
+    if source.ends_with('\n') {
+        let _ = source.pop();
+    }
+    let ast = match Ast::from_str(&source, Some(Arc::from(Path::new(path))), syntax) {
+        Ok(ast) => ast,
+        Err(err) => return Some(Err(crate::Error::Custom(Box::new(err)))),
+    };
+
+    let mut interpreter = Interpreter {
+        fields,
+        parts: Vec::new(),
+        ws: WsState::new(whitespace),
+    };
+    if interpreter.handle(ast.nodes()).is_none() {
+        fall_back(path, "uses syntax that cannot be interpreted at runtime");
+        return None;
+    }
+
+    for part in interpreter.parts {
+        let result = match part {
+            Part::Lit(s) => writer.write_str(s),
+            Part::Expr(value) => write!(writer, "{value}"),
+        };
+        if let Err(err) = result {
+            return Some(Err(err.into()));
+        }
+    }
+    Some(Ok(()))
+}
+
+/// Tells the observer that the compiled version of the template at `path` is rendered
+fn fall_back(path: &str, reason: &str) {
+    crate::observer::hot_reload_fallback(path, reason);
+}
+
+enum Part<'a> {
+    Lit(&'a str),
+    Expr(&'a dyn fmt::Display),
+}
+
+// Mirrors the whitespace handling of the code generator.
+struct Interpreter<'a> {
+    fields: &'a [(&'a str, Option<FieldValue<'a>>)],
+    parts: Vec<Part<'a>>,
+    ws: WsState<'a>,
+}
+
+impl<'a> Interpreter<'a> {
+    fn handle(&mut self, nodes: &'a [Node<'a>]) -> Option<()> {
+        for node in nodes {
+            match node {
+                Node::Lit(lit) => self.visit_lit(lit),
+                Node::Comment(comment) => self.handle_ws(comment.ws),
+                Node::Expr(ws, expr) => {
+                    self.handle_ws(*ws);
+                    let value = self.visit_expr(expr)?;
+                    self.parts.push(Part::Expr(value));
+                }
+                _ => return None,
+            }
+        }
+        self.ws.finish(|s| self.parts.push(Part::Lit(s)));
+        Some(())
+    }
+
+    fn visit_expr(&self, expr: &Expr<'_>) -> Option<&'a dyn fmt::Display> {
+        match expr {
+            Expr::Var(name) => Some(self.field(name)?.escaped),
+            Expr::Filter(Filter {
+                name: "safe",
+                arguments,
+            }) => match arguments.as_slice() {
+                [arg] => match **arg {
+                    Expr::Var(name) => Some(self.field(name)?.raw),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn field(&self, name: &str) -> Option<FieldValue<'a>> {
+        let fields = self.fields;
+        fields.iter().find(|(n, _)| *n == name)?.1
+    }
+
+    fn visit_lit(&mut self, lit: &'a Lit<'a>) {
+        self.ws.visit_lit(lit, |s| self.parts.push(Part::Lit(s)));
+    }
+
+    fn handle_ws(&mut self, ws: Ws) {
+        self.ws.handle_ws(ws, |s| self.parts.push(Part::Lit(s)));
+    }
+}
@@ -0,0 +1,136 @@
+//! Support for translated messages, i.e. `{% trans %}` blocks
+//!
+//! A template that uses `{% trans %}` blocks has to implement [`Translate`].
+//! Usually the implementation delegates to a message catalog stored in the template struct,
+//! e.g. a [`FluentBundle`](fluent_bundle::FluentBundle) if the feature `"fluent"` is enabled.
+//!
+//! ```
+//! # use std::fmt;
+//! # use rinja::Template;
+//! # use rinja::i18n::Translate;
+//! #[derive(Template)]
+//! #[template(
+//!     source = r#"{% trans "greeting" name = name %}Hello, {{ name }}!{% endtrans %}"#,
+//!     ext = "txt"
+//! )]
+//! struct Greeting<'a> {
+//!     name: &'a str,
+//!     lang: &'a str,
+//! }
+//!
+//! impl Translate for Greeting<'_> {
+//!     fn translate(&self, id: &str, args: &[(&str, &dyn fmt::Display)]) -> Option<String> {
+//!         match (self.lang, id, args) {
+//!             ("de", "greeting", [("name", name)]) => Some(format!("Hallo, {name}!")),
+//!             _ => None,
+//!         }
+//!     }
+//! }
+//!
+//! assert_eq!(Greeting { name: "Ferris", lang: "de" }.to_string(), "Hallo, Ferris!");
+//! // the content of the block is used if there is no translation
+//! assert_eq!(Greeting { name: "Ferris", lang: "en" }.to_string(), "Hello, Ferris!");
+//! assert_eq!(Greeting::MESSAGE_IDS, &["greeting"]);
+//! ```
+
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::fmt;
+
+/// Looks up translated messages for `{% trans %}` blocks
+pub trait Translate {
+    /// Returns the message `id` formatted with the named arguments `args`
+    ///
+    /// Returns `None` if there is no translation for the message, so the fallback content of
+    /// the `{% trans %}` block gets rendered instead. The result is escaped like any other
+    /// expression in the template.
+    fn translate(&self, id: &str, args: &[(&str, &dyn fmt::Display)]) -> Option<String>;
+}
+
+macro_rules! impl_for_ref {
+    ($($ty:ty)*) => { $(
+        impl<T: Translate + ?Sized> Translate for $ty {
+            #[inline]
+            fn translate(&self, id: &str, args: &[(&str, &dyn fmt::Display)]) -> Option<String> {
+                T::translate(self, id, args)
+            }
+        }
+    )* };
+}
+
+impl_for_ref!(&T &mut T Box<T> Rc<T> Arc<T>);
+
+#[cfg(feature = "fluent")]
+mod fluent {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+    use core::borrow::Borrow;
+
+    use fluent_bundle::bundle::FluentBundle;
+    use fluent_bundle::memoizer::MemoizerKind;
+    use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+
+    use super::*;
+
+    /// Formats the message `id` of the bundle
+    ///
+    /// Arguments that look like numbers are passed as numbers, so they can be used to select
+    /// plural forms. Formatting errors, e.g. missing arguments, are ignored.
+    impl<R: Borrow<FluentResource>, M: MemoizerKind> Translate for FluentBundle<R, M> {
+        fn translate(&self, id: &str, args: &[(&str, &dyn fmt::Display)]) -> Option<String> {
+            let pattern = self.get_message(id)?.value()?;
+            let values: Vec<String> = args.iter().map(|(_, value)| value.to_string()).collect();
+            let mut fluent_args = FluentArgs::with_capacity(args.len());
+            for ((name, _), value) in args.iter().zip(&values) {
+                fluent_args.set(*name, FluentValue::try_number(value));
+            }
+            let mut errors = Vec::new();
+            Some(
+                self.format_pattern(pattern, Some(&fluent_args), &mut errors)
+                    .into_owned(),
+            )
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use alloc::borrow::ToOwned;
+        use alloc::vec;
+
+        use fluent_bundle::FluentBundle;
+
+        use super::*;
+
+        #[test]
+        fn test_fluent() {
+            let ftl = "\
+hello = Hello, { $name }!
+emails = { $count ->
+    [one] One new email
+   *[other] { $count } new emails
+}
+";
+            let mut bundle = FluentBundle::new(vec!["en-US".parse().unwrap()]);
+            bundle.set_use_isolating(false);
+            bundle
+                .add_resource(FluentResource::try_new(ftl.to_owned()).unwrap())
+                .unwrap();
+
+            assert_eq!(
+                bundle.translate("hello", &[("name", &"Ferris")]).as_deref(),
+                Some("Hello, Ferris!"),
+            );
+            assert_eq!(
+                bundle.translate("emails", &[("count", &1)]).as_deref(),
+                Some("One new email"),
+            );
+            assert_eq!(
+                bundle.translate("emails", &[("count", &5)]).as_deref(),
+                Some("5 new emails"),
+            );
+            assert_eq!(bundle.translate("missing", &[]), None);
+        }
+    }
+}
@@ -50,21 +50,53 @@
 //! [Tera](https://github.com/Keats/tera).
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
+#![no_std]
 #![deny(elided_lifetimes_in_paths)]
 #![deny(unreachable_pub)]
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+#[cfg(feature = "buffer-pool")]
+pub mod buffer_pool;
+#[cfg(feature = "std")]
+pub mod build;
+pub mod cache;
+#[cfg(feature = "runtime")]
+mod environment;
 mod error;
+mod fast_writable;
 pub mod filters;
 pub mod helpers;
-
-use std::{fmt, io};
+#[cfg(feature = "hot-reload")]
+pub mod hot_reload;
+pub mod i18n;
+#[cfg(feature = "std")]
+pub mod observer;
+mod render_chunks;
+#[cfg(feature = "std")]
+pub mod testing;
+pub mod values;
+#[cfg(any(feature = "hot-reload", feature = "runtime"))]
+mod whitespace;
+
+use alloc::string::String;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
 
 pub use rinja_derive::Template;
 
 #[doc(hidden)]
 pub use crate as shared;
-pub use crate::error::{Error, Result};
+#[cfg(feature = "runtime")]
+pub use crate::environment::Environment;
+pub use crate::error::{CustomError, Error, PowError, Result};
+pub use crate::fast_writable::FastWritable;
+pub use crate::render_chunks::RenderChunks;
+pub use crate::values::{get_value, Values};
 
 /// Main `Template` trait; implementations are generally derived
 ///
@@ -72,29 +104,109 @@ pub use crate::error::{Error, Result};
 pub trait Template: fmt::Display {
     /// Helper method which allocates a new `String` and renders into it
     fn render(&self) -> Result<String> {
-        let mut buf = String::new();
-        let _ = buf.try_reserve(Self::SIZE_HINT);
-        self.render_into(&mut buf)?;
-        Ok(buf)
+        observe::<Self, _>(|| {
+            let mut buf = String::new();
+            let _ = buf.try_reserve(Self::SIZE_HINT);
+            self.render_into(&mut buf)?;
+            Ok(buf)
+        })
+    }
+
+    /// Helper method which renders the template into a [`BytesMut`][bytes::BytesMut] buffer
+    ///
+    /// The buffer is pre-sized from [`SIZE_HINT`][Template::SIZE_HINT], and frozen into
+    /// [`Bytes`][bytes::Bytes] afterwards, which can be handed to an HTTP stack as is.
+    #[cfg(feature = "bytes")]
+    #[inline]
+    fn render_bytes(&self) -> Result<bytes::Bytes> {
+        observe::<Self, _>(|| {
+            let mut buf = bytes::BytesMut::with_capacity(Self::SIZE_HINT);
+            self.render_into(&mut buf)?;
+            Ok(buf.freeze())
+        })
     }
 
     /// Renders the template to the given `writer` fmt buffer
     fn render_into<W: fmt::Write + ?Sized>(&self, writer: &mut W) -> Result<()>;
 
+    /// Helper method which allocates a new `String` and renders into it, with runtime `values`
+    /// that the template can read with [`get_value()`]
+    ///
+    /// See the module [`values`] for an example.
+    fn render_with_values(&self, values: &dyn Values) -> Result<String> {
+        observe::<Self, _>(|| {
+            let mut buf = String::new();
+            let _ = buf.try_reserve(Self::SIZE_HINT);
+            self.render_into_with_values(&mut buf, values)?;
+            Ok(buf)
+        })
+    }
+
+    /// Renders the template to the given `writer` fmt buffer, with runtime `values` that the
+    /// template can read with [`get_value()`]
+    ///
+    /// Derived implementations pass the values to [`get_value()`], other implementations
+    /// ignore them by default.
+    #[inline]
+    fn render_into_with_values<W: fmt::Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        values: &dyn Values,
+    ) -> Result<()> {
+        let _ = values;
+        self.render_into(writer)
+    }
+
     /// Renders the template to the given `writer` io buffer
+    #[cfg(feature = "std")]
     #[inline]
     fn write_into<W: io::Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
         writer.write_fmt(format_args!("{self}"))
     }
 
+    /// Renders the template into a sequence of chunks
+    ///
+    /// The whole template is rendered before this method returns, so the chunks are not streamed.
+    /// Static text of derived templates is borrowed as `&'static str`, while the output of
+    /// expressions is yielded as owned strings, so e.g. an HTTP response body can be built
+    /// without copying the static text.
+    #[inline]
+    fn render_chunks(&self) -> RenderChunks<'static> {
+        RenderChunks::new(self)
+    }
+
+    /// Renders the template to the given `writer` async io buffer
+    ///
+    /// The template is rendered into an intermediate buffer when this method is called, and the
+    /// returned future writes the result into `writer` without blocking the executor. Templates
+    /// derived with `render_async = true` skip the buffer: the returned future writes their output
+    /// into `writer` piece by piece while they are rendered, so a large page can be sent before it
+    /// is complete.
+    ///
+    /// The returned future is `Send` if everything it borrows is `Send`.
+    #[cfg(feature = "tokio")]
+    #[inline]
+    fn render_into_async<'a, W>(
+        &'a self,
+        writer: &'a mut W,
+    ) -> impl core::future::Future<Output = Result<()>> + 'a
+    where
+        W: tokio::io::AsyncWrite + Unpin + ?Sized,
+    {
+        let mut buf = String::new();
+        let _ = buf.try_reserve(Self::SIZE_HINT);
+        let result = self.render_into(&mut buf);
+        async move { helpers::finish_async(writer, &mut buf, result).await }
+    }
+
     /// The template's extension, if provided
     const EXTENSION: Option<&'static str>;
 
     /// Provides a rough estimate of the expanded length of the rendered template. Larger
     /// values result in higher memory usage but fewer reallocations. Smaller values result in the
-    /// opposite. This value only affects [`render`]. It does not take effect when calling
-    /// [`render_into`], [`write_into`], the [`fmt::Display`] implementation, or the blanket
-    /// [`ToString::to_string`] implementation.
+    /// opposite. This value only affects [`render`] and `render_bytes`. It does not take effect
+    /// when calling [`render_into`], [`write_into`], the [`fmt::Display`] implementation, or the
+    /// blanket [`ToString::to_string`] implementation.
     ///
     /// [`render`]: Template::render
     /// [`render_into`]: Template::render_into
@@ -103,6 +215,12 @@ pub trait Template: fmt::Display {
 
     /// The MIME type (Content-Type) of the data that gets rendered by this Template
     const MIME_TYPE: &'static str;
+
+    /// The IDs of all messages in `{% trans %}` blocks of this Template, sorted and deduplicated
+    ///
+    /// The list can be used to check that a message catalog contains every message
+    /// that is used in the template, see [`i18n`].
+    const MESSAGE_IDS: &'static [&'static str] = &[];
 }
 
 impl<T: Template + ?Sized> Template for &T {
@@ -116,16 +234,60 @@ impl<T: Template + ?Sized> Template for &T {
         T::render(self)
     }
 
+    #[inline]
+    fn render_into_with_values<W: fmt::Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        values: &dyn Values,
+    ) -> Result<()> {
+        T::render_into_with_values(self, writer, values)
+    }
+
+    #[inline]
+    fn render_chunks(&self) -> RenderChunks<'static> {
+        T::render_chunks(self)
+    }
+
+    #[cfg(feature = "std")]
     #[inline]
     fn write_into<W: io::Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
         T::write_into(self, writer)
     }
 
+    #[cfg(feature = "tokio")]
+    #[inline]
+    fn render_into_async<'a, W>(
+        &'a self,
+        writer: &'a mut W,
+    ) -> impl core::future::Future<Output = Result<()>> + 'a
+    where
+        W: tokio::io::AsyncWrite + Unpin + ?Sized,
+    {
+        T::render_into_async(self, writer)
+    }
+
     const EXTENSION: Option<&'static str> = T::EXTENSION;
 
     const SIZE_HINT: usize = T::SIZE_HINT;
 
     const MIME_TYPE: &'static str = T::MIME_TYPE;
+
+    const MESSAGE_IDS: &'static [&'static str] = T::MESSAGE_IDS;
+}
+
+// Reports the rendering of a `T` to the installed `RenderObserver`, if any.
+// There is no observer without `std`, so the template type is not needed then.
+#[inline]
+#[cfg_attr(not(feature = "std"), allow(clippy::extra_unused_type_parameters))]
+fn observe<T: ?Sized, R: AsRef<[u8]>>(render: impl FnOnce() -> Result<R>) -> Result<R> {
+    #[cfg(feature = "std")]
+    {
+        observer::observe::<T, R>(render)
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        render()
+    }
 }
 
 /// Object-safe wrapper trait around [`Template`] implementers
@@ -135,11 +297,36 @@ pub trait DynTemplate {
     /// Helper method which allocates a new `String` and renders into it
     fn dyn_render(&self) -> Result<String>;
 
+    /// Helper method which allocates a new `String` with room for at least `capacity` bytes,
+    /// and renders into it
+    ///
+    /// Use this method if you know better than [`size_hint()`][DynTemplate::size_hint]
+    /// how long the output will be, e.g. from previous renderings.
+    fn dyn_render_with_capacity(&self, capacity: usize) -> Result<String> {
+        let mut buf = String::new();
+        let _ = buf.try_reserve(capacity.max(self.size_hint()));
+        self.dyn_render_into(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Helper method which allocates a new `String` and renders into it, with runtime `values`
+    ///
+    /// Implementations for [`Template`]s pass the values to [`get_value()`], other
+    /// implementations ignore them by default.
+    fn dyn_render_with_values(&self, values: &dyn Values) -> Result<String> {
+        let _ = values;
+        self.dyn_render()
+    }
+
     /// Renders the template to the given `writer` fmt buffer
     fn dyn_render_into(&self, writer: &mut dyn fmt::Write) -> Result<()>;
 
     /// Renders the template to the given `writer` io buffer
-    fn dyn_write_into(&self, writer: &mut dyn io::Write) -> io::Result<()>;
+    #[cfg(feature = "std")]
+    fn dyn_write_into(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        let buf = self.dyn_render().map_err(io::Error::other)?;
+        writer.write_all(buf.as_bytes())
+    }
 
     /// Helper function to inspect the template's extension
     fn extension(&self) -> Option<&'static str>;
@@ -156,10 +343,24 @@ impl<T: Template> DynTemplate for T {
         <Self as Template>::render(self)
     }
 
+    fn dyn_render_with_capacity(&self, capacity: usize) -> Result<String> {
+        observe::<Self, _>(|| {
+            let mut buf = String::new();
+            let _ = buf.try_reserve(capacity.max(Self::SIZE_HINT));
+            <Self as Template>::render_into(self, &mut buf)?;
+            Ok(buf)
+        })
+    }
+
+    fn dyn_render_with_values(&self, values: &dyn Values) -> Result<String> {
+        <Self as Template>::render_with_values(self, values)
+    }
+
     fn dyn_render_into(&self, writer: &mut dyn fmt::Write) -> Result<()> {
         <Self as Template>::render_into(self, writer)
     }
 
+    #[cfg(feature = "std")]
     #[inline]
     fn dyn_write_into(&self, writer: &mut dyn io::Write) -> io::Result<()> {
         <Self as Template>::write_into(self, writer)
@@ -186,6 +387,8 @@ impl fmt::Display for dyn DynTemplate {
 
 #[cfg(test)]
 mod tests {
+    use alloc::format;
+    use alloc::string::ToString;
     use std::fmt;
 
     use super::*;
@@ -221,12 +424,58 @@ mod tests {
 
         assert_eq!(render(test), "test");
 
+        let buf = test.dyn_render_with_capacity(64).unwrap();
+        assert_eq!(buf, "test");
+        assert!(buf.capacity() >= 64);
+
         assert_eq!(test.to_string(), "test");
 
         assert_eq!(format!("{test}"), "test");
 
-        let mut vec = Vec::new();
-        test.dyn_write_into(&mut vec).unwrap();
-        assert_eq!(vec, vec![b't', b'e', b's', b't']);
+        #[cfg(feature = "std")]
+        {
+            let mut vec = alloc::vec::Vec::new();
+            test.dyn_write_into(&mut vec).unwrap();
+            assert_eq!(vec, alloc::vec![b't', b'e', b's', b't']);
+        }
+    }
+
+    #[test]
+    fn dyn_template_defaults() {
+        // Implementors that only provide the required methods get the others for free.
+        struct Test;
+        impl DynTemplate for Test {
+            fn dyn_render(&self) -> Result<String> {
+                Ok("test".into())
+            }
+
+            fn dyn_render_into(&self, writer: &mut dyn fmt::Write) -> Result<()> {
+                Ok(writer.write_str("test")?)
+            }
+
+            fn extension(&self) -> Option<&'static str> {
+                Some("txt")
+            }
+
+            fn size_hint(&self) -> usize {
+                4
+            }
+
+            fn mime_type(&self) -> &'static str {
+                "text/plain; charset=utf-8"
+            }
+        }
+
+        let buf = Test.dyn_render_with_capacity(64).unwrap();
+        assert_eq!(buf, "test");
+        assert!(buf.capacity() >= 64);
+        assert_eq!(Test.dyn_render_with_values(&()).unwrap(), "test");
+
+        #[cfg(feature = "std")]
+        {
+            let mut vec = alloc::vec::Vec::new();
+            Test.dyn_write_into(&mut vec).unwrap();
+            assert_eq!(vec, b"test");
+        }
     }
 }
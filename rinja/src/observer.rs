@@ -0,0 +1,139 @@
+//! Hooks to measure renderings, e.g. to export metrics about the performance of templates
+//!
+//! An application or a web framework integration installs a [`RenderObserver`] with
+//! [`set_observer()`]. Afterwards, every rendering with [`Template::render()`],
+//! [`Template::render_with_values()`], [`Template::render_bytes()`][bytes],
+//! [`DynTemplate::dyn_render_with_capacity()`] or [`buffer_pool::render()`] is reported to it,
+//! including how long it took and how long the output is. Renderings into a writer, e.g. with
+//! [`Template::render_into()`], are not reported.
+//!
+//! The target `wasm32-unknown-unknown`, e.g. in Cloudflare Workers, has no clock, so the duration
+//! of every rendering is reported as zero there.
+//!
+//! ```
+//! # use std::sync::atomic::{AtomicUsize, Ordering};
+//! # use std::time::Duration;
+//! # use rinja::Template;
+//! # use rinja::observer::{set_observer, RenderObserver};
+//! struct Metrics {
+//!     bytes: AtomicUsize,
+//! }
+//!
+//! impl RenderObserver for Metrics {
+//!     fn rendered(&self, template: &'static str, duration: Duration, size: usize) {
+//!         // e.g. update a Prometheus histogram, labeled with `template`
+//!         self.bytes.fetch_add(size, Ordering::Relaxed);
+//!     }
+//! }
+//!
+//! static METRICS: Metrics = Metrics { bytes: AtomicUsize::new(0) };
+//! assert!(set_observer(&METRICS));
+//!
+//! #[derive(Template)]
+//! #[template(source = "Hello, {{ name }}!", ext = "txt")]
+//! struct Hello<'a> {
+//!     name: &'a str,
+//! }
+//!
+//! Hello { name: "world" }.render().unwrap();
+//! assert_eq!(METRICS.bytes.load(Ordering::Relaxed), 13);
+//! ```
+//!
+//! [`Template::render()`]: crate::Template::render
+//! [`Template::render_with_values()`]: crate::Template::render_with_values
+//! [`DynTemplate::dyn_render_with_capacity()`]: crate::DynTemplate::dyn_render_with_capacity
+//! [`Template::render_into()`]: crate::Template::render_into
+//! [bytes]: crate::Template#method.render_bytes
+//! [`buffer_pool::render()`]: crate::buffer_pool::render
+
+use std::boxed::Box;
+use std::sync::OnceLock;
+use std::time::Duration;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Instant;
+
+use crate::{Error, Result};
+
+static OBSERVER: OnceLock<Box<dyn RenderObserver>> = OnceLock::new();
+
+/// Is told about every rendering of a template, see the [module documentation](self)
+pub trait RenderObserver: Send + Sync {
+    /// Called after `template` was rendered successfully
+    ///
+    /// `template` is the type name of the template, `size` is the length of the output in bytes.
+    fn rendered(&self, template: &'static str, duration: Duration, size: usize);
+
+    /// Called after rendering `template` failed
+    fn failed(&self, template: &'static str, duration: Duration, error: &Error) {
+        let _ = (template, duration, error);
+    }
+
+    /// Called when the feature `hot-reload` renders the compiled version of the template file at
+    /// `path`, because the file could not be interpreted, e.g. because it uses `{% if %}` blocks
+    ///
+    /// `reason` says why, e.g. `"could not be read"`.
+    #[cfg(feature = "hot-reload")]
+    fn hot_reload_fallback(&self, path: &str, reason: &str) {
+        let _ = (path, reason);
+    }
+}
+
+macro_rules! impl_for_ref {
+    ($($ty:ty)*) => { $(
+        impl<T: RenderObserver + ?Sized> RenderObserver for $ty {
+            #[inline]
+            fn rendered(&self, template: &'static str, duration: Duration, size: usize) {
+                T::rendered(self, template, duration, size)
+            }
+
+            #[inline]
+            fn failed(&self, template: &'static str, duration: Duration, error: &Error) {
+                T::failed(self, template, duration, error)
+            }
+
+            #[cfg(feature = "hot-reload")]
+            #[inline]
+            fn hot_reload_fallback(&self, path: &str, reason: &str) {
+                T::hot_reload_fallback(self, path, reason)
+            }
+        }
+    )* };
+}
+
+impl_for_ref!(&T Box<T> std::sync::Arc<T>);
+
+/// Installs the observer of all renderings
+///
+/// Returns `false` if an observer was installed before. It stays installed in that case.
+pub fn set_observer(observer: impl RenderObserver + 'static) -> bool {
+    OBSERVER.set(Box::new(observer)).is_ok()
+}
+
+/// Reports to the installed observer, if any, that the file at `path` could not be hot-reloaded
+#[cfg(feature = "hot-reload")]
+pub(crate) fn hot_reload_fallback(path: &str, reason: &str) {
+    if let Some(observer) = OBSERVER.get() {
+        observer.hot_reload_fallback(path, reason);
+    }
+}
+
+/// Reports the rendering of a `T` with `render` to the installed observer, if any
+pub(crate) fn observe<T: ?Sized, R: AsRef<[u8]>>(render: impl FnOnce() -> Result<R>) -> Result<R> {
+    let Some(observer) = OBSERVER.get() else {
+        return render();
+    };
+    let template = core::any::type_name::<T>();
+    // `Instant::now()` panics on `wasm32-unknown-unknown`, so nothing is timed there.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    let start = Instant::now();
+    let result = render();
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    let duration = start.elapsed();
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    let duration = Duration::ZERO;
+    match &result {
+        Ok(output) => observer.rendered(template, duration, output.as_ref().len()),
+        Err(err) => observer.failed(template, duration, err),
+    }
+    result
+}
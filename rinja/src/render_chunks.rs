@@ -0,0 +1,124 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::{self, Vec};
+use core::{fmt, mem};
+
+use crate::helpers::{RenderSink, Sink};
+use crate::{Error, Result, Template};
+
+/// Iterator returned by [`Template::render_chunks()`]
+///
+/// The template is rendered completely when the iterator is created, the iterator only hands
+/// out the result. It does not stream the output, and needs as much memory as
+/// [`Template::render()`]. Static text of a derived template is yielded as [`Cow::Borrowed`] chunks
+/// without copying it. The output of expressions, filters, etc. between two static chunks is
+/// collected in a single [`Cow::Owned`] chunk. Templates that are not derived, and templates
+/// of `enum` variants, are yielded as owned chunks.
+///
+/// If rendering fails, the chunks that were produced up to that point are yielded first,
+/// followed by the error.
+#[must_use = "the template is already rendered, its output is lost unless the chunks are consumed"]
+pub struct RenderChunks<'a> {
+    chunks: vec::IntoIter<Cow<'a, str>>,
+    err: Option<Error>,
+}
+
+impl<'a> RenderChunks<'a> {
+    pub(crate) fn new<T: Template + ?Sized>(tmpl: &T) -> Self {
+        Self::collect(|writer| tmpl.render_into(writer))
+    }
+
+    pub(crate) fn with_sink<T: RenderSink + ?Sized>(tmpl: &T) -> Self {
+        Self::collect(|writer| {
+            tmpl.render_into_sink(
+                &mut Sink::with_static(writer, ChunkWriter::write_static),
+                &(),
+            )
+        })
+    }
+
+    fn collect(render: impl FnOnce(&mut ChunkWriter<'a>) -> Result<()>) -> Self {
+        let mut writer = ChunkWriter::default();
+        let err = render(&mut writer).err();
+        let mut chunks = writer.chunks;
+        if !writer.pending.is_empty() {
+            chunks.push(Cow::Owned(writer.pending));
+        }
+        Self {
+            chunks: chunks.into_iter(),
+            err,
+        }
+    }
+}
+
+impl<'a> Iterator for RenderChunks<'a> {
+    type Item = Result<Cow<'a, str>>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.chunks.next() {
+            Some(chunk) => Some(Ok(chunk)),
+            None => self.err.take().map(Err),
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.chunks.len() + usize::from(self.err.is_some());
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for RenderChunks<'_> {}
+
+impl core::iter::FusedIterator for RenderChunks<'_> {}
+
+#[derive(Default)]
+struct ChunkWriter<'a> {
+    chunks: Vec<Cow<'a, str>>,
+    pending: String,
+}
+
+impl ChunkWriter<'_> {
+    fn write_static(&mut self, s: &'static str) -> fmt::Result {
+        if !s.is_empty() {
+            if !self.pending.is_empty() {
+                self.chunks.push(Cow::Owned(mem::take(&mut self.pending)));
+            }
+            self.chunks.push(Cow::Borrowed(s));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Write for ChunkWriter<'_> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.pending.push_str(s);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_chunk_writer() {
+        let mut writer = ChunkWriter::default();
+        let mut sink = Sink::with_static(&mut writer, ChunkWriter::write_static);
+        sink.write_str("<p>").unwrap();
+        Write::write_str(&mut sink, "a").unwrap();
+        let n = 1;
+        write!(sink, "{n}").unwrap();
+        sink.write_str("</p>").unwrap();
+        Write::write_str(&mut sink, &String::from("!")).unwrap();
+        assert!(matches!(writer.chunks[0], Cow::Borrowed("<p>")));
+        assert!(matches!(&writer.chunks[1], Cow::Owned(s) if s == "a1"));
+        assert!(matches!(writer.chunks[2], Cow::Borrowed("</p>")));
+        assert_eq!(writer.chunks.len(), 3);
+        assert_eq!(writer.pending, "!");
+    }
+}
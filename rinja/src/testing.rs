@@ -0,0 +1,233 @@
+//! Assertions for tests that compare the output of a template
+//!
+//! [`assert_rendered_eq_ignoring_ws!()`][crate::assert_rendered_eq_ignoring_ws] renders a
+//! template, and compares the output with a string, after collapsing all whitespace, so
+//! assertions don't break if the indentation or the whitespace control of a template changes.
+//!
+//! [`assert_rendered_matches_file!()`][crate::assert_rendered_matches_file] renders a template,
+//! and compares the output with the content of a file, which is relative to the crate root.
+//! If they differ, the assertion panics with a line diff of both.
+//!
+//! If the environment variable `RINJA_UPDATE_GOLDEN` is set to `1`, the file is overwritten
+//! with the output instead, e.g. after an intended change of a template:
+//!
+//! ```sh
+//! RINJA_UPDATE_GOLDEN=1 cargo test
+//! ```
+
+use std::path::Path;
+use std::string::String;
+use std::vec::Vec;
+use std::{format, fs, vec};
+
+use crate::Template;
+
+/// The environment variable that makes the assertions update the golden files
+pub const UPDATE_ENV_VAR: &str = "RINJA_UPDATE_GOLDEN";
+
+/// Asserts that a template renders to a string, if whitespace is ignored
+///
+/// Before the output and the expected string are compared, each sequence of whitespace
+/// characters is replaced with a single space, and whitespace at the start and at the end is
+/// removed.
+///
+/// ```
+/// # use rinja::Template;
+/// #[derive(Template)]
+/// #[template(
+///     source = "<ul>{% for item in items %}\n  <li>{{ item }}</li>\n{% endfor %}</ul>",
+///     ext = "html"
+/// )]
+/// struct List<'a> {
+///     items: &'a [&'a str],
+/// }
+///
+/// rinja::assert_rendered_eq_ignoring_ws!(
+///     List { items: &["a", "b"] },
+///     "<ul> <li>a</li> <li>b</li> </ul>",
+/// );
+/// ```
+#[macro_export]
+macro_rules! assert_rendered_eq_ignoring_ws {
+    ($tmpl:expr, $expected:expr $(,)?) => {
+        $crate::testing::assert_rendered_eq_ignoring_ws(&$tmpl, $expected)
+    };
+}
+
+/// Asserts that a template renders to the content of a file
+///
+/// The path of the file is relative to the crate root. See the [module documentation](self)
+/// for how the file is updated.
+///
+/// ```no_run
+/// # use rinja::Template;
+/// #[derive(Template)]
+/// #[template(source = "<h1>{{ title }}</h1>", ext = "html")]
+/// struct Card<'a> {
+///     title: &'a str,
+/// }
+///
+/// rinja::assert_rendered_matches_file!(Card { title: "Ferris" }, "tests/golden/card.html");
+/// ```
+#[macro_export]
+macro_rules! assert_rendered_matches_file {
+    ($tmpl:expr, $path:expr $(,)?) => {
+        $crate::testing::assert_rendered_matches_file(
+            &$tmpl,
+            ::std::path::Path::new(::core::env!("CARGO_MANIFEST_DIR")).join($path),
+        )
+    };
+}
+
+/// Asserts that `tmpl` renders to the content of the file at `path`
+///
+/// A relative `path` is relative to the current directory. Usually you want to use
+/// [`assert_rendered_matches_file!()`][crate::assert_rendered_matches_file] instead, which
+/// makes the path relative to the crate root.
+#[track_caller]
+pub fn assert_rendered_matches_file<T: Template + ?Sized>(tmpl: &T, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let rendered = match tmpl.render() {
+        Ok(rendered) => rendered,
+        Err(err) => panic!("could not render template for {}: {err}", path.display()),
+    };
+    let update = std::env::var_os(UPDATE_ENV_VAR).is_some_and(|value| value == "1");
+    if let Err(msg) = check(&rendered, path, update) {
+        panic!("{msg}");
+    }
+}
+
+/// Asserts that `tmpl` renders to `expected`, if whitespace is ignored
+///
+/// See [`assert_rendered_eq_ignoring_ws!()`][crate::assert_rendered_eq_ignoring_ws].
+#[track_caller]
+pub fn assert_rendered_eq_ignoring_ws<T: Template + ?Sized>(tmpl: &T, expected: &str) {
+    let rendered = match tmpl.render() {
+        Ok(rendered) => rendered,
+        Err(err) => panic!("could not render template: {err}"),
+    };
+    let (rendered, expected) = (normalize_ws(&rendered), normalize_ws(expected));
+    if rendered != expected {
+        panic!(
+            "rendered output does not match, ignoring whitespace\n\
+             rendered: {rendered:?}\n\
+             expected: {expected:?}",
+        );
+    }
+}
+
+// Replaces each sequence of whitespace with a single space, and trims the ends.
+fn normalize_ws(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn check(rendered: &str, path: &Path, update: bool) -> Result<(), String> {
+    if update {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|err| format!("could not create {}: {err}", dir.display()))?;
+        }
+        return fs::write(path, rendered)
+            .map_err(|err| format!("could not write {}: {err}", path.display()));
+    }
+
+    let expected = fs::read_to_string(path).map_err(|err| {
+        format!(
+            "could not read {}: {err}\n\
+             set {UPDATE_ENV_VAR}=1 to create it with the rendered output",
+            path.display(),
+        )
+    })?;
+    if expected == rendered {
+        return Ok(());
+    }
+    Err(format!(
+        "rendered output does not match {}\n\
+         set {UPDATE_ENV_VAR}=1 to update the file\n\
+         --- expected\n\
+         +++ rendered\n\
+         {}",
+        path.display(),
+        diff(&expected, rendered),
+    ))
+}
+
+// Returns the lines of `old` and `new`, prefixed with `-` if they were removed, with `+` if they
+// were added, and with ` ` if they are in both.
+fn diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.split('\n').collect();
+    let new: Vec<&str> = new.split('\n').collect();
+
+    // `lcs[i][j]` is the length of the longest common subsequence of `old[i..]` and `new[j..]`.
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = match old[i] == new[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+
+    let mut output = String::new();
+    let mut push = |prefix: char, line: &str| {
+        output.push(prefix);
+        output.push_str(line);
+        output.push('\n');
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            push(' ', old[i]);
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            push('-', old[i]);
+            i += 1;
+        } else {
+            push('+', new[j]);
+            j += 1;
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_ws() {
+        assert_eq!(normalize_ws(""), "");
+        assert_eq!(normalize_ws(" \n\t "), "");
+        assert_eq!(normalize_ws("a b"), "a b");
+        assert_eq!(normalize_ws("\n  <p>\n a \t b\n</p>\n"), "<p> a b </p>");
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("a\nb\nc", "a\nb\nc"), " a\n b\n c\n");
+        assert_eq!(diff("a\nb\nc", "a\nx\nc"), " a\n-b\n+x\n c\n");
+        assert_eq!(diff("a\nc", "a\nb\nc"), " a\n+b\n c\n");
+        assert_eq!(diff("a\nb\n", "a\nb"), " a\n b\n-\n");
+    }
+
+    #[test]
+    fn test_check() {
+        let path = std::env::temp_dir()
+            .join(format!("rinja-golden-{}", std::process::id()))
+            .join("card.html");
+        let err = check("<h1>Ferris</h1>", &path, false).unwrap_err();
+        assert!(err.starts_with("could not read "), "{err}");
+
+        check("<h1>Ferris</h1>", &path, true).unwrap();
+        check("<h1>Ferris</h1>", &path, false).unwrap();
+        let err = check("<h1>Corro</h1>", &path, false).unwrap_err();
+        assert!(
+            err.ends_with("-<h1>Ferris</h1>\n+<h1>Corro</h1>\n"),
+            "{err}"
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+//! Values that are passed to a template at runtime, with [`Template::render_with_values()`]
+//!
+//! Values are e.g. per-request data like the locale or a CSP nonce, which a web middleware
+//! wants to make available to every template, without adding it to every template struct.
+//! A template reads them with `rinja::get_value::<T>("key")`, which returns an error if there
+//! is no value for the key, or if it is not of type `T`:
+//!
+//! ```
+//! # use std::collections::HashMap;
+//! # use std::any::Any;
+//! # use rinja::Template;
+//! #[derive(Template)]
+//! #[template(
+//!     source = r#"<script nonce="{{ rinja::get_value::<String>("nonce")? }}"></script>"#,
+//!     ext = "html"
+//! )]
+//! struct Page;
+//!
+//! let mut values: HashMap<&str, Box<dyn Any>> = HashMap::new();
+//! values.insert("nonce", Box::new("c2VjcmV0".to_owned()));
+//! assert_eq!(
+//!     Page.render_with_values(&values).unwrap(),
+//!     r#"<script nonce="c2VjcmV0"></script>"#,
+//! );
+//! assert!(Page.render().is_err());
+//! ```
+//!
+//! [`Template::render_with_values()`]: crate::Template::render_with_values
+
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use core::any::Any;
+use core::borrow::Borrow;
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::{Error, Result};
+
+/// A collection of values that are looked up by their key
+pub trait Values {
+    /// Returns the value stored for `key`
+    fn get_value<'a>(&'a self, key: &str) -> Option<&'a dyn Any>;
+}
+
+/// Returns the value stored for `key` in `values`, if it is of type `T`
+///
+/// Values stored as `Box<dyn Any>`, e.g. in a map that contains values of different types,
+/// are unboxed.
+pub fn get_value<'a, T: Any>(values: &'a dyn Values, key: &str) -> Result<&'a T> {
+    let Some(value) = values.get_value(key) else {
+        return Err(Error::ValueMissing(key.to_owned()));
+    };
+    let value = if let Some(value) = value.downcast_ref::<Box<dyn Any>>() {
+        &**value
+    } else if let Some(value) = value.downcast_ref::<Box<dyn Any + Send + Sync>>() {
+        &**value
+    } else {
+        value
+    };
+    value
+        .downcast_ref()
+        .ok_or_else(|| Error::ValueType(key.to_owned()))
+}
+
+/// No values at all
+impl Values for () {
+    #[inline]
+    fn get_value<'a>(&'a self, _: &str) -> Option<&'a dyn Any> {
+        None
+    }
+}
+
+impl<K: Borrow<str>, V: Any> Values for [(K, V)] {
+    fn get_value<'a>(&'a self, key: &str) -> Option<&'a dyn Any> {
+        self.iter()
+            .find_map(|(k, v)| (k.borrow() == key).then_some(v as &dyn Any))
+    }
+}
+
+impl<K: Borrow<str>, V: Any, const N: usize> Values for [(K, V); N] {
+    #[inline]
+    fn get_value<'a>(&'a self, key: &str) -> Option<&'a dyn Any> {
+        self.as_slice().get_value(key)
+    }
+}
+
+impl<K: Borrow<str> + Ord, V: Any> Values for BTreeMap<K, V> {
+    #[inline]
+    fn get_value<'a>(&'a self, key: &str) -> Option<&'a dyn Any> {
+        Some(self.get(key)? as &dyn Any)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Borrow<str> + Eq + Hash, V: Any, S: BuildHasher> Values for HashMap<K, V, S> {
+    #[inline]
+    fn get_value<'a>(&'a self, key: &str) -> Option<&'a dyn Any> {
+        Some(self.get(key)? as &dyn Any)
+    }
+}
+
+macro_rules! impl_for_ref {
+    ($($ty:ty)*) => { $(
+        impl<T: Values + ?Sized> Values for $ty {
+            #[inline]
+            fn get_value<'a>(&'a self, key: &str) -> Option<&'a dyn Any> {
+                T::get_value(self, key)
+            }
+        }
+    )* };
+}
+
+impl_for_ref!(&T &mut T Box<T> Rc<T> Arc<T>);
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use super::*;
+
+    #[test]
+    fn test_get_value() {
+        let values = [("a", 1u32), ("b", 2)];
+        assert_eq!(get_value::<u32>(&values, "b").unwrap(), &2);
+        assert!(matches!(
+            get_value::<u32>(&values, "c"),
+            Err(Error::ValueMissing(key)) if key == "c",
+        ));
+        assert!(matches!(
+            get_value::<i32>(&values, "a"),
+            Err(Error::ValueType(key)) if key == "a",
+        ));
+        assert!(matches!(
+            get_value::<u32>(&(), "a"),
+            Err(Error::ValueMissing(_))
+        ));
+
+        let mut values: BTreeMap<&str, Box<dyn Any>> = BTreeMap::new();
+        values.insert("name", Box::new(String::from("Ferris")));
+        values.insert("age", Box::new(7u8));
+        assert_eq!(get_value::<String>(&values, "name").unwrap(), "Ferris");
+        assert_eq!(get_value::<u8>(&values, "age").unwrap(), &7);
+        assert!(get_value::<u32>(&values, "age").is_err());
+    }
+}
@@ -0,0 +1,79 @@
+//! Whitespace handling of the runtime interpreters, the same as in the code generator
+//!
+//! Used by the interpreter of the `hot-reload` feature and by [`crate::Environment`].
+
+use parser::node::{Lit, Whitespace, Ws};
+
+/// The whitespace around literals that is pending until the next tag decides what to do with it
+pub(crate) struct WsState<'a> {
+    // The handling of tags without `-`, `+` or `~`
+    default: Whitespace,
+    next_ws: Option<&'a str>,
+    skip_ws: Whitespace,
+}
+
+impl<'a> WsState<'a> {
+    pub(crate) fn new(default: Whitespace) -> Self {
+        Self {
+            default,
+            next_ws: None,
+            skip_ws: default,
+        }
+    }
+
+    /// Emits the literal, and keeps its trailing whitespace until the next tag
+    pub(crate) fn visit_lit(&mut self, lit: &'a Lit<'a>, mut emit: impl FnMut(&'a str)) {
+        let Lit { lws, val, rws } = *lit;
+        if !lws.is_empty() {
+            match self.skip_ws {
+                Whitespace::Suppress => {}
+                _ if val.is_empty() => self.next_ws = Some(lws),
+                Whitespace::Preserve => emit(lws),
+                Whitespace::Minimize => emit(minimized(lws)),
+            }
+        }
+        if !val.is_empty() {
+            self.skip_ws = Whitespace::Preserve;
+            emit(val);
+        }
+        if !rws.is_empty() {
+            self.next_ws = Some(rws);
+        }
+    }
+
+    pub(crate) fn handle_ws(&mut self, ws: Ws, emit: impl FnMut(&'a str)) {
+        self.flush_ws(ws, emit);
+        self.prepare_ws(ws);
+    }
+
+    /// Emits the pending whitespace as the start of the tag `ws` wants it
+    pub(crate) fn flush_ws(&mut self, ws: Ws, mut emit: impl FnMut(&'a str)) {
+        let Some(val) = self.next_ws.take() else {
+            return;
+        };
+        match ws.0.unwrap_or(self.default) {
+            Whitespace::Preserve if !val.is_empty() => emit(val),
+            Whitespace::Minimize if !val.is_empty() => emit(minimized(val)),
+            _ => {}
+        }
+    }
+
+    /// Remembers how the end of the tag `ws` wants the leading whitespace of the next literal
+    pub(crate) fn prepare_ws(&mut self, ws: Ws) {
+        self.skip_ws = ws.1.unwrap_or(self.default);
+    }
+
+    /// Emits the whitespace that is still pending at the end of the template
+    pub(crate) fn finish(&mut self, emit: impl FnMut(&'a str)) {
+        if self.next_ws.is_some() {
+            self.flush_ws(Ws(Some(self.skip_ws), None), emit);
+        }
+    }
+}
+
+fn minimized(ws: &str) -> &'static str {
+    match ws.contains('\n') {
+        true => "\n",
+        false => " ",
+    }
+}
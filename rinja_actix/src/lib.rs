@@ -22,6 +22,9 @@ pub fn into_response<T: ?Sized + rinja::Template>(tmpl: &T) -> HttpResponse<BoxB
 pub fn try_into_response<T: ?Sized + rinja::Template>(
     tmpl: &T,
 ) -> Result<HttpResponse<BoxBody>, Error> {
+    #[cfg(feature = "buffer-pool")]
+    let value = bytes::Bytes::from_owner(rinja::buffer_pool::render(tmpl)?);
+    #[cfg(not(feature = "buffer-pool"))]
     let value = tmpl.render()?;
     Ok(HttpResponseBuilder::new(StatusCode::OK)
         .content_type(HeaderValue::from_static(T::MIME_TYPE))
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::{env, fs};
+
+use rinja_derive_standalone::check_templates;
+
+const USAGE: &str = "\
+Usage: rinja-cli check [OPTIONS]
+
+Checks all templates in the configured template directories of a crate.
+
+Options:
+    --manifest-dir <DIR>  The directory of the crate's `Cargo.toml` [default: .]
+    --config <PATH>       The configuration file, relative to the crate [default: rinja.toml]
+    -h, --help            Print this help";
+
+fn main() -> ExitCode {
+    match parse_args(env::args().skip(1)) {
+        Ok(Some(args)) => check(args),
+        Ok(None) => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+struct Args {
+    manifest_dir: PathBuf,
+    config: Option<String>,
+}
+
+/// Returns `None` if the help was requested
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    match args.next().as_deref() {
+        Some("check") => {}
+        Some("-h" | "--help") => return Ok(None),
+        Some(command) => return Err(format!("unknown command `{command}`")),
+        None => return Err("missing command".to_string()),
+    }
+
+    let mut result = Args {
+        manifest_dir: PathBuf::from("."),
+        config: None,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for `{arg}`"))
+        };
+        match arg.as_str() {
+            "--manifest-dir" => result.manifest_dir = PathBuf::from(value()?),
+            "--config" => result.config = Some(value()?),
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("unknown argument `{arg}`")),
+        }
+    }
+    Ok(Some(result))
+}
+
+fn check(args: Args) -> ExitCode {
+    let manifest_dir = match fs::canonicalize(&args.manifest_dir) {
+        Ok(manifest_dir) => manifest_dir,
+        Err(err) => {
+            eprintln!("error: unable to open {:?}: {err}", args.manifest_dir);
+            return ExitCode::from(2);
+        }
+    };
+    // The configuration is looked up like in the derive macro, which runs in the crate's directory.
+    env::set_var("CARGO_MANIFEST_DIR", manifest_dir);
+
+    let results = match check_templates(args.config.as_deref()) {
+        Ok(results) => results,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut errors = 0;
+    for template in &results {
+        if let Some(err) = &template.error {
+            eprintln!("error: {err}\n  --> in template {:?}\n", template.name);
+            errors += 1;
+        }
+    }
+    println!("checked {} templates, found {errors} errors", results.len());
+    match errors {
+        0 => ExitCode::SUCCESS,
+        _ => ExitCode::FAILURE,
+    }
+}
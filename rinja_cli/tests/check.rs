@@ -0,0 +1,53 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn check(dir: &str) -> Output {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(dir);
+    Command::new(env!("CARGO_BIN_EXE_rinja-cli"))
+        .arg("check")
+        .arg("--manifest-dir")
+        .arg(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_check_valid() {
+    let output = check("valid");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "checked 4 templates, found 0 errors\n"
+    );
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+}
+
+#[test]
+fn test_check_invalid() {
+    let output = check("invalid");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "checked 3 templates, found 2 errors\n"
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("template \"missing.html\" not found"));
+    assert!(stderr.contains("--> in template \"missing-include.html\""));
+    assert!(stderr.contains("failed to parse template source"));
+    assert!(stderr.contains("--> in template \"syntax.html\""));
+    assert!(!stderr.contains("\"valid.html\""));
+}
+
+#[test]
+fn test_check_usage() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rinja-cli"))
+        .arg("fix")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("error: unknown command `fix`\n"));
+}
@@ -0,0 +1,69 @@
+//! Checks the templates of a crate without compiling it, used by `rinja-cli check`
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::config::{read_config_file, Config};
+use crate::heritage::Context;
+use crate::input::{TemplateArgs, TemplateInput};
+use crate::CompileError;
+
+/// A template that was checked by [`check_templates()`]
+#[derive(Debug, Clone)]
+pub struct CheckedTemplate {
+    /// The name of the template, relative to its template directory
+    pub name: String,
+    /// The error the `Template` derive macro would report for the template, if any
+    pub error: Option<String>,
+}
+
+/// Parses every template in the configured template directories, and all templates it uses, and
+/// reports the errors the `Template` derive macro would report for it
+///
+/// Like for the derive macro, the configuration file and relative template directories are looked
+/// up in the directory the `CARGO_MANIFEST_DIR` environment variable points to. Expressions are
+/// not type checked, and templates with a dynamic `{% include %}` or `{% extends %}` are checked
+/// without the list of templates from `#[template(includes = [...])]` or `extends = [...]`.
+///
+/// Returns every checked template, sorted by name.
+pub fn check_templates(config_path: Option<&str>) -> Result<Vec<CheckedTemplate>, String> {
+    let (source, config_file) = read_config_file(config_path).map_err(|err| err.msg)?;
+    let config = Config::new(&source, config_path, None).map_err(|err| err.msg)?;
+
+    // A template in a later directory is shadowed by a template of the same name in an earlier
+    // one, so every name only needs to be checked once.
+    let mut names = BTreeSet::new();
+    for provider in &config.providers {
+        provider.collect_names(&mut names)?;
+    }
+
+    Ok(names
+        .into_iter()
+        .map(|name| {
+            let error = check_template(&name, config, config_file.as_deref()).err();
+            CheckedTemplate {
+                name,
+                error: error.map(|err| err.msg),
+            }
+        })
+        .collect())
+}
+
+fn check_template(
+    name: &str,
+    config: &Config,
+    config_file: Option<&Path>,
+) -> Result<(), CompileError> {
+    let ast: syn::DeriveInput = syn::parse_quote!(
+        struct __Rinja__Check;
+    );
+    let template_args = TemplateArgs::for_path(name);
+    let input = TemplateInput::new(&ast, config, config_file, &template_args)?;
+
+    let mut templates = HashMap::new();
+    input.find_used_templates(&mut templates)?;
+    for (path, parsed) in &templates {
+        Context::new(input.config, path, parsed)?;
+    }
+    Ok(())
+}
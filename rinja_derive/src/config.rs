@@ -4,23 +4,49 @@ use std::mem::transmute;
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, OnceLock};
-use std::{env, fs};
+use std::{env, fs, io};
 
 use once_map::sync::OnceMap;
-use parser::node::Whitespace;
+use parser::node::{Whitespace, BUILTIN_TAGS};
 use parser::{ParseError, Parsed, Syntax};
 #[cfg(feature = "config")]
 use serde::Deserialize;
 
+use crate::provider::{Directories, InMemory, TemplateProvider};
 use crate::{CompileError, FileInfo, CRATE};
 
 #[derive(Debug)]
 pub(crate) struct Config {
     pub(crate) dirs: Vec<PathBuf>,
+    /// Where templates are looked up, in order: the `dirs`, then the `[templates]` table
+    pub(crate) providers: Vec<Box<dyn TemplateProvider>>,
     pub(crate) syntaxes: BTreeMap<String, SyntaxAndCache<'static>>,
     pub(crate) default_syntax: &'static str,
     pub(crate) escapers: Vec<(Vec<Cow<'static, str>>, Cow<'static, str>)>,
     pub(crate) whitespace: WhitespaceHandling,
+    pub(crate) trim_blocks: bool,
+    pub(crate) lstrip_blocks: bool,
+    /// Write HTML comments that tell which template produced which part of the output
+    pub(crate) debug_origins: bool,
+    /// Warn about unused `{% let %}`s, macros and imports in templates
+    pub(crate) warn_unused: bool,
+    pub(crate) filter_modules: Vec<&'static str>,
+    /// Functions that can be called by name in every template, configured with `[globals]`
+    pub(crate) globals: BTreeMap<&'static str, &'static str>,
+    /// Constants and statics that can be used by name in every template, configured with
+    /// `[constants]`
+    pub(crate) constants: BTreeMap<&'static str, &'static str>,
+    /// Custom tags, configured with `[tags]`: the name of each tag, and the path of the macro
+    /// that renders it
+    pub(crate) tags: BTreeMap<&'static str, &'static str>,
+    /// The names of the `tags`, which the parser needs to know
+    pub(crate) extension_tags: &'static [&'static str],
+    /// The names of the `tags` that have a body, configured with `[block_tags]`
+    pub(crate) extension_block_tags: &'static [&'static str],
+    /// Filters that are expanded by macros, configured with `[filters.macros]`: the name of
+    /// each filter, and the path of the macro
+    pub(crate) filter_macros: BTreeMap<&'static str, &'static str>,
+    pub(crate) directories: Vec<DirectoryConfig>,
     // `Config` is self referential and `_key` owns it data, so it must come last
     _key: OwnedConfigKey,
 }
@@ -32,6 +58,17 @@ impl Drop for Config {
     }
 }
 
+/// Settings for the templates in a directory, configured with `[[directory]]`
+#[derive(Debug)]
+pub(crate) struct DirectoryConfig {
+    pub(crate) path: PathBuf,
+    pub(crate) escape: Option<&'static str>,
+    pub(crate) syntax: Option<&'static str>,
+    pub(crate) whitespace: Option<WhitespaceHandling>,
+    pub(crate) trim_blocks: Option<bool>,
+    pub(crate) lstrip_blocks: Option<bool>,
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 struct OwnedConfigKey(Arc<ConfigKey<'static>>);
 
@@ -105,34 +142,117 @@ impl Config {
         let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
         let default_dirs = vec![root.join("templates")];
 
-        let mut syntaxes = BTreeMap::new();
-        syntaxes.insert(DEFAULT_SYNTAX_NAME.to_string(), SyntaxAndCache::default());
-
         let raw = if s.is_empty() {
             RawConfig::default()
         } else {
             RawConfig::from_toml_str(s)?
         };
 
-        let (dirs, default_syntax, mut whitespace) = match raw.general {
+        let file_info = config_path.map(|path| FileInfo::new(Path::new(path), None, None));
+
+        let mut tags = BTreeMap::new();
+        let block_tags = raw.block_tags.unwrap_or_default();
+        for (name, path) in raw
+            .tags
+            .unwrap_or_default()
+            .into_iter()
+            .chain(block_tags.clone())
+        {
+            if syn::parse_str::<syn::Ident>(name).is_err() {
+                return Err(CompileError::new(
+                    format!("invalid tag name: {name:?}"),
+                    file_info,
+                ));
+            }
+            if BUILTIN_TAGS.contains(&name) {
+                return Err(CompileError::new(
+                    format!("tag {name:?} is a built-in tag"),
+                    file_info,
+                ));
+            }
+            if syn::parse_str::<syn::Path>(path).is_err() {
+                return Err(CompileError::new(
+                    format!("invalid macro path of tag {name:?}: {path:?}"),
+                    file_info,
+                ));
+            }
+            if tags.insert(name, path).is_some() {
+                return Err(CompileError::new(
+                    format!("tag {name:?} is defined in `[tags]` and in `[block_tags]`"),
+                    file_info,
+                ));
+            }
+        }
+        for name in block_tags.keys() {
+            let end = format!("end{name}");
+            if BUILTIN_TAGS.contains(&end.as_str()) || tags.contains_key(end.as_str()) {
+                return Err(CompileError::new(
+                    format!("the end tag of block tag {name:?} is the tag {end:?}"),
+                    file_info,
+                ));
+            }
+        }
+        // A `Config` is never dropped, so the names may as well be leaked.
+        let extension_tags: &'static [&'static str] =
+            Box::leak(tags.keys().copied().collect::<Vec<_>>().into_boxed_slice());
+        let extension_block_tags: &'static [&'static str] = Box::leak(
+            block_tags
+                .into_keys()
+                .collect::<Vec<_>>()
+                .into_boxed_slice(),
+        );
+
+        let mut syntaxes = BTreeMap::new();
+        syntaxes.insert(
+            DEFAULT_SYNTAX_NAME.to_string(),
+            SyntaxAndCache::new(Syntax {
+                extension_tags,
+                extension_block_tags,
+                ..Syntax::default()
+            }),
+        );
+        let (
+            dirs,
+            default_syntax,
+            mut whitespace,
+            trim_blocks,
+            lstrip_blocks,
+            debug_origins,
+            warn_unused,
+        ) = match raw.general {
             Some(General {
                 dirs,
                 default_syntax,
                 whitespace,
+                trim_blocks,
+                lstrip_blocks,
+                debug_origins,
+                warn_unused,
             }) => (
-                dirs.map_or(default_dirs, |v| {
-                    v.into_iter().map(|dir| root.join(dir)).collect()
-                }),
+                match dirs {
+                    Some(dirs) => dirs
+                        .into_iter()
+                        .map(|dir| Ok(root.join(expand_path(dir, config_path)?)))
+                        .collect::<Result<_, CompileError>>()?,
+                    None => default_dirs,
+                },
                 default_syntax.unwrap_or(DEFAULT_SYNTAX_NAME),
                 whitespace,
+                trim_blocks,
+                lstrip_blocks,
+                debug_origins,
+                warn_unused,
             ),
             None => (
                 default_dirs,
                 DEFAULT_SYNTAX_NAME,
                 WhitespaceHandling::default(),
+                false,
+                false,
+                false,
+                false,
             ),
         };
-        let file_info = config_path.map(|path| FileInfo::new(Path::new(path), None, None));
         if let Some(template_whitespace) = template_whitespace {
             whitespace = match template_whitespace {
                 "suppress" => WhitespaceHandling::Suppress,
@@ -152,7 +272,24 @@ impl Config {
                 let name = raw_s.name;
                 match syntaxes.entry(name.to_string()) {
                     Entry::Vacant(entry) => {
-                        entry.insert(SyntaxAndCache::new(raw_s.try_into()?));
+                        let syntax: Syntax<'_> = raw_s.try_into()?;
+                        if let Some((alias, _)) = syntax
+                            .keyword_aliases
+                            .iter()
+                            .find(|(alias, _)| extension_tags.contains(alias))
+                        {
+                            return Err(CompileError::new(
+                                format!(
+                                    "keyword alias {alias:?} of syntax {name:?} is a custom tag"
+                                ),
+                                file_info,
+                            ));
+                        }
+                        entry.insert(SyntaxAndCache::new(Syntax {
+                            extension_tags,
+                            extension_block_tags,
+                            ..syntax
+                        }));
                     }
                     Entry::Occupied(_) => {
                         return Err(CompileError::new(
@@ -171,6 +308,103 @@ impl Config {
             ));
         }
 
+        let mut filter_modules = Vec::new();
+        let mut filter_macros = BTreeMap::new();
+        if let Some(RawFilters { modules, macros }) = raw.filters {
+            for module in modules {
+                if syn::parse_str::<syn::Path>(module).is_err() {
+                    return Err(CompileError::new(
+                        format!("invalid filter module path: {module:?}"),
+                        file_info,
+                    ));
+                }
+                filter_modules.push(module);
+            }
+            for (name, path) in macros.unwrap_or_default() {
+                if syn::parse_str::<syn::Ident>(name).is_err() {
+                    return Err(CompileError::new(
+                        format!("invalid filter name: {name:?}"),
+                        file_info,
+                    ));
+                }
+                if crate::BUILT_IN_FILTERS.contains(&name) {
+                    return Err(CompileError::new(
+                        format!("filter {name:?} is a built-in filter"),
+                        file_info,
+                    ));
+                }
+                if syn::parse_str::<syn::Path>(path).is_err() {
+                    return Err(CompileError::new(
+                        format!("invalid macro path of filter {name:?}: {path:?}"),
+                        file_info,
+                    ));
+                }
+                filter_macros.insert(name, path);
+            }
+        }
+
+        let mut globals = BTreeMap::new();
+        for (name, path) in raw.globals.unwrap_or_default() {
+            if syn::parse_str::<syn::Ident>(name).is_err() {
+                return Err(CompileError::new(
+                    format!("invalid global function name: {name:?}"),
+                    file_info,
+                ));
+            }
+            if syn::parse_str::<syn::Path>(path).is_err() {
+                return Err(CompileError::new(
+                    format!("invalid path of global function {name:?}: {path:?}"),
+                    file_info,
+                ));
+            }
+            globals.insert(name, path);
+        }
+
+        let mut constants = BTreeMap::new();
+        for (name, path) in raw.constants.unwrap_or_default() {
+            if syn::parse_str::<syn::Ident>(name).is_err() {
+                return Err(CompileError::new(
+                    format!("invalid constant name: {name:?}"),
+                    file_info,
+                ));
+            }
+            if syn::parse_str::<syn::Path>(path).is_err() {
+                return Err(CompileError::new(
+                    format!("invalid path of constant {name:?}: {path:?}"),
+                    file_info,
+                ));
+            }
+            constants.insert(name, path);
+        }
+
+        let mut directories = Vec::new();
+        for dir in raw.directory.unwrap_or_default() {
+            if let Some(syntax) = dir.syntax {
+                if !syntaxes.contains_key(syntax) {
+                    return Err(CompileError::new(
+                        format!("syntax \"{syntax}\" of directory {:?} not found", dir.path),
+                        file_info,
+                    ));
+                }
+            }
+            directories.push(DirectoryConfig {
+                path: root.join(expand_path(dir.path, config_path)?),
+                escape: dir.escape,
+                syntax: dir.syntax,
+                whitespace: dir.whitespace,
+                trim_blocks: dir.trim_blocks,
+                lstrip_blocks: dir.lstrip_blocks,
+            });
+        }
+
+        let mut providers: Vec<Box<dyn TemplateProvider>> =
+            vec![Box::new(Directories(dirs.clone()))];
+        if let Some(templates) = raw.templates {
+            // The templates get paths below the configuration file, which cannot exist.
+            let root = root.join(config_path.unwrap_or(CONFIG_FILE_NAME));
+            providers.push(Box::new(InMemory::new(root, templates)));
+        }
+
         let mut escapers = Vec::new();
         if let Some(configured) = raw.escaper {
             for escaper in configured {
@@ -186,10 +420,23 @@ impl Config {
 
         Ok(Arc::new(Config {
             dirs,
+            providers,
             syntaxes,
             default_syntax,
             escapers,
             whitespace,
+            trim_blocks,
+            lstrip_blocks,
+            debug_origins,
+            warn_unused,
+            filter_modules,
+            globals,
+            constants,
+            tags,
+            extension_tags,
+            extension_block_tags,
+            filter_macros,
+            directories,
             _key: key,
         }))
     }
@@ -201,22 +448,49 @@ impl Config {
     ) -> Result<Arc<Path>, CompileError> {
         if let Some(root) = start_at {
             let relative = root.with_file_name(path);
-            if relative.exists() {
+            if self.providers.iter().any(|p| p.contains(&relative)) {
                 return Ok(relative.into());
             }
         }
 
-        for dir in &self.dirs {
-            let rooted = dir.join(path);
-            if rooted.exists() {
-                return Ok(rooted.into());
+        for provider in &self.providers {
+            if let Some(path) = provider.find(path) {
+                return Ok(path.into());
             }
         }
 
-        Err(CompileError::no_file_info(format!(
+        let mut err = CompileError::no_file_info(format!(
             "template {:?} not found in directories {:?}",
             path, self.dirs
-        )))
+        ));
+        err.location = start_at.map(|root| (root.to_path_buf(), None));
+        Err(err)
+    }
+
+    /// Reads the source of the template at `path` from the provider that contains it
+    pub(crate) fn read_template(&self, path: &Path) -> io::Result<String> {
+        match self.providers.iter().find(|p| p.contains(path)) {
+            Some(provider) => provider.read(path),
+            None => fs::read_to_string(path),
+        }
+    }
+
+    /// Returns `true` if the template at `path` is a file, and not e.g. defined in `rinja.toml`
+    pub(crate) fn is_template_file(&self, path: &Path) -> bool {
+        self.providers
+            .iter()
+            .find(|p| p.contains(path))
+            .map_or(true, |provider| provider.is_file())
+    }
+
+    /// Returns the settings of the configured `[[directory]]` containing the template at `path`
+    ///
+    /// If the template is contained in multiple configured directories, the innermost one wins.
+    pub(crate) fn find_directory(&self, path: &Path) -> Option<&DirectoryConfig> {
+        self.directories
+            .iter()
+            .filter(|dir| path.starts_with(&dir.path))
+            .max_by_key(|dir| dir.path.components().count())
     }
 }
 
@@ -296,11 +570,112 @@ impl<'a> SyntaxAndCache<'a> {
     }
 }
 
+/// The delimiters given in the `syntax_inline(..)` argument of the `template()` attribute
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) struct InlineSyntax {
+    pub(crate) block_start: Option<String>,
+    pub(crate) block_end: Option<String>,
+    pub(crate) expr_start: Option<String>,
+    pub(crate) expr_end: Option<String>,
+    pub(crate) comment_start: Option<String>,
+    pub(crate) comment_end: Option<String>,
+    pub(crate) line_statement_prefix: Option<String>,
+    /// The `extension_tags` and `extension_block_tags` of the configuration, which are not
+    /// arguments of `syntax_inline`
+    extension_tags: &'static [&'static str],
+    extension_block_tags: &'static [&'static str],
+}
+
+impl InlineSyntax {
+    /// Returns the delimiter called `name`, e.g. `"block_start"`
+    pub(crate) fn delimiter_mut(&mut self, name: &str) -> Option<&mut Option<String>> {
+        Some(match name {
+            "block_start" => &mut self.block_start,
+            "block_end" => &mut self.block_end,
+            "expr_start" => &mut self.expr_start,
+            "expr_end" => &mut self.expr_end,
+            "comment_start" => &mut self.comment_start,
+            "comment_end" => &mut self.comment_end,
+            "line_statement_prefix" => &mut self.line_statement_prefix,
+            _ => return None,
+        })
+    }
+
+    /// Returns the syntax with these delimiters
+    ///
+    /// All templates that use the same delimiters share the syntax, and with it the cache of
+    /// parsed templates.
+    pub(crate) fn syntax(
+        &self,
+        extension_tags: &'static [&'static str],
+        extension_block_tags: &'static [&'static str],
+    ) -> Result<&'static SyntaxAndCache<'static>, CompileError> {
+        static CACHE: OnceLock<OnceMap<Arc<InlineSyntax>, Arc<SyntaxAndCache<'static>>>> =
+            OnceLock::new();
+
+        let key = InlineSyntax {
+            extension_tags,
+            extension_block_tags,
+            ..self.clone()
+        };
+        let syntax = CACHE.get_or_init(OnceMap::new).get_or_try_insert_ref(
+            &key,
+            (),
+            |key| Arc::new(key.clone()),
+            |_, key| -> Result<_, CompileError> {
+                // SAFETY: the key owns the delimiters, and an inserted key will never be evicted
+                let key = unsafe { transmute::<&InlineSyntax, &'static InlineSyntax>(key) };
+                let syntax = RawSyntax {
+                    name: "",
+                    block_start: key.block_start.as_deref(),
+                    block_end: key.block_end.as_deref(),
+                    expr_start: key.expr_start.as_deref(),
+                    expr_end: key.expr_end.as_deref(),
+                    comment_start: key.comment_start.as_deref(),
+                    comment_end: key.comment_end.as_deref(),
+                    line_statement_prefix: key.line_statement_prefix.as_deref(),
+                    keyword_aliases: None,
+                }
+                .try_into()?;
+                let syntax = Syntax {
+                    extension_tags: key.extension_tags,
+                    extension_block_tags: key.extension_block_tags,
+                    ..syntax
+                };
+                let syntax = Arc::new(SyntaxAndCache::new(syntax));
+                Ok((Arc::clone(&syntax), syntax))
+            },
+            |_, _, value| Arc::clone(value),
+        )?;
+        // SAFETY: an inserted `SyntaxAndCache` will never be evicted
+        Ok(unsafe {
+            transmute::<&SyntaxAndCache<'static>, &'static SyntaxAndCache<'static>>(syntax.as_ref())
+        })
+    }
+}
+
 impl<'a> TryInto<Syntax<'a>> for RawSyntax<'a> {
     type Error = CompileError;
 
     fn try_into(self) -> Result<Syntax<'a>, Self::Error> {
         let default = Syntax::default();
+        let mut keyword_aliases = Vec::new();
+        for (alias, keyword) in self.keyword_aliases.unwrap_or_default() {
+            if syn::parse_str::<syn::Ident>(alias).is_err() {
+                return Err(CompileError::no_file_info(format!(
+                    "invalid keyword alias: {alias:?}"
+                )));
+            } else if BUILTIN_TAGS.contains(&alias) {
+                return Err(CompileError::no_file_info(format!(
+                    "keyword alias {alias:?} is a built-in tag"
+                )));
+            } else if !BUILTIN_TAGS.contains(&keyword) {
+                return Err(CompileError::no_file_info(format!(
+                    "keyword alias {alias:?} refers to {keyword:?}, which is not a built-in tag"
+                )));
+            }
+            keyword_aliases.push((alias, keyword));
+        }
         let syntax = Syntax {
             block_start: self.block_start.unwrap_or(default.block_start),
             block_end: self.block_end.unwrap_or(default.block_end),
@@ -308,6 +683,11 @@ impl<'a> TryInto<Syntax<'a>> for RawSyntax<'a> {
             expr_end: self.expr_end.unwrap_or(default.expr_end),
             comment_start: self.comment_start.unwrap_or(default.comment_start),
             comment_end: self.comment_end.unwrap_or(default.comment_end),
+            line_statement_prefix: self.line_statement_prefix,
+            extension_tags: default.extension_tags,
+            extension_block_tags: default.extension_block_tags,
+            // A `Config` is never dropped, so the aliases may as well be leaked.
+            keyword_aliases: Box::leak(keyword_aliases.into_boxed_slice()),
         };
 
         for s in [
@@ -341,6 +721,26 @@ impl<'a> TryInto<Syntax<'a>> for RawSyntax<'a> {
             }
         }
 
+        if let Some(prefix) = syntax.line_statement_prefix {
+            if prefix.is_empty() {
+                return Err(CompileError::no_file_info(
+                    "the line statement prefix may not be empty",
+                ));
+            } else if prefix.chars().any(|c| c.is_whitespace()) {
+                return Err(CompileError::no_file_info(format!(
+                    "the line statement prefix may not contain white spaces: {prefix:?}"
+                )));
+            }
+            for s in [syntax.block_start, syntax.expr_start, syntax.comment_start] {
+                if s.starts_with(prefix) || prefix.starts_with(s) {
+                    return Err(CompileError::no_file_info(format!(
+                        "the line statement prefix may not be the prefix of a delimiter: \
+                         {prefix:?} vs {s:?}",
+                    )));
+                }
+            }
+        }
+
         Ok(syntax)
     }
 }
@@ -352,6 +752,18 @@ struct RawConfig<'a> {
     general: Option<General<'a>>,
     syntax: Option<Vec<RawSyntax<'a>>>,
     escaper: Option<Vec<RawEscaper<'a>>>,
+    filters: Option<RawFilters<'a>>,
+    #[cfg_attr(feature = "config", serde(borrow))]
+    globals: Option<BTreeMap<&'a str, &'a str>>,
+    #[cfg_attr(feature = "config", serde(borrow))]
+    constants: Option<BTreeMap<&'a str, &'a str>>,
+    #[cfg_attr(feature = "config", serde(borrow))]
+    tags: Option<BTreeMap<&'a str, &'a str>>,
+    #[cfg_attr(feature = "config", serde(borrow))]
+    block_tags: Option<BTreeMap<&'a str, &'a str>>,
+    #[cfg_attr(feature = "config", serde(borrow))]
+    templates: Option<BTreeMap<&'a str, String>>,
+    directory: Option<Vec<RawDirectory<'a>>>,
 }
 
 impl RawConfig<'_> {
@@ -393,6 +805,16 @@ impl From<WhitespaceHandling> for Whitespace {
     }
 }
 
+impl From<Whitespace> for WhitespaceHandling {
+    fn from(ws: Whitespace) -> Self {
+        match ws {
+            Whitespace::Suppress => WhitespaceHandling::Suppress,
+            Whitespace::Preserve => WhitespaceHandling::Preserve,
+            Whitespace::Minimize => WhitespaceHandling::Minimize,
+        }
+    }
+}
+
 #[cfg_attr(feature = "config", derive(Deserialize))]
 struct General<'a> {
     #[cfg_attr(feature = "config", serde(borrow))]
@@ -400,6 +822,14 @@ struct General<'a> {
     default_syntax: Option<&'a str>,
     #[cfg_attr(feature = "config", serde(default))]
     whitespace: WhitespaceHandling,
+    #[cfg_attr(feature = "config", serde(default))]
+    trim_blocks: bool,
+    #[cfg_attr(feature = "config", serde(default))]
+    lstrip_blocks: bool,
+    #[cfg_attr(feature = "config", serde(default))]
+    debug_origins: bool,
+    #[cfg_attr(feature = "config", serde(default))]
+    warn_unused: bool,
 }
 
 #[cfg_attr(feature = "config", derive(Deserialize))]
@@ -411,6 +841,10 @@ struct RawSyntax<'a> {
     expr_end: Option<&'a str>,
     comment_start: Option<&'a str>,
     comment_end: Option<&'a str>,
+    line_statement_prefix: Option<&'a str>,
+    /// Alternative names of built-in tags, e.g. `fi = "endif"`
+    #[cfg_attr(feature = "config", serde(borrow))]
+    keyword_aliases: Option<BTreeMap<&'a str, &'a str>>,
 }
 
 #[cfg_attr(feature = "config", derive(Deserialize))]
@@ -419,27 +853,139 @@ struct RawEscaper<'a> {
     extensions: Vec<&'a str>,
 }
 
-pub(crate) fn read_config_file(config_path: Option<&str>) -> Result<String, CompileError> {
+#[cfg_attr(feature = "config", derive(Deserialize))]
+struct RawDirectory<'a> {
+    path: &'a str,
+    escape: Option<&'a str>,
+    syntax: Option<&'a str>,
+    whitespace: Option<WhitespaceHandling>,
+    trim_blocks: Option<bool>,
+    lstrip_blocks: Option<bool>,
+}
+
+#[cfg_attr(feature = "config", derive(Deserialize))]
+struct RawFilters<'a> {
+    #[cfg_attr(feature = "config", serde(borrow, default))]
+    modules: Vec<&'a str>,
+    #[cfg_attr(feature = "config", serde(borrow))]
+    macros: Option<BTreeMap<&'a str, &'a str>>,
+}
+
+/// Reads the configuration file
+///
+/// Returns the content of the file, and its path if there is one. No configuration file is an
+/// empty configuration.
+pub(crate) fn read_config_file(
+    config_path: Option<&str>,
+) -> Result<(String, Option<PathBuf>), CompileError> {
     let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
     let filename = match config_path {
         Some(config_path) => root.join(config_path),
         None => root.join(CONFIG_FILE_NAME),
     };
 
-    if filename.exists() {
-        fs::read_to_string(&filename).map_err(|_| {
-            CompileError::no_file_info(format!("unable to read {:?}", filename.to_str().unwrap()))
-        })
+    let filename = if filename.exists() {
+        filename
     } else if config_path.is_some() {
-        Err(CompileError::no_file_info(format!(
+        return Err(CompileError::no_file_info(format!(
             "`{}` does not exist",
             root.display()
-        )))
+        )));
+    } else if let Some(filename) = find_workspace_config_file(&root) {
+        filename
     } else {
-        Ok("".to_string())
+        return Ok(("".to_string(), None));
+    };
+
+    match fs::read_to_string(&filename) {
+        Ok(source) => Ok((source, Some(filename))),
+        Err(_) => Err(CompileError::no_file_info(format!(
+            "unable to read {:?}",
+            filename.to_str().unwrap()
+        ))),
     }
 }
 
+/// Looks for a `rinja.toml` in the parent directories of the crate `root`, up to and including
+/// the root of the workspace the crate belongs to
+///
+/// Crates that are not part of a workspace never use a configuration file of a parent directory.
+fn find_workspace_config_file(root: &Path) -> Option<PathBuf> {
+    let workspace_root = find_workspace_root(root)?;
+    root.ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(workspace_root))
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|filename| filename.is_file())
+}
+
+/// Returns the root directory of the workspace the crate `root` belongs to, if it is in one
+pub(crate) fn find_workspace_root(root: &Path) -> Option<&Path> {
+    root.ancestors().find(|dir| is_workspace_root(dir))
+}
+
+fn is_workspace_root(dir: &Path) -> bool {
+    let Ok(manifest) = fs::read_to_string(dir.join("Cargo.toml")) else {
+        return false;
+    };
+    manifest.lines().any(|line| {
+        let line = line.trim();
+        line == "[workspace]" || line.starts_with("[workspace.")
+    })
+}
+
+/// Expands a leading `~` to the home directory, and `$VAR` or `${VAR}` to the value of the
+/// environment variable `VAR`
+fn expand_path(path: &str, config_path: Option<&str>) -> Result<PathBuf, CompileError> {
+    let error = |msg: String| {
+        CompileError::new(
+            msg,
+            config_path.map(|path| FileInfo::new(Path::new(path), None, None)),
+        )
+    };
+    let var = |name: &str| {
+        env::var(name).map_err(|_| {
+            error(format!(
+                "environment variable `{name}` used in {path:?} is not set"
+            ))
+        })
+    };
+
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+    if rest == "~" || rest.starts_with("~/") {
+        expanded.push_str(&match env::var("HOME") {
+            Ok(home) => home,
+            Err(_) => var("USERPROFILE")?,
+        });
+        rest = &rest[1..];
+    }
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let (name, tail) = match rest.strip_prefix('{') {
+            Some(braced) => braced
+                .split_once('}')
+                .ok_or_else(|| error(format!("unclosed `${{` in {path:?}")))?,
+            None => {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                rest.split_at(end)
+            }
+        };
+        if name.is_empty() {
+            return Err(error(format!(
+                "missing variable name after `$` in {path:?}"
+            )));
+        }
+        expanded.push_str(&var(name)?);
+        rest = tail;
+    }
+    expanded.push_str(rest);
+    Ok(expanded.into())
+}
+
 fn str_set(vals: &[&'static str]) -> Vec<Cow<'static, str>> {
     vals.iter().map(|s| Cow::Borrowed(*s)).collect()
 }
@@ -451,6 +997,10 @@ static DEFAULT_ESCAPERS: &[(&[&str], &str)] = &[
         &["html", "htm", "j2", "jinja", "jinja2", "svg", "xml"],
         "Html",
     ),
+    (&["attr"], "HtmlAttr"),
+    (&["js"], "Js"),
+    (&["json", "jsonl"], "Json"),
+    (&["sh"], "Shell"),
     (&["md", "none", "txt", "yml", ""], "Text"),
 ];
 
@@ -528,6 +1078,7 @@ mod tests {
         [[syntax]]
         name = "foo"
         block_start = "{<"
+        line_statement_prefix = "%"
 
         [[syntax]]
         name = "bar"
@@ -545,6 +1096,7 @@ mod tests {
         assert_eq!(foo.expr_end, default_syntax.expr_end);
         assert_eq!(foo.comment_start, default_syntax.comment_start);
         assert_eq!(foo.comment_end, default_syntax.comment_end);
+        assert_eq!(foo.line_statement_prefix, Some("%"));
 
         let bar = config.syntaxes.get("bar").unwrap();
         assert_eq!(bar.block_start, default_syntax.block_start);
@@ -553,6 +1105,7 @@ mod tests {
         assert_eq!(bar.expr_end, default_syntax.expr_end);
         assert_eq!(bar.comment_start, default_syntax.comment_start);
         assert_eq!(bar.comment_end, default_syntax.comment_end);
+        assert_eq!(bar.line_statement_prefix, None);
     }
 
     #[cfg(feature = "config")]
@@ -661,6 +1214,28 @@ mod tests {
             expect_err(config).msg,
             r#"a delimiter may not be the prefix of another delimiter: "{{" vs "{{$""#,
         );
+
+        for (prefix, msg) in [
+            ("", "the line statement prefix may not be empty"),
+            (
+                "% ",
+                r#"the line statement prefix may not contain white spaces: "% ""#,
+            ),
+            (
+                "{",
+                r#"the line statement prefix may not be the prefix of a delimiter: "{" vs "{%""#,
+            ),
+        ] {
+            let raw_config = format!(
+                r#"
+                [[syntax]]
+                name = "line_statement"
+                line_statement_prefix = "{prefix}"
+                "#
+            );
+            let config = Config::new(&raw_config, None, None);
+            assert_eq!(expect_err(config).msg, msg);
+        }
     }
 
     #[cfg(feature = "config")]
@@ -719,6 +1294,10 @@ mod tests {
                     str_set(&["html", "htm", "j2", "jinja", "jinja2", "svg", "xml"]),
                     "::rinja::filters::Html".into()
                 ),
+                (str_set(&["attr"]), "::rinja::filters::HtmlAttr".into()),
+                (str_set(&["js"]), "::rinja::filters::Js".into()),
+                (str_set(&["json", "jsonl"]), "::rinja::filters::Json".into()),
+                (str_set(&["sh"]), "::rinja::filters::Shell".into()),
                 (
                     str_set(&["md", "none", "txt", "yml", ""]),
                     "::rinja::filters::Text".into()
@@ -767,6 +1346,47 @@ mod tests {
         assert_eq!(config.whitespace, WhitespaceHandling::Minimize);
     }
 
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_trim_lstrip_blocks_parsing() {
+        let config = Config::new(r#""#, None, None).unwrap();
+        assert!(!config.trim_blocks);
+        assert!(!config.lstrip_blocks);
+
+        let config = Config::new(
+            r#"
+            [general]
+            trim_blocks = true
+            lstrip_blocks = true
+            "#,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(config.trim_blocks);
+        assert!(config.lstrip_blocks);
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_debug_origins_parsing() {
+        let config = Config::new(r#""#, None, None).unwrap();
+        assert!(!config.debug_origins);
+
+        let config = Config::new("[general]\ndebug_origins = true", None, None).unwrap();
+        assert!(config.debug_origins);
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_warn_unused_parsing() {
+        let config = Config::new(r#""#, None, None).unwrap();
+        assert!(!config.warn_unused);
+
+        let config = Config::new("[general]\nwarn_unused = true", None, None).unwrap();
+        assert!(config.warn_unused);
+    }
+
     #[cfg(feature = "config")]
     #[test]
     fn test_whitespace_in_template() {
@@ -788,6 +1408,439 @@ mod tests {
         assert_eq!(config.whitespace, WhitespaceHandling::Minimize);
     }
 
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_filter_modules() {
+        let config = Config::new("", None, None).unwrap();
+        assert!(config.filter_modules.is_empty());
+
+        let config = Config::new(
+            r#"
+            [filters]
+            modules = ["my_crate::filters", "crate::more::filters"]
+            "#,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            config.filter_modules,
+            vec!["my_crate::filters", "crate::more::filters"],
+        );
+
+        let err = Config::new(
+            r#"
+            [filters]
+            modules = ["not a path"]
+            "#,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err.msg, "invalid filter module path: \"not a path\"");
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_filter_macros() {
+        let config = Config::new(
+            r#"
+            [filters.macros]
+            shout = "my_crate::shout"
+            "#,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(config.filter_modules.is_empty());
+        assert_eq!(config.filter_macros.get("shout"), Some(&"my_crate::shout"));
+
+        let err = Config::new("[filters.macros]\n\"a-b\" = \"crate::a\"", None, None).unwrap_err();
+        assert_eq!(err.msg, "invalid filter name: \"a-b\"");
+        let err = Config::new("[filters.macros]\nupper = \"crate::a\"", None, None).unwrap_err();
+        assert_eq!(err.msg, "filter \"upper\" is a built-in filter");
+        let err = Config::new("[filters.macros]\na = \"a b\"", None, None).unwrap_err();
+        assert_eq!(err.msg, "invalid macro path of filter \"a\": \"a b\"");
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_globals() {
+        let config = Config::new("", None, None).unwrap();
+        assert!(config.globals.is_empty());
+
+        let config = Config::new(
+            r#"
+            [globals]
+            now = "crate::helpers::now"
+            version = "my_crate::version"
+            "#,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            config
+                .globals
+                .iter()
+                .map(|(k, v)| (*k, *v))
+                .collect::<Vec<_>>(),
+            vec![
+                ("now", "crate::helpers::now"),
+                ("version", "my_crate::version"),
+            ],
+        );
+
+        let err = Config::new(
+            r#"
+            [globals]
+            "not a name" = "crate::f"
+            "#,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err.msg, "invalid global function name: \"not a name\"");
+
+        let err = Config::new(
+            r#"
+            [globals]
+            f = "not a path"
+            "#,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.msg,
+            "invalid path of global function \"f\": \"not a path\""
+        );
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_constants() {
+        let config = Config::new(
+            r#"
+            [constants]
+            VERSION = "crate::VERSION"
+            MAX_ITEMS = "crate::limits::MAX_ITEMS"
+            "#,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(config.constants.get("VERSION"), Some(&"crate::VERSION"));
+        assert_eq!(
+            config.constants.get("MAX_ITEMS"),
+            Some(&"crate::limits::MAX_ITEMS")
+        );
+
+        let err = Config::new("[constants]\n\"A B\" = \"crate::A\"", None, None).unwrap_err();
+        assert_eq!(err.msg, "invalid constant name: \"A B\"");
+        let err = Config::new("[constants]\nA = \"a b\"", None, None).unwrap_err();
+        assert_eq!(err.msg, "invalid path of constant \"A\": \"a b\"");
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_tags() {
+        let config = Config::new(
+            r#"
+            [tags]
+            icon = "my_crate::icon"
+            csrf = "crate::csrf_token"
+
+            [[syntax]]
+            name = "other"
+            block_start = "<%"
+            "#,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(config.tags.get("icon"), Some(&"my_crate::icon"));
+        assert_eq!(config.extension_tags, ["csrf", "icon"]);
+        for syntax in config.syntaxes.values() {
+            assert_eq!(syntax.extension_tags, ["csrf", "icon"]);
+        }
+
+        let err = Config::new("[tags]\n\"a-b\" = \"crate::a\"", None, None).unwrap_err();
+        assert_eq!(err.msg, "invalid tag name: \"a-b\"");
+        let err = Config::new("[tags]\nendif = \"crate::a\"", None, None).unwrap_err();
+        assert_eq!(err.msg, "tag \"endif\" is a built-in tag");
+        let err = Config::new("[tags]\na = \"a b\"", None, None).unwrap_err();
+        assert_eq!(err.msg, "invalid macro path of tag \"a\": \"a b\"");
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_block_tags() {
+        let config = Config::new(
+            r#"
+            [tags]
+            icon = "my_crate::icon"
+
+            [block_tags]
+            markdown = "my_crate::markdown"
+            "#,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(config.tags.get("markdown"), Some(&"my_crate::markdown"));
+        assert_eq!(config.extension_tags, ["icon", "markdown"]);
+        assert_eq!(config.extension_block_tags, ["markdown"]);
+        for syntax in config.syntaxes.values() {
+            assert_eq!(syntax.extension_block_tags, ["markdown"]);
+        }
+
+        let err = Config::new(
+            "[tags]\na = \"crate::a\"\n[block_tags]\na = \"crate::b\"",
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.msg,
+            "tag \"a\" is defined in `[tags]` and in `[block_tags]`"
+        );
+        let err = Config::new("[block_tags]\nblock = \"crate::a\"", None, None).unwrap_err();
+        assert_eq!(err.msg, "tag \"block\" is a built-in tag");
+        let err = Config::new(
+            "[tags]\nendx = \"crate::a\"\n[block_tags]\nx = \"crate::b\"",
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.msg,
+            "the end tag of block tag \"x\" is the tag \"endx\""
+        );
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_keyword_aliases() {
+        let config = Config::new(
+            r#"
+            [[syntax]]
+            name = "shell"
+            keyword_aliases = { fi = "endif", done = "endfor" }
+            "#,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            config.syntaxes["shell"].keyword_aliases,
+            [("done", "endfor"), ("fi", "endif")],
+        );
+        assert!(config.syntaxes["default"].keyword_aliases.is_empty());
+
+        for (aliases, msg) in [
+            (r#"{ "a-b" = "endif" }"#, r#"invalid keyword alias: "a-b""#),
+            (
+                r#"{ endfor = "endif" }"#,
+                r#"keyword alias "endfor" is a built-in tag"#,
+            ),
+            (
+                r#"{ fi = "fi" }"#,
+                r#"keyword alias "fi" refers to "fi", which is not a built-in tag"#,
+            ),
+        ] {
+            let config = format!("[[syntax]]\nname = \"a\"\nkeyword_aliases = {aliases}");
+            let err = Config::new(&config, None, None).unwrap_err();
+            assert_eq!(err.msg, msg);
+        }
+
+        let err = Config::new(
+            r#"
+            [tags]
+            icon = "crate::icon"
+
+            [[syntax]]
+            name = "a"
+            keyword_aliases = { icon = "endif" }
+            "#,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.msg,
+            r#"keyword alias "icon" of syntax "a" is a custom tag"#
+        );
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_inline_templates() {
+        let config = Config::new(
+            r#"
+            [templates]
+            "layout.html" = "<main>{% block body %}{% endblock %}</main>"
+            "parts/nav.html" = "<nav></nav>"
+            "b.html" = "shadowed"
+            "#,
+            None,
+            None,
+        )
+        .unwrap();
+        let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join(CONFIG_FILE_NAME);
+
+        let layout = config.find_template("layout.html", None).unwrap();
+        assert_eq!(&*layout, root.join("layout.html"));
+        assert!(!config.is_template_file(&layout));
+        assert_eq!(
+            config.read_template(&layout).unwrap(),
+            "<main>{% block body %}{% endblock %}</main>"
+        );
+
+        // relative to another template that was defined in the configuration
+        let nav = config
+            .find_template("parts/nav.html", Some(&layout))
+            .unwrap();
+        assert_eq!(config.read_template(&nav).unwrap(), "<nav></nav>");
+
+        // templates files in `dirs` come first
+        let b = config.find_template("b.html", None).unwrap();
+        assert!(config.is_template_file(&b));
+        assert_eq!(config.read_template(&b).unwrap(), "bar\n");
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_directories() {
+        let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+        let config = Config::new(
+            r#"
+            [[syntax]]
+            name = "mail"
+            expr_start = "[["
+            expr_end = "]]"
+
+            [[directory]]
+            path = "templates/emails"
+            escape = "txt"
+            syntax = "mail"
+            whitespace = "suppress"
+
+            [[directory]]
+            path = "templates/emails/html"
+            escape = "html"
+            "#,
+            None,
+            None,
+        )
+        .unwrap();
+
+        let dir = config
+            .find_directory(&root.join("templates/emails/welcome.txt"))
+            .unwrap();
+        assert_eq!(dir.escape, Some("txt"));
+        assert_eq!(dir.syntax, Some("mail"));
+        assert_eq!(dir.whitespace, Some(WhitespaceHandling::Suppress));
+        assert_eq!(dir.trim_blocks, None);
+
+        let dir = config
+            .find_directory(&root.join("templates/emails/html/welcome.html"))
+            .unwrap();
+        assert_eq!(dir.escape, Some("html"));
+        assert_eq!(dir.syntax, None);
+
+        assert!(config
+            .find_directory(&root.join("templates/index.html"))
+            .is_none());
+
+        let err = Config::new(
+            r#"
+            [[directory]]
+            path = "templates/emails"
+            syntax = "mail"
+            "#,
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.msg,
+            "syntax \"mail\" of directory \"templates/emails\" not found"
+        );
+    }
+
+    #[test]
+    fn test_expand_path() {
+        env::set_var("RINJA_TEST_EXPAND_PATH", "/srv/tpl");
+        let home = env::var("HOME")
+            .or_else(|_| env::var("USERPROFILE"))
+            .unwrap();
+
+        assert_eq!(
+            expand_path("templates", None).unwrap(),
+            PathBuf::from("templates")
+        );
+        assert_eq!(
+            expand_path("$RINJA_TEST_EXPAND_PATH/web", None).unwrap(),
+            PathBuf::from("/srv/tpl/web")
+        );
+        assert_eq!(
+            expand_path("${RINJA_TEST_EXPAND_PATH}web", None).unwrap(),
+            PathBuf::from("/srv/tplweb")
+        );
+        assert_eq!(
+            expand_path("~/shared-templates", None).unwrap(),
+            PathBuf::from(format!("{home}/shared-templates"))
+        );
+        assert_eq!(expand_path("a~/b", None).unwrap(), PathBuf::from("a~/b"));
+
+        let err = expand_path("$RINJA_TEST_NOT_SET/web", None).unwrap_err();
+        assert_eq!(
+            err.msg,
+            "environment variable `RINJA_TEST_NOT_SET` used in \"$RINJA_TEST_NOT_SET/web\" is \
+             not set"
+        );
+        let err = expand_path("${RINJA_TEST_EXPAND_PATH/web", None).unwrap_err();
+        assert_eq!(err.msg, "unclosed `${` in \"${RINJA_TEST_EXPAND_PATH/web\"");
+        let err = expand_path("$/web", None).unwrap_err();
+        assert_eq!(err.msg, "missing variable name after `$` in \"$/web\"");
+    }
+
+    #[test]
+    fn test_find_workspace_config_file() {
+        let base = env::temp_dir().join(format!("rinja-workspace-config-{}", std::process::id()));
+        let workspace = base.join("workspace");
+        let krate = workspace.join("crates").join("web");
+        fs::create_dir_all(&krate).unwrap();
+        fs::write(krate.join("Cargo.toml"), "[package]\nname = \"web\"\n").unwrap();
+
+        // Not in a workspace, so a configuration file above the crate is ignored.
+        fs::write(base.join(CONFIG_FILE_NAME), "").unwrap();
+        assert_eq!(find_workspace_config_file(&krate), None);
+
+        fs::write(
+            workspace.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\n",
+        )
+        .unwrap();
+        assert_eq!(find_workspace_config_file(&krate), None);
+
+        fs::write(workspace.join(CONFIG_FILE_NAME), "").unwrap();
+        assert_eq!(
+            find_workspace_config_file(&krate),
+            Some(workspace.join(CONFIG_FILE_NAME))
+        );
+
+        // The nearest configuration file wins.
+        fs::write(workspace.join("crates").join(CONFIG_FILE_NAME), "").unwrap();
+        assert_eq!(
+            find_workspace_config_file(&krate),
+            Some(workspace.join("crates").join(CONFIG_FILE_NAME))
+        );
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
     #[test]
     fn test_config_whitespace_error() {
         let config = Config::new(r#""#, None, Some("trim"));
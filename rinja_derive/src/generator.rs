@@ -1,21 +1,27 @@
 use std::borrow::Cow;
-use std::collections::hash_map::{Entry, HashMap};
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt::{Arguments, Display, Write};
 use std::ops::Deref;
 use std::path::Path;
+use std::rc::Rc;
 use std::sync::Arc;
 use std::{cmp, hash, mem, str};
 
 use parser::node::{
-    Call, Comment, CondTest, FilterBlock, If, Include, Let, Lit, Loop, Match, Whitespace, Ws,
+    AutoEscape, Cache, Call, Comment, Cond, CondTest, Do, Escaping, Extension, FilterBlock, If,
+    Include, Let, LetBlock, Lit, Loop, Macro, Match, Once, TemplatePath, Trans, Whitespace,
+    WhitespaceBlock, With, Ws,
 };
 use parser::{Expr, Filter, Node, Target, WithSpan};
-use quote::quote;
+use quote::{format_ident, quote};
 
 use crate::config::WhitespaceHandling;
 use crate::heritage::{Context, Heritage};
 use crate::input::{Source, TemplateInput};
-use crate::{CompileError, MsgValidEscapers, CRATE};
+use crate::{
+    allow_attrs, CompileError, CompileErrors, MsgValidEscapers, CRATE, UNUSED_TEMPLATE_CODE,
+};
 
 pub(crate) struct Generator<'a> {
     // The template input state: original struct AST and attributes
@@ -33,14 +39,35 @@ pub(crate) struct Generator<'a> {
     // Whitespace suppression from the previous non-literal. Will be used to
     // determine whether to flush prefix whitespace from the next literal.
     skip_ws: WhitespaceHandling,
+    // The handling of tags without whitespace marker, changed by `{% whitespace %}` blocks
+    whitespace: WhitespaceHandling,
+    // The path of the escaper of expressions, changed by `{% autoescape %}` blocks
+    escaper: &'a str,
+    // Set by a block tag if `trim_blocks` is enabled, so the first newline of
+    // the next literal gets removed.
+    trim_newline: bool,
+    // Whether the whitespace in `next_ws` starts at the beginning of a line,
+    // so `lstrip_blocks` can remove all of it.
+    at_line_start: bool,
     // If currently in a block, this will contain the name of a potential parent block
     super_block: Option<(&'a str, usize)>,
     // Buffer for writable
     buf_writable: WritableBuffer<'a>,
-    // Counter for write! hash named arguments
-    named: usize,
     // Used in blocks to check if we are inside a filter block.
     is_in_filter_block: usize,
+    // Nesting of blocks whose output is collected in a local buffer instead of being written
+    // into the template's writer, e.g. `{% let %}` blocks and macro calls
+    in_local_output: usize,
+    // The message IDs of all `{% trans %}` blocks, for `Template::MESSAGE_IDS`
+    message_ids: BTreeSet<String>,
+    // Set in a template included with `{% include ... only %}`: variables that are not local
+    // cannot be resolved as fields of the template
+    isolated: bool,
+    // The `{% let %}`s, macros and imports that were used, to warn about the unused ones
+    lints: Lints,
+    // Set while the body of `render_into_async()` is generated: the output is written into the
+    // `AsyncWrite` at every flush point, and `tracing` spans are not entered
+    async_body: bool,
 }
 
 impl<'a> Generator<'a> {
@@ -59,101 +86,532 @@ impl<'a> Generator<'a> {
             locals,
             next_ws: None,
             skip_ws: WhitespaceHandling::Preserve,
+            whitespace: input.whitespace,
+            escaper: input.escaper,
+            trim_newline: false,
+            at_line_start: true,
             super_block: None,
             buf_writable: WritableBuffer {
                 discard: buf_writable_discard,
                 ..Default::default()
             },
-            named: 0,
             is_in_filter_block,
+            in_local_output: 0,
+            message_ids: BTreeSet::new(),
+            isolated: false,
+            lints: Lints::default(),
+            async_body: false,
         }
     }
 
     // Takes a Context and generates the relevant implementations.
-    pub(crate) fn build(mut self, ctx: &Context<'a>) -> Result<String, CompileError> {
+    // Returns the generated code and the template's size hint.
+    pub(crate) fn build(
+        mut self,
+        ctx: &Context<'a>,
+        kind: TmplKind<'_>,
+    ) -> Result<(String, usize), CompileError> {
         let mut buf = Buffer::new();
 
-        self.impl_template(ctx, &mut buf)?;
+        let size_hint = self.impl_template(ctx, &mut buf, kind)?;
         self.impl_display(&mut buf);
+        self.write_lints(ctx, &mut buf, kind);
+        if let TmplKind::Variant | TmplKind::Block = kind {
+            return Ok((buf.buf, size_hint));
+        }
+        if let Some(error) = self.input.error {
+            self.impl_try_render(&mut buf, error);
+        }
 
         #[cfg(feature = "with-actix-web")]
         self.impl_actix_web_responder(&mut buf);
         #[cfg(feature = "with-axum")]
         self.impl_axum_into_response(&mut buf);
+        #[cfg(feature = "with-ntex")]
+        self.impl_ntex_responder(&mut buf);
+        #[cfg(feature = "with-poem")]
+        self.impl_poem_into_response(&mut buf);
         #[cfg(feature = "with-rocket")]
         self.impl_rocket_responder(&mut buf);
+        #[cfg(feature = "with-salvo")]
+        self.impl_salvo_scribe(&mut buf);
+        #[cfg(feature = "with-tide")]
+        self.impl_tide_integrations(&mut buf);
         #[cfg(feature = "with-warp")]
         self.impl_warp_reply(&mut buf);
 
-        Ok(buf.buf)
+        Ok((buf.buf, size_hint))
     }
 
-    // Implement `Template` for the given context struct.
-    fn impl_template(&mut self, ctx: &Context<'a>, buf: &mut Buffer) -> Result<(), CompileError> {
-        self.write_header(buf, format_args!("{CRATE}::Template"), None);
+    // Implement `helpers::RenderSink` and `Template` for the given context struct.
+    fn impl_template(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        kind: TmplKind<'_>,
+    ) -> Result<usize, CompileError> {
+        self.write_header(buf, format_args!("{CRATE}::helpers::RenderSink"), None);
+        if let TmplKind::Enum(variants) = kind {
+            if variants.exhaustive {
+                buf.writeln("#[allow(unreachable_code)]");
+            }
+        }
         buf.writeln(format_args!(
-            "fn render_into<RinjaW>(&self, writer: &mut RinjaW) -> {CRATE}::Result<()>\n\
+            "fn render_into_sink<RinjaW>(\
+                &self, \
+                writer: &mut {CRATE}::helpers::Sink<'_, RinjaW>, \
+                {VALUES}: &dyn {CRATE}::Values\
+            ) -> {CRATE}::Result<()>\n\
             where\n\
                 RinjaW: ::core::fmt::Write + ?::core::marker::Sized,\n\
             {{",
         ));
+        // The body is generated before anything else changes the state of the generator.
+        #[cfg(feature = "tokio")]
+        let async_generator = self.input.render_async.then(|| self.async_generator());
+        let size_hint = self.write_body(ctx, buf, kind)?;
+        buf.writeln("}");
+        buf.writeln("}");
+
+        self.write_header(buf, format_args!("{CRATE}::Template"), None);
+        buf.writeln(format_args!(
+            "#[inline]\n\
+            fn render_into_with_values<RinjaW>(\
+                &self, writer: &mut RinjaW, values: &dyn {CRATE}::Values\
+            ) -> {CRATE}::Result<()>\n\
+            where\n\
+                RinjaW: ::core::fmt::Write + ?::core::marker::Sized,\n\
+            {{\n\
+                <Self as {CRATE}::helpers::RenderSink>::render_into_sink(\
+                    self, &mut {CRATE}::helpers::Sink::new(writer), values\
+                )\n\
+            }}",
+        ));
+        buf.writeln(format_args!(
+            "#[inline]\n\
+            fn render_into<RinjaW>(&self, writer: &mut RinjaW) -> {CRATE}::Result<()>\n\
+            where\n\
+                RinjaW: ::core::fmt::Write + ?::core::marker::Sized,\n\
+            {{\n\
+                <Self as {CRATE}::Template>::render_into_with_values(self, writer, &())\n\
+            }}",
+        ));
+        buf.writeln(format_args!(
+            "#[inline]\n\
+            fn render_chunks(&self) -> {CRATE}::RenderChunks<'static> {{\n\
+                {CRATE}::helpers::render_chunks(self)\n\
+            }}",
+        ));
+
+        #[cfg(feature = "std")]
+        self.impl_write_into(buf);
+        #[cfg(feature = "tokio")]
+        if let Some(generator) = async_generator {
+            generator.impl_render_into_async(ctx, buf, kind)?;
+        }
+
+        buf.writeln(format_args!(
+            "const EXTENSION: ::core::option::Option<&'static ::core::primitive::str> = {:?};",
+            self.input.extension(),
+        ));
+        buf.writeln(format_args!(
+            "const SIZE_HINT: ::core::primitive::usize = {size_hint};",
+        ));
+        buf.writeln(format_args!(
+            "const MIME_TYPE: &'static ::core::primitive::str = {:?};",
+            self.input.mime_type,
+        ));
+        if !self.message_ids.is_empty() {
+            buf.write("const MESSAGE_IDS: &'static [&'static ::core::primitive::str] = &[");
+            for id in &self.message_ids {
+                buf.write(format_args!("\"{id}\","));
+            }
+            buf.writeln("];");
+        }
+
+        buf.writeln("}");
+        Ok(size_hint)
+    }
+
+    // Writes the body of `render_into_sink()`, or of `render_into_async()` if `self.async_body`
+    // is set. Returns the template's size hint.
+    fn write_body(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        kind: TmplKind<'_>,
+    ) -> Result<usize, CompileError> {
         buf.writeln(format_args!("use {CRATE}::filters::AutoEscape as _;"));
+        buf.writeln(format_args!("use {CRATE}::helpers::ContainsItem as _;"));
+        buf.writeln(format_args!("use {CRATE}::helpers::WriteWritable as _;"));
         buf.writeln(format_args!("use ::core::fmt::Write as _;"));
 
-        buf.discard = self.buf_writable.discard;
-        // Make sure the compiler understands that the generated code depends on the template files.
-        for path in self.contexts.keys() {
-            // Skip the fake path of templates defined in rust source.
-            let path_is_valid = match self.input.source {
-                Source::Path(_) => true,
-                Source::Source(_) => **path != self.input.path,
-            };
-            if path_is_valid {
-                let path = path.to_str().unwrap();
-                buf.writeln(
-                    quote! {
-                        const _: &[::core::primitive::u8] = ::core::include_bytes!(#path);
-                    }
-                    .to_string(),
-                );
+        if let TmplKind::Enum(variants) = kind {
+            buf.writeln("match self {");
+            buf.writeln(&variants.arms);
+            if !variants.exhaustive {
+                buf.writeln("_ => {}");
             }
+            buf.writeln("}");
         }
 
-        let size_hint = if let Some(heritage) = self.heritage {
-            self.handle(heritage.root, heritage.root.nodes, buf, AstLevel::Top)
+        #[cfg(feature = "hot-reload")]
+        self.write_hot_reload(buf);
+        self.write_filter_modules(buf);
+
+        self.write_dependencies(buf);
+        buf.discard = self.buf_writable.discard;
+
+        let mut size_hint = if let Some(heritage) = self.heritage {
+            self.handle_with_origin(ctx, buf, |this, buf| {
+                this.handle_template(heritage.root, buf)
+            })
+        } else if let Some(expr) = ctx.dynamic_extends {
+            self.handle_with_origin(ctx, buf, |this, buf| {
+                this.write_dynamic_extends(ctx, buf, expr)
+            })
         } else {
-            self.handle(ctx, ctx.nodes, buf, AstLevel::Top)
+            self.handle_template(ctx, buf)
         }?;
         buf.discard = false;
+        if let TmplKind::Enum(variants) = kind {
+            size_hint = if variants.exhaustive {
+                variants.size_hint
+            } else {
+                cmp::max(size_hint, variants.size_hint)
+            };
+        }
 
         self.flush_ws(Ws(None, None));
+        if !self.input.config.filter_modules.is_empty() {
+            buf.writeln("}");
+        }
         buf.write(CRATE);
         buf.writeln("::Result::Ok(())");
+        Ok(size_hint)
+    }
+
+    // Warns about variables declared with `{% let %}` that are never used, and about macros and
+    // imports of the template that are never used, if `warn_unused` is enabled. A proc-macro
+    // cannot emit warnings on stable Rust, so every warning is emitted as the use of a deprecated
+    // item.
+    fn write_lints(&self, ctx: &Context<'a>, buf: &mut Buffer, kind: TmplKind<'_>) {
+        // The code of a block of `blocks = [...]` was already checked for the whole template.
+        if !self.input.warn_unused || matches!(kind, TmplKind::Block) {
+            return;
+        }
+
+        // A `{% let %}` in a macro or included template can be visited multiple times.
+        let mut lets = BTreeMap::new();
+        for (msg, used) in &self.lints.lets {
+            *lets.entry(msg.as_str()).or_insert(false) |= used.get();
+        }
+        let mut warnings = lets
+            .into_iter()
+            .filter(|&(_, used)| !used)
+            .map(|(msg, _)| msg.to_string())
+            .collect::<Vec<_>>();
+
+        // If only a block is rendered, the macros and imports may be used outside of it.
+        if self.input.block.is_none() {
+            let lints = &self.lints;
+            for node in ctx.nodes {
+                match node {
+                    Node::Macro(m) if !lints.is_called(m) => {
+                        warnings
+                            .push(ctx.generate_warning(&format!("unused macro `{}`", m.name), m));
+                    }
+                    Node::Import(i) if !lints.used_scopes.contains(i.scope) => {
+                        warnings
+                            .push(ctx.generate_warning(&format!("unused import `{}`", i.scope), i));
+                    }
+                    Node::FromImport(i) => {
+                        for &(name, alias) in &i.names {
+                            let name = alias.unwrap_or(name);
+                            if !lints.used_macro_imports.contains(name) {
+                                warnings.push(ctx.generate_warning(
+                                    &format!("unused import of macro `{name}`"),
+                                    i,
+                                ));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let item = format_ident!("{UNUSED_TEMPLATE_CODE}");
+        let allow_attrs = allow_attrs(self.input.ast);
+        for warning in warnings {
+            buf.writeln(
+                quote! {
+                    #(#allow_attrs)*
+                    const _: () = {
+                        #[deprecated(note = #warning)]
+                        struct #item;
+                        let _ = #item;
+                    };
+                }
+                .to_string(),
+            );
+        }
+    }
+
+    // Make sure the compiler understands that the generated code depends on the template files,
+    // including all extended, included and imported templates, and on the configuration file.
+    fn write_dependencies(&self, buf: &mut Buffer) {
+        let mut paths = self
+            .contexts
+            .keys()
+            // Skip the fake path of templates defined in rust source, and templates that are
+            // not files, e.g. templates defined in the configuration file.
+            .filter(|path| match self.input.source {
+                Source::Path(_) => self.input.config.is_template_file(path),
+                Source::Source(_) => {
+                    ***path != self.input.path && self.input.config.is_template_file(path)
+                }
+            })
+            .map(|path| &***path)
+            .chain(self.input.config_file)
+            .collect::<Vec<_>>();
+        paths.sort_unstable();
+        for path in paths {
+            let path = path.to_str().unwrap();
+            buf.writeln(
+                quote! {
+                    const _: &[::core::primitive::u8] = ::core::include_bytes!(#path);
+                }
+                .to_string(),
+            );
+        }
+    }
+
+    // Custom filters are called as `filters::name()`. If filter modules are configured, a
+    // `filters` module re-exporting all of them is declared in the function body. In an inner
+    // scope, all items of the template's module are imported again, so a `filters` module
+    // declared next to the template takes precedence.
+    fn write_filter_modules(&mut self, buf: &mut Buffer) {
+        let modules = &self.input.config.filter_modules;
+        if modules.is_empty() {
+            return;
+        }
+
+        buf.writeln("#[allow(unused_imports)]");
+        buf.writeln("mod __rinja_filters {");
+        buf.writeln("pub(super) mod filters {");
+        for module in modules {
+            buf.writeln(format_args!("pub(in super::super) use {module}::*;"));
+        }
+        buf.writeln("}");
         buf.writeln("}");
+        buf.writeln("#[allow(unused_imports)]");
+        buf.writeln("use __rinja_filters::filters;");
+        buf.writeln("{");
+        buf.writeln("#[allow(unused_imports)]");
+        buf.writeln("use self::*;");
+    }
+
+    // In debug builds, try to interpret the current version of the template file first.
+    #[cfg(feature = "hot-reload")]
+    fn write_hot_reload(&mut self, buf: &mut Buffer) {
+        use syn::ext::IdentExt;
+
+        if !matches!(self.input.source, Source::Path(_))
+            || !self.input.config.is_template_file(&self.input.path)
+            || self.heritage.is_some()
+            || self.input.block.is_some()
+            || self.input.trim_blocks
+            || self.input.lstrip_blocks
+            || self.input.debug_origins
+        {
+            return;
+        }
+        let syn::Data::Struct(data) = &self.input.ast.data else {
+            return;
+        };
+        let fields: Vec<_> = match &data.fields {
+            syn::Fields::Named(fields) => fields
+                .named
+                .iter()
+                .filter_map(|f| f.ident.as_ref())
+                .collect(),
+            syn::Fields::Unit => Vec::new(),
+            syn::Fields::Unnamed(_) => return,
+        };
 
+        let syntax = self.input.syntax;
+        buf.writeln("#[cfg(debug_assertions)]");
+        buf.writeln("{");
         buf.writeln(format_args!(
-            "const EXTENSION: ::std::option::Option<&'static ::std::primitive::str> = {:?};",
-            self.input.extension(),
+            "use {CRATE}::hot_reload::HotReloadField as _;\n\
+            if let ::core::option::Option::Some(result) = {CRATE}::hot_reload::render(\n\
+                writer,\n\
+                {path:?},\n\
+                &{CRATE}::hot_reload::Syntax {{\n\
+                    block_start: {block_start:?},\n\
+                    block_end: {block_end:?},\n\
+                    expr_start: {expr_start:?},\n\
+                    expr_end: {expr_end:?},\n\
+                    comment_start: {comment_start:?},\n\
+                    comment_end: {comment_end:?},\n\
+                    line_statement_prefix: {line_statement_prefix},\n\
+                    extension_tags: &{extension_tags:?},\n\
+                    extension_block_tags: &{extension_block_tags:?},\n\
+                    keyword_aliases: &{keyword_aliases:?},\n\
+                }},\n\
+                {CRATE}::hot_reload::Whitespace::{whitespace:?},\n\
+                &[",
+            path = self.input.path.to_str().unwrap(),
+            block_start = syntax.block_start,
+            block_end = syntax.block_end,
+            expr_start = syntax.expr_start,
+            expr_end = syntax.expr_end,
+            comment_start = syntax.comment_start,
+            comment_end = syntax.comment_end,
+            extension_tags = syntax.extension_tags,
+            extension_block_tags = syntax.extension_block_tags,
+            keyword_aliases = syntax.keyword_aliases,
+            line_statement_prefix = match syntax.line_statement_prefix {
+                Some(prefix) => format!("::core::option::Option::Some({prefix:?})"),
+                None => "::core::option::Option::None".to_owned(),
+            },
+            whitespace = Whitespace::from(self.input.whitespace),
         ));
+        for ident in fields {
+            buf.writeln(format_args!(
+                "({name:?}, (&&&{CRATE}::hot_reload::Field::new(&self.{ident}, {escaper}))\
+                    .rinja_hot_reload_field()),",
+                name = ident.unraw().to_string(),
+                escaper = self.input.escaper,
+            ));
+        }
+        buf.writeln("],");
+        buf.writeln(") {");
+        buf.writeln("return result;");
+        buf.writeln("}");
+        buf.writeln("}");
+    }
+
+    // The body of `render_into_with_values()` is generated again for an `async` block, which
+    // writes the output into the `AsyncWrite` at every flush point, i.e. whenever static text or
+    // expressions were written into the template's writer.
+    #[cfg(feature = "tokio")]
+    fn impl_render_into_async(
+        mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        kind: TmplKind<'_>,
+    ) -> Result<(), CompileError> {
+        if let TmplKind::Enum(variants) = kind {
+            if variants.exhaustive {
+                buf.writeln("#[allow(unreachable_code)]");
+            }
+        }
         buf.writeln(format_args!(
-            "const SIZE_HINT: ::std::primitive::usize = {size_hint};",
+            "#[inline]\n\
+            fn render_into_async<'rinja_a, RinjaW>(\
+                &'rinja_a self, writer: &'rinja_a mut RinjaW\
+            ) -> impl ::core::future::Future<Output = {CRATE}::Result<()>> + 'rinja_a\n\
+            where\n\
+                RinjaW: {CRATE}::helpers::tokio::io::AsyncWrite\
+                    + ::core::marker::Unpin + ?::core::marker::Sized,\n\
+            {{\n\
+                async move {{\n\
+                    let __rinja_async_writer = writer;\n\
+                    let mut __rinja_buf = {CRATE}::helpers::alloc::string::String::new();\n\
+                    let __rinja_result: {CRATE}::Result<()> = {{\n\
+                        let writer = &mut __rinja_buf;\n\
+                        let __rinja_async_writer = &mut *__rinja_async_writer;\n\
+                        async move {{\n\
+                        let {VALUES} = &();",
         ));
+        let mut body = Buffer::new();
+        self.write_body(ctx, &mut body, kind)?;
+        buf.write(body.buf);
         buf.writeln(format_args!(
-            "const MIME_TYPE: &'static ::std::primitive::str = {:?};",
-            self.input.mime_type,
+            "}}}}.await;\n\
+            {CRATE}::helpers::finish_async(__rinja_async_writer, &mut __rinja_buf, __rinja_result)\
+                .await\n\
+            }}\n\
+            }}",
+        ));
+        Ok(())
+    }
+
+    // A generator in the same state as `self` before the template was generated, which generates
+    // the body of `render_into_async()`
+    #[cfg(feature = "tokio")]
+    fn async_generator(&self) -> Generator<'a> {
+        Generator {
+            async_body: true,
+            ..Generator::new(
+                self.input,
+                self.contexts,
+                self.heritage,
+                self.locals.clone(),
+                self.buf_writable.discard,
+                self.is_in_filter_block,
+            )
+        }
+    }
+
+    // Implement `Template::write_into()`, so `io::Write` targets are written to directly,
+    // without formatting into an intermediate `String`, and I/O errors are kept intact.
+    #[cfg(feature = "std")]
+    fn impl_write_into(&mut self, buf: &mut Buffer) {
+        buf.writeln("#[inline]");
+        buf.writeln(format_args!(
+            "fn write_into<RinjaW>(&self, writer: &mut RinjaW) -> {CRATE}::helpers::std::io::Result<()>\n\
+            where\n\
+                RinjaW: {CRATE}::helpers::std::io::Write + ?::core::marker::Sized,\n\
+            {{\n\
+                let mut writer = {CRATE}::helpers::IoWriter::new(writer);\n\
+                let result = <Self as {CRATE}::Template>::render_into(self, &mut writer);\n\
+                writer.finish(result)\n\
+            }}",
         ));
+    }
 
+    // Implement `try_render()` and `try_render_into()`, which return the error type given in
+    // `error = "..."` instead of `rinja::Error`.
+    fn impl_try_render(&mut self, buf: &mut Buffer, error: &str) {
+        let ident = &self.input.ast.ident;
+        let vis = &self.input.ast.vis;
+        let (impl_generics, ty_generics, where_clause) = self.input.ast.generics.split_for_impl();
+        buf.writeln(format_args!(
+            "{} {ident}{} {{",
+            quote!(impl #impl_generics),
+            quote!(#ty_generics #where_clause),
+        ));
+        buf.writeln(format_args!(
+            "/// Renders the template into a new `String`, like `Template::render()`\n\
+            #[inline]\n\
+            {vis} fn try_render(&self) -> \
+                ::core::result::Result<{CRATE}::helpers::alloc::string::String, {error}> {{\n\
+                {CRATE}::Template::render(self).map_err({CRATE}::Error::into_custom)\n\
+            }}\n\
+            /// Renders the template into `writer`, like `Template::render_into()`\n\
+            #[inline]\n\
+            {vis} fn try_render_into<RinjaW>(&self, writer: &mut RinjaW) -> \
+                ::core::result::Result<(), {error}>\n\
+            where\n\
+                RinjaW: ::core::fmt::Write + ?::core::marker::Sized,\n\
+            {{\n\
+                {CRATE}::Template::render_into(self, writer).map_err({CRATE}::Error::into_custom)\n\
+            }}",
+            vis = quote!(#vis),
+        ));
         buf.writeln("}");
-        Ok(())
     }
 
     // Implement `Display` for the given context struct.
     fn impl_display(&mut self, buf: &mut Buffer) {
-        self.write_header(buf, "::std::fmt::Display", None);
+        self.write_header(buf, "::core::fmt::Display", None);
         buf.writeln("#[inline]");
-        buf.writeln("fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {");
+        buf.writeln("fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {");
         buf.write(CRATE);
-        buf.writeln("::Template::render_into(self, f).map_err(|_| ::std::fmt::Error {})");
+        buf.writeln("::Template::render_into(self, f).map_err(|_| ::core::fmt::Error {})");
         buf.writeln("}");
         buf.writeln("}");
     }
@@ -187,6 +645,38 @@ impl<'a> Generator<'a> {
         buf.writeln("}");
     }
 
+    // Implement ntex's `Responder` for any `ErrorRenderer` of the application.
+    #[cfg(feature = "with-ntex")]
+    fn impl_ntex_responder(&mut self, buf: &mut Buffer) {
+        let param: syn::GenericParam =
+            syn::parse_quote!(RinjaErr: ::rinja_ntex::ntex::web::ErrorRenderer);
+
+        self.write_header(
+            buf,
+            "::rinja_ntex::ntex::web::Responder<RinjaErr>",
+            Some(vec![param]),
+        );
+        buf.writeln("#[inline]");
+        buf.writeln(
+            "async fn respond_to(self, _: &::rinja_ntex::ntex::web::HttpRequest) \
+             -> ::rinja_ntex::ntex::web::HttpResponse {",
+        );
+        buf.writeln("::rinja_ntex::into_response(&self)");
+        buf.writeln("}");
+        buf.writeln("}");
+    }
+
+    // Implement Poem's `IntoResponse`.
+    #[cfg(feature = "with-poem")]
+    fn impl_poem_into_response(&mut self, buf: &mut Buffer) {
+        self.write_header(buf, "::rinja_poem::poem::IntoResponse", None);
+        buf.writeln("#[inline]");
+        buf.writeln("fn into_response(self) -> ::rinja_poem::poem::Response {");
+        buf.writeln("::rinja_poem::into_response(&self)");
+        buf.writeln("}");
+        buf.writeln("}");
+    }
+
     // Implement Rocket's `Responder`.
     #[cfg(feature = "with-rocket")]
     fn impl_rocket_responder(&mut self, buf: &mut Buffer) {
@@ -208,6 +698,48 @@ impl<'a> Generator<'a> {
         buf.writeln("}");
     }
 
+    // Implement Salvo's `Scribe`, which implies `Writer`.
+    #[cfg(feature = "with-salvo")]
+    fn impl_salvo_scribe(&mut self, buf: &mut Buffer) {
+        self.write_header(buf, "::rinja_salvo::salvo_core::writing::Scribe", None);
+        buf.writeln("#[inline]");
+        buf.writeln("fn render(self, res: &mut ::rinja_salvo::salvo_core::http::Response) {");
+        buf.writeln("::rinja_salvo::render(&self, res)");
+        buf.writeln("}");
+        buf.writeln("}");
+    }
+
+    // Implement conversions into Tide's `Body` and `Response`.
+    #[cfg(feature = "with-tide")]
+    fn impl_tide_integrations(&mut self, buf: &mut Buffer) {
+        self.write_header(
+            buf,
+            "::core::convert::TryInto<::rinja_tide::tide::Body>",
+            None,
+        );
+        buf.writeln("type Error = ::rinja_tide::Error;");
+        buf.writeln("#[inline]");
+        buf.writeln(
+            "fn try_into(self) \
+             -> ::rinja_tide::Result<::rinja_tide::tide::Body, Self::Error> {",
+        );
+        buf.writeln("::rinja_tide::try_into_body(&self)");
+        buf.writeln("}");
+        buf.writeln("}");
+
+        buf.writeln("#[allow(clippy::from_over_into)]");
+        self.write_header(
+            buf,
+            "::core::convert::Into<::rinja_tide::tide::Response>",
+            None,
+        );
+        buf.writeln("#[inline]");
+        buf.writeln("fn into(self) -> ::rinja_tide::tide::Response {");
+        buf.writeln("::rinja_tide::into_response(&self)");
+        buf.writeln("}");
+        buf.writeln("}");
+    }
+
     #[cfg(feature = "with-warp")]
     fn impl_warp_reply(&mut self, buf: &mut Buffer) {
         self.write_header(buf, "::rinja_warp::warp::reply::Reply", None);
@@ -258,85 +790,38 @@ impl<'a> Generator<'a> {
         buf: &mut Buffer,
         level: AstLevel,
     ) -> Result<usize, CompileError> {
+        // An error in one node does not stop the generation, so the errors of all nodes are
+        // reported at once.
+        let mut errors = CompileErrors::default();
         let mut size_hint = 0;
-        for n in nodes {
-            match *n {
-                Node::Lit(ref lit) => {
-                    self.visit_lit(lit);
-                }
-                Node::Comment(ref comment) => {
-                    self.write_comment(comment);
-                }
-                Node::Expr(ws, ref val) => {
-                    self.write_expr(ws, val);
-                }
-                Node::Let(ref l) => {
-                    self.write_let(ctx, buf, l)?;
-                }
-                Node::If(ref i) => {
-                    size_hint += self.write_if(ctx, buf, i)?;
-                }
-                Node::Match(ref m) => {
-                    size_hint += self.write_match(ctx, buf, m)?;
-                }
-                Node::Loop(ref loop_block) => {
-                    size_hint += self.write_loop(ctx, buf, loop_block)?;
-                }
-                Node::BlockDef(ref b) => {
-                    size_hint +=
-                        self.write_block(ctx, buf, Some(b.name), Ws(b.ws1.0, b.ws2.1), b)?;
-                }
-                Node::Include(ref i) => {
-                    size_hint += self.handle_include(ctx, buf, i)?;
-                }
-                Node::Call(ref call) => {
-                    size_hint += self.write_call(ctx, buf, call)?;
-                }
-                Node::FilterBlock(ref filter) => {
-                    size_hint += self.write_filter_block(ctx, buf, filter)?;
-                }
-                Node::Macro(ref m) => {
-                    if level != AstLevel::Top {
-                        return Err(
-                            ctx.generate_error("macro blocks only allowed at the top level", m)
-                        );
-                    }
-                    self.flush_ws(m.ws1);
-                    self.prepare_ws(m.ws2);
-                }
-                Node::Raw(ref raw) => {
-                    self.handle_ws(raw.ws1);
-                    self.visit_lit(&raw.lit);
-                    self.handle_ws(raw.ws2);
-                }
-                Node::Import(ref i) => {
-                    if level != AstLevel::Top {
-                        return Err(
-                            ctx.generate_error("import blocks only allowed at the top level", i)
-                        );
-                    }
-                    self.handle_ws(i.ws);
-                }
-                Node::Extends(ref e) => {
-                    if level != AstLevel::Top {
-                        return Err(
-                            ctx.generate_error("extend blocks only allowed at the top level", e)
-                        );
-                    }
-                    // No whitespace handling: child template top-level is not used,
-                    // except for the blocks defined in it.
-                }
-                Node::Break(ref ws) => {
-                    self.handle_ws(**ws);
-                    self.write_buf_writable(ctx, buf)?;
-                    buf.writeln("break;");
-                }
-                Node::Continue(ref ws) => {
-                    self.handle_ws(**ws);
-                    self.write_buf_writable(ctx, buf)?;
-                    buf.writeln("continue;");
+        for (idx, n) in nodes.iter().enumerate() {
+            let scopes = self.locals.scopes.len();
+            let (whitespace, escaper, super_block, is_in_filter_block, in_local_output) = (
+                self.whitespace,
+                self.escaper,
+                self.super_block,
+                self.is_in_filter_block,
+                self.in_local_output,
+            );
+            match self.handle_node(ctx, nodes, idx, buf, level) {
+                Ok(hint) => size_hint += hint,
+                Err(err) => {
+                    // Undo what the failed node left behind, so it cannot cause errors in the
+                    // following nodes.
+                    errors.push(err);
+                    self.next_ws = None;
+                    self.locals.scopes.truncate(scopes);
+                    self.whitespace = whitespace;
+                    self.escaper = escaper;
+                    self.super_block = super_block;
+                    self.is_in_filter_block = is_in_filter_block;
+                    self.in_local_output = in_local_output;
                 }
             }
+            if matches!(n, Node::Let(l) if l.else_nodes.is_some()) {
+                // The rest of the scope was handled by `write_let_else()`.
+                break;
+            }
         }
 
         if AstLevel::Top == level {
@@ -345,37 +830,278 @@ impl<'a> Generator<'a> {
                 self.flush_ws(Ws(Some(self.skip_ws.into()), None));
             }
 
-            size_hint += self.write_buf_writable(ctx, buf)?;
+            if let Some(hint) = errors.check(self.write_buf_writable(ctx, buf)) {
+                size_hint += hint;
+            }
         }
+        errors.into_result()?;
         Ok(size_hint)
     }
 
-    fn write_if(
+    fn handle_node(
         &mut self,
         ctx: &Context<'a>,
+        nodes: &'a [Node<'_>],
+        idx: usize,
         buf: &mut Buffer,
-        i: &'a If<'_>,
+        level: AstLevel,
     ) -> Result<usize, CompileError> {
-        let mut flushed = 0;
-        let mut arm_sizes = Vec::new();
-        let mut has_else = false;
-        for (i, cond) in i.branches.iter().enumerate() {
-            self.handle_ws(cond.ws);
-            flushed += self.write_buf_writable(ctx, buf)?;
-            if i > 0 {
-                self.locals.pop();
+        let mut size_hint = 0;
+        match nodes[idx] {
+            Node::Lit(ref lit) => {
+                self.visit_lit(lit);
             }
-
-            self.locals.push();
-            let mut arm_size = 0;
-            if let Some(CondTest { target, expr }) = &cond.cond {
-                if i == 0 {
-                    buf.write("if ");
-                } else {
-                    buf.write("} else if ");
+            Node::Comment(ref comment) => {
+                self.write_comment(comment);
+            }
+            Node::Expr(ws, ref val) if is_super_call(val) => {
+                size_hint += self.write_block(ctx, buf, None, ws, val)?;
+            }
+            Node::Expr(ws, ref val) => {
+                self.write_expr(ws, val);
+            }
+            Node::Let(ref l) if l.else_nodes.is_some() => {
+                // The rest of the scope is only rendered if the pattern matches.
+                size_hint += self.write_let_else(ctx, buf, l, &nodes[idx + 1..], level)?;
+            }
+            Node::Let(ref l) => {
+                self.write_let(ctx, buf, l)?;
+            }
+            Node::LetBlock(ref l) => {
+                self.write_let_block(ctx, buf, l)?;
+            }
+            Node::If(ref i) => {
+                size_hint += self.write_if(ctx, buf, i)?;
+            }
+            Node::Match(ref m) => {
+                size_hint += self.write_match(ctx, buf, m)?;
+            }
+            Node::Loop(ref loop_block) => {
+                size_hint += self.write_loop(ctx, buf, loop_block)?;
+            }
+            Node::BlockDef(ref b) => {
+                size_hint += self.write_block(ctx, buf, Some(b.name), Ws(b.ws1.0, b.ws2.1), b)?;
+            }
+            Node::Include(ref i) => {
+                size_hint += self.handle_include(ctx, buf, i)?;
+            }
+            Node::Call(ref call) => {
+                size_hint += self.write_call(ctx, buf, call)?;
+            }
+            Node::FilterBlock(ref filter) => {
+                size_hint += self.write_filter_block(ctx, buf, filter)?;
+            }
+            Node::With(ref w) => {
+                size_hint += self.write_with(ctx, buf, w)?;
+            }
+            Node::Trans(ref t) => {
+                size_hint += self.write_trans(ctx, buf, t)?;
+            }
+            Node::Do(ref d) => {
+                size_hint += self.write_do(ctx, buf, d)?;
+            }
+            Node::Cache(ref c) => {
+                size_hint += self.write_cache(ctx, buf, c)?;
+            }
+            Node::Once(ref o) => {
+                size_hint += self.write_once(ctx, buf, o)?;
+            }
+            Node::WhitespaceBlock(ref w) => {
+                size_hint += self.write_whitespace_block(ctx, buf, w)?;
+            }
+            Node::AutoEscape(ref a) => {
+                size_hint += self.write_autoescape(ctx, buf, a)?;
+            }
+            Node::Extension(ref e) => {
+                size_hint += self.write_extension(ctx, buf, e)?;
+            }
+            Node::Macro(ref m) => {
+                if level != AstLevel::Top {
+                    return Err(ctx.generate_error("macro blocks only allowed at the top level", m));
                 }
-
-                if let Some(target) = target {
+                self.flush_ws(m.ws1);
+                self.prepare_ws(m.ws2);
+            }
+            Node::Raw(ref raw) => {
+                self.handle_ws(raw.ws1);
+                self.visit_lit(&raw.lit);
+                self.handle_ws(raw.ws2);
+            }
+            Node::Import(ref i) => {
+                if level != AstLevel::Top {
+                    return Err(
+                        ctx.generate_error("import blocks only allowed at the top level", i)
+                    );
+                }
+                self.handle_ws(i.ws);
+            }
+            Node::FromImport(ref i) => {
+                if level != AstLevel::Top {
+                    return Err(
+                        ctx.generate_error("import blocks only allowed at the top level", i)
+                    );
+                }
+                self.handle_ws(i.ws);
+            }
+            Node::Extends(ref e) => {
+                if level != AstLevel::Top {
+                    return Err(
+                        ctx.generate_error("extend blocks only allowed at the top level", e)
+                    );
+                }
+                // No whitespace handling: child template top-level is not used,
+                // except for the blocks defined in it.
+            }
+            Node::Break(ref ws, ref cond) => {
+                self.write_loop_control(ctx, buf, **ws, cond.as_ref(), "break")?;
+            }
+            Node::Continue(ref ws, ref cond) => {
+                self.write_loop_control(ctx, buf, **ws, cond.as_ref(), "continue")?;
+            }
+        }
+        Ok(size_hint)
+    }
+
+    // Handles the top-level nodes of a template.
+    fn handle_template(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+    ) -> Result<usize, CompileError> {
+        self.handle_with_origin(ctx, buf, |this, buf| {
+            this.handle(ctx, ctx.nodes, buf, AstLevel::Top)
+        })
+    }
+
+    // Writes the output of `handle` as the output of the template `ctx`. The output of a
+    // template that extends another template is written by the other template.
+    fn handle_with_origin(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        handle: impl FnOnce(&mut Self, &mut Buffer) -> Result<usize, CompileError>,
+    ) -> Result<usize, CompileError> {
+        let origin = ctx.display_path();
+        let mut size_hint = self.write_origin_begin(ctx, buf, &origin)?;
+        size_hint += handle(self, buf)?;
+        size_hint += self.write_origin_end(ctx, buf, &origin)?;
+        Ok(size_hint)
+    }
+
+    // Starts the output of a template or block: opens a `tracing` span for it if the feature
+    // `"tracing"` is enabled, and writes an HTML comment if `debug_origins` is enabled.
+    fn write_origin_begin(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        origin: &str,
+    ) -> Result<usize, CompileError> {
+        if !cfg!(feature = "tracing") && !self.writes_origin_comments() {
+            return Ok(0);
+        }
+        let size_hint = self.write_buf_writable(ctx, buf)?;
+        if cfg!(feature = "tracing") {
+            // An entered span must not be held over an `.await`, so in `render_into_async()` the
+            // spans are only created and closed.
+            let enter = if self.async_body { "" } else { ".entered()" };
+            buf.writeln(format_args!(
+                "{{\n\
+                let __rinja_span = {CRATE}::helpers::tracing::info_span!({origin:?}){enter};"
+            ));
+        }
+        self.write_origin_comment(buf, "begin", origin);
+        Ok(size_hint)
+    }
+
+    // Ends what `write_origin_begin()` started.
+    fn write_origin_end(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        origin: &str,
+    ) -> Result<usize, CompileError> {
+        if !cfg!(feature = "tracing") && !self.writes_origin_comments() {
+            return Ok(0);
+        }
+        let size_hint = self.write_buf_writable(ctx, buf)?;
+        self.write_origin_comment(buf, "end", origin);
+        if cfg!(feature = "tracing") {
+            buf.writeln("}");
+        }
+        Ok(size_hint)
+    }
+
+    fn writes_origin_comments(&self) -> bool {
+        // The content of a filter block is filtered, so it must not contain the comment.
+        self.input.debug_origins && self.is_in_filter_block == 0
+    }
+
+    fn write_origin_comment(&self, buf: &mut Buffer, marker: &str, origin: &str) {
+        if self.writes_origin_comments() {
+            // `--` must not appear inside of an HTML comment.
+            let comment = format!("<!-- {marker} {} -->", origin.replace("--", "- -"));
+            buf.writeln(format_args!(
+                "#[cfg(debug_assertions)]\nwriter.write_str({comment:?})?;"
+            ));
+        }
+    }
+
+    fn write_if(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        i: &'a If<'_>,
+    ) -> Result<usize, CompileError> {
+        self.write_if_branches(ctx, buf, &i.branches, i.ws)
+    }
+
+    // `end_ws` is the whitespace handling of the `{% endif %}` tag.
+    fn write_if_branches(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        branches: &'a [WithSpan<'_, Cond<'_>>],
+        end_ws: Ws,
+    ) -> Result<usize, CompileError> {
+        if branches.is_empty() {
+            self.handle_ws(end_ws);
+            return self.write_buf_writable(ctx, buf);
+        }
+
+        let mut flushed = 0;
+        let mut arm_sizes = Vec::new();
+        let mut has_else = false;
+        for (i, cond) in branches.iter().enumerate() {
+            self.handle_ws(cond.ws);
+            flushed += self.write_buf_writable(ctx, buf)?;
+            if i > 0 {
+                self.locals.pop();
+            }
+
+            if let Some(CondTest { target: None, expr }) = &cond.cond {
+                if let Expr::Cfg(predicate) = **expr {
+                    if i > 0 {
+                        buf.writeln("} else {");
+                    }
+                    arm_sizes
+                        .push(self.write_cfg_branch(ctx, buf, predicate, branches, i, end_ws)?);
+                    if i > 0 {
+                        buf.writeln("}");
+                    }
+                    return Ok(flushed + median(&mut arm_sizes));
+                }
+            }
+
+            self.locals.push();
+            let mut arm_size = 0;
+            if let Some(CondTest { target, expr }) = &cond.cond {
+                if i == 0 {
+                    buf.write("if ");
+                } else {
+                    buf.write("} else if ");
+                }
+
+                if let Some(target) = target {
                     let mut expr_buf = Buffer::new();
                     buf.write("let ");
                     // If this is a chain condition, then we need to declare the variable after the
@@ -405,9 +1131,12 @@ impl<'a> Generator<'a> {
                     buf.write(self.visit_expr_root(ctx, expr)?);
                     buf.write(") as &bool)");
                 }
-            } else {
+            } else if i > 0 {
                 buf.write("} else");
                 has_else = true;
+            } else {
+                // The first branch is an `else` if it follows a `cfg()` branch.
+                has_else = true;
             }
 
             buf.writeln(" {");
@@ -415,7 +1144,7 @@ impl<'a> Generator<'a> {
             arm_size += self.handle(ctx, &cond.nodes, buf, AstLevel::Nested)?;
             arm_sizes.push(arm_size);
         }
-        self.handle_ws(i.ws);
+        self.handle_ws(end_ws);
         flushed += self.write_buf_writable(ctx, buf)?;
         buf.writeln("}");
 
@@ -427,6 +1156,63 @@ impl<'a> Generator<'a> {
         Ok(flushed + median(&mut arm_sizes))
     }
 
+    // A branch `{% if cfg(predicate) %}` is not part of the `if` chain. Its content and the
+    // following branches are written into two blocks with opposite `#[cfg]` attributes, so only
+    // one of them is compiled, and the other one may use items that don't exist.
+    fn write_cfg_branch(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        predicate: &str,
+        branches: &'a [WithSpan<'_, Cond<'_>>],
+        index: usize,
+        end_ws: Ws,
+    ) -> Result<usize, CompileError> {
+        let branch = &branches[index];
+        if syn::parse_str::<syn::Meta>(predicate).is_err() {
+            return Err(
+                ctx.generate_error(&format!("invalid `cfg` predicate: {predicate:?}"), branch)
+            );
+        }
+
+        buf.writeln(format_args!("#[cfg({predicate})] {{"));
+        self.locals.push();
+        let mut arm_size = self.handle(ctx, &branch.nodes, buf, AstLevel::Nested)?;
+        let next_ws = branches.get(index + 1).map_or(end_ws, |next| next.ws);
+        self.handle_ws(next_ws);
+        arm_size += self.write_buf_writable(ctx, buf)?;
+        self.locals.pop();
+        buf.writeln("}");
+
+        buf.writeln(format_args!("#[cfg(not({predicate}))] {{"));
+        let else_size = self.write_if_branches(ctx, buf, &branches[index + 1..], end_ws)?;
+        buf.writeln("}");
+        Ok(median(&mut [arm_size, else_size]))
+    }
+
+    // Writes `break` or `continue`, wrapped in an `if` if the tag has a condition.
+    fn write_loop_control(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        ws: Ws,
+        cond: Option<&WithSpan<'_, Expr<'_>>>,
+        keyword: &str,
+    ) -> Result<(), CompileError> {
+        self.handle_ws(ws);
+        self.write_buf_writable(ctx, buf)?;
+        match cond {
+            Some(cond) => {
+                // Coerces e.g. `&&bool` to `bool`, like in `write_if()`.
+                buf.write("if *(&(");
+                buf.write(self.visit_expr_root(ctx, cond)?);
+                buf.writeln(format_args!(") as &bool) {{ {keyword}; }}"));
+            }
+            None => buf.writeln(format_args!("{keyword};")),
+        }
+        Ok(())
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn write_match(
         &mut self,
@@ -501,12 +1287,12 @@ impl<'a> Generator<'a> {
             // If `iter` is a call then we assume it's something that returns
             // an iterator. If not then the user can explicitly add the needed
             // call without issues.
-            Expr::Call(..) | Expr::Index(..) => {
-                buf.writeln(format_args!("let _iter = ({expr_code}).into_iter();"))
-            }
+            Expr::Call(..) => buf.writeln(format_args!("let _iter = ({expr_code}).into_iter();")),
+            // An element of a collection has to be borrowed, like a field.
+            Expr::Index(..) => buf.writeln(format_args!("let _iter = (&{expr_code}).into_iter();")),
             // If accessing `self` then it most likely needs to be
             // borrowed, to prevent an attempt of moving.
-            _ if expr_code.starts_with("self.") => {
+            _ if expr_code.starts_with("self.") || expr_code.starts_with("(*self.") => {
                 buf.writeln(format_args!("let _iter = (&{expr_code}).into_iter();"))
             }
             // If accessing a field then it most likely needs to be
@@ -555,6 +1341,7 @@ impl<'a> Generator<'a> {
             size_hint2 = self.write_buf_writable(ctx, buf)?;
         }
 
+        self.locals.pop();
         buf.writeln("}");
 
         Ok(flushed + ((size_hint1 * 3) + size_hint2) / 2)
@@ -576,33 +1363,81 @@ impl<'a> Generator<'a> {
             return self.write_block(ctx, buf, None, ws, call);
         }
 
-        let (def, own_ctx) = match scope {
+        let (def, own_ctx) = self.find_macro(ctx, scope, name, call)?;
+
+        self.flush_ws(ws); // Cannot handle_ws() here: whitespace from macro definition comes first
+        self.locals.push();
+        self.write_buf_writable(ctx, buf)?;
+        buf.writeln("{");
+        self.prepare_ws(def.ws1);
+
+        self.write_macro_args(ctx, buf, name, def, args, call)?;
+
+        let mut size_hint = self.handle(own_ctx, &def.nodes, buf, AstLevel::Nested)?;
+
+        self.flush_ws(def.ws2);
+        size_hint += self.write_buf_writable(ctx, buf)?;
+        buf.writeln("}");
+        self.locals.pop();
+        self.prepare_ws(ws);
+        Ok(size_hint)
+    }
+
+    fn find_macro<'b, T>(
+        &mut self,
+        ctx: &'b Context<'a>,
+        scope: Option<&str>,
+        name: &str,
+        node: &WithSpan<'_, T>,
+    ) -> Result<(&'a Macro<'a>, &'b Context<'a>), CompileError> {
+        Ok(match scope {
             Some(s) => {
                 let path = ctx.imports.get(s).ok_or_else(|| {
-                    ctx.generate_error(&format!("no import found for scope {s:?}"), call)
+                    ctx.generate_error(&format!("no import found for scope {s:?}"), node)
                 })?;
                 let mctx = self.contexts.get(path).ok_or_else(|| {
-                    ctx.generate_error(&format!("context for {path:?} not found"), call)
+                    ctx.generate_error(&format!("context for {path:?} not found"), node)
                 })?;
                 let def = mctx.macros.get(name).ok_or_else(|| {
-                    ctx.generate_error(&format!("macro {name:?} not found in scope {s:?}"), call)
+                    ctx.generate_error(&format!("macro {name:?} not found in scope {s:?}"), node)
                 })?;
+                self.lints.used_scopes.insert(s.to_string());
+                self.lints.called(def);
                 (def, mctx)
             }
             None => {
-                let def = ctx.macros.get(name).ok_or_else(|| {
-                    ctx.generate_error(&format!("macro {name:?} not found"), call)
+                if let Some(def) = ctx.macros.get(name) {
+                    self.lints.called(def);
+                    return Ok((def, ctx));
+                }
+                let (path, orig_name) = ctx.macro_imports.get(name).ok_or_else(|| {
+                    ctx.generate_error(&format!("macro {name:?} not found"), node)
+                })?;
+                let mctx = self.contexts.get(path).ok_or_else(|| {
+                    ctx.generate_error(&format!("context for {path:?} not found"), node)
+                })?;
+                let def = mctx.macros.get(orig_name).ok_or_else(|| {
+                    ctx.generate_error(
+                        &format!("macro {orig_name:?} not found in the imported template"),
+                        node,
+                    )
                 })?;
-                (def, ctx)
+                self.lints.used_macro_imports.insert(name.to_string());
+                self.lints.called(def);
+                (def, mctx)
             }
-        };
-
-        self.flush_ws(ws); // Cannot handle_ws() here: whitespace from macro definition comes first
-        self.locals.push();
-        self.write_buf_writable(ctx, buf)?;
-        buf.writeln("{");
-        self.prepare_ws(def.ws1);
+        })
+    }
 
+    fn write_macro_args<T>(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        name: &str,
+        def: &'a Macro<'a>,
+        args: &[WithSpan<'_, Expr<'_>>],
+        node: &WithSpan<'_, T>,
+    ) -> Result<(), CompileError> {
         let mut names = Buffer::new();
         let mut values = Buffer::new();
         let mut is_first_variable = true;
@@ -614,7 +1449,7 @@ impl<'a> Generator<'a> {
                     if def.args.len() != 1 { "s" } else { "" },
                     args.len()
                 ),
-                call,
+                node,
             ));
         }
         let mut named_arguments = HashMap::new();
@@ -629,7 +1464,7 @@ impl<'a> Generator<'a> {
                 if !def.args.iter().any(|arg| arg == arg_name) {
                     return Err(ctx.generate_error(
                         &format!("no argument named `{arg_name}` in macro {name:?}"),
-                        call,
+                        node,
                     ));
                 }
                 named_arguments.insert(Cow::Borrowed(arg_name), arg);
@@ -648,7 +1483,7 @@ impl<'a> Generator<'a> {
             let expr = match named_arguments.get(&Cow::Borrowed(arg)) {
                 Some(expr) => {
                     allow_positional = false;
-                    expr
+                    *expr
                 }
                 None => {
                     if !allow_positional {
@@ -659,7 +1494,7 @@ impl<'a> Generator<'a> {
                             "cannot have unnamed argument (`{arg}`) after named argument in macro \
                              {name:?}"
                         ),
-                            call,
+                            node,
                         ));
                     }
                     &args[index]
@@ -669,8 +1504,14 @@ impl<'a> Generator<'a> {
                 // If `expr` is already a form of variable then
                 // don't reintroduce a new variable. This is
                 // to avoid moving non-copyable values.
-                Expr::Var(name) if *name != "self" => {
-                    let var = self.locals.resolve_or_self(name);
+                Expr::Var(name)
+                    if *name != "self"
+                        && (!self.isolated || self.locals.resolve(name).is_some()) =>
+                {
+                    let var = self
+                        .locals
+                        .resolve(name)
+                        .unwrap_or_else(|| self.resolve_non_local(name));
                     self.locals
                         .insert(Cow::Borrowed(arg), LocalMeta::with_ref(var));
                 }
@@ -710,15 +1551,7 @@ impl<'a> Generator<'a> {
         if !names.buf.is_empty() {
             buf.writeln(format_args!("let ({}) = ({});", names.buf, values.buf));
         }
-
-        let mut size_hint = self.handle(own_ctx, &def.nodes, buf, AstLevel::Nested)?;
-
-        self.flush_ws(def.ws2);
-        size_hint += self.write_buf_writable(ctx, buf)?;
-        buf.writeln("}");
-        self.locals.pop();
-        self.prepare_ws(ws);
-        Ok(size_hint)
+        Ok(())
     }
 
     fn write_filter_block(
@@ -729,8 +1562,11 @@ impl<'a> Generator<'a> {
     ) -> Result<usize, CompileError> {
         self.write_buf_writable(ctx, buf)?;
         self.flush_ws(filter.ws1);
-        self.is_in_filter_block += 1;
         self.write_buf_writable(ctx, buf)?;
+        if let Some(size_hint) = self.write_const_filter_block(ctx, buf, filter)? {
+            return Ok(size_hint);
+        }
+        self.is_in_filter_block += 1;
         buf.writeln("{");
 
         // build `FmtCell` that contains the inner block
@@ -760,7 +1596,7 @@ impl<'a> Generator<'a> {
             DisplayWrap::Wrapped => filter_buf.buf,
             DisplayWrap::Unwrapped => format!(
                 "(&&{CRATE}::filters::AutoEscaper::new(&({}), {})).rinja_auto_escape()?",
-                filter_buf.buf, self.input.escaper,
+                filter_buf.buf, self.escaper,
             ),
         };
         buf.writeln(format_args!(
@@ -775,78 +1611,297 @@ impl<'a> Generator<'a> {
         Ok(size_hint)
     }
 
-    fn handle_include(
+    // A filter block that only contains text, and is only filtered by filters that do not depend
+    // on anything but their input, is filtered and escaped at compile time, so no `FmtCell` is
+    // needed at runtime.
+    fn write_const_filter_block(
         &mut self,
         ctx: &Context<'a>,
         buf: &mut Buffer,
-        i: &'a Include<'_>,
-    ) -> Result<usize, CompileError> {
-        self.flush_ws(i.ws);
-        self.write_buf_writable(ctx, buf)?;
-        let path = self
-            .input
-            .config
-            .find_template(i.path, Some(&self.input.path))?;
-
-        // Make sure the compiler understands that the generated code depends on the template file.
+        filter: &'a WithSpan<'_, FilterBlock<'_>>,
+    ) -> Result<Option<usize>, CompileError> {
+        if !filter
+            .nodes
+            .iter()
+            .all(|node| matches!(node, Node::Lit(_) | Node::Comment(_)))
         {
-            let path = path.to_str().unwrap();
-            buf.writeln(
-                quote! {
-                    const _: &[::core::primitive::u8] = ::core::include_bytes!(#path);
-                }
-                .to_string(),
-            );
+            return Ok(None);
         }
 
-        // We clone the context of the child in order to preserve their macros and imports.
-        // But also add all the imports and macros from this template that don't override the
-        // child's ones to preserve this template's context.
-        let child_ctx = &mut self.contexts[&path].clone();
-        for (name, mac) in &ctx.macros {
-            child_ctx.macros.entry(name).or_insert(mac);
-        }
-        for (name, import) in &ctx.imports {
-            child_ctx
-                .imports
-                .entry(name)
-                .or_insert_with(|| import.clone());
+        // the outermost filter comes first
+        let mut filters = Vec::new();
+        let mut current = &filter.filters;
+        loop {
+            if !CONST_FILTERS.contains(&current.name) {
+                return Ok(None);
+            }
+            filters.push(current.name);
+            match current.arguments.as_slice() {
+                [source] => match **source {
+                    Expr::FilterSource => break,
+                    Expr::Filter(ref inner) => current = inner,
+                    _ => return Ok(None),
+                },
+                _ => return Ok(None),
+            }
         }
 
-        // Create a new generator for the child, and call it like in `impl_template` as if it were
-        // a full template, while preserving the context.
-        let heritage = if !child_ctx.blocks.is_empty() || child_ctx.extends.is_some() {
-            Some(Heritage::new(child_ctx, self.contexts))
-        } else {
-            None
+        let Some(escape_html) = self.const_escaper() else {
+            return Ok(None);
         };
 
-        let handle_ctx = match &heritage {
-            Some(heritage) => heritage.root,
-            None => child_ctx,
-        };
-        let locals = MapChain::with_parent(&self.locals);
-        let mut child = Self::new(
-            self.input,
-            self.contexts,
-            heritage.as_ref(),
-            locals,
-            self.buf_writable.discard,
-            self.is_in_filter_block,
-        );
-        let mut size_hint = child.handle(handle_ctx, handle_ctx.nodes, buf, AstLevel::Top)?;
-        size_hint += child.write_buf_writable(handle_ctx, buf)?;
-        self.prepare_ws(i.ws);
+        self.prepare_ws(filter.ws1);
+        self.handle(ctx, &filter.nodes, buf, AstLevel::Nested)?;
+        self.flush_ws(filter.ws2);
+        let mut text = String::new();
+        for writable in mem::take(&mut self.buf_writable.buf) {
+            if let Writable::Lit(s) = writable {
+                text.push_str(&s);
+            }
+        }
+        for name in filters.into_iter().rev() {
+            text = apply_const_filter(name, &text);
+        }
+        if escape_html {
+            text = escape_html_const(&text);
+        }
+        if !text.is_empty() {
+            buf.write_lit(&text);
+        }
+        self.prepare_ws(filter.ws2);
+        Ok(Some(text.len()))
+    }
 
-        Ok(size_hint)
+    // Returns `Some(true)` if the current escaper is `Html`, `Some(false)` if it is `Text`, and
+    // `None` if text cannot be escaped at compile time.
+    fn const_escaper(&self) -> Option<bool> {
+        if self.escaper == format!("{CRATE}::filters::Html") {
+            Some(true)
+        } else if self.escaper == format!("{CRATE}::filters::Text") {
+            Some(false)
+        } else {
+            None
+        }
     }
 
-    fn is_shadowing_variable<T>(
-        &self,
-        ctx: &Context<'_>,
-        var: &Target<'a>,
-        l: &WithSpan<'_, T>,
-    ) -> Result<bool, CompileError> {
+    fn handle_include(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        i: &'a WithSpan<'_, Include<'_>>,
+    ) -> Result<usize, CompileError> {
+        self.flush_ws(i.ws);
+        self.write_buf_writable(ctx, buf)?;
+        let size_hint = match i.path {
+            TemplatePath::Static(path) => {
+                let path = self
+                    .input
+                    .config
+                    .find_template(path, Some(&self.input.path))?;
+                self.write_include(ctx, buf, i, path)?
+            }
+            TemplatePath::Dynamic(ref expr) => {
+                if self.input.includes.is_empty() {
+                    return Err(ctx.generate_error(
+                        "the path of an `include` must be a string literal, or the templates it \
+                         can include must be listed in `#[template(includes = [...])]`",
+                        i,
+                    ));
+                }
+
+                let expr = self.visit_expr_root(ctx, expr)?;
+                buf.writeln(format_args!(
+                    "match ::core::convert::AsRef::<::core::primitive::str>::as_ref(&({expr})) {{"
+                ));
+                let mut arm_sizes = Vec::with_capacity(self.input.includes.len());
+                for (name, path) in &self.input.includes {
+                    buf.writeln(format_args!("{name:?} => {{"));
+                    arm_sizes.push(self.write_include(ctx, buf, i, path.clone())?);
+                    buf.writeln("}");
+                }
+                buf.writeln(format_args!(
+                    "rinja_path => return ::core::result::Result::Err(\
+                        {CRATE}::Error::UnknownInclude({CRATE}::helpers::alloc::string::String::from(rinja_path))\
+                    ),"
+                ));
+                buf.writeln("}");
+                median(&mut arm_sizes)
+            }
+        };
+        self.prepare_ws(i.ws);
+
+        Ok(size_hint)
+    }
+
+    /// Renders the template once for every parent template listed in `extends = [...]`, and
+    /// selects the heritage to use at runtime
+    fn write_dynamic_extends(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        expr: &WithSpan<'_, Expr<'_>>,
+    ) -> Result<usize, CompileError> {
+        if self.input.extends.is_empty() {
+            return Err(ctx.generate_error(
+                "the path of an `extends` must be a string literal, or the templates it can \
+                 extend must be listed in `#[template(extends = [...])]`",
+                expr,
+            ));
+        }
+
+        let expr = self.visit_expr_root(ctx, expr)?;
+        buf.writeln(format_args!(
+            "match ::core::convert::AsRef::<::core::primitive::str>::as_ref(&({expr})) {{"
+        ));
+        let mut arm_sizes = Vec::with_capacity(self.input.extends.len());
+        for (name, path) in &self.input.extends {
+            buf.writeln(format_args!("{name:?} => {{"));
+
+            let mut child_ctx = ctx.clone();
+            child_ctx.dynamic_extends = None;
+            child_ctx.extends = Some(path.clone());
+            let heritage = Heritage::new(&child_ctx, self.contexts)?;
+            let mut child = Self::new(
+                self.input,
+                self.contexts,
+                Some(&heritage),
+                MapChain::with_parent(&self.locals),
+                self.buf_writable.discard,
+                self.is_in_filter_block,
+            );
+            child.isolated = self.isolated;
+            child.whitespace = self.whitespace;
+            child.escaper = self.escaper;
+            let mut size_hint = child.handle_template(heritage.root, buf)?;
+            child.flush_ws(Ws(None, None));
+            size_hint += child.write_buf_writable(heritage.root, buf)?;
+            self.message_ids.append(&mut child.message_ids);
+            self.lints.append(&mut child.lints);
+            arm_sizes.push(size_hint);
+
+            buf.writeln("}");
+        }
+        buf.writeln(format_args!(
+            "rinja_path => return ::core::result::Result::Err(\
+                {CRATE}::Error::UnknownExtends({CRATE}::helpers::alloc::string::String::from(rinja_path))\
+            ),"
+        ));
+        buf.writeln("}");
+        Ok(median(&mut arm_sizes))
+    }
+
+    fn write_include(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        i: &'a WithSpan<'_, Include<'_>>,
+        path: Arc<Path>,
+    ) -> Result<usize, CompileError> {
+        let has_scope = !i.vars.is_empty() || i.only;
+        if has_scope {
+            buf.writeln("{");
+            self.locals.push();
+            self.write_include_vars(ctx, buf, i)?;
+        }
+
+        // We clone the context of the child in order to preserve their macros and imports.
+        // But also add all the imports and macros from this template that don't override the
+        // child's ones to preserve this template's context.
+        let child_ctx = &mut self.contexts[&path].clone();
+        for (name, mac) in &ctx.macros {
+            child_ctx.macros.entry(name).or_insert(mac);
+        }
+        for (name, import) in &ctx.imports {
+            child_ctx
+                .imports
+                .entry(name)
+                .or_insert_with(|| import.clone());
+        }
+        for (name, import) in &ctx.macro_imports {
+            child_ctx
+                .macro_imports
+                .entry(name)
+                .or_insert_with(|| import.clone());
+        }
+
+        // Create a new generator for the child, and call it like in `impl_template` as if it were
+        // a full template, while preserving the context.
+        let heritage = if !child_ctx.blocks.is_empty() || child_ctx.extends.is_some() {
+            Some(Heritage::new(child_ctx, self.contexts)?)
+        } else {
+            None
+        };
+
+        let handle_ctx = match &heritage {
+            Some(heritage) => heritage.root,
+            None => child_ctx,
+        };
+        let locals = match i.only {
+            // Only the variables given with `with` are visible in the included template.
+            true => MapChain {
+                parent: None,
+                scopes: vec![self.locals.scopes.last().unwrap().clone()],
+            },
+            false => MapChain::with_parent(&self.locals),
+        };
+        let mut child = Self::new(
+            self.input,
+            self.contexts,
+            heritage.as_ref(),
+            locals,
+            self.buf_writable.discard,
+            self.is_in_filter_block,
+        );
+        child.isolated = self.isolated || i.only;
+        child.whitespace = self.whitespace;
+        child.escaper = self.escaper;
+        let mut size_hint = child.handle_template(handle_ctx, buf)?;
+        size_hint += child.write_buf_writable(handle_ctx, buf)?;
+        self.lints.append(&mut child.lints);
+        if has_scope {
+            self.locals.pop();
+            buf.writeln("}");
+        }
+
+        Ok(size_hint)
+    }
+
+    fn write_include_vars(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        i: &'a WithSpan<'_, Include<'_>>,
+    ) -> Result<(), CompileError> {
+        // All values are evaluated before any variable is declared, so e.g. in
+        // `with a = b, b = a` the values are swapped.
+        let mut targets = Buffer::new();
+        let mut values = Buffer::new();
+        for (var, val) in &i.vars {
+            // rejects literals on the left-hand side
+            self.is_shadowing_variable(ctx, var, i)?;
+            values.write(match is_copyable(val) {
+                true => "(",
+                false => "&(",
+            });
+            values.write(self.visit_expr_root(ctx, val)?);
+            values.write("),");
+        }
+        for (var, _) in &i.vars {
+            self.visit_target(&mut targets, true, true, var);
+            targets.write(",");
+        }
+        if !i.vars.is_empty() {
+            buf.writeln(format_args!("let ({}) = ({});", targets.buf, values.buf));
+        }
+        Ok(())
+    }
+
+    fn is_shadowing_variable<T>(
+        &self,
+        ctx: &Context<'a>,
+        var: &Target<'a>,
+        l: &WithSpan<'_, T>,
+    ) -> Result<bool, CompileError> {
         match var {
             Target::Name(name) => {
                 let name = normalize_identifier(name);
@@ -877,6 +1932,8 @@ impl<'a> Generator<'a> {
                 }
                 Ok(false)
             }
+            // `_` and `..` do not bind anything
+            Target::Placeholder(_) | Target::Rest(_) => Ok(false),
             _ => Err(ctx.generate_error(
                 "literals are not allowed on the left-hand side of an assignment",
                 l,
@@ -886,7 +1943,7 @@ impl<'a> Generator<'a> {
 
     fn write_let(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         l: &'a WithSpan<'_, Let<'_>>,
     ) -> Result<(), CompileError> {
@@ -896,6 +1953,7 @@ impl<'a> Generator<'a> {
             self.write_buf_writable(ctx, buf)?;
             buf.write("let ");
             self.visit_target(buf, false, true, &l.var);
+            self.track_let_usage(ctx, &l.var, Vec::new(), l);
             buf.writeln(";");
             return Ok(());
         };
@@ -916,7 +1974,9 @@ impl<'a> Generator<'a> {
             buf.write("let ");
         }
 
+        let declared = self.declared_usage(&l.var);
         self.visit_target(buf, true, true, &l.var);
+        self.track_let_usage(ctx, &l.var, declared, l);
         let (before, after) = if !is_copyable(val) {
             ("&(", ")")
         } else {
@@ -926,6 +1986,489 @@ impl<'a> Generator<'a> {
         Ok(())
     }
 
+    fn write_do(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        d: &'a WithSpan<'_, Do<'_>>,
+    ) -> Result<usize, CompileError> {
+        self.handle_ws(d.ws);
+        // The pending output must be written first, because it can depend on the side effects.
+        let flushed = self.write_buf_writable(ctx, buf)?;
+        buf.write("let _ = ");
+        self.visit_expr(ctx, buf, &d.expr)?;
+        buf.writeln(";");
+        Ok(flushed)
+    }
+
+    fn write_extension(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        e: &'a WithSpan<'_, Extension<'_>>,
+    ) -> Result<usize, CompileError> {
+        let Some(path) = self.input.config.tags.get(e.name) else {
+            return Err(ctx.generate_error(&format!("unknown tag `{}`", e.name), e));
+        };
+        if let Some((nodes, ws2)) = &e.body {
+            return self.write_extension_block(ctx, buf, e, path, nodes, *ws2);
+        }
+        self.handle_ws(e.ws);
+        let flushed = self.write_buf_writable(ctx, buf)?;
+        // The output of the macro is escaped like the value of an expression.
+        buf.writeln(format_args!(
+            "(&&{CRATE}::helpers::Writable(\
+                &(&&{CRATE}::filters::AutoEscaper::new(&({path}!({args:?})), {escaper}))\
+                    .rinja_auto_escape()?\
+            )).rinja_write(writer)?;",
+            args = e.args,
+            escaper = self.escaper,
+        ));
+        Ok(flushed + 3)
+    }
+
+    // The body is rendered like the body of a `{% filter %}` block, and given to the macro as
+    // a value that implements `Display`.
+    fn write_extension_block(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        e: &'a WithSpan<'_, Extension<'_>>,
+        path: &str,
+        nodes: &'a [Node<'_>],
+        ws2: Ws,
+    ) -> Result<usize, CompileError> {
+        self.write_buf_writable(ctx, buf)?;
+        self.flush_ws(e.ws);
+        self.write_buf_writable(ctx, buf)?;
+        self.is_in_filter_block += 1;
+        buf.writeln("{");
+
+        buf.writeln(format_args!(
+            "let {FILTER_SOURCE} = {CRATE}::helpers::FmtCell::new(\
+                |writer: &mut ::core::fmt::Formatter<'_>| -> {CRATE}::Result<()> {{"
+        ));
+        self.locals.push();
+        self.prepare_ws(e.ws);
+        let size_hint = self.handle(ctx, nodes, buf, AstLevel::Nested)?;
+        self.flush_ws(ws2);
+        self.write_buf_writable(ctx, buf)?;
+        self.locals.pop();
+        buf.writeln(format_args!("{CRATE}::Result::Ok(())"));
+        buf.writeln("});");
+
+        buf.writeln(format_args!(
+            "if ::core::write!(\
+                writer,\
+                \"{{}}\",\
+                (&&{CRATE}::filters::AutoEscaper::new(\
+                    &({path}!({args:?}, &{FILTER_SOURCE})),\
+                    {escaper},\
+                )).rinja_auto_escape()?,\
+            ).is_err() {{\n\
+                return {FILTER_SOURCE}.take_err();\n\
+            }}",
+            args = e.args,
+            escaper = self.escaper,
+        ));
+
+        buf.writeln("}");
+        self.is_in_filter_block -= 1;
+        self.prepare_ws(ws2);
+        Ok(size_hint)
+    }
+
+    fn write_let_else(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        l: &'a WithSpan<'_, Let<'_>>,
+        rest: &'a [Node<'_>],
+        level: AstLevel,
+    ) -> Result<usize, CompileError> {
+        let (Some(val), Some(else_nodes)) = (&l.val, &l.else_nodes) else {
+            unreachable!("`let ... else` always has a value and an `else` body");
+        };
+
+        self.flush_ws(l.ws);
+        let mut flushed = self.write_buf_writable(ctx, buf)?;
+
+        let mut expr_buf = Buffer::new();
+        self.visit_expr(ctx, &mut expr_buf, val)?;
+
+        // Like `{% if let %}`, but the content after `{% endlet %}` is the `if` branch.
+        self.locals.push();
+        buf.write("if let ");
+        self.visit_target(buf, true, true, &l.var);
+        self.track_let_usage(ctx, &l.var, Vec::new(), l);
+        buf.write(" = &");
+        buf.write(expr_buf.buf);
+        buf.writeln(" {");
+        self.prepare_ws(l.ws2);
+        // The whitespace in front of the end tag of the enclosing block is only known when the
+        // end tag is handled, after the branch is closed, so it is rendered in both cases.
+        let then_size = self.handle(ctx, rest, buf, level)? + self.write_buf_writable(ctx, buf)?;
+        let pending_ws = self.next_ws.take();
+        self.locals.pop();
+
+        buf.writeln("} else {");
+        self.locals.push();
+        self.prepare_ws(l.ws);
+        let mut else_size = self.handle(ctx, else_nodes, buf, AstLevel::Nested)?;
+        self.flush_ws(l.ws2);
+        else_size += self.write_buf_writable(ctx, buf)?;
+        self.locals.pop();
+        buf.writeln("}");
+
+        self.next_ws = pending_ws;
+        flushed += median(&mut [then_size, else_size]);
+        Ok(flushed)
+    }
+
+    fn write_let_block(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        l: &'a WithSpan<'_, LetBlock<'_>>,
+    ) -> Result<(), CompileError> {
+        self.write_buf_writable(ctx, buf)?;
+        self.flush_ws(l.ws1);
+
+        let var = Target::Name(l.var);
+        if self.is_shadowing_variable(ctx, &var, l)?
+            || self
+                .locals
+                .get(&Cow::Borrowed(normalize_identifier(l.var)))
+                .is_none()
+        {
+            buf.write("let ");
+        }
+        let declared = self.declared_usage(&var);
+        self.visit_target(buf, true, true, &var);
+        self.track_let_usage(ctx, &var, declared, l);
+        buf.writeln(" = {");
+
+        // render the content into a string, which is then used as an already escaped value
+        buf.writeln(format_args!(
+            "let mut {LET_BLOCK_OUTPUT} = {CRATE}::helpers::alloc::string::String::new();"
+        ));
+        buf.writeln("{");
+        buf.writeln(format_args!("let writer = &mut {LET_BLOCK_OUTPUT};"));
+        self.locals.push();
+        self.in_local_output += 1;
+        self.prepare_ws(l.ws1);
+        self.handle(ctx, &l.nodes, buf, AstLevel::Nested)?;
+        self.flush_ws(l.ws2);
+        self.write_buf_writable(ctx, buf)?;
+        self.in_local_output -= 1;
+        self.locals.pop();
+        buf.writeln("}");
+        buf.writeln(format_args!("{CRATE}::filters::Safe({LET_BLOCK_OUTPUT})"));
+        buf.writeln("};");
+
+        self.prepare_ws(l.ws2);
+        Ok(())
+    }
+
+    // Returns for every variable in `target` whether it is used, if it was declared without a
+    // value by an earlier `{% let %}`, so an assignment does not count as a new variable.
+    fn declared_usage(&self, target: &Target<'a>) -> Vec<Option<Rc<Cell<bool>>>> {
+        let mut names = Vec::new();
+        target_names(target, &mut names);
+        names
+            .into_iter()
+            .map(|name| match self.locals.get(&Cow::Borrowed(name)) {
+                Some(meta) if !meta.initialized => meta.used.clone(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Remembers the variables of a `{% let %}` that was just visited, to warn if one of them is
+    // never used.
+    fn track_let_usage<T>(
+        &mut self,
+        ctx: &Context<'a>,
+        target: &Target<'a>,
+        declared: Vec<Option<Rc<Cell<bool>>>>,
+        node: &WithSpan<'_, T>,
+    ) {
+        let mut names = Vec::new();
+        target_names(target, &mut names);
+        let mut declared = declared.into_iter();
+        for name in names {
+            let used = match declared.next().flatten() {
+                Some(used) => used,
+                None => {
+                    let used = Rc::new(Cell::new(false));
+                    let msg = ctx.generate_warning(
+                        &format!("unused variable `{name}` declared with `{{% let %}}`"),
+                        node,
+                    );
+                    self.lints.lets.push((msg, Rc::clone(&used)));
+                    used
+                }
+            };
+            if let Some(meta) = self.locals.scopes.last_mut().unwrap().get_mut(name) {
+                meta.used = Some(used);
+            }
+        }
+    }
+
+    fn write_with(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        w: &'a WithSpan<'_, With<'_>>,
+    ) -> Result<usize, CompileError> {
+        self.write_buf_writable(ctx, buf)?;
+        self.flush_ws(w.ws1);
+        buf.writeln("{");
+        self.locals.push();
+
+        for (var, val) in &w.vars {
+            // rejects literals on the left-hand side
+            self.is_shadowing_variable(ctx, var, w)?;
+            let mut expr_buf = Buffer::new();
+            self.visit_expr(ctx, &mut expr_buf, val)?;
+            buf.write("let ");
+            self.visit_target(buf, true, true, var);
+            let (before, after) = if !is_copyable(val) {
+                ("&(", ")")
+            } else {
+                ("", "")
+            };
+            buf.writeln(format_args!(" = {before}{}{after};", &expr_buf.buf));
+        }
+
+        self.prepare_ws(w.ws1);
+        let size_hint = self.handle(ctx, &w.nodes, buf, AstLevel::Nested)?;
+        self.flush_ws(w.ws2);
+        self.write_buf_writable(ctx, buf)?;
+        self.locals.pop();
+        buf.writeln("}");
+        self.prepare_ws(w.ws2);
+        Ok(size_hint)
+    }
+
+    fn write_trans(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        t: &'a WithSpan<'_, Trans<'_>>,
+    ) -> Result<usize, CompileError> {
+        self.message_ids.insert(t.id.to_owned());
+        self.flush_ws(t.ws1);
+        let flushed = self.write_buf_writable(ctx, buf)?;
+        buf.writeln("{");
+        self.locals.push();
+
+        // The arguments are visible inside of the fallback content, like in a `{% with %}` block.
+        for (name, val) in &t.args {
+            let target = Target::Name(name);
+            self.is_shadowing_variable(ctx, &target, t)?;
+            let mut expr_buf = Buffer::new();
+            self.visit_expr(ctx, &mut expr_buf, val)?;
+            buf.write("let ");
+            self.visit_target(buf, true, true, &target);
+            let (before, after) = if !is_copyable(val) {
+                ("&(", ")")
+            } else {
+                ("", "")
+            };
+            buf.writeln(format_args!(" = {before}{}{after};", &expr_buf.buf));
+        }
+
+        buf.write(format_args!(
+            "match {CRATE}::i18n::Translate::translate(self, \"{}\", &[",
+            t.id,
+        ));
+        for (name, _) in &t.args {
+            buf.write(format_args!(
+                "({name:?}, &{} as &dyn ::core::fmt::Display),",
+                normalize_identifier(name),
+            ));
+        }
+        buf.writeln("]) {");
+        buf.writeln(format_args!(
+            "::core::option::Option::Some(__rinja_trans) => ::core::write!(\
+                writer, \"{{}}\", \
+                (&&{CRATE}::filters::AutoEscaper::new(&__rinja_trans, {})).rinja_auto_escape()?\
+            )?,",
+            self.escaper,
+        ));
+        buf.writeln("::core::option::Option::None => {");
+
+        // The arguments are borrowed as `&dyn Display` until the end of the `match`, which must
+        // not be held over an `.await` in `render_into_async()`.
+        self.in_local_output += 1;
+        self.prepare_ws(t.ws1);
+        let mut size_hint = self.handle(ctx, &t.nodes, buf, AstLevel::Nested)?;
+        self.flush_ws(t.ws2);
+        size_hint += self.write_buf_writable(ctx, buf)?;
+        self.in_local_output -= 1;
+        self.locals.pop();
+        buf.writeln("}");
+        buf.writeln("}");
+        buf.writeln("}");
+        self.prepare_ws(t.ws2);
+        Ok(flushed + size_hint)
+    }
+
+    fn write_cache(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        c: &'a WithSpan<'_, Cache<'_>>,
+    ) -> Result<usize, CompileError> {
+        self.flush_ws(c.ws1);
+        let flushed = self.write_buf_writable(ctx, buf)?;
+        buf.writeln("{");
+
+        // The template type, the position of the block and the escaper are part of the key, so
+        // blocks with the same key don't share their content, even if the templates of several
+        // modules or crates use the same cache.
+        let key = self.visit_expr_root(ctx, &c.key)?;
+        buf.writeln(format_args!(
+            "let {CACHE_KEY} = {CRATE}::helpers::alloc::format!(\"{{}}:{{}}\", \
+                ::core::concat!(::core::module_path!(), \"::\", {:?}, \"@\", {:?}, \"@\", {:?}), \
+                &({key}));",
+            self.input.ast.ident.to_string(),
+            ctx.node_id(c),
+            self.escaper,
+        ));
+        buf.writeln(format_args!(
+            "match {CRATE}::cache::RenderCache::get(self, &{CACHE_KEY}) {{"
+        ));
+        buf.writeln(
+            "::core::option::Option::Some(__rinja_cached) => \
+                ::core::fmt::Write::write_str(writer, &__rinja_cached)?,",
+        );
+        buf.writeln("::core::option::Option::None => {");
+
+        // render the content into a string, which is written and then stored in the cache
+        buf.writeln(format_args!(
+            "let mut {CACHE_OUTPUT} = {CRATE}::helpers::alloc::string::String::new();"
+        ));
+        buf.writeln("{");
+        buf.writeln(format_args!("let writer = &mut {CACHE_OUTPUT};"));
+        self.locals.push();
+        self.in_local_output += 1;
+        self.prepare_ws(c.ws1);
+        let mut size_hint = self.handle(ctx, &c.nodes, buf, AstLevel::Nested)?;
+        self.flush_ws(c.ws2);
+        size_hint += self.write_buf_writable(ctx, buf)?;
+        self.in_local_output -= 1;
+        self.locals.pop();
+        buf.writeln("}");
+        buf.writeln(format_args!(
+            "::core::fmt::Write::write_str(writer, &{CACHE_OUTPUT})?;"
+        ));
+        buf.writeln(format_args!(
+            "{CRATE}::cache::RenderCache::insert(self, {CACHE_KEY}, {CACHE_OUTPUT});"
+        ));
+        buf.writeln("}");
+        buf.writeln("}");
+        buf.writeln("}");
+        self.prepare_ws(c.ws2);
+        Ok(flushed + size_hint)
+    }
+
+    fn write_once(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        o: &'a WithSpan<'_, Once<'_>>,
+    ) -> Result<usize, CompileError> {
+        if !cfg!(feature = "std") {
+            return Err(ctx.generate_error("`once` blocks need the feature `std`", o));
+        }
+
+        self.flush_ws(o.ws1);
+        let flushed = self.write_buf_writable(ctx, buf)?;
+        // `module_path!()` starts with the name of the crate, and the full path of the template
+        // file tells apart the templates of crates that use the same relative paths.
+        buf.writeln(format_args!(
+            "if {CRATE}::helpers::first_render(\
+                ::core::concat!(::core::module_path!(), \"@\", {:?})) {{",
+            ctx.node_location(o),
+        ));
+        self.locals.push();
+        self.prepare_ws(o.ws1);
+        let mut size_hint = self.handle(ctx, &o.nodes, buf, AstLevel::Nested)?;
+        self.flush_ws(o.ws2);
+        size_hint += self.write_buf_writable(ctx, buf)?;
+        self.locals.pop();
+        buf.writeln("}");
+        self.prepare_ws(o.ws2);
+        // Most renderings skip the content.
+        Ok(flushed + median(&mut [size_hint, 0, 0]))
+    }
+
+    fn write_whitespace_block(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        w: &'a WithSpan<'_, WhitespaceBlock<'_>>,
+    ) -> Result<usize, CompileError> {
+        // The whitespace around the tags is handled like the content they are next to.
+        self.flush_ws(w.ws1);
+        let mut size_hint = self.write_buf_writable(ctx, buf)?;
+        let outer = mem::replace(&mut self.whitespace, w.mode.into());
+        self.prepare_ws(w.ws1);
+        buf.writeln("{");
+        self.locals.push();
+        size_hint += self.handle(ctx, &w.nodes, buf, AstLevel::Nested)?;
+        self.flush_ws(w.ws2);
+        size_hint += self.write_buf_writable(ctx, buf)?;
+        self.locals.pop();
+        buf.writeln("}");
+        self.whitespace = outer;
+        self.prepare_ws(w.ws2);
+        Ok(size_hint)
+    }
+
+    fn write_autoescape(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        a: &'a WithSpan<'_, AutoEscape<'_>>,
+    ) -> Result<usize, CompileError> {
+        let name = match a.escaping {
+            Escaping::Template => None,
+            Escaping::Disabled => Some("none"),
+            Escaping::Named(name) => Some(name),
+        };
+        let escaper = match name {
+            Some(name) => self.find_escaper(name).ok_or_else(|| {
+                ctx.generate_error(
+                    &format!(
+                        "invalid escaper '{name}' for `autoescape` block. {}",
+                        MsgValidEscapers(&self.input.config.escapers),
+                    ),
+                    a,
+                )
+            })?,
+            None => self.input.escaper,
+        };
+
+        // The expressions in the buffer have to be written with the escaper they were found with.
+        self.flush_ws(a.ws1);
+        let mut size_hint = self.write_buf_writable(ctx, buf)?;
+        let outer = mem::replace(&mut self.escaper, escaper);
+        self.prepare_ws(a.ws1);
+        buf.writeln("{");
+        self.locals.push();
+        size_hint += self.handle(ctx, &a.nodes, buf, AstLevel::Nested)?;
+        self.flush_ws(a.ws2);
+        size_hint += self.write_buf_writable(ctx, buf)?;
+        self.locals.pop();
+        buf.writeln("}");
+        self.escaper = outer;
+        self.prepare_ws(a.ws2);
+        Ok(size_hint)
+    }
+
     // If `name` is `Some`, this is a call to a block definition, and we have to find
     // the first block for that name from the ancestry chain. If name is `None`, this
     // is from a `super()` call, and we can get the name from `self.super_block`.
@@ -999,6 +2542,12 @@ impl<'a> Generator<'a> {
                 .entry(name)
                 .or_insert_with(|| import.clone());
         }
+        for (name, import) in &ctx.macro_imports {
+            child_ctx
+                .macro_imports
+                .entry(name)
+                .or_insert_with(|| import.clone());
+        }
 
         let mut child = Self::new(
             self.input,
@@ -1010,12 +2559,16 @@ impl<'a> Generator<'a> {
             self.is_in_filter_block,
         );
         child.buf_writable = mem::take(&mut self.buf_writable);
+        child.whitespace = self.whitespace;
+        child.escaper = self.escaper;
 
         // Handle inner whitespace suppression spec and process block nodes
         child.prepare_ws(def.ws1);
 
         child.super_block = Some(cur);
-        let size_hint = child.handle(&child_ctx, &def.nodes, buf, AstLevel::Block)?;
+        let origin = format!("{}#block {}", child_ctx.display_path(), cur.0);
+        let mut size_hint = child.write_origin_begin(&child_ctx, buf, &origin)?;
+        size_hint += child.handle(&child_ctx, &def.nodes, buf, AstLevel::Block)?;
 
         if !child.locals.is_current_empty() {
             // Need to flush the buffer before popping the variable stack
@@ -1023,7 +2576,9 @@ impl<'a> Generator<'a> {
         }
 
         child.flush_ws(def.ws2);
+        size_hint += child.write_origin_end(&child_ctx, buf, &origin)?;
         self.buf_writable = child.buf_writable;
+        self.lints.append(&mut child.lints);
 
         // Restore original block context and set whitespace suppression for
         // succeeding whitespace according to the outer WS spec
@@ -1046,41 +2601,75 @@ impl<'a> Generator<'a> {
     }
 
     fn write_expr(&mut self, ws: Ws, s: &'a WithSpan<'a, Expr<'a>>) {
-        self.handle_ws(ws);
+        self.flush_ws_inner(ws, false);
+        self.prepare_ws_inner(ws, false);
+        // An expression that only consists of literals is evaluated at compile time.
+        if let (Some(value), Some(escape_html)) = (ConstValue::eval(s), self.const_escaper()) {
+            let text = value.to_string();
+            let text = match escape_html {
+                true => escape_html_const(&text),
+                false => text,
+            };
+            if !text.is_empty() {
+                self.buf_writable.push(Writable::Lit(text.into()));
+            }
+            return;
+        }
         self.buf_writable.push(Writable::Expr(s));
     }
 
     // Write expression buffer and empty
     fn write_buf_writable(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+    ) -> Result<usize, CompileError> {
+        // Static text gets written on its own and is not merged into the format string of the
+        // expressions around it, so the writer receives it as a `&'static str`.
+        let mut size_hint = 0;
+        let start = buf.buf.len();
+        let mut writables = mem::take(&mut self.buf_writable.buf);
+        while let Some(first) = writables.first() {
+            let is_lit = matches!(first, Writable::Lit(_));
+            let len = writables
+                .iter()
+                .position(|w| matches!(w, Writable::Lit(_)) != is_lit)
+                .unwrap_or(writables.len());
+            let rest = writables.split_off(len);
+            self.buf_writable.buf = writables;
+            size_hint += self.write_buf_writable_run(ctx, buf)?;
+            writables = rest;
+        }
+        if self.async_body
+            && self.is_in_filter_block == 0
+            && self.in_local_output == 0
+            && buf.buf.len() > start
+        {
+            buf.write_async_flush();
+        }
+        Ok(size_hint)
+    }
+
+    fn write_buf_writable_run(
+        &mut self,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
     ) -> Result<usize, CompileError> {
         let WriteParts { size_hint, buffers } = self.prepare_format(ctx)?;
         match buffers {
-            None => Ok(size_hint),
-            Some(WritePartsBuffers { format, expr: None }) => {
-                buf.writeln(format_args!("writer.write_str({:#?})?;", &format.buf));
-                Ok(size_hint)
-            }
-            Some(WritePartsBuffers {
-                format,
-                expr: Some(expr),
-            }) => {
-                buf.writeln("::std::write!(");
-                buf.writeln("writer,");
-                buf.writeln(format_args!("{:#?},", &format.buf));
-                buf.writeln(expr.buf.trim());
-                buf.writeln(")?;");
-                Ok(size_hint)
+            None => {}
+            Some(WritePartsBuffers::Lit(lit)) => {
+                buf.write_lit(&lit);
             }
+            Some(WritePartsBuffers::Exprs(exprs)) => buf.write(exprs.buf),
         }
+        Ok(size_hint)
     }
 
     /// This is the common code to generate an expression. It is used for filter blocks and for
     /// expressions more generally. It stores the size it represents and the buffers. Take a look
     /// at `WriteParts` for more details.
-    fn prepare_format(&mut self, ctx: &Context<'_>) -> Result<WriteParts, CompileError> {
+    fn prepare_format(&mut self, ctx: &Context<'a>) -> Result<WriteParts, CompileError> {
         if self.buf_writable.is_empty() {
             return Ok(WriteParts {
                 size_hint: 0,
@@ -1093,96 +2682,87 @@ impl<'a> Generator<'a> {
             .iter()
             .all(|w| matches!(w, Writable::Lit(_)))
         {
-            let mut buf_lit = Buffer::new();
+            let mut lit = String::new();
             for s in mem::take(&mut self.buf_writable.buf) {
                 if let Writable::Lit(s) = s {
-                    buf_lit.write(s);
+                    lit.push_str(&s);
                 };
             }
             return Ok(WriteParts {
-                size_hint: buf_lit.buf.len(),
-                buffers: Some(WritePartsBuffers {
-                    format: buf_lit,
-                    expr: None,
-                }),
+                size_hint: lit.len(),
+                buffers: Some(WritePartsBuffers::Lit(lit)),
             });
         }
 
-        let mut expr_cache = HashMap::with_capacity(self.buf_writable.len());
-
+        // Every expression is written on its own, so `WriteWritable` can pick `FastWritable` or
+        // `Display` for each of them.
         let mut size_hint = 0;
-        let mut buf_format = Buffer::new();
         let mut buf_expr = Buffer::new();
-
+        let mut errors = CompileErrors::default();
         for s in mem::take(&mut self.buf_writable.buf) {
             match s {
                 Writable::Lit(s) => {
-                    buf_format.write(s.replace('{', "{{").replace('}', "}}"));
+                    buf_expr.writeln(format_args!("writer.write_str({s:#?})?;"));
                     size_hint += s.len();
                 }
                 Writable::Expr(s) => {
-                    let mut expr_buf = Buffer::new();
-                    let wrapped = self.visit_expr(ctx, &mut expr_buf, s)?;
-                    let cacheable = is_cacheable(s);
-                    size_hint += self.named_expression(
-                        &mut buf_expr,
-                        &mut buf_format,
-                        expr_buf.buf,
-                        wrapped,
-                        cacheable,
-                        &mut expr_cache,
-                    );
+                    let result = self.write_expr_output(ctx, &mut buf_expr, s);
+                    if errors.check(result).is_some() {
+                        size_hint += 3;
+                    }
                 }
             }
         }
+        errors.into_result()?;
         Ok(WriteParts {
             size_hint,
-            buffers: Some(WritePartsBuffers {
-                format: buf_format,
-                expr: Some(buf_expr),
-            }),
+            buffers: Some(WritePartsBuffers::Exprs(buf_expr)),
         })
     }
 
-    fn named_expression(
+    // Writes the value of `expr` into `writer`.
+    fn write_expr_output(
         &mut self,
-        buf_expr: &mut Buffer,
-        buf_format: &mut Buffer,
-        expr: String,
-        wrapped: DisplayWrap,
-        cacheable: bool,
-        expr_cache: &mut HashMap<String, usize>,
-    ) -> usize {
-        let expression = match wrapped {
-            DisplayWrap::Wrapped => expr,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        expr: &WithSpan<'_, Expr<'_>>,
+    ) -> Result<(), CompileError> {
+        if let Expr::IfElse(cond, then, otherwise) = &**expr {
+            // Every branch is written on its own, so their types don't have to be the same,
+            // e.g. a `String` field and a `&str` literal.
+            let mut cond_buf = Buffer::new();
+            self.visit_expr(ctx, &mut cond_buf, cond)?;
+            buf.writeln(format_args!("if *(&({}) as &bool) {{", cond_buf.buf));
+            self.write_expr_output(ctx, buf, then)?;
+            buf.writeln("} else {");
+            self.write_expr_output(ctx, buf, otherwise)?;
+            buf.writeln("}");
+            return Ok(());
+        }
+
+        let mut expr_buf = Buffer::new();
+        let expression = match self.visit_expr(ctx, &mut expr_buf, expr)? {
+            DisplayWrap::Wrapped => expr_buf.buf,
             DisplayWrap::Unwrapped => format!(
-                "(&&{CRATE}::filters::AutoEscaper::new(&({expr}), {})).rinja_auto_escape()?",
-                self.input.escaper,
+                "(&&{CRATE}::filters::AutoEscaper::new(&({}), {})).rinja_auto_escape()?",
+                expr_buf.buf, self.escaper,
             ),
         };
-        let id = match expr_cache.entry(expression) {
-            Entry::Occupied(e) if cacheable => *e.get(),
-            entry => {
-                let id = self.named;
-                self.named += 1;
-
-                buf_expr.write(format_args!("expr{id} = &{},", entry.key()));
-
-                if let Entry::Vacant(e) = entry {
-                    e.insert(id);
-                }
-
-                id
-            }
-        };
-
-        buf_format.write(format_args!("{{expr{id}}}"));
-        3
+        buf.writeln(format_args!(
+            "(&&{CRATE}::helpers::Writable(&({expression}))).rinja_write(writer)?;"
+        ));
+        Ok(())
     }
 
     fn visit_lit(&mut self, lit: &'a Lit<'_>) {
         assert!(self.next_ws.is_none());
-        let Lit { lws, val, rws } = *lit;
+        let Lit { mut lws, val, rws } = *lit;
+        if mem::take(&mut self.trim_newline) {
+            if let Some(rest) = lws.strip_prefix('\n').or_else(|| lws.strip_prefix("\r\n")) {
+                lws = rest;
+                self.at_line_start = true;
+            }
+        }
         if !lws.is_empty() {
             match self.skip_ws {
                 WhitespaceHandling::Suppress => {}
@@ -1190,12 +2770,12 @@ impl<'a> Generator<'a> {
                     assert!(rws.is_empty());
                     self.next_ws = Some(lws);
                 }
-                WhitespaceHandling::Preserve => self.buf_writable.push(Writable::Lit(lws)),
+                WhitespaceHandling::Preserve => self.buf_writable.push(Writable::Lit(lws.into())),
                 WhitespaceHandling::Minimize => {
                     self.buf_writable
                         .push(Writable::Lit(match lws.contains('\n') {
-                            true => "\n",
-                            false => " ",
+                            true => "\n".into(),
+                            false => " ".into(),
                         }));
                 }
             }
@@ -1203,7 +2783,8 @@ impl<'a> Generator<'a> {
 
         if !val.is_empty() {
             self.skip_ws = WhitespaceHandling::Preserve;
-            self.buf_writable.push(Writable::Lit(val));
+            self.at_line_start = false;
+            self.buf_writable.push(Writable::Lit(val.into()));
         }
 
         if !rws.is_empty() {
@@ -1219,7 +2800,7 @@ impl<'a> Generator<'a> {
 
     fn visit_expr_root(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         expr: &WithSpan<'_, Expr<'_>>,
     ) -> Result<String, CompileError> {
         let mut buf = Buffer::new();
@@ -1227,52 +2808,207 @@ impl<'a> Generator<'a> {
         Ok(buf.buf)
     }
 
-    fn visit_expr(
+    fn visit_expr(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        expr: &WithSpan<'_, Expr<'_>>,
+    ) -> Result<DisplayWrap, CompileError> {
+        Ok(match **expr {
+            Expr::BoolLit(s) => self.visit_bool_lit(buf, s),
+            Expr::NumLit(s) => self.visit_num_lit(buf, s),
+            Expr::StrLit(s) => self.visit_str_lit(buf, s),
+            Expr::CharLit(s) => self.visit_char_lit(buf, s),
+            Expr::Var(s) => self.visit_var(ctx, buf, s, expr)?,
+            Expr::Path(ref path) => match **path {
+                // Names starting with an uppercase letter are parsed as paths.
+                [name]
+                    if self.locals.resolve(name).is_none() && self.const_path(name).is_some() =>
+                {
+                    buf.write(self.const_path(name).unwrap());
+                    DisplayWrap::Unwrapped
+                }
+                _ => self.visit_path(buf, path),
+            },
+            Expr::Array(ref elements) => self.visit_array(ctx, buf, elements)?,
+            Expr::Map(ref entries) => self.visit_map(ctx, buf, entries)?,
+            Expr::Attr(ref obj, name) => self.visit_attr(ctx, buf, obj, name)?,
+            Expr::Index(ref obj, ref key) => self.visit_index(ctx, buf, obj, key)?,
+            Expr::Filter(Filter {
+                name,
+                ref arguments,
+            }) => self.visit_filter(ctx, buf, name, arguments, expr)?,
+            Expr::Unary(op, ref inner) => self.visit_unary(ctx, buf, op, inner)?,
+            Expr::BinOp(op, ref left, ref right) => self.visit_binop(ctx, buf, op, left, right)?,
+            Expr::Range(op, ref left, ref right) => {
+                self.visit_range(ctx, buf, op, left.as_deref(), right.as_deref())?
+            }
+            Expr::Group(ref inner) => self.visit_group(ctx, buf, inner)?,
+            Expr::Call(ref obj, ref args) => self.visit_call(ctx, buf, obj, args)?,
+            Expr::RustMacro(ref path, args) => self.visit_rust_macro(buf, path, args),
+            Expr::Try(ref expr) => self.visit_try(ctx, buf, expr)?,
+            Expr::Turbofish(ref expr, generics) => {
+                self.visit_turbofish(ctx, buf, expr, generics)?
+            }
+            Expr::AssociatedItem(ref expr, name) => {
+                self.visit_associated_item(ctx, buf, expr, name)?
+            }
+            Expr::Matches(ref expr, ref pattern, ref guard) => {
+                self.visit_matches(ctx, buf, expr, pattern, guard.as_deref())?
+            }
+            Expr::Tuple(ref exprs) => self.visit_tuple(ctx, buf, exprs)?,
+            Expr::NamedArgument(_, ref expr) => self.visit_named_argument(ctx, buf, expr)?,
+            Expr::Cfg(predicate) => self.visit_cfg(ctx, buf, predicate, expr)?,
+            Expr::Closure(ref params, ref body) => self.visit_closure(ctx, buf, params, body)?,
+            Expr::IfElse(ref cond, ref then, ref otherwise) => {
+                self.visit_if_else(ctx, buf, cond, then, otherwise)?
+            }
+            Expr::FilterSource => self.visit_filter_source(buf),
+        })
+    }
+
+    fn visit_if_else(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        cond: &WithSpan<'_, Expr<'_>>,
+        then: &WithSpan<'_, Expr<'_>>,
+        otherwise: &WithSpan<'_, Expr<'_>>,
+    ) -> Result<DisplayWrap, CompileError> {
+        // Same coercion to `bool` as in `{% if %}`.
+        buf.write("(if *(&(");
+        self.visit_expr(ctx, buf, cond)?;
+        buf.write(") as &bool) { ");
+        let then = self.visit_expr(ctx, buf, then)?;
+        buf.write(" } else { ");
+        let otherwise = self.visit_expr(ctx, buf, otherwise)?;
+        buf.write(" })");
+        Ok(match (then, otherwise) {
+            (DisplayWrap::Wrapped, DisplayWrap::Wrapped) => DisplayWrap::Wrapped,
+            _ => DisplayWrap::Unwrapped,
+        })
+    }
+
+    fn visit_try(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        expr: &WithSpan<'_, Expr<'_>>,
+    ) -> Result<DisplayWrap, CompileError> {
+        if is_get_value_call(expr) {
+            // The error already is a `rinja::Error`, which is passed on as is.
+            self.visit_expr(ctx, buf, expr)?;
+            buf.write("?");
+            return Ok(DisplayWrap::Unwrapped);
+        }
+        buf.write("::core::result::Result::map_err(");
+        self.visit_expr(ctx, buf, expr)?;
+        buf.write(", |err| ");
+        self.write_custom_error(buf);
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn visit_turbofish(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        expr: &WithSpan<'_, Expr<'_>>,
+        generics: &str,
+    ) -> Result<DisplayWrap, CompileError> {
+        self.visit_expr(ctx, buf, expr)?;
+        buf.write(format_args!("::<{generics}>"));
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn visit_closure(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        params: &[Target<'_>],
+        body: &WithSpan<'_, Expr<'_>>,
+    ) -> Result<DisplayWrap, CompileError> {
+        // The parameters shadow the fields of the template in the body.
+        self.locals.push();
+        buf.write("|");
+        for (i, param) in params.iter().enumerate() {
+            if i > 0 {
+                buf.write(", ");
+            }
+            self.visit_target(buf, true, false, param);
+        }
+        buf.write("| ");
+        self.visit_expr(ctx, buf, body)?;
+        self.locals.pop();
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn visit_matches(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         expr: &WithSpan<'_, Expr<'_>>,
+        pattern: &Target<'_>,
+        guard: Option<&WithSpan<'_, Expr<'_>>>,
     ) -> Result<DisplayWrap, CompileError> {
-        Ok(match **expr {
-            Expr::BoolLit(s) => self.visit_bool_lit(buf, s),
-            Expr::NumLit(s) => self.visit_num_lit(buf, s),
-            Expr::StrLit(s) => self.visit_str_lit(buf, s),
-            Expr::CharLit(s) => self.visit_char_lit(buf, s),
-            Expr::Var(s) => self.visit_var(buf, s),
-            Expr::Path(ref path) => self.visit_path(buf, path),
-            Expr::Array(ref elements) => self.visit_array(ctx, buf, elements)?,
-            Expr::Attr(ref obj, name) => self.visit_attr(ctx, buf, obj, name)?,
-            Expr::Index(ref obj, ref key) => self.visit_index(ctx, buf, obj, key)?,
-            Expr::Filter(Filter {
-                name,
-                ref arguments,
-            }) => self.visit_filter(ctx, buf, name, arguments, expr)?,
-            Expr::Unary(op, ref inner) => self.visit_unary(ctx, buf, op, inner)?,
-            Expr::BinOp(op, ref left, ref right) => self.visit_binop(ctx, buf, op, left, right)?,
-            Expr::Range(op, ref left, ref right) => {
-                self.visit_range(ctx, buf, op, left.as_deref(), right.as_deref())?
-            }
-            Expr::Group(ref inner) => self.visit_group(ctx, buf, inner)?,
-            Expr::Call(ref obj, ref args) => self.visit_call(ctx, buf, obj, args)?,
-            Expr::RustMacro(ref path, args) => self.visit_rust_macro(buf, path, args),
-            Expr::Try(ref expr) => self.visit_try(ctx, buf, expr)?,
-            Expr::Tuple(ref exprs) => self.visit_tuple(ctx, buf, exprs)?,
-            Expr::NamedArgument(_, ref expr) => self.visit_named_argument(ctx, buf, expr)?,
-            Expr::FilterSource => self.visit_filter_source(buf),
-        })
+        // Unlike the expression of a `{% match %}` block, the value is not borrowed, so the
+        // pattern and the guard work like in the Rust macro.
+        buf.write("::core::matches!(");
+        self.visit_expr(ctx, buf, expr)?;
+        buf.write(", ");
+        // The names bound by the pattern are only visible in the guard.
+        self.locals.push();
+        self.visit_target(buf, true, false, pattern);
+        if let Some(guard) = guard {
+            buf.write(" if ");
+            self.visit_expr(ctx, buf, guard)?;
+        }
+        self.locals.pop();
+        buf.write(")");
+        Ok(DisplayWrap::Unwrapped)
     }
 
-    fn visit_try(
+    fn visit_associated_item(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         expr: &WithSpan<'_, Expr<'_>>,
+        name: &str,
     ) -> Result<DisplayWrap, CompileError> {
-        buf.write("::core::result::Result::map_err(");
         self.visit_expr(ctx, buf, expr)?;
-        buf.write(", |err| ");
-        buf.write(CRATE);
-        buf.write("::shared::Error::Custom(::core::convert::Into::into(err)))?");
+        buf.write(format_args!("::{name}"));
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // Writes the conversion of an error `err` into `rinja::Error::Custom`.
+    fn write_custom_error(&self, buf: &mut Buffer) {
+        buf.write(format_args!(
+            "{{\
+                use {CRATE}::helpers::IntoCustomError as _;\
+                (&&{CRATE}::helpers::CustomErr::new("
+        ));
+        if let Some(error) = self.input.error {
+            // Keep the template's own error type, so `Error::into_custom()` can get it back.
+            buf.write(format_args!("<{error} as ::core::convert::From<_>>::from(err)"));
+        } else {
+            buf.write("err");
+        }
+        buf.write(")).rinja_into_error()}");
+    }
+
+    fn visit_cfg(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        predicate: &str,
+        node: &WithSpan<'_, Expr<'_>>,
+    ) -> Result<DisplayWrap, CompileError> {
+        if syn::parse_str::<syn::Meta>(predicate).is_err() {
+            return Err(
+                ctx.generate_error(&format!("invalid `cfg` predicate: {predicate:?}"), node)
+            );
+        }
+        buf.write(format_args!("::core::cfg!({predicate})"));
         Ok(DisplayWrap::Unwrapped)
     }
 
@@ -1287,40 +3023,81 @@ impl<'a> Generator<'a> {
 
     fn visit_filter<T>(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         name: &str,
         args: &[WithSpan<'_, Expr<'_>>],
         filter: &WithSpan<'_, T>,
     ) -> Result<DisplayWrap, CompileError> {
         match name {
+            "currency" => return self._visit_currency_filter(ctx, buf, args, filter),
+            "date" => return self._visit_date_filter(ctx, buf, args, filter),
+            "default" => return self._visit_default_filter(ctx, buf, args, filter),
             "deref" => return self._visit_deref_filter(ctx, buf, args, filter),
-            "escape" | "e" => return self._visit_escape_filter(ctx, buf, args, filter),
+            "escape" | "e" => return self._visit_escape_filter(ctx, buf, args, filter, false),
             "fmt" => return self._visit_fmt_filter(ctx, buf, args, filter),
+            "forceescape" => return self._visit_escape_filter(ctx, buf, args, filter, true),
             "format" => return self._visit_format_filter(ctx, buf, args, filter),
             "join" => return self._visit_join_filter(ctx, buf, args),
             "json" | "tojson" => return self._visit_json_filter(ctx, buf, args, filter),
+            "markdown" => return self._visit_markdown_filter(ctx, buf, args, filter),
             "linebreaks" | "linebreaksbr" | "paragraphbreaks" => {
                 return self._visit_linebreaks_filter(ctx, buf, name, args, filter);
             }
             "ref" => return self._visit_ref_filter(ctx, buf, args, filter),
+            "reltime" => return self._visit_reltime_filter(ctx, buf, args, filter),
             "safe" => return self._visit_safe_filter(ctx, buf, args, filter),
+            "attr" | "group_by" | "map" | "sort_by" => {
+                return self._visit_attr_key_filter(ctx, buf, name, args, filter);
+            }
             _ => {}
         }
 
-        if crate::BUILT_IN_FILTERS.contains(&name) {
+        if let Some(path) = self.input.config.filter_macros.get(name) {
+            // The macro gets the arguments as expressions, and expands to the filtered value.
+            buf.write(format_args!("{path}!("));
+            self._visit_args(ctx, buf, args)?;
+            buf.write(")");
+            return Ok(DisplayWrap::Unwrapped);
+        }
+
+        if matches!(name, "batch" | "slice" | "ljust" | "rjust") && args.len() == 3 {
+            // the variant with a fill value has a different signature
+            buf.write(format_args!("{CRATE}::filters::{name}_fill("));
+        } else if crate::BUILT_IN_FILTERS.contains(&name) {
             buf.write(format_args!("{CRATE}::filters::{name}("));
+        } else if self.input.error.is_some() {
+            // Errors of custom filters are converted into the template's error type, too.
+            buf.write(format_args!(
+                "::core::result::Result::map_err(filters::{name}("
+            ));
+            self._visit_args(ctx, buf, args)?;
+            buf.write("), |err| ");
+            self.write_custom_error(buf);
+            buf.write(")?");
+            return Ok(DisplayWrap::Unwrapped);
         } else {
             buf.write(format_args!("filters::{name}("));
         }
-        self._visit_args(ctx, buf, args)?;
+        let wrap = self._visit_args(ctx, buf, args)?;
         buf.write(")?");
-        Ok(DisplayWrap::Unwrapped)
+        // Changing the case of already escaped text, or trimming or indenting it, keeps it
+        // escaped, e.g. the output of a macro call. The content of a filter block is not escaped
+        // as a whole, though: its static text is written as is.
+        match name {
+            "capfirst" | "capitalize" | "center" | "indent" | "lower" | "lowercase" | "title"
+            | "trim" | "upper" | "uppercase"
+                if !matches!(args.first().map(|arg| &**arg), Some(Expr::FilterSource)) =>
+            {
+                Ok(wrap)
+            }
+            _ => Ok(DisplayWrap::Unwrapped),
+        }
     }
 
     fn _visit_linebreaks_filter<T>(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         name: &str,
         args: &[WithSpan<'_, Expr<'_>>],
@@ -1345,7 +3122,7 @@ impl<'a> Generator<'a> {
 
     fn _visit_ref_filter<T>(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         args: &[WithSpan<'_, Expr<'_>>],
         node: &WithSpan<'_, T>,
@@ -1359,9 +3136,37 @@ impl<'a> Generator<'a> {
         Ok(DisplayWrap::Unwrapped)
     }
 
+    fn _visit_default_filter<T>(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'_>>],
+        node: &WithSpan<'_, T>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let (input, rest) = match args {
+            [input, rest @ ..] if matches!(rest.len(), 1 | 2) => (input, rest),
+            _ => {
+                return Err(ctx.generate_error(
+                    "the `default` filter expects a fallback value \
+                     and an optional boolean argument",
+                    node,
+                ));
+            }
+        };
+        buf.write(format_args!("{CRATE}::filters::default(&("));
+        self.visit_expr(ctx, buf, input)?;
+        buf.write("), ");
+        self._visit_args(ctx, buf, rest)?;
+        if rest.len() == 1 {
+            buf.write(", false");
+        }
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
     fn _visit_deref_filter<T>(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         args: &[WithSpan<'_, Expr<'_>>],
         node: &WithSpan<'_, T>,
@@ -1375,9 +3180,240 @@ impl<'a> Generator<'a> {
         Ok(DisplayWrap::Unwrapped)
     }
 
+    fn _visit_currency_filter<T>(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'_>>],
+        node: &WithSpan<'_, T>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let filter = match args.len() {
+            2 => "currency",
+            3 => "currency_precision",
+            _ => {
+                return Err(ctx.generate_error(
+                    "the `currency` filter expects the currency code, and optionally the \
+                     precision, as arguments",
+                    node,
+                ));
+            }
+        };
+
+        // The locale is read from the runtime values.
+        buf.write(format_args!("{CRATE}::filters::{filter}("));
+        self._visit_args(ctx, buf, args)?;
+        buf.write(format_args!(", {VALUES})?"));
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_date_filter<T>(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'_>>],
+        node: &WithSpan<'_, T>,
+    ) -> Result<DisplayWrap, CompileError> {
+        if cfg!(not(any(feature = "chrono", feature = "time"))) {
+            return Err(ctx.generate_error(
+                "the `date` filter requires the `chrono` or `time` feature to be enabled",
+                node,
+            ));
+        }
+        if args.len() != 2 {
+            return Err(ctx.generate_error(
+                "the `date` filter expects exactly one argument: the format string",
+                node,
+            ));
+        }
+
+        buf.write(format_args!("{CRATE}::filters::date("));
+        self._visit_args(ctx, buf, args)?;
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_reltime_filter<T>(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'_>>],
+        node: &WithSpan<'_, T>,
+    ) -> Result<DisplayWrap, CompileError> {
+        if cfg!(not(feature = "reltime")) {
+            return Err(ctx.generate_error(
+                "the `reltime` filter requires the `reltime` feature to be enabled",
+                node,
+            ));
+        }
+
+        let filter = match args.len() {
+            1 => "reltime",
+            2 => "reltime_from",
+            _ => return Err(ctx.generate_error("unexpected argument(s) in `reltime` filter", node)),
+        };
+
+        buf.write(format_args!("{CRATE}::filters::{filter}("));
+        self._visit_args(ctx, buf, args)?;
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_markdown_filter<T>(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        args: &[WithSpan<'_, Expr<'_>>],
+        node: &WithSpan<'_, T>,
+    ) -> Result<DisplayWrap, CompileError> {
+        if cfg!(not(feature = "markdown")) {
+            return Err(ctx.generate_error(
+                "the `markdown` filter requires the `markdown` feature to be enabled",
+                node,
+            ));
+        }
+        if args.len() != 1 {
+            return Err(ctx.generate_error("unexpected argument(s) in `markdown` filter", node));
+        }
+
+        buf.write(format_args!("{CRATE}::filters::markdown("));
+        self._visit_args(ctx, buf, args)?;
+        buf.write(")?");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    fn _visit_attr_key_filter<T>(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        name: &str,
+        args: &[WithSpan<'_, Expr<'_>>],
+        node: &WithSpan<'_, T>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let (input, attr) = match args {
+            [input, attr] => match **attr {
+                Expr::StrLit(attr) if !attr.is_empty() => (input, attr),
+                Expr::Closure(..) if name != "attr" => {
+                    return self._visit_key_closure_filter(ctx, buf, name, input, attr);
+                }
+                _ => {
+                    let expected = match name {
+                        "attr" => "an attribute name as string literal",
+                        _ => "an attribute name as string literal, or a closure",
+                    };
+                    return Err(ctx.generate_error(
+                        &format!("the argument of the `{name}` filter must be {expected}"),
+                        attr,
+                    ));
+                }
+            },
+            _ => {
+                return Err(ctx.generate_error(
+                    &format!("the `{name}` filter expects exactly one argument: an attribute name"),
+                    node,
+                ));
+            }
+        };
+
+        // `"a.b"` becomes `.a.b`, which is appended to the input or the closure arguments
+        let mut path = String::new();
+        for part in attr.split('.') {
+            let part = normalize_identifier(part);
+            let is_index = !part.is_empty()
+                && part.bytes().all(|c| c.is_ascii_digit())
+                && (part == "0" || !part.starts_with('0'));
+            if !is_index && syn::parse_str::<syn::Ident>(part).is_err() {
+                return Err(ctx.generate_error(
+                    &format!(
+                        "the argument of the `{name}` filter must be a dot-separated path of \
+                         field names or tuple indices, found {attr:?}"
+                    ),
+                    &args[1],
+                ));
+            }
+            path.push('.');
+            path.push_str(part);
+        }
+        if name == "attr" {
+            buf.write("(");
+            self.visit_expr(ctx, buf, input)?;
+            buf.write(format_args!("){path}"));
+            return Ok(DisplayWrap::Unwrapped);
+        }
+
+        buf.write(format_args!("{CRATE}::filters::{name}("));
+        self._visit_args(ctx, buf, std::slice::from_ref(input))?;
+        match name {
+            "sort_by" => buf.write(format_args!(
+                ", |__rinja_l, __rinja_r| \
+                 ::core::cmp::Ord::cmp(&__rinja_l{path}, &__rinja_r{path}))?"
+            )),
+            "group_by" => buf.write(format_args!(
+                ", |__rinja_v| ::core::clone::Clone::clone(&__rinja_v{path}))?"
+            )),
+            _ => buf.write(format_args!(", |__rinja_v| &__rinja_v{path})?")),
+        }
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // `map`, `group_by` and `sort_by` with a closure, e.g. `users|map(|u| u.email)`
+    fn _visit_key_closure_filter(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        name: &str,
+        input: &WithSpan<'_, Expr<'_>>,
+        closure: &WithSpan<'_, Expr<'_>>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let Expr::Closure(params, body) = &**closure else {
+            unreachable!();
+        };
+        let [param] = &params[..] else {
+            return Err(ctx.generate_error(
+                &format!("the closure of the `{name}` filter must take exactly one argument"),
+                closure,
+            ));
+        };
+
+        buf.write(format_args!("{CRATE}::filters::{name}("));
+        self._visit_args(ctx, buf, std::slice::from_ref(input))?;
+        if name == "sort_by" {
+            buf.write(", |__rinja_l, __rinja_r| ::core::cmp::Ord::cmp(&");
+            self._visit_closure_key(ctx, buf, param, body, "__rinja_l")?;
+            buf.write(", &");
+            self._visit_closure_key(ctx, buf, param, body, "__rinja_r")?;
+            buf.write("))?");
+        } else {
+            buf.write(", |__rinja_v| ");
+            self._visit_closure_key(ctx, buf, param, body, "__rinja_v")?;
+            buf.write(")?");
+        }
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // The closure is inlined, so the type of its argument is inferred from the filter. The items
+    // are borrowed, so the value of the closure is cloned, like the key of `group_by("attr")`.
+    fn _visit_closure_key(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        param: &Target<'_>,
+        body: &WithSpan<'_, Expr<'_>>,
+        value: &str,
+    ) -> Result<(), CompileError> {
+        self.locals.push();
+        buf.write("{ let ");
+        self.visit_target(buf, true, false, param);
+        buf.write(format_args!(" = {value}; ::core::clone::Clone::clone(&("));
+        let result = self.visit_expr(ctx, buf, body);
+        self.locals.pop();
+        result?;
+        buf.write(")) }");
+        Ok(())
+    }
+
     fn _visit_json_filter<T>(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         args: &[WithSpan<'_, Expr<'_>>],
         node: &WithSpan<'_, T>,
@@ -1398,12 +3434,18 @@ impl<'a> Generator<'a> {
         buf.write(format_args!("{CRATE}::filters::{filter}("));
         self._visit_args(ctx, buf, args)?;
         buf.write(")?");
-        Ok(DisplayWrap::Unwrapped)
+
+        // In JSON templates, the output is a JSON value that must not be escaped again
+        if self.escaper == format!("{CRATE}::filters::Json") {
+            Ok(DisplayWrap::Wrapped)
+        } else {
+            Ok(DisplayWrap::Unwrapped)
+        }
     }
 
     fn _visit_safe_filter<T>(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         args: &[WithSpan<'_, Expr<'_>>],
         node: &WithSpan<'_, T>,
@@ -1413,48 +3455,50 @@ impl<'a> Generator<'a> {
         }
         buf.write(format_args!("{CRATE}::filters::safe("));
         self._visit_args(ctx, buf, args)?;
-        buf.write(format_args!(", {})?", self.input.escaper));
+        buf.write(format_args!(", {})?", self.escaper));
         Ok(DisplayWrap::Wrapped)
     }
 
+    // `forceescape` escapes even if the template does not, i.e. if its escaper is the one of
+    // `escape = "none"`, by using the HTML escaper instead.
     fn _visit_escape_filter<T>(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         args: &[WithSpan<'_, Expr<'_>>],
         node: &WithSpan<'_, T>,
+        force: bool,
     ) -> Result<DisplayWrap, CompileError> {
+        let filter = if force { "forceescape" } else { "escape" };
         if args.len() > 2 {
-            return Err(ctx.generate_error("only two arguments allowed to escape filter", node));
+            return Err(ctx.generate_error(
+                &format!("only two arguments allowed to {filter} filter"),
+                node,
+            ));
         }
         let opt_escaper = match args.get(1).map(|expr| &**expr) {
             Some(Expr::StrLit(name)) => Some(*name),
             Some(_) => {
-                return Err(ctx.generate_error("invalid escaper type for escape filter", node));
+                return Err(
+                    ctx.generate_error(&format!("invalid escaper type for {filter} filter"), node)
+                );
             }
             None => None,
         };
-        let escaper = match opt_escaper {
-            Some(name) => self
-                .input
-                .config
-                .escapers
-                .iter()
-                .find_map(|(extensions, path)| {
-                    extensions
-                        .contains(&Cow::Borrowed(name))
-                        .then_some(path.as_ref())
-                })
-                .ok_or_else(|| {
-                    ctx.generate_error(
-                        &format!(
-                            "invalid escaper '{name}' for `escape` filter. {}",
-                            MsgValidEscapers(&self.input.config.escapers),
-                        ),
-                        node,
-                    )
-                })?,
-            None => self.input.escaper,
+        let escaper: Cow<'_, str> = match opt_escaper {
+            Some(name) => self.find_escaper(name).map(Cow::Borrowed).ok_or_else(|| {
+                ctx.generate_error(
+                    &format!(
+                        "invalid escaper '{name}' for `{filter}` filter. {}",
+                        MsgValidEscapers(&self.input.config.escapers),
+                    ),
+                    node,
+                )
+            })?,
+            None if force && self.find_escaper("none") == Some(self.escaper) => {
+                format!("{CRATE}::filters::Html").into()
+            }
+            None => self.escaper.into(),
         };
         buf.write(format_args!("{CRATE}::filters::escape("));
         self._visit_args(ctx, buf, &args[..1])?;
@@ -1462,16 +3506,28 @@ impl<'a> Generator<'a> {
         Ok(DisplayWrap::Wrapped)
     }
 
+    fn find_escaper(&self, name: &str) -> Option<&'a str> {
+        self.input
+            .config
+            .escapers
+            .iter()
+            .find_map(|(extensions, path)| {
+                extensions
+                    .contains(&Cow::Borrowed(name))
+                    .then_some(path.as_ref())
+            })
+    }
+
     fn _visit_format_filter<T>(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         args: &[WithSpan<'_, Expr<'_>>],
         node: &WithSpan<'_, T>,
     ) -> Result<DisplayWrap, CompileError> {
         if !args.is_empty() {
             if let Expr::StrLit(fmt) = *args[0] {
-                buf.write("::std::format!(");
+                buf.write(format_args!("{CRATE}::helpers::alloc::format!("));
                 self.visit_str_lit(buf, fmt);
                 if args.len() > 1 {
                     buf.write(", ");
@@ -1486,14 +3542,14 @@ impl<'a> Generator<'a> {
 
     fn _visit_fmt_filter<T>(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         args: &[WithSpan<'_, Expr<'_>>],
         node: &WithSpan<'_, T>,
     ) -> Result<DisplayWrap, CompileError> {
         if let [_, arg2] = args {
             if let Expr::StrLit(fmt) = **arg2 {
-                buf.write("::std::format!(");
+                buf.write(format_args!("{CRATE}::helpers::alloc::format!("));
                 self.visit_str_lit(buf, fmt);
                 buf.write(", ");
                 self._visit_args(ctx, buf, &args[..1])?;
@@ -1507,7 +3563,7 @@ impl<'a> Generator<'a> {
     // Force type coercion on first argument to `join` filter (see #39).
     fn _visit_join_filter(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         args: &[WithSpan<'_, Expr<'_>>],
     ) -> Result<DisplayWrap, CompileError> {
@@ -1526,16 +3582,14 @@ impl<'a> Generator<'a> {
         Ok(DisplayWrap::Unwrapped)
     }
 
+    // Returns whether the first argument is already escaped.
     fn _visit_args(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         args: &[WithSpan<'_, Expr<'_>>],
-    ) -> Result<(), CompileError> {
-        if args.is_empty() {
-            return Ok(());
-        }
-
+    ) -> Result<DisplayWrap, CompileError> {
+        let mut first_wrap = DisplayWrap::Unwrapped;
         for (i, arg) in args.iter().enumerate() {
             if i > 0 {
                 buf.write(", ");
@@ -1546,27 +3600,29 @@ impl<'a> Generator<'a> {
                 buf.write("&(");
             }
 
-            match **arg {
+            let wrap = match **arg {
                 Expr::Call(ref left, _) if !matches!(***left, Expr::Path(_)) => {
                     buf.writeln("{");
-                    self.visit_expr(ctx, buf, arg)?;
+                    let wrap = self.visit_expr(ctx, buf, arg)?;
                     buf.writeln("}");
+                    wrap
                 }
-                _ => {
-                    self.visit_expr(ctx, buf, arg)?;
-                }
+                _ => self.visit_expr(ctx, buf, arg)?,
+            };
+            if i == 0 {
+                first_wrap = wrap;
             }
 
             if borrow {
                 buf.write(")");
             }
         }
-        Ok(())
+        Ok(first_wrap)
     }
 
     fn visit_attr(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         obj: &WithSpan<'_, Expr<'_>>,
         attr: &str,
@@ -1590,6 +3646,10 @@ impl<'a> Generator<'a> {
                 }
             }
         }
+        if matches!(**obj, Expr::Var("self")) && self.is_borrowed_field(attr) {
+            buf.write(format_args!("(*self.{})", normalize_identifier(attr)));
+            return Ok(DisplayWrap::Unwrapped);
+        }
         self.visit_expr(ctx, buf, obj)?;
         buf.write(format_args!(".{}", normalize_identifier(attr)));
         Ok(DisplayWrap::Unwrapped)
@@ -1597,12 +3657,13 @@ impl<'a> Generator<'a> {
 
     fn visit_index(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         obj: &WithSpan<'_, Expr<'_>>,
         key: &WithSpan<'_, Expr<'_>>,
     ) -> Result<DisplayWrap, CompileError> {
-        buf.write("&");
+        // Like a field, the element is not borrowed, so it can be compared and used in arithmetic
+        // directly. It gets borrowed where it would be moved otherwise, e.g. as an argument.
         self.visit_expr(ctx, buf, obj)?;
         buf.write("[");
         self.visit_expr(ctx, buf, key)?;
@@ -1612,7 +3673,7 @@ impl<'a> Generator<'a> {
 
     fn visit_call(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         left: &WithSpan<'_, Expr<'_>>,
         args: &[WithSpan<'_, Expr<'_>>],
@@ -1620,6 +3681,11 @@ impl<'a> Generator<'a> {
         match &**left {
             Expr::Attr(sub_left, method) if ***sub_left == Expr::Var("loop") => match *method {
                 "cycle" => match args {
+                    [] => {
+                        return Err(
+                            ctx.generate_error("loop.cycle(…) expects at least one argument", left)
+                        );
+                    }
                     [arg] => {
                         if matches!(**arg, Expr::Array(ref arr) if arr.is_empty()) {
                             return Err(
@@ -1639,19 +3705,50 @@ impl<'a> Generator<'a> {
                         buf.writeln("_cycle[_loop_item.index % _len]");
                         buf.writeln("})");
                     }
-                    _ => {
-                        return Err(
-                            ctx.generate_error("loop.cycle(…) cannot use an empty array", left)
-                        );
+                    // `loop.cycle(a, b, …)` is the same as `loop.cycle([a, b, …])`
+                    args => {
+                        buf.write("({");
+                        buf.write("let _cycle = [");
+                        for (i, arg) in args.iter().enumerate() {
+                            if i > 0 {
+                                buf.write(", ");
+                            }
+                            buf.write("&(");
+                            self.visit_expr(ctx, buf, arg)?;
+                            buf.write(")");
+                        }
+                        buf.writeln("];");
+                        buf.writeln(format_args!("_cycle[_loop_item.index % {}]", args.len()));
+                        buf.writeln("})");
                     }
                 },
                 s => return Err(ctx.generate_error(&format!("unknown loop method: {s:?}"), left)),
             },
+            Expr::Var(name)
+                if self.locals.resolve(name).is_none()
+                    && (ctx.macros.contains_key(name) || ctx.macro_imports.contains_key(name)) =>
+            {
+                return self.visit_macro_call(ctx, buf, None, name, args, left);
+            }
+            Expr::Path(path) if path.len() == 2 && ctx.imports.contains_key(path[0]) => {
+                return self.visit_macro_call(ctx, buf, Some(path[0]), path[1], args, left);
+            }
+            Expr::Path(ref path) if is_get_value_path(path) => {
+                return self.visit_get_value(ctx, buf, None, args, left);
+            }
+            Expr::Turbofish(ref path, generics) if matches!(***path, Expr::Path(ref path) if is_get_value_path(path)) =>
+            {
+                return self.visit_get_value(ctx, buf, Some(generics), args, left);
+            }
             sub_left => {
                 match sub_left {
                     Expr::Var(name) => match self.locals.resolve(name) {
                         Some(resolved) => buf.write(resolved),
-                        None => buf.write(format_args!("self.{}", normalize_identifier(name))),
+                        // Functions configured in `[globals]` are called instead of methods.
+                        None => match self.input.config.globals.get(name) {
+                            Some(path) => buf.write(*path),
+                            None => buf.write(format_args!("self.{}", normalize_identifier(name))),
+                        },
                     },
                     _ => {
                         self.visit_expr(ctx, buf, left)?;
@@ -1665,9 +3762,84 @@ impl<'a> Generator<'a> {
         Ok(DisplayWrap::Unwrapped)
     }
 
+    // `rinja::get_value::<T>(key)` reads a value that was passed to `render_with_values()`.
+    fn visit_get_value(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        generics: Option<&str>,
+        args: &[WithSpan<'_, Expr<'_>>],
+        node: &WithSpan<'_, Expr<'_>>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let [key] = args else {
+            return Err(ctx.generate_error(
+                "`rinja::get_value()` expects exactly one argument, the key of the value",
+                node,
+            ));
+        };
+        buf.write(format_args!("{CRATE}::get_value"));
+        if let Some(generics) = generics {
+            buf.write(format_args!("::<{generics}>"));
+        }
+        buf.write(format_args!("({VALUES}, &("));
+        self.visit_expr(ctx, buf, key)?;
+        buf.write("))");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // Renders the macro into a temporary buffer, so it can be used like any other expression.
+    fn visit_macro_call<T>(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        scope: Option<&str>,
+        name: &str,
+        args: &[WithSpan<'_, Expr<'_>>],
+        node: &WithSpan<'_, T>,
+    ) -> Result<DisplayWrap, CompileError> {
+        let (def, own_ctx) = self.find_macro(ctx, scope, name, node)?;
+
+        // The expression is generated while its surrounding output is being written,
+        // so the pending output and whitespace state must not leak into the macro body.
+        let buf_writable = mem::take(&mut self.buf_writable.buf);
+        let next_ws = self.next_ws.take();
+        let skip_ws = mem::replace(&mut self.skip_ws, WhitespaceHandling::Preserve);
+        let trim_newline = mem::take(&mut self.trim_newline);
+        let at_line_start = self.at_line_start;
+
+        let mut macro_buf = Buffer::new();
+        macro_buf.writeln("{");
+        macro_buf.writeln(format_args!(
+            "let mut {MACRO_OUTPUT} = {CRATE}::helpers::alloc::string::String::new();"
+        ));
+        macro_buf.writeln("{");
+        macro_buf.writeln(format_args!("let writer = &mut {MACRO_OUTPUT};"));
+        self.locals.push();
+        self.in_local_output += 1;
+        self.prepare_ws(def.ws1);
+        self.write_macro_args(ctx, &mut macro_buf, name, def, args, node)?;
+        self.handle(own_ctx, &def.nodes, &mut macro_buf, AstLevel::Nested)?;
+        self.flush_ws(def.ws2);
+        self.write_buf_writable(ctx, &mut macro_buf)?;
+        self.in_local_output -= 1;
+        self.locals.pop();
+        macro_buf.writeln("}");
+        macro_buf.writeln(format_args!("{CRATE}::filters::Safe({MACRO_OUTPUT})"));
+        macro_buf.write("}");
+
+        self.buf_writable.buf = buf_writable;
+        self.next_ws = next_ws;
+        self.skip_ws = skip_ws;
+        self.trim_newline = trim_newline;
+        self.at_line_start = at_line_start;
+
+        buf.write(macro_buf.buf);
+        Ok(DisplayWrap::Wrapped)
+    }
+
     fn visit_unary(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         op: &str,
         inner: &WithSpan<'_, Expr<'_>>,
@@ -1679,30 +3851,80 @@ impl<'a> Generator<'a> {
 
     fn visit_range(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         op: &str,
         left: Option<&WithSpan<'_, Expr<'_>>>,
         right: Option<&WithSpan<'_, Expr<'_>>>,
     ) -> Result<DisplayWrap, CompileError> {
         if let Some(left) = left {
-            self.visit_expr(ctx, buf, left)?;
+            self.visit_range_bound(ctx, buf, left)?;
         }
         buf.write(op);
         if let Some(right) = right {
-            self.visit_expr(ctx, buf, right)?;
+            self.visit_range_bound(ctx, buf, right)?;
         }
         Ok(DisplayWrap::Unwrapped)
     }
 
+    fn visit_range_bound(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        bound: &WithSpan<'_, Expr<'_>>,
+    ) -> Result<(), CompileError> {
+        if is_copyable(bound) {
+            self.visit_expr(ctx, buf, bound)?;
+        } else {
+            // Variables are often references, e.g. the item of a `for` loop over a `Vec<u32>`,
+            // but both bounds must have the same type. The method call dereferences them.
+            buf.write("(");
+            self.visit_expr(ctx, buf, bound)?;
+            buf.write(").clone()");
+        }
+        Ok(())
+    }
+
     fn visit_binop(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         op: &str,
         left: &WithSpan<'_, Expr<'_>>,
         right: &WithSpan<'_, Expr<'_>>,
     ) -> Result<DisplayWrap, CompileError> {
+        if let "in" | "not in" = op {
+            if op == "not in" {
+                buf.write("!");
+            }
+            buf.write(format_args!("(&&{CRATE}::helpers::Contains(&("));
+            self.visit_expr(ctx, buf, right)?;
+            buf.write("), &(");
+            self.visit_expr(ctx, buf, left)?;
+            buf.write("))).rinja_contains()");
+            return Ok(DisplayWrap::Unwrapped);
+        }
+        if op == "**" {
+            buf.write(format_args!("{CRATE}::helpers::Pow::rinja_pow("));
+            // Like in Rust, the type of a number literal without a suffix is only inferred,
+            // but the implementation of `Pow` can only be found if the type of the base is known.
+            match &**left {
+                Expr::NumLit(s) => write_typed_num_lit(buf, s),
+                Expr::Unary("-", inner) if matches!(***inner, Expr::NumLit(_)) => {
+                    buf.write("-");
+                    if let Expr::NumLit(s) = ***inner {
+                        write_typed_num_lit(buf, s);
+                    }
+                }
+                _ => {
+                    self.visit_expr(ctx, buf, left)?;
+                }
+            }
+            buf.write(", ");
+            self.visit_expr(ctx, buf, right)?;
+            buf.write(")?");
+            return Ok(DisplayWrap::Unwrapped);
+        }
         self.visit_expr(ctx, buf, left)?;
         buf.write(format_args!(" {op} "));
         self.visit_expr(ctx, buf, right)?;
@@ -1711,7 +3933,7 @@ impl<'a> Generator<'a> {
 
     fn visit_group(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         inner: &WithSpan<'_, Expr<'_>>,
     ) -> Result<DisplayWrap, CompileError> {
@@ -1723,7 +3945,7 @@ impl<'a> Generator<'a> {
 
     fn visit_tuple(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         exprs: &[WithSpan<'_, Expr<'_>>],
     ) -> Result<DisplayWrap, CompileError> {
@@ -1741,7 +3963,7 @@ impl<'a> Generator<'a> {
 
     fn visit_named_argument(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         expr: &WithSpan<'_, Expr<'_>>,
     ) -> Result<DisplayWrap, CompileError> {
@@ -1751,7 +3973,7 @@ impl<'a> Generator<'a> {
 
     fn visit_array(
         &mut self,
-        ctx: &Context<'_>,
+        ctx: &Context<'a>,
         buf: &mut Buffer,
         elements: &[WithSpan<'_, Expr<'_>>],
     ) -> Result<DisplayWrap, CompileError> {
@@ -1766,6 +3988,27 @@ impl<'a> Generator<'a> {
         Ok(DisplayWrap::Unwrapped)
     }
 
+    fn visit_map(
+        &mut self,
+        ctx: &Context<'a>,
+        buf: &mut Buffer,
+        entries: &[(WithSpan<'_, Expr<'_>>, WithSpan<'_, Expr<'_>>)],
+    ) -> Result<DisplayWrap, CompileError> {
+        buf.write(format_args!("{CRATE}::helpers::MapLiteral(["));
+        for (i, (key, value)) in entries.iter().enumerate() {
+            if i > 0 {
+                buf.write(", ");
+            }
+            buf.write("(");
+            self.visit_expr(ctx, buf, key)?;
+            buf.write(", ");
+            self.visit_expr(ctx, buf, value)?;
+            buf.write(")");
+        }
+        buf.write("])");
+        Ok(DisplayWrap::Unwrapped)
+    }
+
     fn visit_path(&mut self, buf: &mut Buffer, path: &[&str]) -> DisplayWrap {
         for (i, part) in path.iter().enumerate() {
             if i > 0 {
@@ -1776,14 +4019,73 @@ impl<'a> Generator<'a> {
         DisplayWrap::Unwrapped
     }
 
-    fn visit_var(&mut self, buf: &mut Buffer, s: &str) -> DisplayWrap {
+    fn visit_var<T>(
+        &mut self,
+        ctx: &Context<'_>,
+        buf: &mut Buffer,
+        s: &str,
+        node: &WithSpan<'_, T>,
+    ) -> Result<DisplayWrap, CompileError> {
         if s == "self" {
             buf.write(s);
-            return DisplayWrap::Unwrapped;
+            return Ok(DisplayWrap::Unwrapped);
         }
 
-        buf.write(normalize_identifier(&self.locals.resolve_or_self(s)));
-        DisplayWrap::Unwrapped
+        let var = match self.locals.resolve(s) {
+            Some(var) => var,
+            None if self.isolated => {
+                return Err(ctx.generate_error(
+                    &format!(
+                        "variable `{s}` is not available in a template included with `only`, \
+                         pass it with `with {s} = ...`"
+                    ),
+                    node,
+                ));
+            }
+            None => self.resolve_non_local(s),
+        };
+        buf.write(normalize_identifier(&var));
+        Ok(DisplayWrap::Unwrapped)
+    }
+
+    // A name that is not a local variable is a constant, or else a field of the template.
+    fn resolve_non_local(&self, name: &str) -> String {
+        match self.const_path(name) {
+            Some(path) => path.to_owned(),
+            None if self.is_borrowed_field(name) => {
+                format!("(*self.{})", normalize_identifier(name))
+            }
+            None => format!("self.{}", normalize_identifier(name)),
+        }
+    }
+
+    // The fields of the `struct` of an `enum` variant borrow the fields of the variant. They are
+    // dereferenced, so they can be used like the fields of any other template.
+    fn is_borrowed_field(&self, name: &str) -> bool {
+        let syn::Data::Struct(data) = &self.input.ast.data else {
+            return false;
+        };
+        if !self.input.borrowed_fields {
+            return false;
+        }
+        // The last field borrows the enum itself.
+        let fields = data.fields.iter().enumerate().take(data.fields.len() - 1);
+        match &data.fields {
+            syn::Fields::Named(_) => fields
+                .filter_map(|(_, field)| field.ident.as_ref())
+                .any(|ident| ident == normalize_identifier(name)),
+            _ => fields
+                .map(|(idx, _)| idx.to_string())
+                .any(|idx| idx == name),
+        }
+    }
+
+    // Returns the path of a constant listed in `consts = [...]` or in the `[constants]` table.
+    fn const_path(&self, name: &str) -> Option<&'a str> {
+        match self.input.consts.iter().find(|(n, _)| n == name) {
+            Some((_, path)) => Some(path),
+            None => self.input.config.constants.get(name).copied(),
+        }
     }
 
     fn visit_filter_source(&mut self, buf: &mut Buffer) -> DisplayWrap {
@@ -1818,7 +4120,7 @@ impl<'a> Generator<'a> {
         buf: &mut Buffer,
         initialized: bool,
         first_level: bool,
-        target: &Target<'a>,
+        target: &Target<'_>,
     ) {
         match target {
             Target::Placeholder(s) | Target::Rest(s) => {
@@ -1826,11 +4128,11 @@ impl<'a> Generator<'a> {
             }
             Target::Name(name) => {
                 let name = normalize_identifier(name);
+                // The pattern of a `matches!()` expression does not live as long as the template.
+                let local = Cow::Owned(name.to_owned());
                 match initialized {
-                    true => self
-                        .locals
-                        .insert(Cow::Borrowed(name), LocalMeta::initialized()),
-                    false => self.locals.insert_with_default(Cow::Borrowed(name)),
+                    true => self.locals.insert(local, LocalMeta::initialized()),
+                    false => self.locals.insert_with_default(local),
                 }
                 buf.write(name);
             }
@@ -1910,10 +4212,8 @@ impl<'a> Generator<'a> {
 
     fn should_trim_ws(&self, ws: Option<Whitespace>) -> WhitespaceHandling {
         match ws {
-            Some(Whitespace::Suppress) => WhitespaceHandling::Suppress,
-            Some(Whitespace::Preserve) => WhitespaceHandling::Preserve,
-            Some(Whitespace::Minimize) => WhitespaceHandling::Minimize,
-            None => self.input.config.whitespace,
+            Some(ws) => ws.into(),
+            None => self.whitespace,
         }
     }
 
@@ -1921,39 +4221,57 @@ impl<'a> Generator<'a> {
     // prefix whitespace suppressor from the given argument, flush that whitespace.
     // In either case, `next_ws` is reset to `None` (no trailing whitespace).
     fn flush_ws(&mut self, ws: Ws) {
-        if self.next_ws.is_none() {
+        self.flush_ws_inner(ws, true);
+    }
+
+    // `lstrip_blocks` only applies to block tags, not to expressions.
+    fn flush_ws_inner(&mut self, ws: Ws, is_block: bool) {
+        let Some(mut val) = self.next_ws.take() else {
             return;
+        };
+
+        // Remove the indentation in front of the block tag.
+        if is_block && ws.0.is_none() && self.input.lstrip_blocks {
+            match val.rfind('\n') {
+                Some(idx) => val = &val[..=idx],
+                None if self.at_line_start => val = "",
+                None => {}
+            }
         }
 
         // If `whitespace` is set to `suppress`, we keep the whitespace characters only if there is
         // a `+` character.
         match self.should_trim_ws(ws.0) {
             WhitespaceHandling::Preserve => {
-                let val = self.next_ws.unwrap();
                 if !val.is_empty() {
-                    self.buf_writable.push(Writable::Lit(val));
+                    self.buf_writable.push(Writable::Lit(val.into()));
                 }
             }
             WhitespaceHandling::Minimize => {
-                let val = self.next_ws.unwrap();
                 if !val.is_empty() {
                     self.buf_writable
                         .push(Writable::Lit(match val.contains('\n') {
-                            true => "\n",
-                            false => " ",
+                            true => "\n".into(),
+                            false => " ".into(),
                         }));
                 }
             }
             WhitespaceHandling::Suppress => {}
         }
-        self.next_ws = None;
     }
 
     // Sets `skip_ws` to match the suffix whitespace suppressor from the given
     // argument, to determine whether to suppress leading whitespace from the
     // next literal.
     fn prepare_ws(&mut self, ws: Ws) {
+        self.prepare_ws_inner(ws, true);
+    }
+
+    // `trim_blocks` only applies to block tags, not to expressions.
+    fn prepare_ws_inner(&mut self, ws: Ws, is_block: bool) {
         self.skip_ws = self.should_trim_ws(ws.1);
+        self.trim_newline = is_block && ws.1.is_none() && self.input.trim_blocks;
+        self.at_line_start = false;
     }
 }
 
@@ -1962,6 +4280,8 @@ struct Buffer {
     // The buffer to generate the code into
     buf: String,
     discard: bool,
+    // The start and end of the last statement written by `write_lit()`, and its text
+    last_lit: Option<(usize, usize, String)>,
 }
 
 impl Buffer {
@@ -1969,6 +4289,44 @@ impl Buffer {
         Self {
             buf: String::new(),
             discard: false,
+            last_lit: None,
+        }
+    }
+
+    /// Writes a statement that writes the static text `lit`
+    ///
+    /// If the previous statement in the buffer wrote static text, too, e.g. because only a
+    /// comment, a `{% let %}` or an `{% include %}` boundary was between them, both are merged
+    /// into a single write.
+    fn write_lit(&mut self, lit: &str) {
+        if self.discard {
+            return;
+        }
+        let (start, lit) = match self.last_lit.take() {
+            Some((start, end, mut prev)) if end == self.buf.len() => {
+                self.buf.truncate(start);
+                prev.push_str(lit);
+                (start, prev)
+            }
+            _ => (self.buf.len(), lit.to_owned()),
+        };
+        // In the body of the template, `writer` is a `helpers::Sink`, whose inherent `write_str()`
+        // lets `Template::render_chunks()` borrow the text.
+        self.writeln(format_args!("writer.write_str({lit:#?})?;"));
+        self.last_lit = Some((start, self.buf.len(), lit));
+    }
+
+    /// Writes the output of `render_into_async()` into the `AsyncWrite`
+    ///
+    /// If the static text before the flush is merged with the next static text, the flush is
+    /// dropped with it, and the merged text is written at the next flush.
+    fn write_async_flush(&mut self) {
+        let after_lit = matches!(self.last_lit, Some((_, end, _)) if end == self.buf.len());
+        self.writeln(format_args!(
+            "{CRATE}::helpers::write_async(__rinja_async_writer, writer).await?;"
+        ));
+        if let (true, Some((_, end, _))) = (after_lit, &mut self.last_lit) {
+            *end = self.buf.len();
         }
     }
 
@@ -2031,6 +4389,8 @@ impl<I: IntoIterator<Item = E> + Copy, E: BufferFmt> BufferFmt for SeparatedPath
 pub(crate) struct LocalMeta {
     refs: Option<String>,
     initialized: bool,
+    // Set when the variable is used, if it was declared by a `{% let %}`
+    used: Option<Rc<Cell<bool>>>,
 }
 
 impl LocalMeta {
@@ -2038,6 +4398,7 @@ impl LocalMeta {
         Self {
             refs: None,
             initialized: true,
+            used: None,
         }
     }
 
@@ -2045,7 +4406,59 @@ impl LocalMeta {
         Self {
             refs: Some(refs),
             initialized: true,
+            used: None,
+        }
+    }
+}
+
+// Collects the names of all variables declared by `target`.
+fn target_names<'a>(target: &Target<'a>, names: &mut Vec<&'a str>) {
+    match target {
+        Target::Name(name) => names.push(normalize_identifier(name)),
+        Target::OrChain(targets) | Target::Tuple(_, targets) => {
+            for target in targets {
+                target_names(target, names);
+            }
+        }
+        Target::Struct(_, targets) => {
+            for (_, target) in targets {
+                target_names(target, names);
+            }
         }
+        _ => {}
+    }
+}
+
+/// The `{% let %}`s, macros and imports that were used while generating the code
+#[derive(Default)]
+struct Lints {
+    /// The warning for every visited `{% let %}` variable, and whether it was used
+    lets: Vec<(String, Rc<Cell<bool>>)>,
+    /// Addresses of the macros that were called
+    called_macros: HashSet<*const ()>,
+    /// Scopes of `{% import %}`s that were used to call a macro
+    used_scopes: HashSet<String>,
+    /// Names of macros imported with `{% from ... import %}` that were called
+    used_macro_imports: HashSet<String>,
+}
+
+impl Lints {
+    fn called(&mut self, def: &Macro<'_>) {
+        self.called_macros
+            .insert(def as *const Macro<'_> as *const ());
+    }
+
+    fn is_called(&self, def: &Macro<'_>) -> bool {
+        self.called_macros
+            .contains(&(def as *const Macro<'_> as *const ()))
+    }
+
+    fn append(&mut self, other: &mut Self) {
+        self.lets.append(&mut other.lets);
+        self.called_macros.extend(other.called_macros.drain());
+        self.used_scopes.extend(other.used_scopes.drain());
+        self.used_macro_imports
+            .extend(other.used_macro_imports.drain());
     }
 }
 
@@ -2114,16 +4527,16 @@ where
 impl MapChain<'_, Cow<'_, str>, LocalMeta> {
     fn resolve(&self, name: &str) -> Option<String> {
         let name = normalize_identifier(name);
-        self.get(&Cow::Borrowed(name)).map(|meta| match &meta.refs {
-            Some(expr) => expr.clone(),
-            None => name.to_string(),
+        self.get(&Cow::Borrowed(name)).map(|meta| {
+            if let Some(used) = &meta.used {
+                used.set(true);
+            }
+            match &meta.refs {
+                Some(expr) => expr.clone(),
+                None => name.to_string(),
+            }
         })
     }
-
-    fn resolve_or_self(&self, name: &str) -> String {
-        let name = normalize_identifier(name);
-        self.resolve(name).unwrap_or_else(|| format!("self.{name}"))
-    }
 }
 
 impl<'a, K: Eq + hash::Hash, V> Default for MapChain<'a, K, V> {
@@ -2135,6 +4548,247 @@ impl<'a, K: Eq + hash::Hash, V> Default for MapChain<'a, K, V> {
     }
 }
 
+// Writes a number literal, with the suffix `i32` or `f64` if it has none, which is the type
+// Rust would fall back to.
+fn write_typed_num_lit(buf: &mut Buffer, lit: &str) {
+    buf.write(lit);
+    // Hexadecimal digits can be mistaken for a float suffix or exponent.
+    let hex = lit.contains('x');
+    if lit.contains(['i', 'u']) || (!hex && lit.contains('f')) {
+        return;
+    }
+    match !hex && lit.contains(['.', 'e', 'E']) {
+        true => buf.write("_f64"),
+        false => buf.write("_i32"),
+    }
+}
+
+// The built-in filters that `write_const_filter_block()` can apply at compile time
+const CONST_FILTERS: &[&str] = &[
+    "capfirst",
+    "capitalize",
+    "lower",
+    "lowercase",
+    "title",
+    "trim",
+    "upper",
+    "uppercase",
+];
+
+// Does the same as the filter `name` of `rinja::filters` at runtime.
+fn apply_const_filter(name: &str, s: &str) -> String {
+    let mut chars = s.chars();
+    match name {
+        "capfirst" => match chars.next() {
+            Some(c) if !c.is_uppercase() => c.to_uppercase().chain(chars).collect(),
+            _ => s.to_owned(),
+        },
+        "capitalize" => match chars.next() {
+            Some(c) => c.to_uppercase().collect::<String>() + &chars.as_str().to_lowercase(),
+            None => String::new(),
+        },
+        "lower" | "lowercase" => s.to_lowercase(),
+        "title" => {
+            let mut output = String::with_capacity(s.len());
+            let mut need_capitalization = true;
+            for c in chars {
+                if c.is_whitespace() {
+                    output.push(c);
+                    need_capitalization = true;
+                } else if mem::take(&mut need_capitalization) {
+                    match c.is_uppercase() {
+                        true => output.push(c),
+                        false => output.extend(c.to_uppercase()),
+                    }
+                } else {
+                    match c.is_lowercase() {
+                        true => output.push(c),
+                        false => output.extend(c.to_lowercase()),
+                    }
+                }
+            }
+            output
+        }
+        "trim" => s.trim().to_owned(),
+        "upper" | "uppercase" => s.to_uppercase(),
+        _ => unreachable!("{name:?} is not in `CONST_FILTERS`"),
+    }
+}
+
+// Does the same as `rinja::filters::Html` at runtime.
+fn escape_html_const(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' | '&' | '\'' | '<' | '>' => write!(escaped, "&#{};", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The value of an expression that only consists of literals, see [`ConstValue::eval()`]
+#[derive(Debug)]
+enum ConstValue {
+    /// An integer, and its type if it is known
+    Int(i128, Option<&'static str>),
+    /// An `f64`
+    Float(f64),
+    Str(String),
+    Char(char),
+    Bool(bool),
+}
+
+impl ConstValue {
+    /// Evaluates `expr` like the generated code would at runtime
+    ///
+    /// Returns `None` if the expression is not made of literals and operators, or if its
+    /// evaluation could fail or would be rejected by `rustc`, e.g. because of an overflow or
+    /// mismatched types. The expression is then generated as usual, so that the error is
+    /// reported, and the behavior is not changed.
+    fn eval(expr: &Expr<'_>) -> Option<Self> {
+        match expr {
+            Expr::BoolLit(s) => Some(Self::Bool(*s == "true")),
+            Expr::NumLit(s) => Self::num_lit(s),
+            Expr::StrLit(s) => {
+                let lit = syn::parse_str::<syn::LitStr>(&format!("\"{s}\"")).ok()?;
+                Some(Self::Str(lit.value()))
+            }
+            Expr::CharLit(s) => {
+                let lit = syn::parse_str::<syn::LitChar>(&format!("'{s}'")).ok()?;
+                Some(Self::Char(lit.value()))
+            }
+            Expr::Group(inner) => Self::eval(inner),
+            Expr::Unary(op, inner) => match (*op, Self::eval(inner)?) {
+                ("!", Self::Bool(b)) => Some(Self::Bool(!b)),
+                ("-", value) => value.neg(),
+                _ => None,
+            },
+            Expr::BinOp(op, left, right) => Self::binop(op, Self::eval(left)?, Self::eval(right)?),
+            _ => None,
+        }
+    }
+
+    fn num_lit(s: &str) -> Option<Self> {
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let value = match syn::parse_str::<syn::Lit>(s).ok()? {
+            syn::Lit::Int(lit) => {
+                let ty = INT_TYPES.iter().find(|ty| **ty == lit.suffix()).copied();
+                if ty.is_none() && !lit.suffix().is_empty() {
+                    return None;
+                }
+                Self::Int(lit.base10_parse().ok()?, ty)
+            }
+            syn::Lit::Float(lit) if matches!(lit.suffix(), "" | "f64") => {
+                Self::Float(lit.base10_parse().ok()?)
+            }
+            _ => return None,
+        };
+        match negative {
+            true => value.neg(),
+            false => Some(value),
+        }
+    }
+
+    fn neg(self) -> Option<Self> {
+        match self {
+            Self::Int(i, ty) => Self::int(i.checked_neg()?, ty),
+            Self::Float(f) => Some(Self::Float(-f)),
+            _ => None,
+        }
+    }
+
+    // Returns `None` if `value` does not fit into the type, which is `i32` if it is not known.
+    // The size of `isize` and `usize` depends on the target, so they are treated as 32 bit types.
+    fn int(value: i128, ty: Option<&'static str>) -> Option<Self> {
+        let fits = match ty.unwrap_or("i32") {
+            "i8" => i8::try_from(value).is_ok(),
+            "i16" => i16::try_from(value).is_ok(),
+            "i32" | "isize" => i32::try_from(value).is_ok(),
+            "i64" => i64::try_from(value).is_ok(),
+            "i128" => true,
+            "u8" => u8::try_from(value).is_ok(),
+            "u16" => u16::try_from(value).is_ok(),
+            "u32" | "usize" => u32::try_from(value).is_ok(),
+            "u64" => u64::try_from(value).is_ok(),
+            "u128" => value >= 0,
+            _ => false,
+        };
+        fits.then_some(Self::Int(value, ty))
+    }
+
+    fn binop(op: &str, left: Self, right: Self) -> Option<Self> {
+        match (left, right) {
+            (Self::Int(a, ty), Self::Int(b, _)) if op == "**" => {
+                // like `rinja::helpers::Pow`, the result has the type of the base
+                Self::int(a.checked_pow(u32::try_from(b).ok()?)?, ty.or(Some("i32")))
+            }
+            // The type of an unsuffixed literal would be inferred from the other operand, which
+            // could change the type of the operations before, so only equal types are allowed.
+            (Self::Int(a, ty), Self::Int(b, ty2)) if ty == ty2 => {
+                let value = match op {
+                    "+" => a.checked_add(b)?,
+                    "-" => a.checked_sub(b)?,
+                    "*" => a.checked_mul(b)?,
+                    "/" => a.checked_div(b)?,
+                    "%" => a.checked_rem(b)?,
+                    _ => return Self::compare(op, &a, &b),
+                };
+                Self::int(value, ty)
+            }
+            (Self::Float(a), Self::Float(b)) => match op {
+                "+" => Some(Self::Float(a + b)),
+                "-" => Some(Self::Float(a - b)),
+                "*" => Some(Self::Float(a * b)),
+                "/" => Some(Self::Float(a / b)),
+                "%" => Some(Self::Float(a % b)),
+                "**" => None,
+                _ => Self::compare(op, &a, &b),
+            },
+            (Self::Bool(a), Self::Bool(b)) => match op {
+                "&&" => Some(Self::Bool(a && b)),
+                "||" => Some(Self::Bool(a || b)),
+                _ => Self::compare(op, &a, &b),
+            },
+            (Self::Str(a), Self::Str(b)) => Self::compare(op, &a, &b),
+            (Self::Char(a), Self::Char(b)) => Self::compare(op, &a, &b),
+            _ => None,
+        }
+    }
+
+    fn compare<T: PartialOrd>(op: &str, a: &T, b: &T) -> Option<Self> {
+        let result = match op {
+            "==" => a == b,
+            "!=" => a != b,
+            "<" => a < b,
+            "<=" => a <= b,
+            ">" => a > b,
+            ">=" => a >= b,
+            _ => return None,
+        };
+        Some(Self::Bool(result))
+    }
+}
+
+impl Display for ConstValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Int(i, _) => i.fmt(f),
+            Self::Float(v) => v.fmt(f),
+            Self::Str(s) => s.fmt(f),
+            Self::Char(c) => c.fmt(f),
+            Self::Bool(b) => b.fmt(f),
+        }
+    }
+}
+
+const INT_TYPES: &[&str] = &[
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+];
+
 /// Returns `true` if enough assumptions can be made,
 /// to determine that `self` is copyable.
 fn is_copyable(expr: &Expr<'_>) -> bool {
@@ -2153,6 +4807,8 @@ fn is_copyable_within_op(expr: &Expr<'_>, within_op: bool) -> bool {
         // as in that case the call is more likely to return a
         // reference in the first place then.
         Expr::Call(..) | Expr::Path(..) | Expr::Filter(..) => true,
+        // Closures are passed by value, like in Rust.
+        Expr::Closure(..) => true,
         // If the `expr` is within a `Unary` or `BinOp` then
         // an assumption can be made that the operand is copy.
         // If not, then the value is moved and adding `.clone()`
@@ -2163,6 +4819,24 @@ fn is_copyable_within_op(expr: &Expr<'_>, within_op: bool) -> bool {
     }
 }
 
+fn is_get_value_path(path: &[&str]) -> bool {
+    path == ["rinja", "get_value"]
+}
+
+/// Returns `true` if this is a call of `rinja::get_value()`, with or without generic arguments.
+fn is_get_value_call(expr: &Expr<'_>) -> bool {
+    let Expr::Call(left, _) = expr else {
+        return false;
+    };
+    match &***left {
+        Expr::Path(path) => is_get_value_path(path),
+        Expr::Turbofish(path, _) => {
+            matches!(***path, Expr::Path(ref path) if is_get_value_path(path))
+        }
+        _ => false,
+    }
+}
+
 /// Returns `true` if this is an `Attr` where the `obj` is `"self"`.
 pub(crate) fn is_attr_self(expr: &Expr<'_>) -> bool {
     match expr {
@@ -2172,43 +4846,12 @@ pub(crate) fn is_attr_self(expr: &Expr<'_>) -> bool {
     }
 }
 
-/// Returns `true` if the outcome of this expression may be used multiple times in the same
-/// `write!()` call, without evaluating the expression again, i.e. the expression should be
-/// side-effect free.
-pub(crate) fn is_cacheable(expr: &WithSpan<'_, Expr<'_>>) -> bool {
-    match &**expr {
-        // Literals are the definition of pure:
-        Expr::BoolLit(_) => true,
-        Expr::NumLit(_) => true,
-        Expr::StrLit(_) => true,
-        Expr::CharLit(_) => true,
-        // fmt::Display should have no effects:
-        Expr::Var(_) => true,
-        Expr::Path(_) => true,
-        // Check recursively:
-        Expr::Array(args) => args.iter().all(is_cacheable),
-        Expr::Attr(lhs, _) => is_cacheable(lhs),
-        Expr::Index(lhs, rhs) => is_cacheable(lhs) && is_cacheable(rhs),
-        Expr::Filter(Filter { arguments, .. }) => arguments.iter().all(is_cacheable),
-        Expr::Unary(_, arg) => is_cacheable(arg),
-        Expr::BinOp(_, lhs, rhs) => is_cacheable(lhs) && is_cacheable(rhs),
-        Expr::Range(_, lhs, rhs) => {
-            lhs.as_ref().map_or(true, |v| is_cacheable(v))
-                && rhs.as_ref().map_or(true, |v| is_cacheable(v))
-        }
-        Expr::Group(arg) => is_cacheable(arg),
-        Expr::Tuple(args) => args.iter().all(is_cacheable),
-        Expr::NamedArgument(_, expr) => is_cacheable(expr),
-        // We have too little information to tell if the expression is pure:
-        Expr::Call(_, _) => false,
-        Expr::RustMacro(_, _) => false,
-        Expr::Try(_) => false,
-        // Should never be encountered:
-        Expr::FilterSource => unreachable!("FilterSource in expression?"),
-    }
-}
-
 const FILTER_SOURCE: &str = "__rinja_filter_block";
+const MACRO_OUTPUT: &str = "__rinja_macro_output";
+const LET_BLOCK_OUTPUT: &str = "__rinja_let_block";
+const CACHE_KEY: &str = "__rinja_cache_key";
+const VALUES: &str = "__rinja_values";
+const CACHE_OUTPUT: &str = "__rinja_cache_output";
 
 fn median(sizes: &mut [usize]) -> usize {
     sizes.sort_unstable();
@@ -2219,6 +4862,30 @@ fn median(sizes: &mut [usize]) -> usize {
     }
 }
 
+/// The kind of type `Template` is implemented for
+#[derive(Clone, Copy)]
+pub(crate) enum TmplKind<'a> {
+    /// A `struct`, or an `enum` that is rendered by a single template
+    Struct,
+    /// An `enum` that dispatches to the templates of its variants
+    Enum(&'a EnumVariants),
+    /// The hidden `struct` generated for an `enum` variant, which does not need any
+    /// web framework integrations
+    Variant,
+    /// The hidden `struct` generated for a block listed in `blocks = [...]`, which does not need
+    /// any web framework integrations
+    Block,
+}
+
+pub(crate) struct EnumVariants {
+    // The `match self` arms rendering the variants that have a template of their own
+    pub(crate) arms: String,
+    // The largest size hint of the variants' templates
+    pub(crate) size_hint: usize,
+    // Whether every variant has a template of its own
+    pub(crate) exhaustive: bool,
+}
+
 #[derive(Clone, Copy, PartialEq)]
 enum AstLevel {
     Top,
@@ -2256,7 +4923,7 @@ impl<'a> Deref for WritableBuffer<'a> {
 
 #[derive(Debug)]
 enum Writable<'a> {
-    Lit(&'a str),
+    Lit(Cow<'a, str>),
     Expr(&'a WithSpan<'a, Expr<'a>>),
 }
 
@@ -2265,21 +4932,21 @@ struct WriteParts {
     buffers: Option<WritePartsBuffers>,
 }
 
-/// If "expr" is `None`, it means we can generate code like this:
-///
-/// ```ignore
-/// let var = format;
-/// ```
-///
-/// Otherwise we need to format "expr" using "format":
-///
-/// ```ignore
-/// let var = format!(format, expr);
-/// ```
+/// The code to write a run of `Writable`s
 #[derive(Debug)]
-struct WritePartsBuffers {
-    format: Buffer,
-    expr: Option<Buffer>,
+enum WritePartsBuffers {
+    /// The run only contains static text, which is written with `Buffer::write_lit()`
+    Lit(String),
+    /// Statements that write the expressions of the run one by one
+    Exprs(Buffer),
+}
+
+/// `{{ super() }}` renders the parent block, just like `{% call super() %}`.
+fn is_super_call(expr: &Expr<'_>) -> bool {
+    matches!(
+        expr,
+        Expr::Call(obj, args) if args.is_empty() && matches!(***obj, Expr::Var("super"))
+    )
 }
 
 /// Identifiers to be replaced with raw identifiers, so as to avoid
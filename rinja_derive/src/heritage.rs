@@ -1,9 +1,10 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::env;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use parser::node::{BlockDef, Macro};
-use parser::{Node, Parsed, WithSpan};
+use parser::node::{BlockDef, Macro, TemplatePath};
+use parser::{Expr, Node, Parsed, WithSpan};
 
 use crate::config::Config;
 use crate::{CompileError, FileInfo};
@@ -17,7 +18,8 @@ impl Heritage<'_> {
     pub(crate) fn new<'n>(
         mut ctx: &'n Context<'n>,
         contexts: &'n HashMap<&'n Arc<Path>, Context<'n>>,
-    ) -> Heritage<'n> {
+    ) -> Result<Heritage<'n>, CompileError> {
+        let mut chain = vec![ctx];
         let mut blocks: BlockAncestry<'n> = ctx
             .blocks
             .iter()
@@ -26,24 +28,52 @@ impl Heritage<'_> {
 
         while let Some(path) = &ctx.extends {
             ctx = &contexts[path];
+            chain.push(ctx);
             for (name, def) in &ctx.blocks {
                 blocks.entry(name).or_default().push((ctx, def));
             }
         }
 
-        Heritage { root: ctx, blocks }
+        // The first definition of a block is the one that is rendered.
+        let mut required = blocks
+            .values()
+            .filter_map(|defs| defs.first())
+            .filter(|(_, def)| def.required)
+            .collect::<Vec<_>>();
+        required.sort_by_key(|(ctx, def)| ctx.node_id(def));
+        if let Some((def_ctx, def)) = required.first() {
+            let chain = chain
+                .iter()
+                .map(|ctx| format!("{:?}", ctx.display_path()))
+                .collect::<Vec<_>>()
+                .join(" extends ");
+            return Err(def_ctx.generate_error(
+                &format!(
+                    "block `{}` is required, but no template that extends {:?} defines it: {chain}",
+                    def.name,
+                    def_ctx.display_path(),
+                ),
+                def,
+            ));
+        }
+
+        Ok(Heritage { root: ctx, blocks })
     }
 }
 
-type BlockAncestry<'a> = HashMap<&'a str, Vec<(&'a Context<'a>, &'a BlockDef<'a>)>>;
+type BlockAncestry<'a> = HashMap<&'a str, Vec<(&'a Context<'a>, &'a WithSpan<'a, BlockDef<'a>>)>>;
 
 #[derive(Clone)]
 pub(crate) struct Context<'a> {
     pub(crate) nodes: &'a [Node<'a>],
     pub(crate) extends: Option<Arc<Path>>,
-    pub(crate) blocks: HashMap<&'a str, &'a BlockDef<'a>>,
+    /// The expression of an `{% extends %}` which selects the parent template at runtime
+    pub(crate) dynamic_extends: Option<&'a WithSpan<'a, Expr<'a>>>,
+    pub(crate) blocks: HashMap<&'a str, &'a WithSpan<'a, BlockDef<'a>>>,
     pub(crate) macros: HashMap<&'a str, &'a Macro<'a>>,
     pub(crate) imports: HashMap<&'a str, Arc<Path>>,
+    /// Macros imported with `{% from "path" import name as alias %}`, as `alias => (path, name)`
+    pub(crate) macro_imports: HashMap<&'a str, (Arc<Path>, &'a str)>,
     path: Option<&'a Path>,
     parsed: &'a Parsed,
 }
@@ -53,9 +83,11 @@ impl Context<'_> {
         Context {
             nodes: &[],
             extends: None,
+            dynamic_extends: None,
             blocks: HashMap::new(),
             macros: HashMap::new(),
             imports: HashMap::new(),
+            macro_imports: HashMap::new(),
             path: None,
             parsed,
         }
@@ -67,9 +99,11 @@ impl Context<'_> {
         parsed: &'n Parsed,
     ) -> Result<Context<'n>, CompileError> {
         let mut extends = None;
+        let mut dynamic_extends = None;
         let mut blocks = HashMap::new();
         let mut macros = HashMap::new();
         let mut imports = HashMap::new();
+        let mut macro_imports = HashMap::new();
         let mut nested = vec![parsed.nodes()];
         let mut top = true;
 
@@ -78,13 +112,18 @@ impl Context<'_> {
                 match n {
                     Node::Extends(e) => {
                         ensure_top(top, e, path, parsed, "extends")?;
-                        if extends.is_some() {
+                        if extends.is_some() || dynamic_extends.is_some() {
                             return Err(CompileError::new(
                                 "multiple extend blocks found",
                                 Some(FileInfo::of(e, path, parsed)),
                             ));
                         }
-                        extends = Some(config.find_template(e.path, Some(path))?);
+                        match e.path {
+                            TemplatePath::Static(parent) => {
+                                extends = Some(config.find_template(parent, Some(path))?);
+                            }
+                            TemplatePath::Dynamic(ref expr) => dynamic_extends = Some(expr),
+                        }
                     }
                     Node::Macro(m) => {
                         ensure_top(top, m, path, parsed, "macro")?;
@@ -95,8 +134,15 @@ impl Context<'_> {
                         let path = config.find_template(import.path, Some(path))?;
                         imports.insert(import.scope, path);
                     }
+                    Node::FromImport(import) => {
+                        ensure_top(top, import, path, parsed, "import")?;
+                        let path = config.find_template(import.path, Some(path))?;
+                        for &(name, alias) in &import.names {
+                            macro_imports.insert(alias.unwrap_or(name), (Arc::clone(&path), name));
+                        }
+                    }
                     Node::BlockDef(b) => {
-                        blocks.insert(b.name, &**b);
+                        blocks.insert(b.name, b);
                         nested.push(&b.nodes);
                     }
                     Node::If(i) => {
@@ -113,6 +159,17 @@ impl Context<'_> {
                             nested.push(&arm.nodes);
                         }
                     }
+                    Node::Let(l) => {
+                        if let Some(else_nodes) = &l.else_nodes {
+                            nested.push(else_nodes);
+                        }
+                    }
+                    Node::WhitespaceBlock(w) => {
+                        nested.push(&w.nodes);
+                    }
+                    Node::AutoEscape(a) => {
+                        nested.push(&a.nodes);
+                    }
                     _ => {}
                 }
             }
@@ -122,9 +179,11 @@ impl Context<'_> {
         Ok(Context {
             nodes: parsed.nodes(),
             extends,
+            dynamic_extends,
             blocks,
             macros,
             imports,
+            macro_imports,
             parsed,
             path: Some(path),
         })
@@ -136,6 +195,45 @@ impl Context<'_> {
             self.path.map(|path| FileInfo::of(node, path, self.parsed)),
         )
     }
+
+    pub(crate) fn generate_warning<T>(&self, msg: &str, node: &WithSpan<'_, T>) -> String {
+        match self.path {
+            Some(path) => format!("{msg}{}", FileInfo::of(node, path, self.parsed)),
+            None => msg.to_string(),
+        }
+    }
+
+    /// Returns `"path:offset"`, which identifies `node` among the templates of the crate
+    pub(crate) fn node_id<T>(&self, node: &WithSpan<'_, T>) -> String {
+        let offset = node.offset(self.parsed.source());
+        format!("{}:{offset}", self.display_path())
+    }
+
+    /// Returns `"path:offset"` with the full path of the template, which identifies `node` among
+    /// all templates, no matter which crate they belong to
+    pub(crate) fn node_location<T>(&self, node: &WithSpan<'_, T>) -> String {
+        let offset = node.offset(self.parsed.source());
+        match self.path {
+            Some(path) => format!("{}:{offset}", path.display()),
+            None => format!(":{offset}"),
+        }
+    }
+
+    /// Returns the path of the template, relative to the crate root if possible
+    pub(crate) fn display_path(&self) -> String {
+        let Some(path) = self.path else {
+            return String::new();
+        };
+        let root = env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from);
+        let path = match root
+            .as_deref()
+            .and_then(|root| path.strip_prefix(root).ok())
+        {
+            Some(path) => path,
+            None => path,
+        };
+        path.display().to_string()
+    }
 }
 
 fn ensure_top<T>(
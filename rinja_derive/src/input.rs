@@ -1,30 +1,59 @@
 use std::borrow::Cow;
 use std::collections::hash_map::{Entry, HashMap};
-use std::fs::read_to_string;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::{Arc, OnceLock};
 
 use mime::Mime;
 use once_map::OnceMap;
+use parser::node::TemplatePath;
 use parser::{Node, Parsed};
 use quote::ToTokens;
+use syn::parse::discouraged::Speculative;
+use syn::parse::{Parse, ParseStream};
 use syn::punctuated::Punctuated;
 
-use crate::config::{Config, SyntaxAndCache};
-use crate::{CompileError, FileInfo, MsgValidEscapers};
+use crate::config::{Config, InlineSyntax, SyntaxAndCache, WhitespaceHandling};
+use crate::{CompileError, CompileErrors, FileInfo, MsgValidEscapers};
 
 pub(crate) struct TemplateInput<'a> {
     pub(crate) ast: &'a syn::DeriveInput,
     pub(crate) config: &'a Config,
+    /// The path of the configuration file, if there is one
+    pub(crate) config_file: Option<&'a Path>,
     pub(crate) syntax: &'a SyntaxAndCache<'a>,
     pub(crate) source: &'a Source,
     pub(crate) block: Option<&'a str>,
     pub(crate) print: Print,
     pub(crate) escaper: &'a str,
+    /// The error type given in `error = "..."`, which errors raised in the template are
+    /// converted into
+    pub(crate) error: Option<&'a str>,
     pub(crate) ext: Option<&'a str>,
     pub(crate) mime_type: String,
     pub(crate) path: Arc<Path>,
+    /// The templates listed in `includes = [...]`, which can be included with a dynamic
+    /// `{% include %}`
+    pub(crate) includes: Vec<(&'a str, Arc<Path>)>,
+    /// The templates listed in `extends = [...]`, which can be extended with a dynamic
+    /// `{% extends %}`
+    pub(crate) extends: Vec<(&'a str, Arc<Path>)>,
+    /// The constants and statics listed in `consts = [...]`, as `(name, path)`, which can be
+    /// used by their name
+    pub(crate) consts: &'a [(String, String)],
+    pub(crate) whitespace: WhitespaceHandling,
+    pub(crate) trim_blocks: bool,
+    pub(crate) lstrip_blocks: bool,
+    pub(crate) debug_origins: bool,
+    /// Warn about unused `{% let %}`s, macros and imports, see `Config::warn_unused`
+    pub(crate) warn_unused: bool,
+    /// Set by `render_async = true`: `Template::render_into_async()` writes the output while the
+    /// template is rendered
+    #[cfg(feature = "tokio")]
+    pub(crate) render_async: bool,
+    /// Set for the hidden `struct` of an `enum` variant, whose fields (except for the last one,
+    /// which borrows the enum) are references to the fields of the variant
+    pub(crate) borrowed_fields: bool,
 }
 
 impl TemplateInput<'_> {
@@ -34,6 +63,7 @@ impl TemplateInput<'_> {
     pub(crate) fn new<'n>(
         ast: &'n syn::DeriveInput,
         config: &'n Config,
+        config_file: Option<&'n Path>,
         args: &'n TemplateArgs,
     ) -> Result<TemplateInput<'n>, CompileError> {
         let TemplateArgs {
@@ -41,11 +71,28 @@ impl TemplateInput<'_> {
             block,
             print,
             escaping,
+            error,
             ext,
             syntax,
+            syntax_inline,
+            trim_blocks,
+            lstrip_blocks,
+            debug_origins,
+            warn_unused,
+            render_async,
+            includes,
+            extends,
+            consts,
+            whitespace,
             ..
         } = args;
 
+        if *render_async && !cfg!(feature = "tokio") {
+            return Err(CompileError::no_file_info(
+                "`render_async = true` needs the feature `tokio`",
+            ));
+        }
+
         // Validate the `source` and `ext` value together, since they are
         // related. In case `source` was used instead of `path`, the value
         // of `ext` is merged into a synthetic `path` value here.
@@ -64,20 +111,38 @@ impl TemplateInput<'_> {
             }
         };
 
+        // Settings of the `[[directory]]` containing the template, which are overridden by the
+        // arguments of the `template()` attribute.
+        let directory = config.find_directory(&path);
+
         // Validate syntax
-        let syntax = syntax.as_deref().map_or_else(
-            || Ok(config.syntaxes.get(config.default_syntax).unwrap()),
-            |s| {
-                config.syntaxes.get(s).ok_or_else(|| {
-                    CompileError::no_file_info(format!("attribute syntax {s} not exist"))
-                })
-            },
-        )?;
+        let syntax = match syntax_inline {
+            Some(_) if syntax.is_some() => {
+                return Err(CompileError::no_file_info(
+                    "must specify 'syntax' or 'syntax_inline', not both",
+                ));
+            }
+            Some(syntax_inline) => {
+                syntax_inline.syntax(config.extension_tags, config.extension_block_tags)?
+            }
+            None => syntax
+                .as_deref()
+                .or(directory.and_then(|d| d.syntax))
+                .map_or_else(
+                    || Ok(config.syntaxes.get(config.default_syntax).unwrap()),
+                    |s| {
+                        config.syntaxes.get(s).ok_or_else(|| {
+                            CompileError::no_file_info(format!("attribute syntax {s} not exist"))
+                        })
+                    },
+                )?,
+        };
 
         // Match extension against defined output formats
 
         let escaping = escaping
             .as_deref()
+            .or(directory.and_then(|d| d.escape))
             .unwrap_or_else(|| path.extension().map(|s| s.to_str().unwrap()).unwrap_or(""));
 
         let escaper = config
@@ -99,17 +164,47 @@ impl TemplateInput<'_> {
             extension_to_mime_type(ext_default_to_path(ext.as_deref(), &path).unwrap_or("txt"))
                 .to_string();
 
+        let includes = find_templates(config, includes, &path)?;
+        let extends = find_templates(config, extends, &path)?;
+
         Ok(TemplateInput {
             ast,
             config,
+            config_file,
             syntax,
             source,
             block: block.as_deref(),
             print: *print,
             escaper,
+            error: error.as_deref(),
             ext: ext.as_deref(),
             mime_type,
             path,
+            includes,
+            extends,
+            consts,
+            // `config.whitespace` already contains the `whitespace` argument if it was given
+            whitespace: match whitespace {
+                Some(_) => config.whitespace,
+                None => directory
+                    .and_then(|d| d.whitespace)
+                    .unwrap_or(config.whitespace),
+            },
+            trim_blocks: trim_blocks
+                .or(directory.and_then(|d| d.trim_blocks))
+                .unwrap_or(config.trim_blocks),
+            lstrip_blocks: lstrip_blocks
+                .or(directory.and_then(|d| d.lstrip_blocks))
+                .unwrap_or(config.lstrip_blocks),
+            // The origins are written as HTML comments, which would break other file types.
+            debug_origins: debug_origins.unwrap_or(config.debug_origins)
+                && config.escapers.iter().any(|(extensions, path)| {
+                    path == escaper && extensions.contains(&Cow::Borrowed("html"))
+                }),
+            warn_unused: warn_unused.unwrap_or(config.warn_unused),
+            #[cfg(feature = "tokio")]
+            render_async: *render_async,
+            borrowed_fields: false,
         })
     }
 
@@ -120,15 +215,23 @@ impl TemplateInput<'_> {
         let (source, source_path) = match &self.source {
             Source::Source(s) => (s.clone(), None),
             Source::Path(_) => (
-                get_template_source(&self.path, None)?,
+                get_template_source(self.config, &self.path, None)?,
                 Some(Arc::clone(&self.path)),
             ),
         };
 
+        // Problems in one template don't keep the other templates from being checked.
+        let mut errors = CompileErrors::default();
         let mut dependency_graph = Vec::new();
         let mut check = vec![(Arc::clone(&self.path), source, source_path)];
         while let Some((path, source, source_path)) = check.pop() {
-            let parsed = self.syntax.parse(source, source_path)?;
+            // Only the first syntax error of a template is reported: after it, the parser could
+            // only guess how the template continues, which would report made-up errors.
+            let Some(parsed) =
+                errors.check(self.syntax.parse(source, source_path).map_err(Into::into))
+            else {
+                continue;
+            };
 
             let mut top = true;
             let mut nested = vec![parsed.nodes()];
@@ -140,6 +243,7 @@ impl TemplateInput<'_> {
                             // multiple times to `check`.
                             let new_path = e.key();
                             let source = get_template_source(
+                                self.config,
                                 new_path,
                                 Some((&path, parsed.source(), n.span())),
                             )?;
@@ -151,32 +255,85 @@ impl TemplateInput<'_> {
 
                     match n {
                         Node::Extends(extends) if top => {
-                            let extends = self.config.find_template(extends.path, Some(&path))?;
-                            let dependency_path = (path.clone(), extends.clone());
-                            if path == extends {
-                                // We add the path into the graph to have a better looking error.
+                            let parents: Vec<_> = match extends.path {
+                                TemplatePath::Static(parent) => errors
+                                    .check(self.config.find_template(parent, Some(&path)))
+                                    .into_iter()
+                                    .collect(),
+                                TemplatePath::Dynamic(_) => {
+                                    self.extends.iter().map(|(_, p)| p.clone()).collect()
+                                }
+                            };
+                            for extends in parents {
+                                let dependency_path = (path.clone(), extends.clone());
+                                if path == extends {
+                                    // We add the path into the graph to have a better looking
+                                    // error.
+                                    dependency_graph.push(dependency_path);
+                                    return cyclic_graph_error(&dependency_graph);
+                                } else if dependency_graph.contains(&dependency_path) {
+                                    return cyclic_graph_error(&dependency_graph);
+                                }
                                 dependency_graph.push(dependency_path);
-                                return cyclic_graph_error(&dependency_graph);
-                            } else if dependency_graph.contains(&dependency_path) {
-                                return cyclic_graph_error(&dependency_graph);
+                                errors.check(add_to_check(extends));
                             }
-                            dependency_graph.push(dependency_path);
-                            add_to_check(extends)?;
                         }
                         Node::Macro(m) if top => {
                             nested.push(&m.nodes);
                         }
                         Node::Import(import) if top => {
-                            let import = self.config.find_template(import.path, Some(&path))?;
-                            add_to_check(import)?;
+                            let import = self.config.find_template(import.path, Some(&path));
+                            errors.check(import.and_then(&mut add_to_check));
+                        }
+                        Node::FromImport(import) if top => {
+                            let import = self.config.find_template(import.path, Some(&path));
+                            errors.check(import.and_then(&mut add_to_check));
                         }
                         Node::FilterBlock(f) => {
                             nested.push(&f.nodes);
                         }
-                        Node::Include(include) => {
-                            let include = self.config.find_template(include.path, Some(&path))?;
-                            add_to_check(include)?;
+                        Node::Let(l) => {
+                            if let Some(else_nodes) = &l.else_nodes {
+                                nested.push(else_nodes);
+                            }
+                        }
+                        Node::LetBlock(l) => {
+                            nested.push(&l.nodes);
                         }
+                        Node::With(w) => {
+                            nested.push(&w.nodes);
+                        }
+                        Node::Trans(t) => {
+                            nested.push(&t.nodes);
+                        }
+                        Node::Cache(c) => {
+                            nested.push(&c.nodes);
+                        }
+                        Node::Once(o) => {
+                            nested.push(&o.nodes);
+                        }
+                        Node::WhitespaceBlock(w) => {
+                            nested.push(&w.nodes);
+                        }
+                        Node::AutoEscape(a) => {
+                            nested.push(&a.nodes);
+                        }
+                        Node::Extension(e) => {
+                            if let Some((nodes, _)) = &e.body {
+                                nested.push(nodes);
+                            }
+                        }
+                        Node::Include(include) => match include.path {
+                            TemplatePath::Static(include) => {
+                                let include = self.config.find_template(include, Some(&path));
+                                errors.check(include.and_then(&mut add_to_check));
+                            }
+                            TemplatePath::Dynamic(_) => {
+                                for (_, include) in &self.includes {
+                                    errors.check(add_to_check(include.clone()));
+                                }
+                            }
+                        },
                         Node::BlockDef(b) => {
                             nested.push(&b.nodes);
                         }
@@ -199,19 +356,20 @@ impl TemplateInput<'_> {
                         | Node::Expr(_, _)
                         | Node::Call(_)
                         | Node::Extends(_)
-                        | Node::Let(_)
                         | Node::Import(_)
+                        | Node::FromImport(_)
                         | Node::Macro(_)
                         | Node::Raw(_)
-                        | Node::Continue(_)
-                        | Node::Break(_) => {}
+                        | Node::Continue(..)
+                        | Node::Break(..)
+                        | Node::Do(_) => {}
                     }
                 }
                 top = false;
             }
             map.insert(path, parsed);
         }
-        Ok(())
+        errors.into_result()
     }
 
     #[inline]
@@ -220,29 +378,48 @@ impl TemplateInput<'_> {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub(crate) struct TemplateArgs {
     source: Option<Source>,
     block: Option<String>,
+    pub(crate) blocks: Vec<String>,
+    pub(crate) fragments: bool,
+    includes: Vec<String>,
+    extends: Vec<String>,
+    consts: Vec<(String, String)>,
     print: Print,
     escaping: Option<String>,
+    error: Option<String>,
     ext: Option<String>,
     syntax: Option<String>,
+    syntax_inline: Option<InlineSyntax>,
     config: Option<String>,
     pub(crate) whitespace: Option<String>,
+    trim_blocks: Option<bool>,
+    lstrip_blocks: Option<bool>,
+    debug_origins: Option<bool>,
+    warn_unused: Option<bool>,
+    render_async: bool,
 }
 
 impl TemplateArgs {
     pub(crate) fn new(ast: &'_ syn::DeriveInput) -> Result<Self, CompileError> {
-        // Check that an attribute called `template()` exists once and that it is
+        Self::from_attrs(&ast.attrs)?
+            .ok_or_else(|| CompileError::no_file_info("no attribute 'template' found"))
+    }
+
+    /// Parses the `template()` attribute in `attrs`, if there is one
+    pub(crate) fn from_attrs(attrs: &[syn::Attribute]) -> Result<Option<Self>, CompileError> {
+        // Check that an attribute called `template()` exists at most once and that it is
         // the proper type (list).
         let mut template_args = None;
-        for attr in &ast.attrs {
+        for attr in attrs {
             if !attr.path().is_ident("template") {
                 continue;
             }
 
-            match attr.parse_args_with(Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated) {
+            match attr.parse_args_with(Punctuated::<TemplateArg, syn::Token![,]>::parse_terminated)
+            {
                 Ok(args) if template_args.is_none() => template_args = Some(args),
                 Ok(_) => {
                     return Err(CompileError::no_file_info(
@@ -257,8 +434,9 @@ impl TemplateArgs {
             };
         }
 
-        let template_args = template_args
-            .ok_or_else(|| CompileError::no_file_info("no attribute 'template' found"))?;
+        let Some(template_args) = template_args else {
+            return Ok(None);
+        };
 
         let mut args = Self::default();
         // Loop over the meta attributes and find everything that we
@@ -266,8 +444,32 @@ impl TemplateArgs {
         // `source` contains an enum that can represent `path` or `source`.
         for item in template_args {
             let pair = match item {
-                syn::Meta::NameValue(pair) => pair,
-                _ => {
+                TemplateArg::Array(ident, values) => {
+                    let values = values.iter().map(|s| s.value()).collect();
+                    if ident == "blocks" {
+                        args.blocks = values;
+                    } else if ident == "includes" {
+                        args.includes = values;
+                    } else if ident == "extends" {
+                        args.extends = values;
+                    } else if ident == "consts" {
+                        args.consts = values
+                            .into_iter()
+                            .map(|path| Ok((const_name(&path)?, path)))
+                            .collect::<Result<_, CompileError>>()?;
+                    } else {
+                        return Err(CompileError::no_file_info(format!(
+                            "unsupported attribute key {ident:?} found"
+                        )));
+                    }
+                    continue;
+                }
+                TemplateArg::Meta(syn::Meta::NameValue(pair)) => pair,
+                TemplateArg::Meta(syn::Meta::List(list)) if list.path.is_ident("syntax_inline") => {
+                    args.syntax_inline = Some(parse_syntax_inline(&list)?);
+                    continue;
+                }
+                TemplateArg::Meta(item) => {
                     return Err(CompileError::no_file_info(format!(
                         "unsupported attribute argument {:?}",
                         item.to_token_stream()
@@ -280,6 +482,12 @@ impl TemplateArgs {
                 None => unreachable!("not possible in syn::Meta::NameValue(…)"),
             };
 
+            if ident == "blocks" || ident == "includes" || ident == "extends" || ident == "consts" {
+                return Err(CompileError::no_file_info(format!(
+                    "{ident} value must be an array of string literals",
+                )));
+            }
+
             let value = match pair.value {
                 syn::Expr::Lit(lit) => lit,
                 syn::Expr::Group(group) => match *group.expr {
@@ -347,6 +555,20 @@ impl TemplateArgs {
                         "escape value must be string literal",
                     ));
                 }
+            } else if ident == "error" {
+                if let syn::Lit::Str(s) = value.lit {
+                    if let Err(err) = syn::parse_str::<syn::Type>(&s.value()) {
+                        return Err(CompileError::no_file_info(format!(
+                            "invalid error type {:?}: {err}",
+                            s.value(),
+                        )));
+                    }
+                    args.error = Some(s.value());
+                } else {
+                    return Err(CompileError::no_file_info(
+                        "error value must be string literal",
+                    ));
+                }
             } else if ident == "ext" {
                 if let syn::Lit::Str(s) = value.lit {
                     args.ext = Some(s.value());
@@ -379,6 +601,54 @@ impl TemplateArgs {
                         "whitespace value must be string literal",
                     ));
                 }
+            } else if ident == "trim_blocks" {
+                if let syn::Lit::Bool(b) = value.lit {
+                    args.trim_blocks = Some(b.value());
+                } else {
+                    return Err(CompileError::no_file_info(
+                        "trim_blocks value must be boolean literal",
+                    ));
+                }
+            } else if ident == "fragments" {
+                if let syn::Lit::Bool(b) = value.lit {
+                    args.fragments = b.value();
+                } else {
+                    return Err(CompileError::no_file_info(
+                        "fragments value must be boolean literal",
+                    ));
+                }
+            } else if ident == "debug_origins" {
+                if let syn::Lit::Bool(b) = value.lit {
+                    args.debug_origins = Some(b.value());
+                } else {
+                    return Err(CompileError::no_file_info(
+                        "debug_origins value must be boolean literal",
+                    ));
+                }
+            } else if ident == "warn_unused" {
+                if let syn::Lit::Bool(b) = value.lit {
+                    args.warn_unused = Some(b.value());
+                } else {
+                    return Err(CompileError::no_file_info(
+                        "warn_unused value must be boolean literal",
+                    ));
+                }
+            } else if ident == "render_async" {
+                if let syn::Lit::Bool(b) = value.lit {
+                    args.render_async = b.value();
+                } else {
+                    return Err(CompileError::no_file_info(
+                        "render_async value must be boolean literal",
+                    ));
+                }
+            } else if ident == "lstrip_blocks" {
+                if let syn::Lit::Bool(b) = value.lit {
+                    args.lstrip_blocks = Some(b.value());
+                } else {
+                    return Err(CompileError::no_file_info(
+                        "lstrip_blocks value must be boolean literal",
+                    ));
+                }
             } else {
                 return Err(CompileError::no_file_info(format!(
                     "unsupported attribute key {ident:?} found"
@@ -386,7 +656,7 @@ impl TemplateArgs {
             }
         }
 
-        Ok(args)
+        Ok(Some(args))
     }
 
     pub(crate) fn fallback() -> Self {
@@ -397,9 +667,135 @@ impl TemplateArgs {
         }
     }
 
+    /// Arguments for an `enum` without a `template()` attribute of its own
+    ///
+    /// The enum's template is empty, and its extension is taken from the template of `variant`.
+    pub(crate) fn fallback_for_variant(variant: &Self) -> Self {
+        let ext = match (&variant.ext, &variant.source) {
+            (Some(ext), _) => Some(ext.as_str()),
+            (None, Some(Source::Path(path))) => extension(Path::new(path)),
+            (None, _) => None,
+        };
+        Self {
+            source: Some(Source::Source("".into())),
+            ext: Some(ext.unwrap_or("txt").to_string()),
+            escaping: variant.escaping.clone(),
+            config: variant.config.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// Arguments for the template file `path`, without any other settings
+    #[cfg(feature = "__standalone")]
+    pub(crate) fn for_path(path: &str) -> Self {
+        Self {
+            source: Some(Source::Path(path.to_string())),
+            ..Self::default()
+        }
+    }
+
     pub(crate) fn config_path(&self) -> Option<&str> {
         self.config.as_deref()
     }
+
+    /// Arguments to render only `block` of this template
+    pub(crate) fn for_block(&self, block: &str) -> Self {
+        Self {
+            block: Some(block.to_owned()),
+            blocks: Vec::new(),
+            fragments: false,
+            print: Print::None,
+            ..self.clone()
+        }
+    }
+}
+
+/// Parses the delimiters of `syntax_inline(block_start = "<%", block_end = "%>", ..)`
+fn parse_syntax_inline(list: &syn::MetaList) -> Result<InlineSyntax, CompileError> {
+    let pairs = list
+        .parse_args_with(Punctuated::<syn::MetaNameValue, syn::Token![,]>::parse_terminated)
+        .map_err(|err| {
+            CompileError::no_file_info(format!("unable to parse `syntax_inline`: {err}"))
+        })?;
+    let mut syntax = InlineSyntax::default();
+    for pair in pairs {
+        let name = pair.path.get_ident().map(|ident| ident.to_string());
+        let Some(delimiter) = name.as_deref().and_then(|name| syntax.delimiter_mut(name)) else {
+            return Err(CompileError::no_file_info(format!(
+                "unsupported key {:?} in `syntax_inline`",
+                pair.path.to_token_stream().to_string(),
+            )));
+        };
+        let value = match pair.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(s),
+                ..
+            }) => s.value(),
+            _ => {
+                return Err(CompileError::no_file_info(format!(
+                    "{} value in `syntax_inline` must be string literal",
+                    name.unwrap_or_default(),
+                )));
+            }
+        };
+        if delimiter.replace(value).is_some() {
+            return Err(CompileError::no_file_info(format!(
+                "duplicated key {:?} in `syntax_inline`",
+                name.unwrap_or_default(),
+            )));
+        }
+    }
+    Ok(syntax)
+}
+
+/// An argument of the `template()` attribute
+///
+/// `syn` cannot parse array expressions without its "full" feature, so arrays of strings like
+/// `blocks = [..]` are parsed by hand, and everything else is parsed as [`syn::Meta`].
+enum TemplateArg {
+    Array(syn::Ident, Punctuated<syn::LitStr, syn::Token![,]>),
+    Meta(syn::Meta),
+}
+
+impl Parse for TemplateArg {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let fork = input.fork();
+        if let Ok(ident) = fork.parse::<syn::Ident>() {
+            if fork.parse::<syn::Token![=]>().is_ok() && fork.peek(syn::token::Bracket) {
+                input.advance_to(&fork);
+                let content;
+                syn::bracketed!(content in input);
+                return Ok(Self::Array(ident, Punctuated::parse_terminated(&content)?));
+            }
+        }
+        input.parse().map(Self::Meta)
+    }
+}
+
+/// Returns the name a path listed in `consts = [...]` is available as, i.e. its last segment
+fn const_name(path: &str) -> Result<String, CompileError> {
+    match syn::parse_str::<syn::Path>(path) {
+        Ok(parsed) => Ok(parsed.segments.last().unwrap().ident.to_string()),
+        Err(_) => Err(CompileError::no_file_info(format!(
+            "invalid path in `consts`: {path:?}"
+        ))),
+    }
+}
+
+/// Resolves the paths of the templates listed in `includes = [...]` or `extends = [...]`,
+/// skipping duplicates
+fn find_templates<'a>(
+    config: &Config,
+    names: &'a [String],
+    path: &Path,
+) -> Result<Vec<(&'a str, Arc<Path>)>, CompileError> {
+    let mut paths: Vec<(&str, Arc<Path>)> = Vec::with_capacity(names.len());
+    for name in names {
+        if !paths.iter().any(|(n, _)| n == name) {
+            paths.push((name, config.find_template(name, Some(path))?));
+        }
+    }
+    Ok(paths)
 }
 
 #[inline]
@@ -421,17 +817,22 @@ fn extension(path: &Path) -> Option<&str> {
     }
 }
 
-#[derive(Debug, Hash, PartialEq)]
+#[derive(Clone, Debug, Hash, PartialEq)]
 pub(crate) enum Source {
     Path(String),
     Source(Arc<str>),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Hash)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Hash)]
 pub(crate) enum Print {
     All,
     Ast,
     Code,
+    /// Write the generated code to a file
+    File,
+    /// Write the generated code and the parse tree to files
+    FileAll,
+    #[default]
     None,
 }
 
@@ -443,6 +844,8 @@ impl FromStr for Print {
             "all" => Print::All,
             "ast" => Print::Ast,
             "code" => Print::Code,
+            "file" => Print::File,
+            "file-all" => Print::FileAll,
             "none" => Print::None,
             v => {
                 return Err(CompileError::no_file_info(format!(
@@ -453,12 +856,6 @@ impl FromStr for Print {
     }
 }
 
-impl Default for Print {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 pub(crate) fn extension_to_mime_type(ext: &str) -> Mime {
     let basic_type = mime_guess::from_ext(ext).first_or_octet_stream();
     for (simple, utf_8) in &TEXT_TYPES {
@@ -496,6 +893,7 @@ fn cyclic_graph_error(dependency_graph: &[(Arc<Path>, Arc<Path>)]) -> Result<(),
 }
 
 pub(crate) fn get_template_source(
+    config: &Config,
     tpl_path: &Arc<Path>,
     import_from: Option<(&Arc<Path>, &str, &str)>,
 ) -> Result<Arc<str>, CompileError> {
@@ -503,9 +901,9 @@ pub(crate) fn get_template_source(
 
     CACHE.get_or_init(OnceMap::new).get_or_try_insert_ref(
         tpl_path,
-        (),
+        config,
         Arc::clone,
-        |_, tpl_path| match read_to_string(tpl_path) {
+        |config, tpl_path| match config.read_template(tpl_path) {
             Ok(mut source) => {
                 if source.ends_with('\n') {
                     let _ = source.pop();
@@ -576,11 +974,60 @@ mod tests {
         assert_eq!(extension(Path::new("foo-bar.jinja2")), Some("jinja2"));
     }
 
+    #[test]
+    fn test_syntax_inline() {
+        let parse = |attr: syn::Attribute| TemplateArgs::from_attrs(&[attr]).map(Option::unwrap);
+
+        let args = parse(syn::parse_quote! {
+            #[template(source = "", ext = "txt", syntax_inline(block_start = "<%", block_end = "%>"))]
+        })
+        .unwrap();
+        let syntax = args.syntax_inline.as_ref().unwrap();
+        assert_eq!(syntax.block_start.as_deref(), Some("<%"));
+        assert_eq!(syntax.block_end.as_deref(), Some("%>"));
+        assert_eq!(syntax.expr_start, None);
+        let syntax = syntax.syntax(&[], &[]).unwrap();
+        assert_eq!(syntax.block_start, "<%");
+        assert_eq!(syntax.expr_start, "{{");
+
+        let err = |attr: syn::Attribute| parse(attr).unwrap_err().msg;
+        assert_eq!(
+            err(syn::parse_quote! { #[template(syntax_inline(start = "<%"))] }),
+            r#"unsupported key "start" in `syntax_inline`"#,
+        );
+        assert_eq!(
+            err(syn::parse_quote! { #[template(syntax_inline(expr_end = 1))] }),
+            "expr_end value in `syntax_inline` must be string literal",
+        );
+        assert_eq!(
+            err(syn::parse_quote! {
+                #[template(syntax_inline(expr_end = "]]", expr_end = "]]"))]
+            }),
+            r#"duplicated key "expr_end" in `syntax_inline`"#,
+        );
+
+        let args = parse(syn::parse_quote! {
+            #[template(source = "", ext = "txt", syntax = "default", syntax_inline())]
+        })
+        .unwrap();
+        let ast = syn::parse_quote! { struct Foo; };
+        let config = Config::new("", None, None).unwrap();
+        assert_eq!(
+            TemplateInput::new(&ast, config, None, &args)
+                .err()
+                .unwrap()
+                .msg,
+            "must specify 'syntax' or 'syntax_inline', not both",
+        );
+    }
+
     #[test]
     fn get_source() {
-        let path = Config::new("", None, None)
-            .and_then(|config| config.find_template("b.html", None))
-            .unwrap();
-        assert_eq!(get_template_source(&path, None).unwrap(), "bar".into());
+        let config = Config::new("", None, None).unwrap();
+        let path = config.find_template("b.html", None).unwrap();
+        assert_eq!(
+            get_template_source(config, &path, None).unwrap(),
+            "bar".into()
+        );
     }
 }
@@ -1,20 +1,27 @@
 #![deny(elided_lifetimes_in_paths)]
 #![deny(unreachable_pub)]
 
+#[cfg(feature = "__standalone")]
+mod check;
 mod config;
 mod generator;
 mod heritage;
 mod input;
+mod provider;
 #[cfg(test)]
 mod tests;
+#[cfg(feature = "__standalone")]
+pub mod tooling;
 
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fmt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::{env, fmt, fs, iter, mem};
 
-use config::{read_config_file, Config};
-use generator::{Generator, MapChain};
+#[cfg(feature = "__standalone")]
+pub use check::{check_templates, CheckedTemplate};
+use config::{find_workspace_root, read_config_file, Config};
+use generator::{EnumVariants, Generator, MapChain, TmplKind};
 use heritage::{Context, Heritage};
 use input::{Print, TemplateArgs, TemplateInput};
 use parser::{generate_error_info, strip_common, ErrorInfo, ParseError, Parsed, WithSpan};
@@ -22,7 +29,8 @@ use parser::{generate_error_info, strip_common, ErrorInfo, ParseError, Parsed, W
 use proc_macro::TokenStream as TokenStream12;
 #[cfg(feature = "__standalone")]
 use proc_macro2::TokenStream as TokenStream12;
-use proc_macro2::{Span, TokenStream};
+use proc_macro2::{Group, Ident, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote};
 
 /// The `Template` derive macro and its `template()` attribute.
 ///
@@ -68,6 +76,9 @@ use proc_macro2::{Span, TokenStream};
 /// Enable debugging by printing nothing (`none`), the parsed syntax tree (`ast`),
 /// the generated code (`code`) or `all` for both.
 /// The requested data will be printed to stdout at compile time.
+/// `file` writes the generated code to `rinja/<StructName>.rs` in the `OUT_DIR`
+/// or `target` directory instead, and `file-all` also writes the syntax tree to
+/// `rinja/<StructName>.ast`.
 ///
 /// ### escape
 ///
@@ -90,7 +101,13 @@ use proc_macro2::{Span, TokenStream};
 pub fn derive_template(input: TokenStream12) -> TokenStream12 {
     let ast = syn::parse2(input.into()).unwrap();
     match build_template(&ast) {
-        Ok(source) => source.parse().unwrap(),
+        Ok(source) => {
+            let code: TokenStream = source.parse().unwrap();
+            match source.contains(UNUSED_TEMPLATE_CODE) {
+                true => respan_warnings(code, ast.ident.span()).into(),
+                false => code.into(),
+            }
+        }
         Err(e) => {
             let mut e = e.into_compile_error();
             if let Ok(source) = build_skeleton(&ast) {
@@ -102,10 +119,29 @@ pub fn derive_template(input: TokenStream12) -> TokenStream12 {
     }
 }
 
+/// The generator emits a warning as the use of a deprecated item. `rustc` does not report
+/// warnings in code that was generated by a macro, so the uses get the span of the template's type.
+fn respan_warnings(code: TokenStream, span: Span) -> TokenStream {
+    code.into_iter()
+        .map(|token| match token {
+            TokenTree::Ident(ident) if ident == UNUSED_TEMPLATE_CODE => {
+                TokenTree::Ident(Ident::new(UNUSED_TEMPLATE_CODE, span))
+            }
+            TokenTree::Group(group) => {
+                let mut new_group =
+                    Group::new(group.delimiter(), respan_warnings(group.stream(), span));
+                new_group.set_span(group.span());
+                TokenTree::Group(new_group)
+            }
+            token => token,
+        })
+        .collect()
+}
+
 fn build_skeleton(ast: &syn::DeriveInput) -> Result<String, CompileError> {
     let template_args = TemplateArgs::fallback();
     let config = Config::new("", None, None)?;
-    let input = TemplateInput::new(ast, config, &template_args)?;
+    let input = TemplateInput::new(ast, config, None, &template_args)?;
     let mut contexts = HashMap::new();
     let parsed = parser::Parsed::default();
     contexts.insert(&input.path, Context::empty(&parsed));
@@ -117,7 +153,8 @@ fn build_skeleton(ast: &syn::DeriveInput) -> Result<String, CompileError> {
         input.block.is_some(),
         0,
     )
-    .build(&contexts[&input.path])
+    .build(&contexts[&input.path], TmplKind::Struct)
+    .map(|(code, _)| code)
 }
 
 /// Takes a `syn::DeriveInput` and generates source code for it
@@ -128,43 +165,279 @@ fn build_skeleton(ast: &syn::DeriveInput) -> Result<String, CompileError> {
 /// the parse tree and/or generated source according to the `print` key's
 /// value as passed to the `template()` attribute.
 pub(crate) fn build_template(ast: &syn::DeriveInput) -> Result<String, CompileError> {
+    if let syn::Data::Enum(data) = &ast.data {
+        if let Some(code) = build_template_enum(ast, data)? {
+            return Ok(code);
+        }
+    }
     let template_args = TemplateArgs::new(ast)?;
+    build_template_item(ast, &template_args, TmplKind::Struct).map(|(code, _)| code)
+}
+
+/// Generates the implementations for an `enum` with per-variant templates
+///
+/// For every variant that has a `template()` attribute of its own, a hidden `struct` is generated,
+/// which borrows the fields of the variant and dereferences to the enum, and `Template` is
+/// implemented for it. The enum's
+/// `Template` implementation dispatches to these structs in a `match self`. Variants without
+/// a template of their own are rendered by the enum's template.
+///
+/// Returns `None` if no variant has a `template()` attribute.
+fn build_template_enum(
+    ast: &syn::DeriveInput,
+    data: &syn::DataEnum,
+) -> Result<Option<String>, CompileError> {
+    let mut code = String::new();
+    let mut variants = EnumVariants {
+        arms: String::new(),
+        size_hint: 0,
+        exhaustive: true,
+    };
+    let mut first_args = None;
+    for variant in &data.variants {
+        let Some(variant_args) = TemplateArgs::from_attrs(&variant.attrs)? else {
+            variants.exhaustive = false;
+            continue;
+        };
+
+        let variant_ast = variant_struct(ast, variant);
+        let (variant_code, size_hint) =
+            build_template_item(&variant_ast, &variant_args, TmplKind::Variant)?;
+        code.push_str(&quote!(#variant_ast).to_string());
+        code.push_str(&variant_deref(ast, &variant_ast).to_string());
+        code.push_str(&variant_code);
+        variants.size_hint = variants.size_hint.max(size_hint);
+
+        let var_ident = &variant.ident;
+        let struct_ident = &variant_ast.ident;
+        let (pattern, value) = match &variant.fields {
+            syn::Fields::Named(fields) => {
+                let names = fields.named.iter().map(|f| &f.ident).collect::<Vec<_>>();
+                (
+                    quote!(Self::#var_ident { #(#names,)* }),
+                    quote!(#struct_ident {
+                        #(#names,)*
+                        _rinja_parent: self,
+                    }),
+                )
+            }
+            syn::Fields::Unnamed(fields) => {
+                let names = (0..fields.unnamed.len())
+                    .map(|i| format_ident!("_rinja_{i}"))
+                    .collect::<Vec<_>>();
+                (
+                    quote!(Self::#var_ident(#(#names,)*)),
+                    quote!(#struct_ident(#(#names,)* self)),
+                )
+            }
+            syn::Fields::Unit => (
+                quote!(Self::#var_ident),
+                quote!(#struct_ident {
+                    _rinja_parent: self,
+                }),
+            ),
+        };
+        variants.arms.push_str(&format!(
+            "{pattern} => return {CRATE}::Template::render_into_with_values(\
+                &{value}, writer, __rinja_values\
+            ),\n",
+        ));
+        first_args.get_or_insert(variant_args);
+    }
+
+    let Some(first_args) = first_args else {
+        return Ok(None);
+    };
+    let template_args = match TemplateArgs::from_attrs(&ast.attrs)? {
+        Some(template_args) => template_args,
+        None if variants.exhaustive => TemplateArgs::fallback_for_variant(&first_args),
+        None => {
+            return Err(CompileError::no_file_info(
+                "no attribute 'template' found on the enum, which is needed to render the \
+                 variants without a 'template' attribute",
+            ));
+        }
+    };
+    let (enum_code, _) = build_template_item(ast, &template_args, TmplKind::Enum(&variants))?;
+    code.push_str(&enum_code);
+    let allow_attrs = allow_attrs(ast);
+    Ok(Some(format!(
+        "{}\nconst _: () = {{\n{code}\n}};",
+        quote!(#(#allow_attrs)*)
+    )))
+}
+
+/// The `#[allow(...)]` attributes of the template's type, which also apply to the warnings about
+/// unused template code
+fn allow_attrs(ast: &syn::DeriveInput) -> Vec<&syn::Attribute> {
+    ast.attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("allow"))
+        .collect()
+}
+
+/// Generates a `struct` that borrows the fields of an `enum` variant
+///
+/// Its last field borrows the enum itself, see [`variant_deref()`].
+fn variant_struct(ast: &syn::DeriveInput, variant: &syn::Variant) -> syn::DeriveInput {
+    let lifetime = syn::Lifetime::new("'rinja_a", Span::call_site());
+    let enum_ident = &ast.ident;
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+    let parent: syn::Type = syn::parse_quote!(&#lifetime #enum_ident #ty_generics);
+
+    let borrow = |field: &syn::Field| syn::Field {
+        attrs: Vec::new(),
+        vis: syn::Visibility::Inherited,
+        ty: {
+            let ty = &field.ty;
+            syn::parse_quote!(&#lifetime #ty)
+        },
+        ..field.clone()
+    };
+    let fields = match &variant.fields {
+        syn::Fields::Unnamed(fields) => {
+            let mut fields = fields.clone();
+            fields.unnamed = fields.unnamed.iter().map(borrow).collect();
+            fields.unnamed.push(syn::parse_quote!(#parent));
+            syn::Fields::Unnamed(fields)
+        }
+        syn::Fields::Named(fields) => {
+            let mut fields = fields.clone();
+            fields.named = fields.named.iter().map(borrow).collect();
+            fields.named.push(syn::parse_quote!(_rinja_parent: #parent));
+            syn::Fields::Named(fields)
+        }
+        syn::Fields::Unit => syn::Fields::Named(syn::parse_quote!({ _rinja_parent: #parent })),
+    };
+    let semi_token = match fields {
+        syn::Fields::Unnamed(_) => Some(Default::default()),
+        _ => None,
+    };
+
+    let mut generics = ast.generics.clone();
+    generics.params.insert(
+        0,
+        syn::GenericParam::Lifetime(syn::LifetimeParam::new(lifetime)),
+    );
+    syn::DeriveInput {
+        attrs: vec![syn::parse_quote!(#[allow(dead_code, non_camel_case_types)])],
+        vis: syn::Visibility::Inherited,
+        ident: format_ident!("__Rinja__{}__{}", enum_ident, variant.ident),
+        generics,
+        data: syn::Data::Struct(syn::DataStruct {
+            struct_token: Default::default(),
+            fields,
+            semi_token,
+        }),
+    }
+}
+
+/// Implements `Deref` for the `struct` of an `enum` variant, so the template can call the methods
+/// of the enum, like the template of a block can call the methods of its template
+fn variant_deref(ast: &syn::DeriveInput, variant_ast: &syn::DeriveInput) -> TokenStream {
+    let enum_ident = &ast.ident;
+    let (_, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let ident = &variant_ast.ident;
+    let (impl_generics, variant_ty_generics, _) = variant_ast.generics.split_for_impl();
+    let parent = match &variant_ast.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Unnamed(fields),
+            ..
+        }) => syn::Member::from(fields.unnamed.len() - 1),
+        _ => syn::Member::from(format_ident!("_rinja_parent")),
+    };
+    quote! {
+        impl #impl_generics ::core::ops::Deref for #ident #variant_ty_generics #where_clause {
+            type Target = #enum_ident #ty_generics;
+
+            #[inline]
+            fn deref(&self) -> &Self::Target {
+                self.#parent
+            }
+        }
+    }
+}
+
+/// Writes the debugging output of `print = "file"` to `rinja/<ident>.<ext>`
+///
+/// The directory is the `OUT_DIR` of crates with a build script, or else the `target` directory
+/// of the workspace or crate.
+fn write_print_file(ident: &Ident, ext: &str, content: &str) -> Result<(), CompileError> {
+    let dir = match (env::var_os("OUT_DIR"), env::var_os("CARGO_TARGET_DIR")) {
+        (Some(dir), _) => PathBuf::from(dir),
+        (None, Some(dir)) => PathBuf::from(dir),
+        (None, None) => {
+            let root = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+            find_workspace_root(&root).unwrap_or(&root).join("target")
+        }
+    }
+    .join("rinja");
+    let path = dir.join(format!("{ident}.{ext}"));
+    fs::create_dir_all(&dir)
+        .and_then(|()| fs::write(&path, content))
+        .map_err(|err| {
+            CompileError::no_file_info(format!("unable to write {:?}: {err}", path.display()))
+        })
+}
+
+/// Generates the implementations for a single template
+///
+/// Returns the generated code and the template's size hint.
+fn build_template_item(
+    ast: &syn::DeriveInput,
+    template_args: &TemplateArgs,
+    kind: TmplKind<'_>,
+) -> Result<(String, usize), CompileError> {
     let config_path = template_args.config_path();
-    let s = read_config_file(config_path)?;
+    let (s, config_file) = read_config_file(config_path)?;
     let config = Config::new(&s, config_path, template_args.whitespace.as_deref())?;
-    let input = TemplateInput::new(ast, config, &template_args)?;
+    let mut input = TemplateInput::new(ast, config, config_file.as_deref(), template_args)?;
+    input.borrowed_fields = matches!(kind, TmplKind::Variant);
 
     let mut templates = HashMap::new();
     input.find_used_templates(&mut templates)?;
 
     let mut contexts = HashMap::new();
-    for (path, parsed) in &templates {
-        contexts.insert(path, Context::new(input.config, path, parsed)?);
+    let mut errors = CompileErrors::default();
+    let mut paths: Vec<_> = templates.keys().collect();
+    paths.sort();
+    for path in paths {
+        let parsed = &templates[path];
+        if let Some(ctx) = errors.check(Context::new(input.config, path, parsed)) {
+            contexts.insert(path, ctx);
+        }
     }
+    errors.into_result()?;
 
     let ctx = &contexts[&input.path];
-    let heritage = if !ctx.blocks.is_empty() || ctx.extends.is_some() {
-        let heritage = Heritage::new(ctx, &contexts);
-
-        if let Some(block_name) = input.block {
-            if !heritage.blocks.contains_key(&block_name) {
-                return Err(CompileError::no_file_info(format!(
-                    "cannot find block {}",
-                    block_name
-                )));
+    // With a dynamic `{% extends %}`, the generator builds the heritage for every candidate.
+    let heritage =
+        if ctx.dynamic_extends.is_none() && (!ctx.blocks.is_empty() || ctx.extends.is_some()) {
+            let heritage = Heritage::new(ctx, &contexts)?;
+
+            if let Some(block_name) = input.block {
+                if !heritage.blocks.contains_key(&block_name) {
+                    return Err(CompileError::no_file_info(format!(
+                        "cannot find block {}",
+                        block_name
+                    )));
+                }
             }
-        }
 
-        Some(heritage)
-    } else {
-        None
-    };
+            Some(heritage)
+        } else {
+            None
+        };
 
     if input.print == Print::Ast || input.print == Print::All {
         eprintln!("{:?}", templates[&input.path].nodes());
     }
+    if input.print == Print::FileAll {
+        let nodes = format!("{:#?}\n", templates[&input.path].nodes());
+        write_print_file(&ast.ident, "ast", &nodes)?;
+    }
 
-    let code = Generator::new(
+    let (code, size_hint) = Generator::new(
         &input,
         &contexts,
         heritage.as_ref(),
@@ -172,28 +445,154 @@ pub(crate) fn build_template(ast: &syn::DeriveInput) -> Result<String, CompileEr
         input.block.is_some(),
         0,
     )
-    .build(&contexts[&input.path])?;
+    .build(&contexts[&input.path], kind)?;
     if input.print == Print::Code || input.print == Print::All {
         eprintln!("{code}");
     }
-    Ok(code)
+    if input.print == Print::File || input.print == Print::FileAll {
+        write_print_file(&ast.ident, "rs", &code)?;
+    }
+
+    let mut blocks: Vec<&str> = template_args.blocks.iter().map(String::as_str).collect();
+    if let (true, Some(heritage)) = (template_args.fragments, &heritage) {
+        let mut names: Vec<&str> = heritage
+            .blocks
+            .keys()
+            .copied()
+            .filter(|name| !blocks.contains(name))
+            .collect();
+        names.sort_unstable();
+        blocks.extend(names);
+    }
+
+    let mut code = code;
+    for block in blocks {
+        code.push_str(&build_block(ast, template_args, block)?);
+    }
+    Ok((code, size_hint))
+}
+
+/// Generates `render_{block}()`, `render_{block}_into()` and `{block}_fragment()` methods for a
+/// block listed in `blocks = [...]`, or for every block if `fragments = true`
+///
+/// A hidden `struct` is generated, which borrows the template and dereferences to it, so the
+/// block can access the template's fields. `Template` is implemented for it like for a template
+/// with a `block = "..."` attribute.
+fn build_block(
+    ast: &syn::DeriveInput,
+    template_args: &TemplateArgs,
+    block: &str,
+) -> Result<String, CompileError> {
+    let Ok(block_ident) = syn::parse_str::<syn::Ident>(block) else {
+        return Err(CompileError::no_file_info(format!(
+            "invalid block name {block:?} in 'blocks'",
+        )));
+    };
+
+    let lifetime = syn::Lifetime::new("'rinja_a", Span::call_site());
+    let ident = &ast.ident;
+    let vis = &ast.vis;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let mut generics = ast.generics.clone();
+    generics.params.insert(
+        0,
+        syn::GenericParam::Lifetime(syn::LifetimeParam::new(lifetime.clone())),
+    );
+    let block_ast = syn::DeriveInput {
+        attrs: vec![syn::parse_quote!(#[allow(non_camel_case_types)])],
+        vis: syn::Visibility::Inherited,
+        ident: format_ident!("__Rinja__{}__block_{}", ident, block_ident),
+        generics,
+        data: syn::Data::Struct(syn::DataStruct {
+            struct_token: Default::default(),
+            fields: syn::Fields::Unnamed(syn::parse_quote!((&#lifetime #ident #ty_generics))),
+            semi_token: Some(Default::default()),
+        }),
+    };
+    let (block_code, _) =
+        build_template_item(&block_ast, &template_args.for_block(block), TmplKind::Block)?;
+    let block_code: TokenStream = block_code.parse().unwrap();
+
+    let krate: TokenStream = CRATE.parse().unwrap();
+    let render = format_ident!("render_{}", block_ident);
+    let render_into = format_ident!("render_{}_into", block_ident);
+    let render_doc = format!("Renders the block `{block}` of the template into a new `String`");
+    let render_into_doc = format!("Renders the block `{block}` of the template into `writer`");
+    let fragment = format_ident!("{}_fragment", block_ident);
+    let fragment_doc =
+        format!("Borrows the template as a `Template` that only renders the block `{block}`");
+    let block_ident = &block_ast.ident;
+    let (block_impl_generics, block_ty_generics, _) = block_ast.generics.split_for_impl();
+    Ok(quote! {
+        const _: () = {
+            #block_ast
+
+            #block_code
+
+            impl #block_impl_generics ::core::ops::Deref for #block_ident #block_ty_generics
+            #where_clause
+            {
+                type Target = #ident #ty_generics;
+
+                #[inline]
+                fn deref(&self) -> &Self::Target {
+                    self.0
+                }
+            }
+
+            impl #impl_generics #ident #ty_generics #where_clause {
+                #[doc = #render_doc]
+                #[inline]
+                #vis fn #render(&self) -> #krate::Result<#krate::helpers::alloc::string::String> {
+                    #krate::Template::render(&#block_ident(self))
+                }
+
+                #[doc = #render_into_doc]
+                #[inline]
+                #vis fn #render_into<RinjaW>(&self, writer: &mut RinjaW) -> #krate::Result<()>
+                where
+                    RinjaW: ::core::fmt::Write + ?::core::marker::Sized,
+                {
+                    #krate::Template::render_into(&#block_ident(self), writer)
+                }
+
+                #[doc = #fragment_doc]
+                #[inline]
+                #vis fn #fragment(&self) -> impl #krate::Template + '_ {
+                    #block_ident(self)
+                }
+            }
+        };
+    }
+    .to_string())
 }
 
 #[derive(Debug, Clone)]
 struct CompileError {
     msg: String,
     span: Span,
+    /// The template file the error was found in, and the byte offset of the error in it
+    #[cfg_attr(not(feature = "__standalone"), allow(dead_code))]
+    location: Option<(PathBuf, Option<usize>)>,
+    /// Independent errors that are reported together with this one
+    others: Vec<CompileError>,
 }
 
 impl CompileError {
     fn new<S: fmt::Display>(msg: S, file_info: Option<FileInfo<'_>>) -> Self {
-        let msg = match file_info {
-            Some(file_info) => format!("{msg}{file_info}"),
-            None => msg.to_string(),
+        let (msg, location) = match file_info {
+            Some(file_info) => (
+                format!("{msg}{file_info}"),
+                Some((file_info.path.to_path_buf(), file_info.offset())),
+            ),
+            None => (msg.to_string(), None),
         };
         Self {
             msg,
             span: Span::call_site(),
+            location,
+            others: Vec::new(),
         }
     }
 
@@ -201,11 +600,54 @@ impl CompileError {
         Self {
             msg: msg.to_string(),
             span: Span::call_site(),
+            location: None,
+            others: Vec::new(),
         }
     }
 
+    /// Reports `other`, and all errors reported with it, together with this error
+    fn combine(&mut self, mut other: CompileError) {
+        let others = mem::take(&mut other.others);
+        self.others.push(other);
+        self.others.extend(others);
+    }
+
+    /// Returns this error, followed by all errors that are reported with it
+    fn iter(&self) -> impl Iterator<Item = &CompileError> {
+        iter::once(self).chain(&self.others)
+    }
+
     fn into_compile_error(self) -> TokenStream {
-        syn::Error::new(self.span, self.msg).to_compile_error()
+        let mut error = syn::Error::new(self.span, self.msg);
+        for other in self.others {
+            error.combine(syn::Error::new(other.span, other.msg));
+        }
+        error.to_compile_error()
+    }
+}
+
+/// Collects the errors of independent steps, so all of them are reported at once
+#[derive(Default)]
+struct CompileErrors(Option<CompileError>);
+
+impl CompileErrors {
+    fn push(&mut self, err: CompileError) {
+        match &mut self.0 {
+            Some(error) => error.combine(err),
+            None => self.0 = Some(err),
+        }
+    }
+
+    /// Returns the value of `result`, or records its error
+    fn check<T>(&mut self, result: Result<T, CompileError>) -> Option<T> {
+        result.map_err(|err| self.push(err)).ok()
+    }
+
+    fn into_result(self) -> Result<(), CompileError> {
+        match self.0 {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
     }
 }
 
@@ -214,7 +656,13 @@ impl std::error::Error for CompileError {}
 impl fmt::Display for CompileError {
     #[inline]
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt.write_str(&self.msg)
+        for (idx, err) in self.iter().enumerate() {
+            if idx > 0 {
+                fmt.write_str("\n")?;
+            }
+            fmt.write_str(&err.msg)?;
+        }
+        Ok(())
     }
 }
 
@@ -222,7 +670,18 @@ impl From<ParseError> for CompileError {
     #[inline]
     fn from(e: ParseError) -> Self {
         // It already has the correct message so no need to do anything.
-        Self::no_file_info(e)
+        let location = match &e {
+            ParseError::Details {
+                file_path: Some(file_path),
+                offset,
+                ..
+            } => Some((file_path.to_path_buf(), Some(*offset))),
+            _ => None,
+        };
+        Self {
+            location,
+            ..Self::no_file_info(e)
+        }
     }
 }
 
@@ -248,6 +707,16 @@ impl<'a> FileInfo<'a> {
             node_source: Some(node.span()),
         }
     }
+
+    /// The byte offset of the node in the source
+    fn offset(&self) -> Option<usize> {
+        Some(span_offset(self.source?, self.node_source?))
+    }
+}
+
+/// The byte offset of `span`, which is a suffix of `source`, in `source`
+fn span_offset(source: &str, span: &str) -> usize {
+    span.as_ptr() as usize - source.as_ptr() as usize
 }
 
 impl fmt::Display for FileInfo<'_> {
@@ -300,23 +769,38 @@ impl fmt::Display for MsgValidEscapers<'_> {
 // in the const vector based on features seems impossible right now).
 const BUILT_IN_FILTERS: &[&str] = &[
     "abs",
+    "batch",
+    "capfirst",
     "capitalize",
     "center",
+    "currency",
+    "date",
+    "default",
     "e",
     "escape",
     "filesizeformat",
     "fmt",
+    "forceescape",
     "format",
+    "group_by",
     "indent",
     "into_f64",
     "into_isize",
     "join",
     "linebreaks",
     "linebreaksbr",
+    "ljust",
     "lower",
     "lowercase",
+    "map",
+    "markdown",
     "paragraphbreaks",
+    "reltime",
+    "rjust",
     "safe",
+    "slice",
+    "sort",
+    "sort_by",
     "title",
     "trim",
     "truncate",
@@ -329,12 +813,23 @@ const BUILT_IN_FILTERS: &[&str] = &[
     "json",
 ];
 
+/// The name of the deprecated item the generator uses to emit warnings
+const UNUSED_TEMPLATE_CODE: &str = "UnusedTemplateCode";
+
 const CRATE: &str = if cfg!(feature = "with-actix-web") {
     "::rinja_actix"
 } else if cfg!(feature = "with-axum") {
     "::rinja_axum"
+} else if cfg!(feature = "with-ntex") {
+    "::rinja_ntex"
+} else if cfg!(feature = "with-poem") {
+    "::rinja_poem"
 } else if cfg!(feature = "with-rocket") {
     "::rinja_rocket"
+} else if cfg!(feature = "with-salvo") {
+    "::rinja_salvo"
+} else if cfg!(feature = "with-tide") {
+    "::rinja_tide"
 } else if cfg!(feature = "with-warp") {
     "::rinja_warp"
 } else {
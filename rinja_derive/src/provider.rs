@@ -0,0 +1,150 @@
+//! Places templates are loaded from
+//!
+//! [`Config::find_template()`](crate::config::Config::find_template) asks the configured
+//! providers in order for a template, and the source of a template is read from the provider
+//! that contains its path.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "__standalone")]
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub(crate) trait TemplateProvider: Debug + Send + Sync {
+    /// Returns the path of the template `name`, if this provider has a template of that name
+    fn find(&self, name: &str) -> Option<PathBuf>;
+
+    /// Returns `true` if the template at `path` belongs to this provider
+    fn contains(&self, path: &Path) -> bool;
+
+    /// Reads the source of the template at `path`
+    fn read(&self, path: &Path) -> io::Result<String>;
+
+    /// Returns `true` if the templates are files, so the generated code can depend on them
+    fn is_file(&self) -> bool;
+
+    /// Adds the name of every template of this provider to `names`
+    #[cfg(feature = "__standalone")]
+    fn collect_names(&self, names: &mut BTreeSet<String>) -> Result<(), String>;
+}
+
+/// Template files in the directories configured with `dirs`
+#[derive(Debug)]
+pub(crate) struct Directories(pub(crate) Vec<PathBuf>);
+
+impl TemplateProvider for Directories {
+    fn find(&self, name: &str) -> Option<PathBuf> {
+        self.0
+            .iter()
+            .map(|dir| dir.join(name))
+            .find(|path| path.exists())
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn is_file(&self) -> bool {
+        true
+    }
+
+    #[cfg(feature = "__standalone")]
+    fn collect_names(&self, names: &mut BTreeSet<String>) -> Result<(), String> {
+        for dir in &self.0 {
+            collect_files(dir, dir, names)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "__standalone")]
+fn collect_files(root: &Path, dir: &Path, names: &mut BTreeSet<String>) -> Result<(), String> {
+    // The default `templates` directory does not need to exist.
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let path = entry
+            .map_err(|err| format!("unable to read {:?}: {err}", dir.display()))?
+            .path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if file_name.starts_with('.') {
+            continue;
+        } else if path.is_dir() {
+            collect_files(root, &path, names)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let name = relative
+                .components()
+                .map(|component| component.as_os_str().to_str())
+                .collect::<Option<Vec<_>>>();
+            if let Some(name) = name {
+                names.insert(name.join("/"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Templates defined in memory, e.g. in the `[templates]` table of `rinja.toml`
+///
+/// The templates get paths below `root`, which must not be a directory, so relative paths in
+/// `{% include %}` etc. work like they do for files, but the paths never refer to files.
+#[derive(Debug)]
+pub(crate) struct InMemory {
+    root: PathBuf,
+    templates: BTreeMap<PathBuf, Arc<str>>,
+}
+
+impl InMemory {
+    pub(crate) fn new<'a>(
+        root: PathBuf,
+        templates: impl IntoIterator<Item = (&'a str, String)>,
+    ) -> Self {
+        let templates = templates
+            .into_iter()
+            .map(|(name, source)| (root.join(name), Arc::from(source)))
+            .collect();
+        Self { root, templates }
+    }
+}
+
+impl TemplateProvider for InMemory {
+    fn find(&self, name: &str) -> Option<PathBuf> {
+        let path = self.root.join(name);
+        self.templates.contains_key(&path).then_some(path)
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.templates.contains_key(path)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<String> {
+        match self.templates.get(path) {
+            Some(source) => Ok(source.to_string()),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn is_file(&self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "__standalone")]
+    fn collect_names(&self, names: &mut BTreeSet<String>) -> Result<(), String> {
+        for path in self.templates.keys() {
+            if let Some(name) = path.strip_prefix(&self.root).ok().and_then(Path::to_str) {
+                names.insert(name.to_owned());
+            }
+        }
+        Ok(())
+    }
+}
@@ -24,25 +24,81 @@ struct Foo;"##
         for line in expected.split('\n') {
             new_expected.write_fmt(format_args!("{line}\n")).unwrap();
         }
+        // With the feature `"tracing"`, the template is rendered in a span.
+        if cfg!(feature = "tracing") {
+            new_expected = format!(
+                "{{\nlet __rinja_span = ::rinja::helpers::tracing::info_span!(\"Foo.txt\").entered();\n\
+                {new_expected}}}\n"
+            );
+        }
+        let write_into = match cfg!(feature = "std") {
+            true => {
+                "#[inline]
+    fn write_into<RinjaW>(&self, writer: &mut RinjaW) -> ::rinja::helpers::std::io::Result<()>
+    where
+        RinjaW: ::rinja::helpers::std::io::Write + ?::core::marker::Sized,
+    {
+        let mut writer = ::rinja::helpers::IoWriter::new(writer);
+        let result = <Self as ::rinja::Template>::render_into(self, &mut writer);
+        writer.finish(result)
+    }"
+            }
+            false => "",
+        };
         let expected = format!(
-            r#"impl ::rinja::Template for Foo {{
-    fn render_into<RinjaW>(&self, writer: &mut RinjaW) -> ::rinja::Result<()>
+            r#"impl ::rinja::helpers::RenderSink for Foo {{
+    fn render_into_sink<RinjaW>(
+        &self,
+        writer: &mut ::rinja::helpers::Sink<'_, RinjaW>,
+        __rinja_values: &dyn ::rinja::Values
+    ) -> ::rinja::Result<()>
     where
         RinjaW: ::core::fmt::Write + ?::core::marker::Sized,
     {{
         use ::rinja::filters::AutoEscape as _;
+        use ::rinja::helpers::ContainsItem as _;
+        use ::rinja::helpers::WriteWritable as _;
         use ::core::fmt::Write as _;
         {new_expected}
         ::rinja::Result::Ok(())
     }}
-    const EXTENSION: ::std::option::Option<&'static ::std::primitive::str> = Some("txt");
-    const SIZE_HINT: ::std::primitive::usize = 3;
-    const MIME_TYPE: &'static ::std::primitive::str = "text/plain; charset=utf-8";
 }}
-impl ::std::fmt::Display for Foo {{
+impl ::rinja::Template for Foo {{
     #[inline]
-    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {{
-        ::rinja::Template::render_into(self, f).map_err(|_| ::std::fmt::Error {{}})
+    fn render_into_with_values<RinjaW>(
+        &self,
+        writer: &mut RinjaW,
+        values: &dyn ::rinja::Values
+    ) -> ::rinja::Result<()>
+    where
+        RinjaW: ::core::fmt::Write + ?::core::marker::Sized,
+    {{
+        <Self as ::rinja::helpers::RenderSink>::render_into_sink(
+            self,
+            &mut ::rinja::helpers::Sink::new(writer),
+            values
+        )
+    }}
+    #[inline]
+    fn render_into<RinjaW>(&self, writer: &mut RinjaW) -> ::rinja::Result<()>
+    where
+        RinjaW: ::core::fmt::Write + ?::core::marker::Sized,
+    {{
+        <Self as ::rinja::Template>::render_into_with_values(self, writer, &())
+    }}
+    #[inline]
+    fn render_chunks(&self) -> ::rinja::RenderChunks<'static> {{
+        ::rinja::helpers::render_chunks(self)
+    }}
+    {write_into}
+    const EXTENSION: ::core::option::Option<&'static ::core::primitive::str> = Some("txt");
+    const SIZE_HINT: ::core::primitive::usize = 3;
+    const MIME_TYPE: &'static ::core::primitive::str = "text/plain; charset=utf-8";
+}}
+impl ::core::fmt::Display for Foo {{
+    #[inline]
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {{
+        ::rinja::Template::render_into(self, f).map_err(|_| ::core::fmt::Error {{}})
     }}
 }}"#
         );
@@ -60,11 +116,7 @@ impl ::std::fmt::Display for Foo {{
     compare(
         "{% if let Some(query) = s && !query.is_empty() %}{{query}}{% endif %}",
         r#"if let Some(query,) = &self.s && !query.is_empty() {
-    ::std::write!(
-        writer,
-        "{expr0}",
-        expr0 = &(&&::rinja::filters::AutoEscaper::new(&(query), ::rinja::filters::Text)).rinja_auto_escape()?,
-    )?;
+    (&&::rinja::helpers::Writable(&((&&::rinja::filters::AutoEscaper::new(&(query), ::rinja::filters::Text)).rinja_auto_escape()?))).rinja_write(writer)?;
 }"#,
     );
 
@@ -73,11 +125,7 @@ impl ::std::fmt::Display for Foo {{
     compare(
         "{% if let Some(s) = s %}{{ s }}{% endif %}",
         r#"if let Some(s,) = &self.s {
-    ::std::write!(
-        writer,
-        "{expr0}",
-        expr0 = &(&&::rinja::filters::AutoEscaper::new(&(s), ::rinja::filters::Text)).rinja_auto_escape()?,
-    )?;
+    (&&::rinja::helpers::Writable(&((&&::rinja::filters::AutoEscaper::new(&(s), ::rinja::filters::Text)).rinja_auto_escape()?))).rinja_write(writer)?;
 }"#,
     );
 
@@ -86,11 +134,249 @@ impl ::std::fmt::Display for Foo {{
     compare(
         "{% if let Some(s) = s && !s.is_empty() %}{{s}}{% endif %}",
         r#"if let Some(s,) = &self.s && !s.is_empty() {
-    ::std::write!(
-        writer,
-        "{expr0}",
-        expr0 = &(&&::rinja::filters::AutoEscaper::new(&(s), ::rinja::filters::Text)).rinja_auto_escape()?,
-    )?;
+    (&&::rinja::helpers::Writable(&((&&::rinja::filters::AutoEscaper::new(&(s), ::rinja::filters::Text)).rinja_auto_escape()?))).rinja_write(writer)?;
 }"#,
     );
 }
+
+#[test]
+fn check_print_file() {
+    let out_dir = std::env::temp_dir().join(format!("rinja-print-file-{}", std::process::id()));
+    std::env::set_var("OUT_DIR", &out_dir);
+    let ast = syn::parse_str::<syn::DeriveInput>(
+        r#"#[template(source = "Hello {{ name }}!", ext = "txt", print = "file-all")]
+        struct PrintFile;"#,
+    )
+    .unwrap();
+    build_template(&ast).unwrap();
+    std::env::remove_var("OUT_DIR");
+
+    let code = std::fs::read_to_string(out_dir.join("rinja").join("PrintFile.rs")).unwrap();
+    assert!(
+        code.contains("impl ::rinja::Template for PrintFile"),
+        "{code}"
+    );
+    let nodes = std::fs::read_to_string(out_dir.join("rinja").join("PrintFile.ast")).unwrap();
+    assert!(nodes.contains("Expr("), "{nodes}");
+    std::fs::remove_dir_all(out_dir).unwrap();
+}
+
+#[test]
+fn check_dependencies() {
+    // The generated code must depend on all used template files, even if only a block of the
+    // template is rendered.
+    let ast = syn::parse_str::<syn::DeriveInput>(
+        r##"#[template(
+            source = r#"{% include "a.html" %}{% block x %}{% include "sub/b.html" %}{% endblock %}"#,
+            ext = "txt",
+            block = "x",
+        )]
+        struct Foo;"##,
+    )
+    .unwrap();
+    let generated = build_template(&ast).unwrap();
+
+    let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("templates");
+    for path in ["a.html", "sub/b.html"] {
+        let path = root.join(path);
+        let path = path.to_str().unwrap();
+        assert!(
+            generated.contains(&format!(":: core :: include_bytes ! ({path:?})")),
+            "missing dependency on {path:?} in:\n{generated}"
+        );
+    }
+}
+
+#[test]
+fn check_coalesced_lits() {
+    // Static text separated only by comments, `{% let %}` or an `{% include %}` of static text
+    // is written in a single call.
+    let ast = syn::parse_str::<syn::DeriveInput>(
+        r##"#[template(
+            source = r#"a{# comment #}b{% let x = 1 %}c{% include "a.html" %}d{{ x }}e"#,
+            ext = "txt",
+        )]
+        struct Foo;"##,
+    )
+    .unwrap();
+    let generated = build_template(&ast).unwrap();
+    let writes: Vec<&str> = generated
+        .lines()
+        .filter(|line| {
+            line.starts_with("writer.write_") || line.starts_with("(&&::rinja::helpers::")
+        })
+        .collect();
+    // With the feature `"tracing"`, an included template is rendered in its own span.
+    if cfg!(feature = "tracing") {
+        assert_eq!(
+            writes,
+            [
+                r#"writer.write_str("abc")?;"#,
+                r#"writer.write_str("foo")?;"#,
+                r#"writer.write_str("d")?;"#,
+                "(&&::rinja::helpers::Writable(&((&&::rinja::filters::AutoEscaper::new(&(x), \
+                 ::rinja::filters::Text)).rinja_auto_escape()?))).rinja_write(writer)?;",
+                r#"writer.write_str("e")?;"#,
+            ],
+        );
+        return;
+    }
+    assert_eq!(
+        writes,
+        [
+            r#"writer.write_str("abcfood")?;"#,
+            "(&&::rinja::helpers::Writable(&((&&::rinja::filters::AutoEscaper::new(&(x), \
+             ::rinja::filters::Text)).rinja_auto_escape()?))).rinja_write(writer)?;",
+            r#"writer.write_str("e")?;"#,
+        ],
+    );
+}
+
+#[test]
+fn check_filter_block_lits() {
+    // Static text is written with `write_str()`, inside and outside of filter blocks.
+    let ast = syn::parse_str::<syn::DeriveInput>(
+        r##"#[template(
+            source = r#"a {% filter upper %}b {{ x }} c{% endfilter %} d"#,
+            ext = "txt",
+        )]
+        struct Foo {
+            x: u32,
+        }"##,
+    )
+    .unwrap();
+    let generated = build_template(&ast).unwrap();
+    let writes: Vec<&str> = generated
+        .lines()
+        .filter(|line| line.starts_with("writer.write_"))
+        .collect();
+    assert_eq!(
+        writes,
+        [
+            r#"writer.write_str("a ")?;"#,
+            r#"writer.write_str("b ")?;"#,
+            r#"writer.write_str(" c")?;"#,
+            r#"writer.write_str(" d")?;"#,
+        ],
+    );
+}
+
+#[test]
+fn check_const_filter_block() {
+    // A filter block that only contains text is filtered and escaped at compile time.
+    let ast = syn::parse_str::<syn::DeriveInput>(
+        r##"#[template(
+            source = r#"a {% filter upper|trim %} b<c> {# x #}{% endfilter %} d"#,
+            ext = "html",
+        )]
+        struct Foo;"##,
+    )
+    .unwrap();
+    let generated = build_template(&ast).unwrap();
+    assert!(!generated.contains("FmtCell"), "{generated}");
+    let writes: Vec<&str> = generated
+        .lines()
+        .filter(|line| line.starts_with("writer.write_"))
+        .collect();
+    assert_eq!(writes, [r#"writer.write_str("a B&#60;C&#62; d")?;"#]);
+}
+
+#[test]
+fn check_const_expr() {
+    // An expression that only consists of literals is evaluated and escaped at compile time.
+    let ast = syn::parse_str::<syn::DeriveInput>(
+        r##"#[template(
+            source = r#"{{ 60 * 60 * 24 }} {{ "a<b" }} {{ 1.5 * 2.0 }} {{ 'x' < 'y' && !false }}"#,
+            ext = "html",
+        )]
+        struct Foo;"##,
+    )
+    .unwrap();
+    let generated = build_template(&ast).unwrap();
+    let writes: Vec<&str> = generated
+        .lines()
+        .filter(|line| line.starts_with("writer.write_"))
+        .collect();
+    assert_eq!(writes, [r#"writer.write_str("86400 a&#60;b 3 true")?;"#]);
+
+    // Expressions that could fail or change their type are left to the compiler.
+    for source in [
+        "{{ 2147483647 + 1 }}",
+        "{{ 1 / 0 }}",
+        "{{ 1_u8 + 1_u16 }}",
+        "{{ 1_u8 + 2 }}",
+        "{{ 1 + 2.0 }}",
+        "{{ -1_u32 }}",
+        "{{ 2.0 ** 2 }}",
+        "{{ 1 << 2 }}",
+    ] {
+        let ast = syn::parse_str::<syn::DeriveInput>(&format!(
+            r##"#[template(source = {source:?}, ext = "html")] struct Foo;"##
+        ))
+        .unwrap();
+        let generated = build_template(&ast).unwrap();
+        assert!(generated.contains("rinja_write"), "{source}: {generated}");
+    }
+}
+
+#[test]
+fn check_required_block() {
+    let ast = syn::parse_str::<syn::DeriveInput>(
+        r##"#[template(
+            source = r#"{% extends "required-base.html" %}{% block title %}Hi{% endblock %}"#,
+            ext = "txt",
+        )]
+        struct Foo;"##,
+    )
+    .unwrap();
+    build_template(&ast).unwrap();
+
+    let ast = syn::parse_str::<syn::DeriveInput>(
+        r##"#[template(
+            source = r#"{% extends "required-base.html" %}{% block content %}Hi{% endblock %}"#,
+            ext = "txt",
+        )]
+        struct Foo;"##,
+    )
+    .unwrap();
+    let err = build_template(&ast).unwrap_err();
+    assert!(
+        err.msg.starts_with(
+            "block `title` is required, but no template that extends \
+             \"templates/required-base.html\" defines it: \
+             \"Foo.txt\" extends \"templates/required-base.html\""
+        ),
+        "{}",
+        err.msg,
+    );
+}
+
+#[test]
+fn check_multiple_errors() {
+    fn messages(source: &str) -> Vec<String> {
+        let ast = syn::parse_str::<syn::DeriveInput>(&format!(
+            "#[template(source = {source:?}, ext = \"txt\")] struct Foo;"
+        ))
+        .unwrap();
+        let err = build_template(&ast).unwrap_err();
+        err.iter()
+            .map(|err| err.msg.lines().next().unwrap().to_owned())
+            .collect()
+    }
+
+    assert_eq!(
+        messages(
+            "{% macro m(a) %}{% endmacro %}{% call missing() %}\
+             {% if cond %}{% call m(1, 2) %}{% endif %}{{ a|safe(1) }}"
+        ),
+        [
+            "macro \"missing\" not found",
+            "macro \"m\" expected 1 argument, found 2",
+            "unexpected argument(s) in `safe` filter",
+        ],
+    );
+    let errors = messages(r#"{% include "missing1.html" %}{% include "missing2.html" %}"#);
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert!(errors[0].starts_with(r#"template "missing1.html" not found"#));
+    assert!(errors[1].starts_with(r#"template "missing2.html" not found"#));
+}
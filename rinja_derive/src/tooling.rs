@@ -0,0 +1,187 @@
+//! Queries about templates for IDE tooling, exposed by the `rinja_tooling` crate
+
+use std::collections::HashMap;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use parser::{Node, Parsed};
+
+use crate::config::{read_config_file, Config};
+use crate::heritage::Context;
+use crate::input::{TemplateArgs, TemplateInput};
+use crate::{CompileError, CompileErrors};
+
+/// Everything [`analyze_template()`] found out about a template
+#[derive(Debug, Default)]
+pub struct Analysis {
+    /// The path of the template file, if it was found
+    pub path: Option<PathBuf>,
+    /// The parsed template, and all templates it extends, includes or imports
+    pub templates: HashMap<PathBuf, Arc<Parsed>>,
+    /// The template, followed by the template it extends, followed by the template that one
+    /// extends, and so on
+    pub extends_chain: Vec<PathBuf>,
+    /// The blocks defined in the templates of [`Analysis::extends_chain`]
+    pub blocks: Vec<Definition>,
+    /// The macros defined in the template, and in all templates it uses
+    pub macros: Vec<Definition>,
+    /// The errors the `Template` derive macro would report for the template
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A named definition in a template, e.g. a `{% block %}` or a `{% macro %}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definition {
+    pub name: String,
+    /// The template file that contains the definition
+    pub path: PathBuf,
+    /// The byte offset of the definition in the template source, right after the `{%` of its tag
+    pub offset: usize,
+}
+
+/// An error in a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The error message, as the `Template` derive macro prints it
+    pub message: String,
+    /// The template file that contains the error, if the error refers to one
+    pub path: Option<PathBuf>,
+    /// The byte offset of the error in the template source, if it is known
+    pub offset: Option<usize>,
+}
+
+impl From<CompileError> for Diagnostic {
+    fn from(err: CompileError) -> Self {
+        let (path, offset) = match err.location {
+            Some((path, offset)) => (Some(path), offset),
+            None => (None, None),
+        };
+        Self {
+            message: err.msg,
+            path,
+            offset,
+        }
+    }
+}
+
+/// Parses the template `name`, and all templates it uses, like `#[template(path = "name")]`
+/// would
+///
+/// The configuration is read from `config_path` or `rinja.toml`, relative to the
+/// `CARGO_MANIFEST_DIR` environment variable. Expressions are not type checked.
+pub fn analyze_template(name: &str, config_path: Option<&str>) -> Analysis {
+    let mut analysis = Analysis::default();
+    if let Err(mut err) = analyze(&mut analysis, name, config_path) {
+        let others = mem::take(&mut err.others);
+        analysis.diagnostics.push(err.into());
+        analysis
+            .diagnostics
+            .extend(others.into_iter().map(Diagnostic::from));
+    }
+    analysis
+}
+
+fn analyze(
+    analysis: &mut Analysis,
+    name: &str,
+    config_path: Option<&str>,
+) -> Result<(), CompileError> {
+    let (source, config_file) = read_config_file(config_path)?;
+    let config = Config::new(&source, config_path, None)?;
+    let ast: syn::DeriveInput = syn::parse_quote!(
+        struct __Rinja__Analysis;
+    );
+    let template_args = TemplateArgs::for_path(name);
+    let input = TemplateInput::new(&ast, config, config_file.as_deref(), &template_args)?;
+    analysis.path = Some(input.path.to_path_buf());
+
+    let mut templates = HashMap::new();
+    input.find_used_templates(&mut templates)?;
+    analysis.templates = templates
+        .iter()
+        .map(|(path, parsed)| (path.to_path_buf(), Arc::clone(parsed)))
+        .collect();
+
+    let mut contexts = HashMap::new();
+    let mut errors = CompileErrors::default();
+    let mut paths: Vec<_> = templates.keys().collect();
+    paths.sort();
+    for path in paths {
+        if let Some(ctx) = errors.check(Context::new(input.config, path, &templates[path])) {
+            contexts.insert(path, ctx);
+        }
+    }
+    errors.into_result()?;
+
+    let mut path = Some(&input.path);
+    while let Some(current) = path {
+        analysis.extends_chain.push(current.to_path_buf());
+        let parsed = &templates[current];
+        collect_blocks(
+            &mut analysis.blocks,
+            current,
+            parsed.source(),
+            parsed.nodes(),
+        );
+        path = contexts[current].extends.as_ref();
+    }
+
+    for (path, parsed) in &templates {
+        for node in parsed.nodes() {
+            if let Node::Macro(m) = node {
+                analysis.macros.push(Definition {
+                    name: m.name.to_string(),
+                    path: path.to_path_buf(),
+                    offset: m.offset(parsed.source()),
+                });
+            }
+        }
+    }
+    analysis
+        .macros
+        .sort_by(|a, b| (&a.path, a.offset).cmp(&(&b.path, b.offset)));
+    Ok(())
+}
+
+// Blocks are found where `Context::new()` finds them.
+fn collect_blocks(blocks: &mut Vec<Definition>, path: &Path, source: &str, nodes: &[Node<'_>]) {
+    for node in nodes {
+        match node {
+            Node::BlockDef(b) => {
+                blocks.push(Definition {
+                    name: b.name.to_string(),
+                    path: path.to_path_buf(),
+                    offset: b.offset(source),
+                });
+                collect_blocks(blocks, path, source, &b.nodes);
+            }
+            Node::If(i) => {
+                for cond in &i.branches {
+                    collect_blocks(blocks, path, source, &cond.nodes);
+                }
+            }
+            Node::Loop(l) => {
+                collect_blocks(blocks, path, source, &l.body);
+                collect_blocks(blocks, path, source, &l.else_nodes);
+            }
+            Node::Match(m) => {
+                for arm in &m.arms {
+                    collect_blocks(blocks, path, source, &arm.nodes);
+                }
+            }
+            Node::Let(l) => {
+                if let Some(else_nodes) = &l.else_nodes {
+                    collect_blocks(blocks, path, source, else_nodes);
+                }
+            }
+            Node::WhitespaceBlock(w) => {
+                collect_blocks(blocks, path, source, &w.nodes);
+            }
+            Node::AutoEscape(a) => {
+                collect_blocks(blocks, path, source, &a.nodes);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,114 @@
+#![forbid(unsafe_code)]
+#![deny(elided_lifetimes_in_paths)]
+#![deny(unreachable_pub)]
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+#[doc(no_inline)]
+pub use http;
+use http::header::{HeaderValue, CONTENT_TYPE};
+use http::{Response, StatusCode};
+#[doc(no_inline)]
+pub use http_body;
+use http_body::{Body, Frame, SizeHint};
+#[doc(no_inline)]
+pub use rinja::*;
+
+/// Render a [`Template`] into a [`Response`], or render an error page.
+pub fn into_response<T: ?Sized + rinja::Template>(tmpl: &T) -> Response<TemplateBody> {
+    match try_into_response(tmpl) {
+        Ok(response) => response,
+        Err(err) => {
+            let mut response = Response::new(TemplateBody::from(err.to_string()));
+            *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            response.headers_mut().insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static("text/plain; charset=utf-8"),
+            );
+            response
+        }
+    }
+}
+
+/// Try to render a [`Template`] into a [`Response`].
+pub fn try_into_response<T: ?Sized + rinja::Template>(
+    tmpl: &T,
+) -> Result<Response<TemplateBody>, Error> {
+    let mut response = Response::new(TemplateBody::new(tmpl)?);
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(T::MIME_TYPE));
+    Ok(response)
+}
+
+/// An [`http_body::Body`] containing a rendered [`Template`]
+///
+/// The template is rendered completely when the body is created, using
+/// [`Template::render_chunks()`]: the static text of the template is passed on as
+/// [`Bytes::from_static()`] without copying it, and only the output of expressions is allocated.
+#[derive(Debug, Default)]
+pub struct TemplateBody {
+    chunks: VecDeque<Bytes>,
+    remaining: u64,
+}
+
+impl TemplateBody {
+    /// Render a [`Template`] into a body.
+    pub fn new<T: ?Sized + rinja::Template>(tmpl: &T) -> Result<Self, Error> {
+        let mut body = Self::default();
+        for chunk in tmpl.render_chunks() {
+            body.push(match chunk? {
+                Cow::Borrowed(chunk) => Bytes::from_static(chunk.as_bytes()),
+                Cow::Owned(chunk) => Bytes::from(chunk),
+            });
+        }
+        Ok(body)
+    }
+
+    fn push(&mut self, chunk: Bytes) {
+        if !chunk.is_empty() {
+            self.remaining += chunk.len() as u64;
+            self.chunks.push_back(chunk);
+        }
+    }
+}
+
+impl From<String> for TemplateBody {
+    #[inline]
+    fn from(value: String) -> Self {
+        let mut body = Self::default();
+        body.push(Bytes::from(value));
+        body
+    }
+}
+
+impl Body for TemplateBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        Poll::Ready(this.chunks.pop_front().map(|chunk| {
+            this.remaining -= chunk.len() as u64;
+            Ok(Frame::data(chunk))
+        }))
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining)
+    }
+}
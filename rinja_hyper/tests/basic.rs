@@ -0,0 +1,37 @@
+use http::StatusCode;
+use http_body::Body;
+use http_body_util::BodyExt;
+use rinja_hyper::{Template, TemplateBody};
+
+#[derive(Template)]
+#[template(path = "hello.html")]
+struct HelloTemplate<'a> {
+    name: &'a str,
+}
+
+#[tokio::test]
+async fn template_to_response() {
+    let res = rinja_hyper::into_response(&HelloTemplate { name: "world" });
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let headers = res.headers();
+    assert_eq!(headers["Content-Type"], "text/html; charset=utf-8");
+
+    let body = res.into_body();
+    assert_eq!(body.size_hint().exact(), Some(13));
+    let body = body.collect().await.unwrap().to_bytes();
+    assert_eq!(&body[..], b"Hello, world!");
+}
+
+#[tokio::test]
+async fn template_body_frames() {
+    let mut body = TemplateBody::new(&HelloTemplate { name: "world" }).unwrap();
+
+    let mut frames = Vec::new();
+    while let Some(frame) = body.frame().await {
+        frames.push(frame.unwrap().into_data().unwrap());
+    }
+    assert_eq!(frames, ["Hello, ", "world", "!"]);
+    assert!(body.is_end_stream());
+    assert_eq!(body.size_hint().exact(), Some(0));
+}
@@ -0,0 +1,31 @@
+#![forbid(unsafe_code)]
+#![deny(elided_lifetimes_in_paths)]
+#![deny(unreachable_pub)]
+
+#[doc(no_inline)]
+pub use ntex;
+use ntex::http::header::{HeaderValue, CONTENT_TYPE};
+use ntex::web::HttpResponse;
+#[doc(no_inline)]
+pub use rinja::*;
+
+/// Render a [`Template`] into a [`HttpResponse`], or render an error page.
+pub fn into_response<T: ?Sized + rinja::Template>(tmpl: &T) -> HttpResponse {
+    match try_into_response(tmpl) {
+        Ok(response) => response,
+        Err(err) => HttpResponse::InternalServerError()
+            .header(
+                CONTENT_TYPE,
+                HeaderValue::from_static("text/plain; charset=utf-8"),
+            )
+            .body(err.to_string()),
+    }
+}
+
+/// Try to render a [`Template`] into a [`HttpResponse`].
+pub fn try_into_response<T: ?Sized + rinja::Template>(tmpl: &T) -> Result<HttpResponse, Error> {
+    let value = tmpl.render()?;
+    Ok(HttpResponse::Ok()
+        .header(CONTENT_TYPE, HeaderValue::from_static(T::MIME_TYPE))
+        .body(value))
+}
@@ -0,0 +1,30 @@
+use ntex::http::header::CONTENT_TYPE;
+use ntex::http::StatusCode;
+use ntex::web::{self, test, App};
+use rinja_ntex::Template;
+
+#[derive(Template)]
+#[template(path = "hello.html")]
+struct HelloTemplate<'a> {
+    name: &'a str,
+}
+
+async fn hello() -> HelloTemplate<'static> {
+    HelloTemplate { name: "world" }
+}
+
+#[ntex::test]
+async fn test_ntex() {
+    let srv = test::init_service(App::new().route("/", web::get().to(hello))).await;
+
+    let request = test::TestRequest::get().uri("/").to_request();
+    let response = test::call_service(&srv, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(CONTENT_TYPE).unwrap(),
+        "text/html; charset=utf-8"
+    );
+
+    let bytes = test::read_body(response).await;
+    assert_eq!(&bytes[..], b"Hello, world!");
+}
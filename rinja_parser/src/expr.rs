@@ -8,21 +8,23 @@ use nom::combinator::{cut, map, not, opt, peek, recognize, value};
 use nom::error::ErrorKind;
 use nom::error_position;
 use nom::multi::{fold_many0, many0, separated_list0};
-use nom::sequence::{pair, preceded, terminated, tuple};
+use nom::sequence::{pair, preceded, separated_pair, terminated, tuple};
 
 use super::{
     char_lit, filter, identifier, keyword, not_ws, num_lit, path_or_identifier, str_lit, ws, Level,
     PathOrIdentifier,
 };
-use crate::{ErrorContext, ParseResult, WithSpan};
+use crate::{ErrorContext, ParseResult, Target, WithSpan};
 
 macro_rules! expr_prec_layer {
-    ( $name:ident, $inner:ident, $op:expr ) => {
+    // `$level` names the level in `$op`, for operators that contain white space.
+    ( $name:ident, $inner:ident, $level:ident => $op:expr ) => {
         fn $name(i: &'a str, level: Level) -> ParseResult<'a, WithSpan<'a, Self>> {
             let (_, level) = level.nest(i)?;
+            let $level = level;
             let start = i;
             let (i, left) = Self::$inner(i, level)?;
-            let (i, right) = many0(pair(ws($op), |i| Self::$inner(i, level)))(i)?;
+            let (i, right) = many0(pair(ws(level, $op), |i| Self::$inner(i, level)))(i)?;
             Ok((
                 i,
                 right.into_iter().fold(left, |left, (op, right)| {
@@ -31,6 +33,9 @@ macro_rules! expr_prec_layer {
             ))
         }
     };
+    ( $name:ident, $inner:ident, $op:expr ) => {
+        expr_prec_layer!($name, $inner, _level => $op);
+    };
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -42,6 +47,8 @@ pub enum Expr<'a> {
     Var(&'a str),
     Path(Vec<&'a str>),
     Array(Vec<WithSpan<'a, Expr<'a>>>),
+    /// `{key: value, …}`
+    Map(Vec<(WithSpan<'a, Expr<'a>>, WithSpan<'a, Expr<'a>>)>),
     Attr(Box<WithSpan<'a, Expr<'a>>>, &'a str),
     Index(Box<WithSpan<'a, Expr<'a>>>, Box<WithSpan<'a, Expr<'a>>>),
     Filter(Filter<'a>),
@@ -62,6 +69,29 @@ pub enum Expr<'a> {
     Call(Box<WithSpan<'a, Expr<'a>>>, Vec<WithSpan<'a, Expr<'a>>>),
     RustMacro(Vec<&'a str>, &'a str),
     Try(Box<WithSpan<'a, Expr<'a>>>),
+    /// `expr::<generics>`, e.g. the method `value.parse::<i32>` in `value.parse::<i32>()`,
+    /// stored as `Turbofish(expr, generics)`
+    Turbofish(Box<WithSpan<'a, Expr<'a>>>, &'a str),
+    /// `path::<generics>::item`, e.g. `Vec::<u8>::new`, stored as `AssociatedItem(path, item)`
+    /// where `path` is a [`Expr::Turbofish`]
+    AssociatedItem(Box<WithSpan<'a, Expr<'a>>>, &'a str),
+    /// `matches!(expr, pattern)` or `matches!(expr, pattern if guard)`, stored as
+    /// `Matches(expr, pattern, guard)`
+    Matches(
+        Box<WithSpan<'a, Expr<'a>>>,
+        Target<'a>,
+        Option<Box<WithSpan<'a, Expr<'a>>>>,
+    ),
+    /// `cfg(predicate)`, e.g. `cfg(feature = "premium")`, which is evaluated at compile time
+    Cfg(&'a str),
+    /// `|params| body`
+    Closure(Vec<Target<'a>>, Box<WithSpan<'a, Expr<'a>>>),
+    /// `then if cond else otherwise`, stored as `IfElse(cond, then, otherwise)`
+    IfElse(
+        Box<WithSpan<'a, Expr<'a>>>,
+        Box<WithSpan<'a, Expr<'a>>>,
+        Box<WithSpan<'a, Expr<'a>>>,
+    ),
     /// This variant should never be used directly. It is created when generating filter blocks.
     FilterSource,
 }
@@ -77,11 +107,11 @@ impl<'a> Expr<'a> {
         let start = i;
 
         preceded(
-            ws(char('(')),
+            ws(level, char('(')),
             cut(terminated(
                 separated_list0(
                     char(','),
-                    ws(move |i| {
+                    ws(level, move |i| {
                         // Needed to prevent borrowing it twice between this closure and the one
                         // calling `Self::named_arguments`.
                         let named_arguments = &mut named_arguments;
@@ -109,7 +139,7 @@ impl<'a> Expr<'a> {
                         }
                     }),
                 ),
-                tuple((opt(ws(char(','))), char(')'))),
+                tuple((opt(ws(level, char(','))), char(')'))),
             )),
         )(i)
     }
@@ -128,8 +158,9 @@ impl<'a> Expr<'a> {
         }
 
         let (_, level) = level.nest(i)?;
-        let (i, (argument, _, value)) =
-            tuple((identifier, ws(char('=')), move |i| Self::parse(i, level)))(i)?;
+        let (i, (argument, _, value)) = tuple((identifier, ws(level, char('=')), move |i| {
+            Self::parse(i, level)
+        }))(i)?;
         if named_arguments.insert(argument) {
             Ok((
                 i,
@@ -145,10 +176,58 @@ impl<'a> Expr<'a> {
 
     pub(super) fn parse(i: &'a str, level: Level) -> ParseResult<'a, WithSpan<'a, Self>> {
         let (_, level) = level.nest(i)?;
+        let start = i;
+        if let (i, Some(closure)) = opt(move |i| Self::closure(i, level))(i)? {
+            return Ok((i, closure));
+        }
+        let (i, then) = Self::range(i, level)?;
+        // Only commit to a conditional expression once `else` was seen, so that e.g. the
+        // `if` of `{% for x in xs if cond %}` is still available to the loop.
+        let (i, if_else) = opt(tuple((
+            ws(level, keyword("if")),
+            move |i| Self::or(i, level),
+            ws(level, keyword("else")),
+            cut(move |i| Self::parse(i, level)),
+        )))(i)?;
+        Ok((
+            i,
+            match if_else {
+                Some((_, cond, _, otherwise)) => WithSpan::new(
+                    Self::IfElse(Box::new(cond), Box::new(then), Box::new(otherwise)),
+                    start,
+                ),
+                None => then,
+            },
+        ))
+    }
+
+    fn closure(i: &'a str, level: Level) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let (_, level) = level.nest(i)?;
+        let start = i;
+
+        // The parameters are parsed like the targets of a `{% let %}`.
+        let (i, params) = alt((
+            value(Vec::new(), tag("||")),
+            preceded(
+                char('|'),
+                cut(terminated(
+                    separated_list0(char(','), ws(level, |i| Target::parse(i, level))),
+                    pair(opt(char(',')), ws(level, char('|'))),
+                )),
+            ),
+        ))(i)?;
+        let (i, body) = cut(ws(level, move |i| Self::parse(i, level)))(i)?;
+        Ok((
+            i,
+            WithSpan::new(Self::Closure(params, Box::new(body)), start),
+        ))
+    }
+
+    fn range(i: &'a str, level: Level) -> ParseResult<'a, WithSpan<'a, Self>> {
         let start = i;
         let range_right = move |i| {
             pair(
-                ws(alt((tag("..="), tag("..")))),
+                ws(level, alt((tag("..="), tag("..")))),
                 opt(move |i| Self::or(i, level)),
             )(i)
         };
@@ -174,13 +253,15 @@ impl<'a> Expr<'a> {
     expr_prec_layer!(
         compare,
         bor,
-        alt((
+        level => alt((
             tag("=="),
             tag("!="),
             tag(">="),
             tag(">"),
             tag("<="),
             tag("<"),
+            value("in", keyword("in")),
+            value("not in", pair(keyword("not"), ws(level, keyword("in")))),
         ))
     );
     expr_prec_layer!(bor, bxor, value("|", tag("bitor")));
@@ -188,7 +269,27 @@ impl<'a> Expr<'a> {
     expr_prec_layer!(band, shifts, token_bitand);
     expr_prec_layer!(shifts, addsub, alt((tag(">>"), tag("<<"))));
     expr_prec_layer!(addsub, muldivmod, alt((tag("+"), tag("-"))));
-    expr_prec_layer!(muldivmod, filtered, alt((tag("*"), tag("/"), tag("%"))));
+    expr_prec_layer!(muldivmod, power, alt((tag("*"), tag("/"), tag("%"))));
+
+    // `**` is right associative, so `a ** b ** c` is `a ** (b ** c)`. Only the right operand
+    // is nested, so expressions without `**` don't use up a nesting level.
+    fn power(i: &'a str, level: Level) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let start = i;
+        let (i, left) = Self::filtered(i, level)?;
+        let (i, right) = opt(preceded(ws(level, tag("**")), |i| {
+            let (_, level) = level.nest(i)?;
+            Self::power(i, level)
+        }))(i)?;
+        Ok((
+            i,
+            match right {
+                Some(right) => {
+                    WithSpan::new(Self::BinOp("**", Box::new(left), Box::new(right)), start)
+                }
+                None => left,
+            },
+        ))
+    }
 
     fn filtered(i: &'a str, mut level: Level) -> ParseResult<'a, WithSpan<'a, Self>> {
         let start = i;
@@ -208,7 +309,7 @@ impl<'a> Expr<'a> {
         let (_, nested) = level.nest(i)?;
         let start = i;
         let (i, (ops, mut expr)) = pair(
-            many0(ws(alt((tag("!"), tag("-"), tag("*"), tag("&"))))),
+            many0(ws(level, alt((tag("!"), tag("-"), tag("*"), tag("&"))))),
             |i| Suffix::parse(i, nested),
         )(i)?;
 
@@ -229,16 +330,51 @@ impl<'a> Expr<'a> {
             Self::num,
             Self::str,
             Self::char,
-            Self::path_var_bool,
+            move |i| Self::matches(i, level),
+            move |i| Self::cfg(i, level),
+            move |i| Self::path_var_bool(i, level),
             move |i| Self::array(i, level),
+            move |i| Self::map(i, level),
             move |i| Self::group(i, level),
         ))(i)
     }
 
+    fn matches(i: &'a str, level: Level) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let (_, level) = level.nest(i)?;
+        let start = i;
+        let (i, _) = tuple((keyword("matches"), ws(level, char('!')), char('(')))(i)?;
+
+        // The pattern is parsed like the target of a `{% when %}` block.
+        let (i, (expr, _, pattern, guard, _)) = cut(tuple((
+            ws(level, |i| Self::parse(i, level)),
+            char(','),
+            ws(level, |i| Target::parse_pattern(i, level)),
+            opt(preceded(
+                ws(level, keyword("if")),
+                ws(level, |i| Self::parse(i, level)),
+            )),
+            pair(opt(char(',')), ws(level, char(')'))),
+        )))(i)?;
+        Ok((
+            i,
+            WithSpan::new(
+                Self::Matches(Box::new(expr), pattern, guard.map(Box::new)),
+                start,
+            ),
+        ))
+    }
+
+    fn cfg(i: &'a str, level: Level) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let start = i;
+        let (i, _) = pair(keyword("cfg"), ws(level, char('(')))(i)?;
+        let (i, predicate) = cut(terminated(recognize(nested_parenthesis), char(')')))(i)?;
+        Ok((i, WithSpan::new(Self::Cfg(predicate.trim()), start)))
+    }
+
     fn group(i: &'a str, level: Level) -> ParseResult<'a, WithSpan<'a, Self>> {
         let (_, level) = level.nest(i)?;
         let start = i;
-        let (i, expr) = preceded(ws(char('(')), opt(|i| Self::parse(i, level)))(i)?;
+        let (i, expr) = preceded(ws(level, char('(')), opt(|i| Self::parse(i, level)))(i)?;
         let expr = match expr {
             Some(expr) => expr,
             None => {
@@ -247,7 +383,7 @@ impl<'a> Expr<'a> {
             }
         };
 
-        let (i, comma) = ws(opt(peek(char(','))))(i)?;
+        let (i, comma) = ws(level, opt(peek(char(','))))(i)?;
         if comma.is_none() {
             let (i, _) = char(')')(i)?;
             return Ok((i, WithSpan::new(Self::Group(Box::new(expr)), start)));
@@ -255,13 +391,13 @@ impl<'a> Expr<'a> {
 
         let mut exprs = vec![expr];
         let (i, _) = fold_many0(
-            preceded(char(','), ws(|i| Self::parse(i, level))),
+            preceded(char(','), ws(level, |i| Self::parse(i, level))),
             || (),
             |_, expr| {
                 exprs.push(expr);
             },
         )(i)?;
-        let (i, _) = pair(ws(opt(char(','))), char(')'))(i)?;
+        let (i, _) = pair(ws(level, opt(char(','))), char(')'))(i)?;
         Ok((i, WithSpan::new(Self::Tuple(exprs), start)))
     }
 
@@ -269,10 +405,10 @@ impl<'a> Expr<'a> {
         let (_, level) = level.nest(i)?;
         let start = i;
         preceded(
-            ws(char('[')),
+            ws(level, char('[')),
             cut(terminated(
                 map(
-                    separated_list0(char(','), ws(move |i| Self::parse(i, level))),
+                    separated_list0(char(','), ws(level, move |i| Self::parse(i, level))),
                     |i| WithSpan::new(Self::Array(i), start),
                 ),
                 char(']'),
@@ -280,14 +416,38 @@ impl<'a> Expr<'a> {
         )(i)
     }
 
-    fn path_var_bool(i: &'a str) -> ParseResult<'a, WithSpan<'a, Self>> {
+    fn map(i: &'a str, level: Level) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let (_, level) = level.nest(i)?;
+        let start = i;
+        let entry = move |i| {
+            separated_pair(
+                move |i| Self::parse(i, level),
+                ws(level, char(':')),
+                move |i| Self::parse(i, level),
+            )(i)
+        };
+        preceded(
+            ws(level, char('{')),
+            cut(terminated(
+                map(separated_list0(char(','), ws(level, entry)), |entries| {
+                    WithSpan::new(Self::Map(entries), start)
+                }),
+                pair(opt(char(',')), ws(level, char('}'))),
+            )),
+        )(i)
+    }
+
+    fn path_var_bool(i: &'a str, level: Level) -> ParseResult<'a, WithSpan<'a, Self>> {
         let start = i;
-        map(path_or_identifier, |v| match v {
-            PathOrIdentifier::Path(v) => Self::Path(v),
-            PathOrIdentifier::Identifier(v @ "true") => Self::BoolLit(v),
-            PathOrIdentifier::Identifier(v @ "false") => Self::BoolLit(v),
-            PathOrIdentifier::Identifier(v) => Self::Var(v),
-        })(i)
+        map(
+            |i| path_or_identifier(i, level),
+            |v| match v {
+                PathOrIdentifier::Path(v) => Self::Path(v),
+                PathOrIdentifier::Identifier(v @ "true") => Self::BoolLit(v),
+                PathOrIdentifier::Identifier(v @ "false") => Self::BoolLit(v),
+                PathOrIdentifier::Identifier(v) => Self::Var(v),
+            },
+        )(i)
         .map(|(i, expr)| (i, WithSpan::new(expr, start)))
     }
 
@@ -347,6 +507,10 @@ enum Suffix<'a> {
     // The value is the arguments of the macro call.
     MacroCall(&'a str),
     Try,
+    // The value is the generic arguments between the angle brackets.
+    Turbofish(&'a str),
+    // The value is the name of the item after `::`.
+    AssociatedItem(&'a str),
 }
 
 impl<'a> Suffix<'a> {
@@ -355,11 +519,13 @@ impl<'a> Suffix<'a> {
         let (mut i, mut expr) = Expr::single(i, level)?;
         loop {
             let (j, suffix) = opt(alt((
-                Self::attr,
+                |i| Self::attr(i, level),
                 |i| Self::index(i, level),
                 |i| Self::call(i, level),
-                Self::r#try,
-                Self::r#macro,
+                |i| Self::r#try(i, level),
+                |i| Self::turbofish(i, level),
+                |i| Self::associated_item(i, level),
+                |i| Self::r#macro(i, level),
             )))(i)?;
 
             match suffix {
@@ -369,6 +535,16 @@ impl<'a> Suffix<'a> {
                 }
                 Some(Self::Call(args)) => expr = WithSpan::new(Expr::Call(expr.into(), args), i),
                 Some(Self::Try) => expr = WithSpan::new(Expr::Try(expr.into()), i),
+                Some(Self::Turbofish(generics)) => {
+                    expr = WithSpan::new(Expr::Turbofish(expr.into(), generics), i)
+                }
+                // Other paths were already parsed completely.
+                Some(Self::AssociatedItem(name)) => match expr.inner {
+                    Expr::Turbofish(..) => {
+                        expr = WithSpan::new(Expr::AssociatedItem(expr.into(), name), i)
+                    }
+                    _ => return Err(nom::Err::Failure(error_position!(i, ErrorKind::Tag))),
+                },
                 Some(Self::MacroCall(args)) => match expr.inner {
                     Expr::Path(path) => expr = WithSpan::new(Expr::RustMacro(path, args), i),
                     Expr::Var(name) => expr = WithSpan::new(Expr::RustMacro(vec![name], args), i),
@@ -382,57 +558,9 @@ impl<'a> Suffix<'a> {
         Ok((i, expr))
     }
 
-    fn r#macro(i: &'a str) -> ParseResult<'a, Self> {
-        fn nested_parenthesis(input: &str) -> ParseResult<'_, ()> {
-            let mut nested = 0;
-            let mut last = 0;
-            let mut in_str = false;
-            let mut escaped = false;
-
-            for (i, c) in input.char_indices() {
-                if !(c == '(' || c == ')') || !in_str {
-                    match c {
-                        '(' => nested += 1,
-                        ')' => {
-                            if nested == 0 {
-                                last = i;
-                                break;
-                            }
-                            nested -= 1;
-                        }
-                        '"' => {
-                            if in_str {
-                                if !escaped {
-                                    in_str = false;
-                                }
-                            } else {
-                                in_str = true;
-                            }
-                        }
-                        '\\' => {
-                            escaped = !escaped;
-                        }
-                        _ => (),
-                    }
-                }
-
-                if escaped && c != '\\' {
-                    escaped = false;
-                }
-            }
-
-            if nested == 0 {
-                Ok((&input[last..], ()))
-            } else {
-                Err(nom::Err::Error(error_position!(
-                    input,
-                    ErrorKind::SeparatedNonEmptyList
-                )))
-            }
-        }
-
+    fn r#macro(i: &'a str, level: Level) -> ParseResult<'a, Self> {
         preceded(
-            pair(ws(char('!')), char('(')),
+            pair(ws(level, char('!')), char('(')),
             cut(terminated(
                 map(recognize(nested_parenthesis), Self::MacroCall),
                 char(')'),
@@ -440,10 +568,10 @@ impl<'a> Suffix<'a> {
         )(i)
     }
 
-    fn attr(i: &'a str) -> ParseResult<'a, Self> {
+    fn attr(i: &'a str, level: Level) -> ParseResult<'a, Self> {
         map(
             preceded(
-                ws(pair(char('.'), not(char('.')))),
+                ws(level, pair(char('.'), not(char('.')))),
                 cut(alt((num_lit, identifier))),
             ),
             Self::Attr,
@@ -454,8 +582,11 @@ impl<'a> Suffix<'a> {
         let (_, level) = level.nest(i)?;
         map(
             preceded(
-                ws(char('[')),
-                cut(terminated(ws(move |i| Expr::parse(i, level)), char(']'))),
+                ws(level, char('[')),
+                cut(terminated(
+                    ws(level, move |i| Expr::parse(i, level)),
+                    char(']'),
+                )),
             ),
             Self::Index,
         )(i)
@@ -466,7 +597,101 @@ impl<'a> Suffix<'a> {
         map(move |i| Expr::arguments(i, level, false), Self::Call)(i)
     }
 
-    fn r#try(i: &'a str) -> ParseResult<'a, Self> {
-        map(preceded(take_till(not_ws), char('?')), |_| Self::Try)(i)
+    fn r#try(i: &'a str, level: Level) -> ParseResult<'a, Self> {
+        map(preceded(take_till(not_ws(level)), char('?')), |_| Self::Try)(i)
+    }
+
+    fn associated_item(i: &'a str, level: Level) -> ParseResult<'a, Self> {
+        map(
+            preceded(ws(level, tag("::")), identifier),
+            Self::AssociatedItem,
+        )(i)
+    }
+
+    fn turbofish(i: &'a str, level: Level) -> ParseResult<'a, Self> {
+        // Only the characters that can be part of a type are accepted, so e.g. a `>` in a
+        // string literal cannot end the generics too early or too late.
+        fn generics(input: &str) -> ParseResult<'_, ()> {
+            let mut nested = 0;
+            for (i, c) in input.char_indices() {
+                match c {
+                    '<' => nested += 1,
+                    '>' if nested == 0 => {
+                        return match input[..i].trim().is_empty() {
+                            true => break,
+                            false => Ok((&input[i..], ())),
+                        };
+                    }
+                    '>' => nested -= 1,
+                    '_' | ':' | ',' | '&' | '*' | '\'' | '(' | ')' | '[' | ']' | ';' => {}
+                    c if c.is_alphanumeric() || c.is_whitespace() => {}
+                    _ => break,
+                }
+            }
+            Err(nom::Err::Error(error_position!(
+                input,
+                ErrorKind::TakeUntil
+            )))
+        }
+
+        preceded(
+            pair(ws(level, tag("::")), char('<')),
+            cut(terminated(
+                map(recognize(generics), |generics: &str| {
+                    Self::Turbofish(generics.trim())
+                }),
+                char('>'),
+            )),
+        )(i)
+    }
+}
+
+// Finds the end of the arguments of a macro call, i.e. the first unbalanced `)` that is not
+// part of a string literal.
+fn nested_parenthesis(input: &str) -> ParseResult<'_, ()> {
+    let mut nested = 0;
+    let mut last = 0;
+    let mut in_str = false;
+    let mut escaped = false;
+
+    for (i, c) in input.char_indices() {
+        if !(c == '(' || c == ')') || !in_str {
+            match c {
+                '(' => nested += 1,
+                ')' => {
+                    if nested == 0 {
+                        last = i;
+                        break;
+                    }
+                    nested -= 1;
+                }
+                '"' => {
+                    if in_str {
+                        if !escaped {
+                            in_str = false;
+                        }
+                    } else {
+                        in_str = true;
+                    }
+                }
+                '\\' => {
+                    escaped = !escaped;
+                }
+                _ => (),
+            }
+        }
+
+        if escaped && c != '\\' {
+            escaped = false;
+        }
+    }
+
+    if nested == 0 {
+        Ok((&input[last..], ()))
+    } else {
+        Err(nom::Err::Error(error_position!(
+            input,
+            ErrorKind::SeparatedNonEmptyList
+        )))
     }
 }
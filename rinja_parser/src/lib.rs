@@ -2,7 +2,8 @@
 #![deny(elided_lifetimes_in_paths)]
 
 use std::borrow::Cow;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 use std::env::current_dir;
 use std::ops::{Deref, DerefMut};
 use std::path::Path;
@@ -16,7 +17,7 @@ use nom::combinator::{cut, eof, map, not, opt, recognize};
 use nom::error::{Error, ErrorKind, FromExternalError};
 use nom::multi::{many0_count, many1};
 use nom::sequence::{delimited, pair, preceded, terminated, tuple};
-use nom::{error_position, AsChar, InputTakeAtPosition};
+use nom::{error_position, InputTakeAtPosition};
 
 pub mod expr;
 pub use expr::{Expr, Filter};
@@ -34,11 +35,12 @@ mod _parsed {
     use std::{fmt, mem};
 
     use super::node::Node;
-    use super::{Ast, ParseError, Syntax};
+    use super::{Ast, ParseError, SourceMap, Syntax};
 
     pub struct Parsed {
-        // `source` must outlive `ast`, so `ast` must be declared before `source`
+        // `source` must outlive `ast` and `source_map`, so they must be declared before `source`
         ast: Ast<'static>,
+        source_map: SourceMap<'static>,
         #[allow(dead_code)]
         source: Arc<str>,
     }
@@ -56,7 +58,33 @@ mod _parsed {
             // However, we only expose the nodes with a lifetime limited to `self`.
             let src = unsafe { mem::transmute::<&str, &'static str>(source.as_ref()) };
             let ast = Ast::from_str(src, file_path, syntax)?;
-            Ok(Self { ast, source })
+            let source_map = SourceMap::new(src);
+            Ok(Self {
+                ast,
+                source_map,
+                source,
+            })
+        }
+
+        /// Parses `source` like [`Parsed::new()`], but does not stop at the first error
+        ///
+        /// Returns the nodes that could be parsed, and all errors. See
+        /// [`Ast::from_str_recovering()`].
+        pub fn new_recovering(
+            source: Arc<str>,
+            file_path: Option<Arc<Path>>,
+            syntax: &Syntax<'_>,
+        ) -> (Self, Vec<ParseError>) {
+            // See `Parsed::new()`.
+            let src = unsafe { mem::transmute::<&str, &'static str>(source.as_ref()) };
+            let (ast, errors) = Ast::from_str_recovering(src, file_path, syntax);
+            let source_map = SourceMap::new(src);
+            let parsed = Self {
+                ast,
+                source_map,
+                source,
+            };
+            (parsed, errors)
         }
 
         // The return value's lifetime must be limited to `self` to uphold the unsafe invariant.
@@ -67,6 +95,12 @@ mod _parsed {
         pub fn source(&self) -> &str {
             &self.source
         }
+
+        /// Returns the lines and columns of the source, e.g. of the spans of the nodes
+        // The return value's lifetime must be limited to `self` to uphold the unsafe invariant.
+        pub fn source_map(&self) -> &SourceMap<'_> {
+            &self.source_map
+        }
     }
 
     impl fmt::Debug for Parsed {
@@ -87,6 +121,7 @@ mod _parsed {
         fn default() -> Self {
             Self {
                 ast: Ast::default(),
+                source_map: SourceMap::default(),
                 source: "".into(),
             }
         }
@@ -108,7 +143,7 @@ impl<'a> Ast<'a> {
         file_path: Option<Arc<Path>>,
         syntax: &Syntax<'_>,
     ) -> Result<Self, ParseError> {
-        let parse = |i: &'a str| Node::many(i, &State::new(syntax));
+        let parse = |i: &'a str| Node::many(i, &State::with_source(src, syntax));
         let (input, message) = match terminated(parse, cut(eof))(src) {
             Ok(("", nodes)) => return Ok(Self { nodes }),
             Ok(_) => unreachable!("eof() is not eof?"),
@@ -119,23 +154,67 @@ impl<'a> Ast<'a> {
             Err(nom::Err::Incomplete(_)) => return Err(ParseError::Incomplete),
         };
 
-        let offset = src.len() - input.len();
-        let (source_before, source_after) = src.split_at(offset);
+        Err(ParseError::new(src, input, message, file_path))
+    }
 
-        let source_after = match source_after.char_indices().enumerate().take(41).last() {
-            Some((40, (i, _))) => format!("{:?}...", &source_after[..i]),
-            _ => format!("{source_after:?}"),
-        };
+    /// Parses `src` like [`Ast::from_str()`], but does not stop at the first error
+    ///
+    /// If a node cannot be parsed, the error is recorded, and parsing continues at the next
+    /// block tag after the error, e.g. at the `{% endif %}` of a broken `{% if %}` block.
+    /// A tag that cannot be parsed at all, like a stray `{% endif %}`, is skipped. Right after
+    /// an error, such tags are skipped without reporting another error, because they are
+    /// usually the end tags of the broken block. Returns the nodes that could be parsed, which
+    /// is the complete AST if there were no errors.
+    ///
+    /// This is meant for tools that have to work on broken templates, e.g. editors.
+    pub fn from_str_recovering(
+        src: &'a str,
+        file_path: Option<Arc<Path>>,
+        syntax: &Syntax<'_>,
+    ) -> (Self, Vec<ParseError>) {
+        let mut nodes = vec![];
+        let mut errors = vec![];
+        let mut recovering = false;
+        let mut i = src;
+        while !i.is_empty() {
+            // A failed parser can leave the state behind, e.g. the nesting level.
+            let state = State::with_source(src, syntax);
+            let (input, message, is_failure) = match Node::one(i, &state) {
+                Ok((rest, node)) => {
+                    nodes.push(node);
+                    recovering = false;
+                    i = rest;
+                    continue;
+                }
+                Err(nom::Err::Error(ErrorContext { input, message, .. })) => {
+                    (input, message, false)
+                }
+                Err(nom::Err::Failure(ErrorContext { input, message, .. })) => {
+                    (input, message, true)
+                }
+                Err(nom::Err::Incomplete(_)) => (i, None, true),
+            };
+            if is_failure || !recovering {
+                errors.push(ParseError::new(src, input, message, file_path.clone()));
+            }
 
-        let (row, last_line) = source_before.lines().enumerate().last().unwrap_or_default();
-        let column = last_line.chars().count();
-        Err(ParseError::Details {
-            message,
-            row,
-            column,
-            source_after,
-            file_path,
-        })
+            i = if is_failure || input.len() < i.len() {
+                // Continue at the next block tag after the error.
+                recovering = true;
+                let input = &input[input.chars().next().map_or(0, char::len_utf8)..];
+                match input.find(syntax.block_start) {
+                    Some(pos) => &input[pos..],
+                    None => "",
+                }
+            } else {
+                // The tag at the start of `i` is unknown: skip it.
+                match i.find(syntax.block_end) {
+                    Some(pos) => &i[pos + syntax.block_end.len()..],
+                    None => "",
+                }
+            };
+        }
+        (Self { nodes }, errors)
     }
 
     pub fn nodes(&self) -> &[Node<'a>] {
@@ -158,6 +237,37 @@ impl<'a, T> WithSpan<'a, T> {
     pub fn span(&self) -> &'a str {
         self.span
     }
+
+    /// Returns the byte offset of the span in `source`, the template source that was parsed
+    ///
+    /// # Panics
+    ///
+    /// Panics if the span is not a part of `source`.
+    pub fn offset(&self, source: &str) -> usize {
+        let offset = (self.span.as_ptr() as usize).wrapping_sub(source.as_ptr() as usize);
+        assert!(
+            offset <= source.len() && self.span.len() <= source.len() - offset,
+            "span is not a part of the source",
+        );
+        offset
+    }
+
+    /// Returns the line and column of the start of the span in `source`, the template source
+    /// that was parsed
+    ///
+    /// Use [`Parsed::source_map()`] instead to look up many spans of the same template.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the span is not a part of `source`.
+    pub fn line_column(&self, source: &str) -> LineColumn {
+        let before = &source[..self.offset(source)];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        LineColumn {
+            line: before.matches('\n').count(),
+            column: before[line_start..].chars().count(),
+        }
+    }
 }
 
 impl<'a, T> Deref for WithSpan<'a, T> {
@@ -196,6 +306,76 @@ impl<'a, T: PartialEq> PartialEq for WithSpan<'a, T> {
     }
 }
 
+/// A position in a template source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineColumn {
+    /// The line, starting at `0`
+    pub line: usize,
+    /// The number of characters in front of the position in its line
+    pub column: usize,
+}
+
+/// Converts byte offsets in a template source to lines and columns
+///
+/// The start of every line is computed in advance, so the lookups are fast.
+#[derive(Debug, Clone)]
+pub struct SourceMap<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Returns the number of lines of the source
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Returns the byte offset of the start of `line`, if the source has that many lines
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        self.line_starts.get(line).copied()
+    }
+
+    /// Returns the line and column of the byte `offset`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is not at a character boundary of the source.
+    pub fn line_column(&self, offset: usize) -> LineColumn {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.source[self.line_starts[line]..offset].chars().count();
+        LineColumn { line, column }
+    }
+
+    /// Returns the line and column of the start of `node`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` is not a part of the source.
+    pub fn span_line_column<T>(&self, node: &WithSpan<'_, T>) -> LineColumn {
+        self.line_column(node.offset(self.source))
+    }
+}
+
+impl Default for SourceMap<'_> {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParseError {
     Incomplete,
@@ -203,11 +383,42 @@ pub enum ParseError {
         message: Option<Cow<'static, str>>,
         row: usize,
         column: usize,
+        /// Byte offset of the error in the template source
+        offset: usize,
         source_after: String,
         file_path: Option<Arc<Path>>,
     },
 }
 
+impl ParseError {
+    // `input` is the part of `src` that starts at the error.
+    fn new(
+        src: &str,
+        input: &str,
+        message: Option<Cow<'static, str>>,
+        file_path: Option<Arc<Path>>,
+    ) -> Self {
+        let offset = src.len() - input.len();
+        let (source_before, source_after) = src.split_at(offset);
+
+        let source_after = match source_after.char_indices().enumerate().take(41).last() {
+            Some((40, (i, _))) => format!("{:?}...", &source_after[..i]),
+            _ => format!("{source_after:?}"),
+        };
+
+        let (row, last_line) = source_before.lines().enumerate().last().unwrap_or_default();
+        let column = last_line.chars().count();
+        Self::Details {
+            message,
+            row,
+            column,
+            offset,
+            source_after,
+            file_path,
+        }
+    }
+}
+
 impl std::error::Error for ParseError {}
 
 impl fmt::Display for ParseError {
@@ -220,6 +431,7 @@ impl fmt::Display for ParseError {
                 column,
                 source_after,
                 file_path,
+                ..
             } => (message, *row, column, source_after, file_path),
         };
 
@@ -350,14 +562,20 @@ fn is_ws(c: char) -> bool {
     matches!(c, ' ' | '\t' | '\r' | '\n')
 }
 
-fn not_ws(c: char) -> bool {
-    !is_ws(c)
+/// In a line statement, line breaks are not white space, because they end the statement.
+fn not_ws(level: Level) -> impl Fn(char) -> bool {
+    move |c| !is_ws(c) || (level.in_line_statement && matches!(c, '\r' | '\n'))
+}
+
+fn is_line_ws(c: char) -> bool {
+    matches!(c, ' ' | '\t')
 }
 
 fn ws<'a, O>(
+    level: Level,
     inner: impl FnMut(&'a str) -> ParseResult<'a, O>,
 ) -> impl FnMut(&'a str) -> ParseResult<'a, O> {
-    delimited(take_till(not_ws), inner, take_till(not_ws))
+    delimited(take_till(not_ws(level)), inner, take_till(not_ws(level)))
 }
 
 /// Skips input until `end` was found, but does not consume it.
@@ -382,7 +600,7 @@ fn skip_till<'a, O>(
     }
 }
 
-fn keyword<'a>(k: &'a str) -> impl FnMut(&'a str) -> ParseResult<'_> {
+fn keyword<'a>(k: &'a str) -> impl FnMut(&'a str) -> ParseResult<'a> {
     move |i: &'a str| -> ParseResult<'a> {
         let (j, v) = identifier(i)?;
         if k == v {
@@ -393,17 +611,19 @@ fn keyword<'a>(k: &'a str) -> impl FnMut(&'a str) -> ParseResult<'_> {
     }
 }
 
+// Identifiers follow the same rules as in Rust: they start with a character with the Unicode
+// property `XID_Start` or with `_`, followed by characters with the property `XID_Continue`.
 fn identifier(input: &str) -> ParseResult<'_> {
     fn start(s: &str) -> ParseResult<'_> {
         s.split_at_position1_complete(
-            |c| !(c.is_alpha() || c == '_' || c >= '\u{0080}'),
+            |c| !(unicode_ident::is_xid_start(c) || c == '_'),
             nom::error::ErrorKind::Alpha,
         )
     }
 
     fn tail(s: &str) -> ParseResult<'_> {
         s.split_at_position1_complete(
-            |c| !(c.is_alphanum() || c == '_' || c >= '\u{0080}'),
+            |c| !unicode_ident::is_xid_continue(c),
             nom::error::ErrorKind::Alpha,
         )
     }
@@ -600,9 +820,9 @@ enum PathOrIdentifier<'a> {
     Identifier(&'a str),
 }
 
-fn path_or_identifier(i: &str) -> ParseResult<'_, PathOrIdentifier<'_>> {
-    let root = ws(opt(tag("::")));
-    let tail = opt(many1(preceded(ws(tag("::")), identifier)));
+fn path_or_identifier(i: &str, level: Level) -> ParseResult<'_, PathOrIdentifier<'_>> {
+    let root = ws(level, opt(tag("::")));
+    let tail = opt(many1(preceded(ws(level, tag("::")), identifier)));
 
     let (i, (root, start, rest)) = tuple((root, identifier, tail))(i)?;
     let rest = rest.as_deref().unwrap_or_default();
@@ -610,7 +830,8 @@ fn path_or_identifier(i: &str) -> ParseResult<'_, PathOrIdentifier<'_>> {
     // The returned identifier can be assumed to be path if:
     // - it is an absolute path (starts with `::`), or
     // - it has multiple components (at least one `::`), or
-    // - the first letter is uppercase
+    // - the first letter is uppercase, or an ASCII character that is not a lowercase letter
+    //   (letters of scripts without cases, like `名前`, are treated like lowercase letters)
     match (root, start, rest) {
         (Some(_), start, tail) => {
             let mut path = Vec::with_capacity(2 + tail.len());
@@ -619,7 +840,11 @@ fn path_or_identifier(i: &str) -> ParseResult<'_, PathOrIdentifier<'_>> {
             path.extend(rest);
             Ok((i, PathOrIdentifier::Path(path)))
         }
-        (None, name, []) if name.chars().next().map_or(true, |c| c.is_lowercase()) => {
+        (None, name, [])
+            if name.chars().next().map_or(true, |c| {
+                c.is_lowercase() || (!c.is_ascii() && !c.is_uppercase())
+            }) =>
+        {
             Ok((i, PathOrIdentifier::Identifier(name)))
         }
         (None, start, tail) => {
@@ -633,16 +858,26 @@ fn path_or_identifier(i: &str) -> ParseResult<'_, PathOrIdentifier<'_>> {
 
 struct State<'a> {
     syntax: &'a Syntax<'a>,
+    // The whole template source, to find the starts of lines for line statements
+    source: &'a str,
     loop_depth: Cell<usize>,
     level: Cell<Level>,
+    // Set while the tag of a line statement is parsed: it ends at the end of the line, so white
+    // space between its tokens may not contain line breaks.
+    in_line_statement: Cell<bool>,
+    // The positions (as remaining lengths) of `{% let name %}` tags without a matching end tag
+    failed_let_blocks: RefCell<HashSet<usize>>,
 }
 
 impl<'a> State<'a> {
-    fn new(syntax: &'a Syntax<'a>) -> State<'a> {
+    fn with_source(source: &'a str, syntax: &'a Syntax<'a>) -> State<'a> {
         State {
             syntax,
+            source,
             loop_depth: Cell::new(0),
             level: Cell::new(Level::default()),
+            in_line_statement: Cell::new(false),
+            failed_let_blocks: RefCell::default(),
         }
     }
 
@@ -659,12 +894,70 @@ impl<'a> State<'a> {
         ret
     }
 
+    /// The level to parse expressions at, which knows if they are part of a line statement
+    fn level(&self) -> Level {
+        Level {
+            in_line_statement: self.in_line_statement.get(),
+            ..self.level.get()
+        }
+    }
+
+    /// Like [`ws()`], but the line statement mode is looked up when `inner` is parsed, not when
+    /// the parser is created, i.e. after the start of the tag was parsed.
+    fn ws<'s, 'i, O>(
+        &'s self,
+        mut inner: impl FnMut(&'i str) -> ParseResult<'i, O> + 's,
+    ) -> impl FnMut(&'i str) -> ParseResult<'i, O> + 's {
+        move |i| ws(self.level(), &mut inner)(i)
+    }
+
+    /// Parses the keyword `k`, or one of its [`Syntax::keyword_aliases`]
+    fn keyword<'i>(&self, k: &'static str) -> impl FnMut(&'i str) -> ParseResult<'i> + '_ {
+        move |i: &'i str| -> ParseResult<'i> {
+            let (j, v) = identifier(i)?;
+            let is_alias = |&(alias, keyword): &(&str, &str)| alias == v && keyword == k;
+            if k == v || self.syntax.keyword_aliases.iter().any(is_alias) {
+                Ok((j, v))
+            } else {
+                Err(nom::Err::Error(error_position!(i, ErrorKind::Tag)))
+            }
+        }
+    }
+
     fn tag_block_start<'i>(&self, i: &'i str) -> ParseResult<'i> {
-        tag(self.syntax.block_start)(i)
+        let (i, (start, in_line_statement)) = alt((
+            map(tag(self.syntax.block_start), |start| (start, false)),
+            map(|i| self.line_statement_start(i), |start| (start, true)),
+        ))(i)?;
+        self.in_line_statement.set(in_line_statement);
+        Ok((i, start))
     }
 
     fn tag_block_end<'i>(&self, i: &'i str) -> ParseResult<'i> {
-        tag(self.syntax.block_end)(i)
+        match self.in_line_statement.get() {
+            false => tag(self.syntax.block_end)(i),
+            true => recognize(pair(
+                take_till(|c| !is_line_ws(c)),
+                alt((tag("\n"), tag("\r\n"), eof)),
+            ))(i),
+        }
+    }
+
+    /// Parses the start of a line statement: its prefix at the start of a line, which may only
+    /// be preceded by spaces and tabs
+    fn line_statement_start<'i>(&self, i: &'i str) -> ParseResult<'i> {
+        let Some(prefix) = self.syntax.line_statement_prefix else {
+            return Err(nom::Err::Error(error_position!(i, ErrorKind::Tag)));
+        };
+        let offset = (i.as_ptr() as usize).wrapping_sub(self.source.as_ptr() as usize);
+        let at_line_start = match offset.checked_sub(1) {
+            Some(before) => self.source.as_bytes().get(before) == Some(&b'\n'),
+            None => offset == 0 && !self.source.is_empty(),
+        };
+        if !at_line_start {
+            return Err(nom::Err::Error(error_position!(i, ErrorKind::Tag)));
+        }
+        recognize(pair(take_till(|c| !is_line_ws(c)), tag(prefix)))(i)
     }
 
     fn tag_comment_start<'i>(&self, i: &'i str) -> ParseResult<'i> {
@@ -676,7 +969,9 @@ impl<'a> State<'a> {
     }
 
     fn tag_expr_start<'i>(&self, i: &'i str) -> ParseResult<'i> {
-        tag(self.syntax.expr_start)(i)
+        let (i, start) = tag(self.syntax.expr_start)(i)?;
+        self.in_line_statement.set(false);
+        Ok((i, start))
     }
 
     fn tag_expr_end<'i>(&self, i: &'i str) -> ParseResult<'i> {
@@ -704,6 +999,19 @@ pub struct Syntax<'a> {
     pub expr_end: &'a str,
     pub comment_start: &'a str,
     pub comment_end: &'a str,
+    /// If set, a line that starts with this prefix, optionally after spaces and tabs, is a block
+    /// tag that ends at the end of the line, e.g. `# for item in items`
+    pub line_statement_prefix: Option<&'a str>,
+    /// The names of tags that are parsed as a [`node::Extension`], which must not be any of the
+    /// [`node::BUILTIN_TAGS`]
+    pub extension_tags: &'a [&'a str],
+    /// The names of the [`Syntax::extension_tags`] that have a body, which ends with a tag
+    /// called `end` followed by the name, e.g. `{% markdown %}...{% endmarkdown %}`
+    pub extension_block_tags: &'a [&'a str],
+    /// Alternative names of built-in tags, as `(alias, tag)`, e.g. `("fi", "endif")`
+    ///
+    /// The built-in names can still be used, too.
+    pub keyword_aliases: &'a [(&'a str, &'a str)],
 }
 
 impl Default for Syntax<'static> {
@@ -715,23 +1023,45 @@ impl Default for Syntax<'static> {
             expr_end: "}}",
             comment_start: "{#",
             comment_end: "#}",
+            line_statement_prefix: None,
+            extension_tags: &[],
+            extension_block_tags: &[],
+            keyword_aliases: &[],
         }
     }
 }
 
+impl<'a> Syntax<'a> {
+    /// Returns the built-in tag that `name` is an alias of, or `name` itself
+    pub fn keyword<'n>(&self, name: &'n str) -> &'n str
+    where
+        'a: 'n,
+    {
+        self.keyword_aliases
+            .iter()
+            .find_map(|&(alias, keyword)| (alias == name).then_some(keyword))
+            .unwrap_or(name)
+    }
+}
+
 #[derive(Clone, Copy, Default)]
-pub(crate) struct Level(u8);
+pub(crate) struct Level {
+    depth: u8,
+    // Copied from `State` when an expression is parsed, see [`not_ws()`]
+    in_line_statement: bool,
+}
 
 impl Level {
     fn nest(self, i: &str) -> ParseResult<'_, Level> {
-        if self.0 >= Self::MAX_DEPTH {
+        if self.depth >= Self::MAX_DEPTH {
             return Err(nom::Err::Failure(ErrorContext::new(
                 "your template code is too deeply nested, or last expression is too complex",
                 i,
             )));
         }
 
-        Ok((i, Level(self.0 + 1)))
+        let depth = self.depth + 1;
+        Ok((i, Level { depth, ..self }))
     }
 
     const MAX_DEPTH: u8 = 128;
@@ -741,14 +1071,14 @@ fn filter<'a>(
     i: &'a str,
     level: &mut Level,
 ) -> ParseResult<'a, (&'a str, Option<Vec<WithSpan<'a, Expr<'a>>>>)> {
-    let (j, _) = take_till(not_ws)(i)?;
+    let (j, _) = take_till(not_ws(*level))(i)?;
     let had_spaces = i.len() != j.len();
     let (j, _) = pair(char('|'), not(char('|')))(j)?;
 
     if !had_spaces {
         *level = level.nest(i)?.1;
         cut(pair(
-            ws(identifier),
+            ws(*level, identifier),
             opt(|i| Expr::arguments(i, *level, false)),
         ))(j)
     } else {
@@ -6,12 +6,12 @@ use nom::character::complete::char;
 use nom::combinator::{complete, consumed, cut, eof, map, not, opt, peek, recognize, value};
 use nom::error::ErrorKind;
 use nom::error_position;
-use nom::multi::{many0, many1, separated_list0};
+use nom::multi::{many0, many1, separated_list0, separated_list1};
 use nom::sequence::{delimited, pair, preceded, tuple};
 
 use crate::{
-    filter, identifier, is_ws, keyword, not_ws, skip_till, str_lit, ws, ErrorContext, Expr, Filter,
-    ParseResult, State, Target, WithSpan,
+    filter, identifier, is_ws, not_ws, skip_till, str_lit, ErrorContext, Expr, Filter, ParseResult,
+    State, Target, WithSpan,
 };
 
 #[derive(Debug, PartialEq)]
@@ -21,6 +21,7 @@ pub enum Node<'a> {
     Expr(Ws, WithSpan<'a, Expr<'a>>),
     Call(WithSpan<'a, Call<'a>>),
     Let(WithSpan<'a, Let<'a>>),
+    LetBlock(WithSpan<'a, LetBlock<'a>>),
     If(WithSpan<'a, If<'a>>),
     Match(WithSpan<'a, Match<'a>>),
     Loop(Box<WithSpan<'a, Loop<'a>>>),
@@ -28,21 +29,36 @@ pub enum Node<'a> {
     BlockDef(WithSpan<'a, BlockDef<'a>>),
     Include(WithSpan<'a, Include<'a>>),
     Import(WithSpan<'a, Import<'a>>),
+    FromImport(WithSpan<'a, FromImport<'a>>),
     Macro(WithSpan<'a, Macro<'a>>),
     Raw(WithSpan<'a, Raw<'a>>),
-    Break(WithSpan<'a, Ws>),
-    Continue(WithSpan<'a, Ws>),
+    /// `{% break %}`, or `{% break if cond %}`
+    Break(WithSpan<'a, Ws>, Option<WithSpan<'a, Expr<'a>>>),
+    /// `{% continue %}`, or `{% continue if cond %}`
+    Continue(WithSpan<'a, Ws>, Option<WithSpan<'a, Expr<'a>>>),
     FilterBlock(WithSpan<'a, FilterBlock<'a>>),
+    With(WithSpan<'a, With<'a>>),
+    Trans(WithSpan<'a, Trans<'a>>),
+    Do(WithSpan<'a, Do<'a>>),
+    Cache(WithSpan<'a, Cache<'a>>),
+    Once(WithSpan<'a, Once<'a>>),
+    WhitespaceBlock(WithSpan<'a, WhitespaceBlock<'a>>),
+    AutoEscape(WithSpan<'a, AutoEscape<'a>>),
+    Extension(WithSpan<'a, Extension<'a>>),
 }
 
 impl<'a> Node<'a> {
     pub(super) fn many(i: &'a str, s: &State<'_>) -> ParseResult<'a, Vec<Self>> {
-        complete(many0(alt((
+        complete(many0(|i| Self::one(i, s)))(i)
+    }
+
+    pub(super) fn one(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
+        alt((
             map(|i| Lit::parse(i, s), Self::Lit),
             map(|i| Comment::parse(i, s), Self::Comment),
             |i| Self::expr(i, s),
             |i| Self::parse(i, s),
-        ))))(i)
+        ))(i)
     }
 
     fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
@@ -58,26 +74,42 @@ impl<'a> Node<'a> {
         let (j, tag) = preceded(
             |i| s.tag_block_start(i),
             peek(preceded(
-                pair(opt(Whitespace::parse), take_till(not_ws)),
+                pair(opt(Whitespace::parse), |i| take_till(not_ws(s.level()))(i)),
                 identifier,
             )),
         )(i)?;
 
-        let func = match tag {
+        let func = match s.syntax.keyword(tag) {
             "call" => |i, s| wrap(Self::Call, Call::parse(i, s)),
-            "let" | "set" => |i, s| wrap(Self::Let, Let::parse(i, s)),
+            "let" | "set" => |i, s| {
+                alt((
+                    map(|i| LetBlock::parse(i, s), Self::LetBlock),
+                    map(|i| Let::parse(i, s), Self::Let),
+                ))(i)
+            },
             "if" => |i, s| wrap(Self::If, If::parse(i, s)),
             "for" => |i, s| wrap(|n| Self::Loop(Box::new(n)), Loop::parse(i, s)),
             "match" => |i, s| wrap(Self::Match, Match::parse(i, s)),
-            "extends" => |i, _s| wrap(Self::Extends, Extends::parse(i)),
-            "include" => |i, _s| wrap(Self::Include, Include::parse(i)),
-            "import" => |i, _s| wrap(Self::Import, Import::parse(i)),
+            "extends" => |i, s| wrap(Self::Extends, Extends::parse(i, s)),
+            "include" => |i, s| wrap(Self::Include, Include::parse(i, s)),
+            "import" => |i, s| wrap(Self::Import, Import::parse(i, s)),
+            "from" => |i, s| wrap(Self::FromImport, FromImport::parse(i, s)),
             "block" => |i, s| wrap(Self::BlockDef, BlockDef::parse(i, s)),
             "macro" => |i, s| wrap(Self::Macro, Macro::parse(i, s)),
             "raw" => |i, s| wrap(Self::Raw, Raw::parse(i, s)),
             "break" => |i, s| Self::r#break(i, s),
             "continue" => |i, s| Self::r#continue(i, s),
             "filter" => |i, s| wrap(Self::FilterBlock, FilterBlock::parse(i, s)),
+            "with" => |i, s| wrap(Self::With, With::parse(i, s)),
+            "trans" => |i, s| wrap(Self::Trans, Trans::parse(i, s)),
+            "do" => |i, s| wrap(Self::Do, Do::parse(i, s)),
+            "cache" => |i, s| wrap(Self::Cache, Cache::parse(i, s)),
+            "once" => |i, s| wrap(Self::Once, Once::parse(i, s)),
+            "whitespace" => |i, s| wrap(Self::WhitespaceBlock, WhitespaceBlock::parse(i, s)),
+            "autoescape" => |i, s| wrap(Self::AutoEscape, AutoEscape::parse(i, s)),
+            tag if s.syntax.extension_tags.contains(&tag) => {
+                |i, s| wrap(Self::Extension, Extension::parse(i, s))
+            }
             _ => {
                 return Err(ErrorContext::from_err(nom::Err::Error(error_position!(
                     i,
@@ -90,7 +122,7 @@ impl<'a> Node<'a> {
 
         let (i, closed) = cut(alt((
             value(true, |i| s.tag_block_end(i)),
-            value(false, ws(eof)),
+            value(false, s.ws(eof)),
         )))(i)?;
         match closed {
             true => Ok((i, node)),
@@ -101,33 +133,41 @@ impl<'a> Node<'a> {
     fn r#break(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
         let mut p = tuple((
             opt(Whitespace::parse),
-            ws(keyword("break")),
+            s.ws(s.keyword("break")),
+            opt(preceded(
+                s.ws(s.keyword("if")),
+                cut(s.ws(|i| Expr::parse(i, s.level()))),
+            )),
             opt(Whitespace::parse),
         ));
-        let (j, (pws, _, nws)) = p(i)?;
+        let (j, (pws, _, cond, nws)) = p(i)?;
         if !s.is_in_loop() {
             return Err(nom::Err::Failure(ErrorContext::new(
                 "you can only `break` inside a `for` loop",
                 i,
             )));
         }
-        Ok((j, Self::Break(WithSpan::new(Ws(pws, nws), i))))
+        Ok((j, Self::Break(WithSpan::new(Ws(pws, nws), i), cond)))
     }
 
     fn r#continue(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
         let mut p = tuple((
             opt(Whitespace::parse),
-            ws(keyword("continue")),
+            s.ws(s.keyword("continue")),
+            opt(preceded(
+                s.ws(s.keyword("if")),
+                cut(s.ws(|i| Expr::parse(i, s.level()))),
+            )),
             opt(Whitespace::parse),
         ));
-        let (j, (pws, _, nws)) = p(i)?;
+        let (j, (pws, _, cond, nws)) = p(i)?;
         if !s.is_in_loop() {
             return Err(nom::Err::Failure(ErrorContext::new(
                 "you can only `continue` inside a `for` loop",
                 i,
             )));
         }
-        Ok((j, Self::Continue(WithSpan::new(Ws(pws, nws), i))))
+        Ok((j, Self::Continue(WithSpan::new(Ws(pws, nws), i), cond)))
     }
 
     fn expr(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
@@ -136,13 +176,13 @@ impl<'a> Node<'a> {
             |i| s.tag_expr_start(i),
             cut(pair(
                 opt(Whitespace::parse),
-                ws(|i| Expr::parse(i, s.level.get())),
+                s.ws(|i| Expr::parse(i, s.level())),
             )),
         )(i)?;
 
         let (i, (nws, closed)) = cut(pair(
             opt(Whitespace::parse),
-            alt((value(true, |i| s.tag_expr_end(i)), value(false, ws(eof)))),
+            alt((value(true, |i| s.tag_expr_end(i)), value(false, s.ws(eof)))),
         ))(i)?;
         match closed {
             true => Ok((i, Self::Expr(Ws(pws, nws), expr))),
@@ -157,6 +197,7 @@ impl<'a> Node<'a> {
             Self::Expr(_, span) => span.span,
             Self::Call(span) => span.span,
             Self::Let(span) => span.span,
+            Self::LetBlock(span) => span.span,
             Self::If(span) => span.span,
             Self::Match(span) => span.span,
             Self::Loop(span) => span.span,
@@ -164,11 +205,20 @@ impl<'a> Node<'a> {
             Self::BlockDef(span) => span.span,
             Self::Include(span) => span.span,
             Self::Import(span) => span.span,
+            Self::FromImport(span) => span.span,
             Self::Macro(span) => span.span,
             Self::Raw(span) => span.span,
-            Self::Break(span) => span.span,
-            Self::Continue(span) => span.span,
+            Self::Break(span, _) => span.span,
+            Self::Continue(span, _) => span.span,
             Self::FilterBlock(span) => span.span,
+            Self::With(span) => span.span,
+            Self::Trans(span) => span.span,
+            Self::Do(span) => span.span,
+            Self::Cache(span) => span.span,
+            Self::Once(span) => span.span,
+            Self::WhitespaceBlock(span) => span.span,
+            Self::AutoEscape(span) => span.span,
+            Self::Extension(span) => span.span,
         }
     }
 }
@@ -186,7 +236,7 @@ impl<'a> When<'a> {
         let mut p = tuple((
             |i| s.tag_block_start(i),
             opt(Whitespace::parse),
-            ws(keyword("else")),
+            s.ws(s.keyword("else")),
             cut(tuple((
                 opt(Whitespace::parse),
                 |i| s.tag_block_end(i),
@@ -213,9 +263,9 @@ impl<'a> When<'a> {
         let mut p = tuple((
             |i| s.tag_block_start(i),
             opt(Whitespace::parse),
-            ws(keyword("when")),
+            s.ws(s.keyword("when")),
             cut(tuple((
-                ws(|i| Target::parse(i, s)),
+                s.ws(|i| Target::parse(i, s.level())),
                 opt(Whitespace::parse),
                 |i| s.tag_block_end(i),
                 cut(|i| Node::many(i, s)),
@@ -250,9 +300,9 @@ impl<'a> Cond<'a> {
             |i| s.tag_block_start(i),
             opt(Whitespace::parse),
             alt((
-                preceded(ws(keyword("else")), opt(|i| CondTest::parse(i, s))),
+                preceded(s.ws(s.keyword("else")), opt(|i| CondTest::parse(i, s))),
                 preceded(
-                    ws(keyword("elif")),
+                    s.ws(s.keyword("elif")),
                     cut(map(|i| CondTest::parse_cond(i, s), Some)),
                 ),
             )),
@@ -282,17 +332,17 @@ pub struct CondTest<'a> {
 
 impl<'a> CondTest<'a> {
     fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
-        preceded(ws(keyword("if")), cut(|i| Self::parse_cond(i, s)))(i)
+        preceded(s.ws(s.keyword("if")), cut(|i| Self::parse_cond(i, s)))(i)
     }
 
     fn parse_cond(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
         let (i, (target, expr)) = pair(
             opt(delimited(
-                ws(alt((keyword("let"), keyword("set")))),
-                ws(|i| Target::parse(i, s)),
-                ws(char('=')),
+                s.ws(alt((s.keyword("let"), s.keyword("set")))),
+                s.ws(|i| Target::parse(i, s.level())),
+                s.ws(char('=')),
             )),
-            ws(|i| Expr::parse(i, s.level.get())),
+            s.ws(|i| Expr::parse(i, s.level())),
         )(i)?;
         Ok((i, Self { target, expr }))
     }
@@ -338,13 +388,13 @@ impl<'a> Loop<'a> {
 
         let start = i;
         let if_cond = preceded(
-            ws(keyword("if")),
-            cut(ws(|i| Expr::parse(i, s.level.get()))),
+            s.ws(s.keyword("if")),
+            cut(s.ws(|i| Expr::parse(i, s.level()))),
         );
 
         let else_block = |i| {
             let mut p = preceded(
-                ws(keyword("else")),
+                s.ws(s.keyword("else")),
                 cut(tuple((
                     opt(Whitespace::parse),
                     delimited(
@@ -361,12 +411,12 @@ impl<'a> Loop<'a> {
 
         let mut p = tuple((
             opt(Whitespace::parse),
-            ws(keyword("for")),
+            s.ws(s.keyword("for")),
             cut(tuple((
-                ws(|i| Target::parse(i, s)),
-                ws(keyword("in")),
+                s.ws(|i| Target::parse(i, s.level())),
+                s.ws(s.keyword("in")),
                 cut(tuple((
-                    ws(|i| Expr::parse(i, s.level.get())),
+                    s.ws(|i| Expr::parse(i, s.level())),
                     opt(if_cond),
                     opt(Whitespace::parse),
                     |i| s.tag_block_end(i),
@@ -376,7 +426,7 @@ impl<'a> Loop<'a> {
                             |i| s.tag_block_start(i),
                             opt(Whitespace::parse),
                             opt(else_block),
-                            ws(keyword("endfor")),
+                            s.ws(s.keyword("endfor")),
                             opt(Whitespace::parse),
                         ))),
                     ))),
@@ -416,21 +466,21 @@ pub struct Macro<'a> {
 
 impl<'a> Macro<'a> {
     fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
-        fn parameters(i: &str) -> ParseResult<'_, Vec<&str>> {
+        let parameters = |i: &'a str| -> ParseResult<'a, Vec<&'a str>> {
             delimited(
-                ws(char('(')),
-                separated_list0(char(','), ws(identifier)),
-                tuple((opt(ws(char(','))), char(')'))),
+                s.ws(char('(')),
+                separated_list0(char(','), s.ws(identifier)),
+                tuple((opt(s.ws(char(','))), char(')'))),
             )(i)
-        }
+        };
 
         let start_s = i;
         let mut start = tuple((
             opt(Whitespace::parse),
-            ws(keyword("macro")),
+            s.ws(s.keyword("macro")),
             cut(tuple((
-                ws(identifier),
-                opt(ws(parameters)),
+                s.ws(identifier),
+                opt(s.ws(parameters)),
                 opt(Whitespace::parse),
                 |i| s.tag_block_end(i),
             ))),
@@ -448,10 +498,10 @@ impl<'a> Macro<'a> {
             cut(tuple((
                 |i| s.tag_block_start(i),
                 opt(Whitespace::parse),
-                ws(keyword("endmacro")),
+                s.ws(s.keyword("endmacro")),
                 cut(preceded(
                     opt(|before| {
-                        let (after, end_name) = ws(identifier)(before)?;
+                        let (after, end_name) = s.ws(identifier)(before)?;
                         check_end_name(before, after, name, end_name, "macro")
                     }),
                     opt(Whitespace::parse),
@@ -486,16 +536,16 @@ pub struct FilterBlock<'a> {
 
 impl<'a> FilterBlock<'a> {
     fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
-        let mut level = s.level.get();
+        let mut level = s.level();
         let start_s = i;
         let mut start = tuple((
             opt(Whitespace::parse),
-            ws(keyword("filter")),
+            s.ws(s.keyword("filter")),
             cut(tuple((
-                ws(identifier),
-                opt(|i| Expr::arguments(i, s.level.get(), false)),
+                s.ws(identifier),
+                opt(|i| Expr::arguments(i, s.level(), false)),
                 many0(|i| filter(i, &mut level).map(|(j, (name, params))| (j, (name, params, i)))),
-                ws(|i| Ok((i, ()))),
+                s.ws(|i| Ok((i, ()))),
                 opt(Whitespace::parse),
                 |i| s.tag_block_end(i),
             ))),
@@ -524,7 +574,7 @@ impl<'a> FilterBlock<'a> {
             cut(tuple((
                 |i| s.tag_block_start(i),
                 opt(Whitespace::parse),
-                ws(keyword("endfilter")),
+                s.ws(s.keyword("endfilter")),
                 opt(Whitespace::parse),
             ))),
         )));
@@ -545,6 +595,458 @@ impl<'a> FilterBlock<'a> {
     }
 }
 
+/// `{% with a = x, b = y %}...{% endwith %}`: the variables are only visible inside the block
+#[derive(Debug, PartialEq)]
+pub struct With<'a> {
+    pub ws1: Ws,
+    pub vars: Vec<(Target<'a>, WithSpan<'a, Expr<'a>>)>,
+    pub nodes: Vec<Node<'a>>,
+    pub ws2: Ws,
+}
+
+impl<'a> With<'a> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let start_s = i;
+        let var = pair(
+            s.ws(|i| Target::parse(i, s.level())),
+            preceded(s.ws(char('=')), s.ws(|i| Expr::parse(i, s.level()))),
+        );
+        let mut start = tuple((
+            opt(Whitespace::parse),
+            s.ws(s.keyword("with")),
+            cut(tuple((
+                separated_list0(char(','), var),
+                opt(Whitespace::parse),
+                |i| s.tag_block_end(i),
+            ))),
+        ));
+        let (i, (pws1, _, (vars, nws1, _))) = start(i)?;
+
+        let mut end = cut(tuple((
+            |i| Node::many(i, s),
+            cut(tuple((
+                |i| s.tag_block_start(i),
+                opt(Whitespace::parse),
+                s.ws(s.keyword("endwith")),
+                opt(Whitespace::parse),
+            ))),
+        )));
+        let (i, (nodes, (_, pws2, _, nws2))) = end(i)?;
+
+        Ok((
+            i,
+            WithSpan::new(
+                Self {
+                    ws1: Ws(pws1, nws1),
+                    vars,
+                    nodes,
+                    ws2: Ws(pws2, nws2),
+                },
+                start_s,
+            ),
+        ))
+    }
+}
+
+/// `{% cache key %}...{% endcache %}`: the content is rendered once per key, and then reused
+#[derive(Debug, PartialEq)]
+pub struct Cache<'a> {
+    pub ws1: Ws,
+    pub key: WithSpan<'a, Expr<'a>>,
+    pub nodes: Vec<Node<'a>>,
+    pub ws2: Ws,
+}
+
+impl<'a> Cache<'a> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let start_s = i;
+        let mut start = tuple((
+            opt(Whitespace::parse),
+            s.ws(s.keyword("cache")),
+            cut(tuple((
+                s.ws(|i| Expr::parse(i, s.level())),
+                opt(Whitespace::parse),
+                |i| s.tag_block_end(i),
+            ))),
+        ));
+        let (i, (pws1, _, (key, nws1, _))) = start(i)?;
+
+        let mut end = cut(tuple((
+            |i| Node::many(i, s),
+            cut(tuple((
+                |i| s.tag_block_start(i),
+                opt(Whitespace::parse),
+                s.ws(s.keyword("endcache")),
+                opt(Whitespace::parse),
+            ))),
+        )));
+        let (i, (nodes, (_, pws2, _, nws2))) = end(i)?;
+
+        Ok((
+            i,
+            WithSpan::new(
+                Self {
+                    ws1: Ws(pws1, nws1),
+                    key,
+                    nodes,
+                    ws2: Ws(pws2, nws2),
+                },
+                start_s,
+            ),
+        ))
+    }
+}
+
+/// `{% once %}...{% endonce %}`: the content is only rendered the first time in a process
+#[derive(Debug, PartialEq)]
+pub struct Once<'a> {
+    pub ws1: Ws,
+    pub nodes: Vec<Node<'a>>,
+    pub ws2: Ws,
+}
+
+impl<'a> Once<'a> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let start_s = i;
+        let mut start = tuple((
+            opt(Whitespace::parse),
+            s.ws(s.keyword("once")),
+            cut(tuple((opt(Whitespace::parse), |i| s.tag_block_end(i)))),
+        ));
+        let (i, (pws1, _, (nws1, _))) = start(i)?;
+
+        let mut end = cut(tuple((
+            |i| Node::many(i, s),
+            cut(tuple((
+                |i| s.tag_block_start(i),
+                opt(Whitespace::parse),
+                s.ws(s.keyword("endonce")),
+                opt(Whitespace::parse),
+            ))),
+        )));
+        let (i, (nodes, (_, pws2, _, nws2))) = end(i)?;
+
+        Ok((
+            i,
+            WithSpan::new(
+                Self {
+                    ws1: Ws(pws1, nws1),
+                    nodes,
+                    ws2: Ws(pws2, nws2),
+                },
+                start_s,
+            ),
+        ))
+    }
+}
+
+/// `{% whitespace suppress %}...{% endwhitespace %}`: the content is rendered with another
+/// whitespace handling than the rest of the template
+///
+/// The handling is used for all tags in the block that have no whitespace marker.
+#[derive(Debug, PartialEq)]
+pub struct WhitespaceBlock<'a> {
+    pub ws1: Ws,
+    pub mode: Whitespace,
+    pub nodes: Vec<Node<'a>>,
+    pub ws2: Ws,
+}
+
+impl<'a> WhitespaceBlock<'a> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let start_s = i;
+        let mut start = tuple((
+            opt(Whitespace::parse),
+            s.ws(s.keyword("whitespace")),
+            cut(tuple((s.ws(identifier), opt(Whitespace::parse), |i| {
+                s.tag_block_end(i)
+            }))),
+        ));
+        let (j, (pws1, _, (mode, nws1, _))) = start(i)?;
+        let mode = match mode {
+            "preserve" => Whitespace::Preserve,
+            "suppress" => Whitespace::Suppress,
+            "minimize" => Whitespace::Minimize,
+            _ => {
+                return Err(nom::Err::Failure(ErrorContext::new(
+                    format!(
+                        "unknown whitespace handling `{mode}`, expected `preserve`, `suppress` \
+                         or `minimize`"
+                    ),
+                    i,
+                )));
+            }
+        };
+
+        let mut end = cut(tuple((
+            |i| Node::many(i, s),
+            cut(tuple((
+                |i| s.tag_block_start(i),
+                opt(Whitespace::parse),
+                s.ws(s.keyword("endwhitespace")),
+                opt(Whitespace::parse),
+            ))),
+        )));
+        let (i, (nodes, (_, pws2, _, nws2))) = end(j)?;
+
+        Ok((
+            i,
+            WithSpan::new(
+                Self {
+                    ws1: Ws(pws1, nws1),
+                    mode,
+                    nodes,
+                    ws2: Ws(pws2, nws2),
+                },
+                start_s,
+            ),
+        ))
+    }
+}
+
+/// `{% autoescape "html" %}...{% endautoescape %}`: the content is escaped with another escaper
+/// than the rest of the template
+#[derive(Debug, PartialEq)]
+pub struct AutoEscape<'a> {
+    pub ws1: Ws,
+    pub escaping: Escaping<'a>,
+    pub nodes: Vec<Node<'a>>,
+    pub ws2: Ws,
+}
+
+/// The argument of an `{% autoescape %}` block
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Escaping<'a> {
+    /// `true`: the escaper of the template
+    Template,
+    /// `false`: no escaping
+    Disabled,
+    /// `"name"`: the escaper of the extension `name`
+    Named(&'a str),
+}
+
+impl<'a> AutoEscape<'a> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let start_s = i;
+        let mut start = tuple((
+            opt(Whitespace::parse),
+            s.ws(s.keyword("autoescape")),
+            cut(tuple((
+                s.ws(alt((
+                    value(Escaping::Template, s.keyword("true")),
+                    value(Escaping::Disabled, s.keyword("false")),
+                    map(str_lit, Escaping::Named),
+                ))),
+                opt(Whitespace::parse),
+                |i| s.tag_block_end(i),
+            ))),
+        ));
+        let (i, (pws1, _, (escaping, nws1, _))) = start(i)?;
+
+        let mut end = cut(tuple((
+            |i| Node::many(i, s),
+            cut(tuple((
+                |i| s.tag_block_start(i),
+                opt(Whitespace::parse),
+                s.ws(s.keyword("endautoescape")),
+                opt(Whitespace::parse),
+            ))),
+        )));
+        let (i, (nodes, (_, pws2, _, nws2))) = end(i)?;
+
+        Ok((
+            i,
+            WithSpan::new(
+                Self {
+                    ws1: Ws(pws1, nws1),
+                    escaping,
+                    nodes,
+                    ws2: Ws(pws2, nws2),
+                },
+                start_s,
+            ),
+        ))
+    }
+}
+
+/// `{% name args %}`: a tag that is not built into rinja, but one of the
+/// [`Syntax::extension_tags`][crate::Syntax::extension_tags]
+///
+/// The arguments are not parsed, but are left to whoever handles the tag. If the tag is one of
+/// the [`Syntax::extension_block_tags`][crate::Syntax::extension_block_tags], it has a body that
+/// ends with `{% endname %}`.
+#[derive(Debug, PartialEq)]
+pub struct Extension<'a> {
+    pub ws: Ws,
+    pub name: &'a str,
+    /// The text between the name and the end of the tag, without surrounding white spaces
+    pub args: &'a str,
+    /// The nodes of the body, and the whitespace handling of the end tag, if it is a block tag
+    pub body: Option<(Vec<Node<'a>>, Ws)>,
+}
+
+impl<'a> Extension<'a> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let start = i;
+        let (i, (pws, name)) = pair(opt(Whitespace::parse), s.ws(identifier))(i)?;
+        let end = pair(opt(Whitespace::parse), |i| s.tag_block_end(i));
+        let Ok((i, args)) = recognize(skip_till(end))(i) else {
+            return Err(ErrorContext::unclosed("block", s.syntax.block_end, start).into());
+        };
+        let (i, nws) = opt(Whitespace::parse)(i)?;
+        if !s.syntax.extension_block_tags.contains(&name) {
+            return Ok((
+                i,
+                WithSpan::new(
+                    Self {
+                        ws: Ws(pws, nws),
+                        name,
+                        args: args.trim_matches(is_ws),
+                        body: None,
+                    },
+                    start,
+                ),
+            ));
+        }
+
+        let end_name = |i: &'a str| -> ParseResult<'a> {
+            let (j, v) = identifier(i)?;
+            match v.strip_prefix("end") == Some(name) {
+                true => Ok((j, v)),
+                false => Err(nom::Err::Error(error_position!(i, ErrorKind::Tag))),
+            }
+        };
+        let mut end = cut(tuple((
+            |i| s.tag_block_end(i),
+            |i| Node::many(i, s),
+            cut(tuple((
+                |i| s.tag_block_start(i),
+                opt(Whitespace::parse),
+                s.ws(end_name),
+                opt(Whitespace::parse),
+            ))),
+        )));
+        let (i, (_, nodes, (_, pws2, _, nws2))) = end(i)?;
+        Ok((
+            i,
+            WithSpan::new(
+                Self {
+                    ws: Ws(pws, nws),
+                    name,
+                    args: args.trim_matches(is_ws),
+                    body: Some((nodes, Ws(pws2, nws2))),
+                },
+                start,
+            ),
+        ))
+    }
+}
+
+/// The names of all built-in tags, including the tags that continue or end a block like
+/// `else` or `endif`, which cannot be used as [`Syntax::extension_tags`][crate::Syntax::extension_tags]
+pub const BUILTIN_TAGS: &[&str] = &[
+    "autoescape",
+    "block",
+    "break",
+    "cache",
+    "call",
+    "continue",
+    "do",
+    "elif",
+    "else",
+    "endautoescape",
+    "endblock",
+    "endcache",
+    "endfilter",
+    "endfor",
+    "endif",
+    "endlet",
+    "endmacro",
+    "endmatch",
+    "endonce",
+    "endraw",
+    "endset",
+    "endtrans",
+    "endwhitespace",
+    "endwith",
+    "extends",
+    "filter",
+    "for",
+    "from",
+    "if",
+    "import",
+    "include",
+    "let",
+    "macro",
+    "match",
+    "once",
+    "raw",
+    "set",
+    "trans",
+    "when",
+    "whitespace",
+    "with",
+];
+
+/// `{% trans "message-id" name = expr %}fallback{% endtrans %}`: a translated message
+///
+/// The arguments are visible inside of the fallback content, which is rendered if there is
+/// no translation for the message.
+#[derive(Debug, PartialEq)]
+pub struct Trans<'a> {
+    pub ws1: Ws,
+    pub id: &'a str,
+    pub args: Vec<(&'a str, WithSpan<'a, Expr<'a>>)>,
+    pub nodes: Vec<Node<'a>>,
+    pub ws2: Ws,
+}
+
+impl<'a> Trans<'a> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let start_s = i;
+        let arg = pair(
+            s.ws(identifier),
+            preceded(s.ws(char('=')), s.ws(|i| Expr::parse(i, s.level()))),
+        );
+        let mut start = tuple((
+            opt(Whitespace::parse),
+            s.ws(s.keyword("trans")),
+            cut(tuple((
+                s.ws(str_lit),
+                separated_list0(char(','), arg),
+                opt(Whitespace::parse),
+                |i| s.tag_block_end(i),
+            ))),
+        ));
+        let (i, (pws1, _, (id, args, nws1, _))) = start(i)?;
+
+        let mut end = cut(tuple((
+            |i| Node::many(i, s),
+            cut(tuple((
+                |i| s.tag_block_start(i),
+                opt(Whitespace::parse),
+                s.ws(s.keyword("endtrans")),
+                opt(Whitespace::parse),
+            ))),
+        )));
+        let (i, (nodes, (_, pws2, _, nws2))) = end(i)?;
+
+        Ok((
+            i,
+            WithSpan::new(
+                Self {
+                    ws1: Ws(pws1, nws1),
+                    id,
+                    args,
+                    nodes,
+                    ws2: Ws(pws2, nws2),
+                },
+                start_s,
+            ),
+        ))
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Import<'a> {
     pub ws: Ws,
@@ -553,15 +1055,15 @@ pub struct Import<'a> {
 }
 
 impl<'a> Import<'a> {
-    fn parse(i: &'a str) -> ParseResult<'a, WithSpan<'a, Self>> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
         let start = i;
         let mut p = tuple((
             opt(Whitespace::parse),
-            ws(keyword("import")),
+            s.ws(s.keyword("import")),
             cut(tuple((
-                ws(str_lit),
-                ws(keyword("as")),
-                cut(pair(ws(identifier), opt(Whitespace::parse))),
+                s.ws(str_lit),
+                s.ws(s.keyword("as")),
+                cut(pair(s.ws(identifier), opt(Whitespace::parse))),
             ))),
         ));
         let (i, (pws, _, (path, _, (scope, nws)))) = p(i)?;
@@ -579,6 +1081,48 @@ impl<'a> Import<'a> {
     }
 }
 
+/// `{% from "macros.html" import input, button as btn %}`: imports single macros into the
+/// current namespace, optionally under a different name
+#[derive(Debug, PartialEq)]
+pub struct FromImport<'a> {
+    pub ws: Ws,
+    pub path: &'a str,
+    /// The imported macros, as `(name, alias)`
+    pub names: Vec<(&'a str, Option<&'a str>)>,
+}
+
+impl<'a> FromImport<'a> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let start = i;
+        let name = pair(
+            s.ws(identifier),
+            opt(preceded(s.ws(s.keyword("as")), cut(s.ws(identifier)))),
+        );
+        let mut p = tuple((
+            opt(Whitespace::parse),
+            s.ws(s.keyword("from")),
+            cut(tuple((
+                s.ws(str_lit),
+                s.ws(s.keyword("import")),
+                separated_list1(s.ws(char(',')), name),
+                opt(Whitespace::parse),
+            ))),
+        ));
+        let (i, (pws, _, (path, _, names, nws))) = p(i)?;
+        Ok((
+            i,
+            WithSpan::new(
+                Self {
+                    ws: Ws(pws, nws),
+                    path,
+                    names,
+                },
+                start,
+            ),
+        ))
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Call<'a> {
     pub ws: Ws,
@@ -592,11 +1136,11 @@ impl<'a> Call<'a> {
         let start = i;
         let mut p = tuple((
             opt(Whitespace::parse),
-            ws(keyword("call")),
+            s.ws(s.keyword("call")),
             cut(tuple((
-                opt(tuple((ws(identifier), ws(tag("::"))))),
-                ws(identifier),
-                opt(ws(|nested| Expr::arguments(nested, s.level.get(), true))),
+                opt(tuple((s.ws(identifier), s.ws(tag("::"))))),
+                s.ws(identifier),
+                opt(s.ws(|nested| Expr::arguments(nested, s.level(), true))),
                 opt(Whitespace::parse),
             ))),
         ));
@@ -631,20 +1175,20 @@ impl<'a> Match<'a> {
         let start = i;
         let mut p = tuple((
             opt(Whitespace::parse),
-            ws(keyword("match")),
+            s.ws(s.keyword("match")),
             cut(tuple((
-                ws(|i| Expr::parse(i, s.level.get())),
+                s.ws(|i| Expr::parse(i, s.level())),
                 opt(Whitespace::parse),
                 |i| s.tag_block_end(i),
                 cut(tuple((
-                    ws(many0(ws(value((), |i| Comment::parse(i, s))))),
+                    s.ws(many0(s.ws(value((), |i| Comment::parse(i, s))))),
                     many1(|i| When::when(i, s)),
                     cut(tuple((
                         opt(|i| When::r#match(i, s)),
                         cut(tuple((
-                            ws(|i| s.tag_block_start(i)),
+                            s.ws(|i| s.tag_block_start(i)),
                             opt(Whitespace::parse),
-                            ws(keyword("endmatch")),
+                            s.ws(s.keyword("endmatch")),
                             opt(Whitespace::parse),
                         ))),
                     ))),
@@ -677,6 +1221,8 @@ impl<'a> Match<'a> {
 pub struct BlockDef<'a> {
     pub ws1: Ws,
     pub name: &'a str,
+    /// `{% block name required %}`, which templates that extend this one must override
+    pub required: bool,
     pub nodes: Vec<Node<'a>>,
     pub ws2: Ws,
 }
@@ -686,22 +1232,25 @@ impl<'a> BlockDef<'a> {
         let start_s = i;
         let mut start = tuple((
             opt(Whitespace::parse),
-            ws(keyword("block")),
-            cut(tuple((ws(identifier), opt(Whitespace::parse), |i| {
-                s.tag_block_end(i)
-            }))),
+            s.ws(s.keyword("block")),
+            cut(tuple((
+                s.ws(identifier),
+                opt(s.ws(s.keyword("required"))),
+                opt(Whitespace::parse),
+                |i| s.tag_block_end(i),
+            ))),
         ));
-        let (i, (pws1, _, (name, nws1, _))) = start(i)?;
+        let (i, (pws1, _, (name, required, nws1, _))) = start(i)?;
 
         let mut end = cut(tuple((
             |i| Node::many(i, s),
             cut(tuple((
                 |i| s.tag_block_start(i),
                 opt(Whitespace::parse),
-                ws(keyword("endblock")),
+                s.ws(s.keyword("endblock")),
                 cut(tuple((
                     opt(|before| {
-                        let (after, end_name) = ws(identifier)(before)?;
+                        let (after, end_name) = s.ws(identifier)(before)?;
                         check_end_name(before, after, name, end_name, "block")
                     }),
                     opt(Whitespace::parse),
@@ -716,6 +1265,7 @@ impl<'a> BlockDef<'a> {
                 BlockDef {
                     ws1: Ws(pws1, nws1),
                     name,
+                    required: required.is_some(),
                     nodes,
                     ws2: Ws(pws2, nws2),
                 },
@@ -759,6 +1309,7 @@ impl<'a> Lit<'a> {
             tag(s.syntax.block_start),
             tag(s.syntax.comment_start),
             tag(s.syntax.expr_start),
+            |i| s.line_statement_start(i),
         ));
 
         let (i, _) = not(eof)(i)?;
@@ -799,14 +1350,14 @@ impl<'a> Raw<'a> {
         let endraw = tuple((
             |i| s.tag_block_start(i),
             opt(Whitespace::parse),
-            ws(keyword("endraw")),
+            s.ws(s.keyword("endraw")),
             opt(Whitespace::parse),
             peek(|i| s.tag_block_end(i)),
         ));
 
         let mut p = tuple((
             opt(Whitespace::parse),
-            ws(keyword("raw")),
+            s.ws(s.keyword("raw")),
             cut(tuple((
                 opt(Whitespace::parse),
                 |i| s.tag_block_end(i),
@@ -827,6 +1378,11 @@ pub struct Let<'a> {
     pub ws: Ws,
     pub var: Target<'a>,
     pub val: Option<WithSpan<'a, Expr<'a>>>,
+    /// The body of `{% let pattern = value else %}...{% endlet %}`, which is rendered instead of
+    /// the rest of the scope if `value` does not match `pattern`
+    pub else_nodes: Option<Vec<Node<'a>>>,
+    /// The whitespace of the `endlet` tag, if there is an `else_nodes` body
+    pub ws2: Ws,
 }
 
 impl<'a> Let<'a> {
@@ -834,17 +1390,43 @@ impl<'a> Let<'a> {
         let start = i;
         let mut p = tuple((
             opt(Whitespace::parse),
-            ws(alt((keyword("let"), keyword("set")))),
+            s.ws(alt((s.keyword("let"), s.keyword("set")))),
             cut(tuple((
-                ws(|i| Target::parse(i, s)),
+                s.ws(|i| Target::parse(i, s.level())),
                 opt(preceded(
-                    ws(char('=')),
-                    ws(|i| Expr::parse(i, s.level.get())),
+                    s.ws(char('=')),
+                    s.ws(|i| Expr::parse(i, s.level())),
                 )),
+                opt(s.ws(s.keyword("else"))),
                 opt(Whitespace::parse),
             ))),
         ));
-        let (i, (pws, _, (var, val, nws))) = p(i)?;
+        let (i, (pws, kw, (var, val, has_else, nws))) = p(i)?;
+
+        let (i, else_nodes, ws2) = match has_else {
+            Some(_) if val.is_none() => {
+                return Err(nom::Err::Failure(ErrorContext::new(
+                    "`let ... else` needs a value to match the pattern against",
+                    start,
+                )));
+            }
+            Some(_) => {
+                let end_kw = match s.syntax.keyword(kw) {
+                    "let" => "endlet",
+                    _ => "endset",
+                };
+                let (i, (_, nodes, _, pws2, _, nws2)) = cut(tuple((
+                    |i| s.tag_block_end(i),
+                    |i| Node::many(i, s),
+                    |i| s.tag_block_start(i),
+                    opt(Whitespace::parse),
+                    s.ws(s.keyword(end_kw)),
+                    opt(Whitespace::parse),
+                )))(i)?;
+                (i, Some(nodes), Ws(pws2, nws2))
+            }
+            None => (i, None, Ws(None, None)),
+        };
 
         Ok((
             i,
@@ -853,6 +1435,8 @@ impl<'a> Let<'a> {
                     ws: Ws(pws, nws),
                     var,
                     val,
+                    else_nodes,
+                    ws2,
                 },
                 start,
             ),
@@ -860,6 +1444,66 @@ impl<'a> Let<'a> {
     }
 }
 
+/// `{% set name %}...{% endset %}`: the rendered content is assigned to a variable
+#[derive(Debug, PartialEq)]
+pub struct LetBlock<'a> {
+    pub ws1: Ws,
+    pub var: &'a str,
+    pub nodes: Vec<Node<'a>>,
+    pub ws2: Ws,
+}
+
+impl<'a> LetBlock<'a> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let start_s = i;
+        // Without a matching end tag, `{% set name %}` is a plain declaration,
+        // so nothing in here may `cut` to let `Let::parse()` take over.
+        let (i, (pws1, kw, var, nws1, _)) = tuple((
+            opt(Whitespace::parse),
+            s.ws(alt((s.keyword("let"), s.keyword("set")))),
+            s.ws(identifier),
+            opt(Whitespace::parse),
+            |i| s.tag_block_end(i),
+        ))(i)?;
+        // A declaration without an end tag is remembered, otherwise the rest of the template
+        // would be parsed again for every declaration in front of it.
+        if s.failed_let_blocks.borrow().contains(&start_s.len()) {
+            return Err(nom::Err::Error(error_position!(start_s, ErrorKind::Tag)));
+        }
+        let end_kw = match s.syntax.keyword(kw) {
+            "let" => "endlet",
+            _ => "endset",
+        };
+        let (i, (nodes, _, pws2, _, nws2)) = match tuple((
+            |i| Node::many(i, s),
+            |i| s.tag_block_start(i),
+            opt(Whitespace::parse),
+            s.ws(s.keyword(end_kw)),
+            opt(Whitespace::parse),
+        ))(i)
+        {
+            Err(nom::Err::Error(err)) => {
+                s.failed_let_blocks.borrow_mut().insert(start_s.len());
+                return Err(nom::Err::Error(err));
+            }
+            result => result?,
+        };
+
+        Ok((
+            i,
+            WithSpan::new(
+                Self {
+                    ws1: Ws(pws1, nws1),
+                    var,
+                    nodes,
+                    ws2: Ws(pws2, nws2),
+                },
+                start_s,
+            ),
+        ))
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct If<'a> {
     pub ws: Ws,
@@ -881,7 +1525,7 @@ impl<'a> If<'a> {
                     cut(tuple((
                         |i| s.tag_block_start(i),
                         opt(Whitespace::parse),
-                        ws(keyword("endif")),
+                        s.ws(s.keyword("endif")),
                         opt(Whitespace::parse),
                     ))),
                 ))),
@@ -912,27 +1556,50 @@ impl<'a> If<'a> {
     }
 }
 
+/// `{% include "file.html" with a = x, b = y only %}`
+///
+/// The variables given after `with` are added to the scope of the included template.
+/// If `only` is given, the included template cannot access any other variables.
+///
+/// The path is usually a string literal. Otherwise it is an expression, which is evaluated when
+/// the template is rendered, and the template to include is selected from a list of candidates.
 #[derive(Debug, PartialEq)]
 pub struct Include<'a> {
     pub ws: Ws,
-    pub path: &'a str,
+    pub path: TemplatePath<'a>,
+    pub vars: Vec<(Target<'a>, WithSpan<'a, Expr<'a>>)>,
+    pub only: bool,
 }
 
 impl<'a> Include<'a> {
-    fn parse(i: &'a str) -> ParseResult<'a, WithSpan<'a, Self>> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
         let start = i;
+        let var = pair(
+            s.ws(|i| Target::parse(i, s.level())),
+            preceded(s.ws(char('=')), s.ws(|i| Expr::parse(i, s.level()))),
+        );
         let mut p = tuple((
             opt(Whitespace::parse),
-            ws(keyword("include")),
-            cut(pair(ws(str_lit), opt(Whitespace::parse))),
+            s.ws(s.keyword("include")),
+            cut(tuple((
+                s.ws(|i| TemplatePath::parse(i, s)),
+                opt(preceded(
+                    s.ws(s.keyword("with")),
+                    cut(separated_list1(s.ws(char(',')), var)),
+                )),
+                opt(s.ws(s.keyword("only"))),
+                opt(Whitespace::parse),
+            ))),
         ));
-        let (i, (pws, _, (path, nws))) = p(i)?;
+        let (i, (pws, _, (path, vars, only, nws))) = p(i)?;
         Ok((
             i,
             WithSpan::new(
                 Self {
                     ws: Ws(pws, nws),
                     path,
+                    vars: vars.unwrap_or_default(),
+                    only: only.is_some(),
                 },
                 start,
             ),
@@ -940,19 +1607,48 @@ impl<'a> Include<'a> {
     }
 }
 
+/// The path of an `{% include %}` or `{% extends %}`
+#[derive(Debug, PartialEq)]
+pub enum TemplatePath<'a> {
+    /// `{% include "file.html" %}`
+    Static(&'a str),
+    /// `{% include partial %}`, which selects the template at runtime
+    Dynamic(WithSpan<'a, Expr<'a>>),
+}
+
+impl<'a> TemplatePath<'a> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
+        let (i, expr) = Expr::parse(i, s.level())?;
+        Ok((
+            i,
+            match *expr {
+                Expr::StrLit(path) => Self::Static(path),
+                _ => Self::Dynamic(expr),
+            },
+        ))
+    }
+}
+
+/// `{% extends "base.html" %}`
+///
+/// Like for [`Include`], the path can be an expression, which selects the parent template at
+/// runtime from a list of candidates.
 #[derive(Debug, PartialEq)]
 pub struct Extends<'a> {
-    pub path: &'a str,
+    pub path: TemplatePath<'a>,
 }
 
 impl<'a> Extends<'a> {
-    fn parse(i: &'a str) -> ParseResult<'a, WithSpan<'a, Self>> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
         let start = i;
 
         let (i, (pws, _, (path, nws))) = tuple((
             opt(Whitespace::parse),
-            ws(keyword("extends")),
-            cut(pair(ws(str_lit), opt(Whitespace::parse))),
+            s.ws(s.keyword("extends")),
+            cut(pair(
+                s.ws(|i| TemplatePath::parse(i, s)),
+                opt(Whitespace::parse),
+            )),
         ))(i)?;
         match (pws, nws) {
             (None, None) => Ok((i, WithSpan::new(Self { path }, start))),
@@ -964,6 +1660,38 @@ impl<'a> Extends<'a> {
     }
 }
 
+/// `{% do expr %}`: evaluates the expression for its side effects, and discards the result
+#[derive(Debug, PartialEq)]
+pub struct Do<'a> {
+    pub ws: Ws,
+    pub expr: WithSpan<'a, Expr<'a>>,
+}
+
+impl<'a> Do<'a> {
+    fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, WithSpan<'a, Self>> {
+        let start = i;
+        let mut p = tuple((
+            opt(Whitespace::parse),
+            s.ws(s.keyword("do")),
+            cut(pair(
+                s.ws(|i| Expr::parse(i, s.level())),
+                opt(Whitespace::parse),
+            )),
+        ));
+        let (i, (pws, _, (expr, nws))) = p(i)?;
+        Ok((
+            i,
+            WithSpan::new(
+                Self {
+                    ws: Ws(pws, nws),
+                    expr,
+                },
+                start,
+            ),
+        ))
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Comment<'a> {
     pub ws: Ws,
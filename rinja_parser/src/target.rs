@@ -7,7 +7,7 @@ use nom::sequence::{pair, preceded};
 
 use crate::{
     bool_lit, char_lit, identifier, keyword, num_lit, path_or_identifier, str_lit, ws,
-    ErrorContext, ParseErr, ParseResult, PathOrIdentifier, State,
+    ErrorContext, Level, ParseErr, ParseResult, PathOrIdentifier,
 };
 
 #[derive(Clone, Debug, PartialEq)]
@@ -27,9 +27,27 @@ pub enum Target<'a> {
 
 impl<'a> Target<'a> {
     /// Parses multiple targets with `or` separating them
-    pub(super) fn parse(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
+    pub(super) fn parse(i: &'a str, level: Level) -> ParseResult<'a, Self> {
         map(
-            separated_list1(ws(tag("or")), |i| s.nest(i, |i| Self::parse_one(i, s))),
+            separated_list1(ws(level, tag("or")), |i| {
+                let (_, level) = level.nest(i)?;
+                Self::parse_one(i, level)
+            }),
+            |mut opts| match opts.len() {
+                1 => opts.pop().unwrap(),
+                _ => Self::OrChain(opts),
+            },
+        )(i)
+    }
+
+    /// Parses the pattern of a `matches!()` expression, where the alternatives can be separated
+    /// by `|` like in Rust, or by `or` like in a `{% when %}` block
+    pub(super) fn parse_pattern(i: &'a str, level: Level) -> ParseResult<'a, Self> {
+        map(
+            separated_list1(ws(level, alt((tag("or"), tag("|")))), |i| {
+                let (_, level) = level.nest(i)?;
+                Self::parse_one(i, level)
+            }),
             |mut opts| match opts.len() {
                 1 => opts.pop().unwrap(),
                 _ => Self::OrChain(opts),
@@ -38,9 +56,9 @@ impl<'a> Target<'a> {
     }
 
     /// Parses a single target without an `or`, unless it is wrapped in parentheses.
-    fn parse_one(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
-        let mut opt_opening_paren = map(opt(ws(char('('))), |o| o.is_some());
-        let mut opt_opening_brace = map(opt(ws(char('{'))), |o| o.is_some());
+    fn parse_one(i: &'a str, level: Level) -> ParseResult<'a, Self> {
+        let mut opt_opening_paren = map(opt(ws(level, char('('))), |o| o.is_some());
+        let mut opt_opening_brace = map(opt(ws(level, char('{'))), |o| o.is_some());
 
         let (i, lit) = opt(Self::lit)(i)?;
         if let Some(lit) = lit {
@@ -50,35 +68,44 @@ impl<'a> Target<'a> {
         // match tuples and unused parentheses
         let (i, target_is_tuple) = opt_opening_paren(i)?;
         if target_is_tuple {
-            let (i, (singleton, mut targets)) = collect_targets(i, s, ')', Self::unnamed)?;
+            let (i, (singleton, mut targets)) = collect_targets(i, level, ')', Self::unnamed)?;
             if singleton {
                 return Ok((i, targets.pop().unwrap()));
             }
             return Ok((i, Self::Tuple(Vec::new(), only_one_rest_pattern(targets)?)));
         }
 
+        // `_` would be taken for a path, because it does not start with a lowercase letter
+        let (i, placeholder) = opt(keyword("_"))(i)?;
+        if let Some(placeholder) = placeholder {
+            return Ok((i, Self::Placeholder(placeholder)));
+        }
+
         let path = |i| {
-            map_res(path_or_identifier, |v| match v {
-                PathOrIdentifier::Path(v) => Ok(v),
-                PathOrIdentifier::Identifier(v) => Err(v),
-            })(i)
+            map_res(
+                |i| path_or_identifier(i, level),
+                |v| match v {
+                    PathOrIdentifier::Path(v) => Ok(v),
+                    PathOrIdentifier::Identifier(v) => Err(v),
+                },
+            )(i)
         };
 
         // match structs
         let (i, path) = opt(path)(i)?;
         if let Some(path) = path {
             let i_before_matching_with = i;
-            let (i, _) = opt(ws(keyword("with")))(i)?;
+            let (i, _) = opt(ws(level, keyword("with")))(i)?;
 
             let (i, is_unnamed_struct) = opt_opening_paren(i)?;
             if is_unnamed_struct {
-                let (i, (_, targets)) = collect_targets(i, s, ')', Self::unnamed)?;
+                let (i, (_, targets)) = collect_targets(i, level, ')', Self::unnamed)?;
                 return Ok((i, Self::Tuple(path, only_one_rest_pattern(targets)?)));
             }
 
             let (i, is_named_struct) = opt_opening_brace(i)?;
             if is_named_struct {
-                let (i, (_, targets)) = collect_targets(i, s, '}', Self::named)?;
+                let (i, (_, targets)) = collect_targets(i, level, '}', Self::named)?;
                 return Ok((i, Self::Struct(path, targets)));
             }
 
@@ -87,11 +114,7 @@ impl<'a> Target<'a> {
 
         // neither literal nor struct nor path
         let (new_i, name) = identifier(i)?;
-        let target = match name {
-            "_" => Self::Placeholder(name),
-            _ => verify_name(i, name)?,
-        };
-        Ok((new_i, target))
+        Ok((new_i, verify_name(i, name)?))
     }
 
     fn lit(i: &'a str) -> ParseResult<'a, Self> {
@@ -103,14 +126,14 @@ impl<'a> Target<'a> {
         ))(i)
     }
 
-    fn unnamed(i: &'a str, s: &State<'_>) -> ParseResult<'a, Self> {
-        alt((Self::rest, |i| Self::parse(i, s)))(i)
+    fn unnamed(i: &'a str, level: Level) -> ParseResult<'a, Self> {
+        alt((Self::rest, |i| Self::parse(i, level)))(i)
     }
 
-    fn named(init_i: &'a str, s: &State<'_>) -> ParseResult<'a, (&'a str, Self)> {
+    fn named(init_i: &'a str, level: Level) -> ParseResult<'a, (&'a str, Self)> {
         let (i, rest) = opt(consumed(Self::rest))(init_i)?;
         if let Some(rest) = rest {
-            let (_, chr) = ws(opt(one_of(",:")))(i)?;
+            let (_, chr) = ws(level, opt(one_of(",:")))(i)?;
             if let Some(chr) = chr {
                 return Err(nom::Err::Failure(ErrorContext::new(
                     format!(
@@ -125,7 +148,7 @@ impl<'a> Target<'a> {
 
         let (i, (src, target)) = pair(
             identifier,
-            opt(preceded(ws(char(':')), |i| Self::parse(i, s))),
+            opt(preceded(ws(level, char(':')), |i| Self::parse(i, level))),
         )(init_i)?;
 
         if src == "_" {
@@ -162,19 +185,19 @@ fn verify_name<'a>(
 
 fn collect_targets<'a, T>(
     i: &'a str,
-    s: &State<'_>,
+    level: Level,
     delim: char,
-    mut one: impl FnMut(&'a str, &State<'_>) -> ParseResult<'a, T>,
+    mut one: impl FnMut(&'a str, Level) -> ParseResult<'a, T>,
 ) -> ParseResult<'a, (bool, Vec<T>)> {
-    let opt_comma = |i| map(ws(opt(char(','))), |o| o.is_some())(i);
-    let opt_end = |i| map(ws(opt(char(delim))), |o| o.is_some())(i);
+    let opt_comma = |i| map(ws(level, opt(char(','))), |o| o.is_some())(i);
+    let opt_end = |i| map(ws(level, opt(char(delim))), |o| o.is_some())(i);
 
     let (i, has_end) = opt_end(i)?;
     if has_end {
         return Ok((i, (false, Vec::new())));
     }
 
-    let (i, targets) = opt(separated_list1(ws(char(',')), |i| one(i, s)))(i)?;
+    let (i, targets) = opt(separated_list1(ws(level, char(',')), |i| one(i, level)))(i)?;
     let Some(targets) = targets else {
         return Err(nom::Err::Failure(ErrorContext::new(
             "expected comma separated list of members",
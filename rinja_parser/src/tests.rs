@@ -1,5 +1,10 @@
-use super::node::{Lit, Whitespace, Ws};
-use super::{Ast, Expr, Filter, Node, Syntax, WithSpan};
+use std::sync::Arc;
+
+use super::node::{
+    AutoEscape, BlockDef, Cache, Do, Escaping, Extension, FromImport, Include, Let, LetBlock, Lit,
+    Loop, Once, TemplatePath, Trans, Whitespace, WhitespaceBlock, With, Ws,
+};
+use super::{Ast, Expr, Filter, LineColumn, Node, ParseError, Parsed, Syntax, Target, WithSpan};
 
 impl<T> WithSpan<'static, T> {
     fn no_span(inner: T) -> Self {
@@ -105,6 +110,31 @@ fn test_parse_numbers() {
             WithSpan::no_span(Expr::NumLit("2.5"))
         )],
     );
+    for num in [
+        "1_000_000",
+        "0xFF",
+        "0o7_7",
+        "0b1010_u8",
+        "1.5e3",
+        "1E+3_f64",
+        "1u64",
+        "1_usize",
+        "2.5f32",
+        "1_000.000_1",
+    ] {
+        assert_eq!(
+            Ast::from_str(&format!("{{{{ {num} }}}}"), None, &syntax)
+                .unwrap()
+                .nodes,
+            vec![Node::Expr(
+                Ws(None, None),
+                WithSpan::no_span(Expr::NumLit(num))
+            )],
+        );
+    }
+    // Like in Rust, a suffix must be a known type.
+    assert!(Ast::from_str("{{ 1u7 }}", None, &syntax).is_err());
+    assert!(Ast::from_str("{{ 1.5usize }}", None, &syntax).is_err());
 }
 
 #[test]
@@ -207,6 +237,46 @@ fn test_parse_path() {
     );
 }
 
+#[test]
+fn test_parse_non_ascii_var() {
+    let s = Syntax::default();
+
+    for name in ["名前", "größe", "ñ_1", "δx"] {
+        assert_eq!(
+            Ast::from_str(&format!("{{{{ {name} }}}}"), None, &s)
+                .unwrap()
+                .nodes,
+            vec![Node::Expr(
+                Ws(None, None),
+                WithSpan::no_span(Expr::Var(name))
+            )],
+        );
+    }
+    assert_eq!(
+        Ast::from_str("{% let 値 = 1 %}", None, &s).unwrap().nodes,
+        vec![Node::Let(WithSpan::no_span(Let {
+            ws: Ws(None, None),
+            var: Target::Name("値"),
+            val: Some(WithSpan::no_span(Expr::NumLit("1"))),
+            else_nodes: None,
+            ws2: Ws(None, None),
+        }))],
+    );
+
+    assert_eq!(
+        Ast::from_str("{{ Δ }}", None, &s).unwrap().nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Path(vec!["Δ"]))
+        )],
+    );
+
+    // Characters that are not allowed in Rust identifiers are not allowed in templates either.
+    for source in ["{{ a…b }}", "{{ ☃ }}", "{{ a\u{a0}b }}", "{{ ٣x }}"] {
+        assert!(Ast::from_str(source, None, &s).is_err(), "{source}");
+    }
+}
+
 #[test]
 fn test_parse_var_call() {
     assert_eq!(
@@ -300,6 +370,206 @@ fn test_parse_root_path() {
     );
 }
 
+#[test]
+fn test_turbofish() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{{ value.parse::<i32>()? }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Try(Box::new(WithSpan::no_span(Expr::Call(
+                Box::new(WithSpan::no_span(Expr::Turbofish(
+                    Box::new(WithSpan::no_span(Expr::Attr(
+                        Box::new(WithSpan::no_span(Expr::Var("value"))),
+                        "parse"
+                    ))),
+                    "i32"
+                ))),
+                vec![]
+            ))))),
+        )],
+    );
+    assert_eq!(
+        Ast::from_str(
+            "{{ iter.collect :: < Vec<(u8, &'a str)> >() }}",
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Call(
+                Box::new(WithSpan::no_span(Expr::Turbofish(
+                    Box::new(WithSpan::no_span(Expr::Attr(
+                        Box::new(WithSpan::no_span(Expr::Var("iter"))),
+                        "collect"
+                    ))),
+                    "Vec<(u8, &'a str)>"
+                ))),
+                vec![]
+            )),
+        )],
+    );
+    assert_eq!(
+        Ast::from_str("{{ f::<u8, char>(1) }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Call(
+                Box::new(WithSpan::no_span(Expr::Turbofish(
+                    Box::new(WithSpan::no_span(Expr::Var("f"))),
+                    "u8, char"
+                ))),
+                vec![WithSpan::no_span(Expr::NumLit("1"))]
+            )),
+        )],
+    );
+
+    assert_eq!(
+        Ast::from_str("{{ Vec::<u8>::new() }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Call(
+                Box::new(WithSpan::no_span(Expr::AssociatedItem(
+                    Box::new(WithSpan::no_span(Expr::Turbofish(
+                        Box::new(WithSpan::no_span(Expr::Path(vec!["Vec"]))),
+                        "u8"
+                    ))),
+                    "new"
+                ))),
+                vec![]
+            )),
+        )],
+    );
+
+    assert!(Ast::from_str("{{ f::<>() }}", None, &syntax).is_err());
+    assert!(Ast::from_str("{{ f()::g() }}", None, &syntax).is_err());
+    assert!(Ast::from_str("{{ f::<u8() }}", None, &syntax).is_err());
+    assert!(Ast::from_str(r#"{{ f::<"u8">() }}"#, None, &syntax).is_err());
+}
+
+#[test]
+fn test_matches() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            "{{ matches!(state, State::Ready | State::Done) }}",
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Matches(
+                Box::new(WithSpan::no_span(Expr::Var("state"))),
+                Target::OrChain(vec![
+                    Target::Path(vec!["State", "Ready"]),
+                    Target::Path(vec!["State", "Done"]),
+                ]),
+                None,
+            )),
+        )],
+    );
+    assert_eq!(
+        Ast::from_str("{{ matches!(a.b, Some(x) if x > 1,) }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Matches(
+                Box::new(WithSpan::no_span(Expr::Attr(
+                    Box::new(WithSpan::no_span(Expr::Var("a"))),
+                    "b"
+                ))),
+                Target::Tuple(vec!["Some"], vec![Target::Name("x")]),
+                Some(Box::new(WithSpan::no_span(Expr::BinOp(
+                    ">",
+                    Box::new(WithSpan::no_span(Expr::Var("x"))),
+                    Box::new(WithSpan::no_span(Expr::NumLit("1"))),
+                )))),
+            )),
+        )],
+    );
+    // Other macros are still passed through as they are.
+    assert_eq!(
+        Ast::from_str("{{ matches }}", None, &syntax).unwrap().nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Var("matches")),
+        )],
+    );
+
+    assert!(Ast::from_str("{{ matches!(a) }}", None, &syntax).is_err());
+    assert!(Ast::from_str("{{ matches!(a, ) }}", None, &syntax).is_err());
+    assert!(Ast::from_str("{{ matches!(a, b || c) }}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_closure() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{{ a.filter(|i| i.b) }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Call(
+                Box::new(WithSpan::no_span(Expr::Attr(
+                    Box::new(WithSpan::no_span(Expr::Var("a"))),
+                    "filter"
+                ))),
+                vec![WithSpan::no_span(Expr::Closure(
+                    vec![Target::Name("i")],
+                    Box::new(WithSpan::no_span(Expr::Attr(
+                        Box::new(WithSpan::no_span(Expr::Var("i"))),
+                        "b"
+                    ))),
+                ))]
+            )),
+        )],
+    );
+    assert_eq!(
+        Ast::from_str("{{ f(|(a, _), b,| a|lower, || 1) }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Call(
+                Box::new(WithSpan::no_span(Expr::Var("f"))),
+                vec![
+                    WithSpan::no_span(Expr::Closure(
+                        vec![
+                            Target::Tuple(
+                                vec![],
+                                vec![Target::Name("a"), Target::Placeholder("_")]
+                            ),
+                            Target::Name("b"),
+                        ],
+                        Box::new(WithSpan::no_span(Expr::Filter(Filter {
+                            name: "lower",
+                            arguments: vec![WithSpan::no_span(Expr::Var("a"))],
+                        }))),
+                    )),
+                    WithSpan::no_span(Expr::Closure(
+                        vec![],
+                        Box::new(WithSpan::no_span(Expr::NumLit("1"))),
+                    )),
+                ]
+            )),
+        )],
+    );
+
+    assert!(Ast::from_str("{{ f(|a| ) }}", None, &syntax).is_err());
+    assert!(Ast::from_str("{{ f(|a b| a) }}", None, &syntax).is_err());
+}
+
 #[test]
 fn test_rust_macro() {
     let syntax = Syntax::default();
@@ -477,6 +747,94 @@ fn test_precedence() {
     );
 }
 
+#[test]
+fn test_in_operator() {
+    let syntax = Syntax::default();
+    let var = |name| Box::new(WithSpan::no_span(Expr::Var(name)));
+    let expr = |expr| vec![Node::Expr(Ws(None, None), WithSpan::no_span(expr))];
+
+    assert_eq!(
+        Ast::from_str("{{ a in b }}", None, &syntax).unwrap().nodes,
+        expr(Expr::BinOp("in", var("a"), var("b"))),
+    );
+    assert_eq!(
+        Ast::from_str("{{ a not  in b }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        expr(Expr::BinOp("not in", var("a"), var("b"))),
+    );
+    assert_eq!(
+        Ast::from_str("{{ a + b in c && d }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        expr(Expr::BinOp(
+            "&&",
+            Box::new(WithSpan::no_span(Expr::BinOp(
+                "in",
+                Box::new(WithSpan::no_span(Expr::BinOp("+", var("a"), var("b")))),
+                var("c"),
+            ))),
+            var("d"),
+        )),
+    );
+    // `in` and `not` are only operators if they are whole words.
+    assert_eq!(
+        Ast::from_str("{{ a in index }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        expr(Expr::BinOp("in", var("a"), var("index"))),
+    );
+    assert!(Ast::from_str("{{ a not b }}", None, &syntax).is_err());
+    assert!(Ast::from_str("{{ a inb }}", None, &syntax).is_err());
+
+    // The `in` of a loop is not affected.
+    assert!(Ast::from_str("{% for x in xs if x in ys %}{% endfor %}", None, &syntax).is_ok());
+}
+
+#[test]
+fn test_power_operator() {
+    let syntax = Syntax::default();
+    let var = |name| Box::new(WithSpan::no_span(Expr::Var(name)));
+    let expr = |expr| vec![Node::Expr(Ws(None, None), WithSpan::no_span(expr))];
+    let binop = |op, left, right| Box::new(WithSpan::no_span(Expr::BinOp(op, left, right)));
+
+    assert_eq!(
+        Ast::from_str("{{ a ** b }}", None, &syntax).unwrap().nodes,
+        expr(Expr::BinOp("**", var("a"), var("b"))),
+    );
+    // `**` is right associative.
+    assert_eq!(
+        Ast::from_str("{{ a ** b ** c }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        expr(Expr::BinOp("**", var("a"), binop("**", var("b"), var("c")))),
+    );
+    // `**` binds tighter than `*`, and looser than unary operators.
+    assert_eq!(
+        Ast::from_str("{{ a * b**c }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        expr(Expr::BinOp("*", var("a"), binop("**", var("b"), var("c")))),
+    );
+    assert_eq!(
+        Ast::from_str("{{ -a ** b }}", None, &syntax).unwrap().nodes,
+        expr(Expr::BinOp(
+            "**",
+            Box::new(WithSpan::no_span(Expr::Unary("-", var("a")))),
+            var("b"),
+        )),
+    );
+    // With a space in between, `* *b` is a multiplication with a dereferenced `b`.
+    assert_eq!(
+        Ast::from_str("{{ a * *b }}", None, &syntax).unwrap().nodes,
+        expr(Expr::BinOp(
+            "*",
+            var("a"),
+            Box::new(WithSpan::no_span(Expr::Unary("*", var("b")))),
+        )),
+    );
+}
+
 #[test]
 fn test_associativity() {
     let syntax = Syntax::default();
@@ -979,6 +1337,280 @@ fn test_parse_array() {
     );
 }
 
+#[test]
+fn test_parse_map() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{{ {} }}", None, &syntax).unwrap().nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Map(vec![]))
+        )],
+    );
+    let entries = vec![
+        (
+            WithSpan::no_span(Expr::StrLit("a")),
+            WithSpan::no_span(Expr::NumLit("1")),
+        ),
+        (
+            WithSpan::no_span(Expr::StrLit("b")),
+            WithSpan::no_span(Expr::Var("b")),
+        ),
+    ];
+    for src in [
+        r#"{{ {"a": 1, "b": b} }}"#,
+        r#"{{ {"a":1,"b":b} }}"#,
+        r#"{{ { "a" : 1 , "b" : b , } }}"#,
+    ] {
+        assert_eq!(
+            Ast::from_str(src, None, &syntax).unwrap().nodes,
+            vec![Node::Expr(
+                Ws(None, None),
+                WithSpan::no_span(Expr::Map(entries.clone()))
+            )],
+        );
+    }
+    assert!(Ast::from_str(r#"{{ {"a"} }}"#, None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_do() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{%- do counter.increment() %}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Do(WithSpan::no_span(Do {
+            ws: Ws(Some(Whitespace::Suppress), None),
+            expr: WithSpan::no_span(Expr::Call(
+                Box::new(WithSpan::no_span(Expr::Attr(
+                    Box::new(WithSpan::no_span(Expr::Var("counter"))),
+                    "increment",
+                ))),
+                vec![],
+            )),
+        }))],
+    );
+    assert!(Ast::from_str("{% do %}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_break_if() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            "{% for a in b %}{% break if a -%}{% endfor %}",
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::Loop(Box::new(WithSpan::no_span(Loop {
+            ws1: Ws(None, None),
+            var: Target::Name("a"),
+            iter: WithSpan::no_span(Expr::Var("b")),
+            cond: None,
+            body: vec![Node::Break(
+                WithSpan::no_span(Ws(None, Some(Whitespace::Suppress))),
+                Some(WithSpan::no_span(Expr::Var("a"))),
+            )],
+            ws2: Ws(None, None),
+            else_nodes: vec![],
+            ws3: Ws(None, None),
+        })))],
+    );
+    assert!(Ast::from_str(
+        "{% for a in b %}{% continue if a %}{% endfor %}",
+        None,
+        &syntax
+    )
+    .is_ok());
+    assert!(Ast::from_str(
+        "{% for a in b %}{% continue if %}{% endfor %}",
+        None,
+        &syntax
+    )
+    .is_err());
+    assert!(Ast::from_str("{% break if a %}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_cache() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{% cache user.id -%} a {%- endcache %}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Cache(WithSpan::no_span(Cache {
+            ws1: Ws(None, Some(Whitespace::Suppress)),
+            key: WithSpan::no_span(Expr::Attr(
+                Box::new(WithSpan::no_span(Expr::Var("user"))),
+                "id",
+            )),
+            nodes: vec![Node::Lit(WithSpan::no_span(Lit {
+                lws: " ",
+                val: "a",
+                rws: " ",
+            }))],
+            ws2: Ws(Some(Whitespace::Suppress), None),
+        }))],
+    );
+    assert!(Ast::from_str("{% cache %}a{% endcache %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% cache key %}a", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_once() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{% once -%} a {% endonce %}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Once(WithSpan::no_span(Once {
+            ws1: Ws(None, Some(Whitespace::Suppress)),
+            nodes: vec![Node::Lit(WithSpan::no_span(Lit {
+                lws: " ",
+                val: "a",
+                rws: " ",
+            }))],
+            ws2: Ws(None, None),
+        }))],
+    );
+    assert!(Ast::from_str("{% once key %}a{% endonce %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% once %}a", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_autoescape() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            "{% autoescape false -%} a {% endautoescape %}",
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::AutoEscape(WithSpan::no_span(AutoEscape {
+            ws1: Ws(None, Some(Whitespace::Suppress)),
+            escaping: Escaping::Disabled,
+            nodes: vec![Node::Lit(WithSpan::no_span(Lit {
+                lws: " ",
+                val: "a",
+                rws: " ",
+            }))],
+            ws2: Ws(None, None),
+        }))],
+    );
+    assert_eq!(
+        Ast::from_str("{% autoescape \"js\" %}{% endautoescape %}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::AutoEscape(WithSpan::no_span(AutoEscape {
+            ws1: Ws(None, None),
+            escaping: Escaping::Named("js"),
+            nodes: vec![],
+            ws2: Ws(None, None),
+        }))],
+    );
+    assert!(Ast::from_str("{% autoescape true %}{% endautoescape %}", None, &syntax).is_ok());
+    assert!(Ast::from_str("{% autoescape %}a{% endautoescape %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% autoescape html %}a{% endautoescape %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% autoescape true %}a", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_whitespace_block() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            "{% whitespace suppress +%} a {%- endwhitespace %}",
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::WhitespaceBlock(WithSpan::no_span(WhitespaceBlock {
+            ws1: Ws(None, Some(Whitespace::Preserve)),
+            mode: Whitespace::Suppress,
+            nodes: vec![Node::Lit(WithSpan::no_span(Lit {
+                lws: " ",
+                val: "a",
+                rws: " ",
+            }))],
+            ws2: Ws(Some(Whitespace::Suppress), None),
+        }))],
+    );
+    assert!(Ast::from_str("{% whitespace %}a{% endwhitespace %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% whitespace trim %}a{% endwhitespace %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% whitespace minimize %}a", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_cfg() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            r#"{{ cfg(all(feature = "a(b)", not(unix))) && x }}"#,
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::BinOp(
+                "&&",
+                Box::new(WithSpan::no_span(Expr::Cfg(
+                    r#"all(feature = "a(b)", not(unix))"#
+                ))),
+                Box::new(WithSpan::no_span(Expr::Var("x"))),
+            )),
+        )],
+    );
+    assert_eq!(
+        Ast::from_str("{{ cfg }}", None, &syntax).unwrap().nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Var("cfg"))
+        )],
+    );
+    assert!(Ast::from_str("{{ cfg(unix }}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_required_block() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            "{% block title required -%} a {% endblock %}",
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::BlockDef(WithSpan::no_span(BlockDef {
+            ws1: Ws(None, Some(Whitespace::Suppress)),
+            name: "title",
+            required: true,
+            nodes: vec![Node::Lit(WithSpan::no_span(Lit {
+                lws: " ",
+                val: "a",
+                rws: " ",
+            }))],
+            ws2: Ws(None, None),
+        }))],
+    );
+    let nodes = Ast::from_str("{% block required %}{% endblock %}", None, &syntax)
+        .unwrap()
+        .nodes;
+    assert!(matches!(
+        &*nodes,
+        [Node::BlockDef(block)] if block.name == "required" && !block.required,
+    ));
+    assert!(Ast::from_str("{% block title optional %}{% endblock %}", None, &syntax).is_err());
+}
+
 #[test]
 fn fuzzed_unicode_slice() {
     let d = "{eeuuu{b&{!!&{!!11{{
@@ -1035,3 +1667,608 @@ fn fuzzed_filter_recursion() {
     const TEMPLATE: &str = include_str!("../tests/filter-recursion.txt");
     assert!(Ast::from_str(TEMPLATE, None, &Syntax::default()).is_err());
 }
+
+#[test]
+fn test_parse_if_else_expr() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{{ a if b || c else d if e else f }}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::IfElse(
+                Box::new(WithSpan::no_span(Expr::BinOp(
+                    "||",
+                    Box::new(WithSpan::no_span(Expr::Var("b"))),
+                    Box::new(WithSpan::no_span(Expr::Var("c"))),
+                ))),
+                Box::new(WithSpan::no_span(Expr::Var("a"))),
+                Box::new(WithSpan::no_span(Expr::IfElse(
+                    Box::new(WithSpan::no_span(Expr::Var("e"))),
+                    Box::new(WithSpan::no_span(Expr::Var("d"))),
+                    Box::new(WithSpan::no_span(Expr::Var("f"))),
+                ))),
+            )),
+        )],
+    );
+
+    // The `if` belongs to the loop, not to the expression.
+    assert!(Ast::from_str("{% for x in y if x %}{% endfor %}", None, &syntax).is_ok());
+    assert!(Ast::from_str("{{ a if b }}", None, &syntax).is_err());
+    assert!(Ast::from_str("{{ a if b else }}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_let_block() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{% set a -%} b {%- endset %}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::LetBlock(WithSpan::no_span(LetBlock {
+            ws1: Ws(None, Some(Whitespace::Suppress)),
+            var: "a",
+            nodes: vec![Node::Lit(WithSpan::no_span(Lit {
+                lws: " ",
+                val: "b",
+                rws: " ",
+            }))],
+            ws2: Ws(Some(Whitespace::Suppress), None),
+        }))],
+    );
+
+    // Without an end tag, this is a declaration.
+    assert!(matches!(
+        Ast::from_str("{% let a %}{% let a = 1 %}", None, &syntax)
+            .unwrap()
+            .nodes[..],
+        [Node::Let(_), Node::Let(_)],
+    ));
+    // The end tag must match the opening keyword.
+    assert!(Ast::from_str("{% let a %}b{% endset %}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_many_let_declarations() {
+    // Every declaration without an end tag must not parse the rest of the template again,
+    // which would take exponential time.
+    let syntax = Syntax::default();
+    let source = "{% set a %}".repeat(40);
+    let ast = Ast::from_str(&source, None, &syntax).unwrap();
+    assert_eq!(ast.nodes.len(), 40);
+    assert!(ast.nodes.iter().all(|node| matches!(node, Node::Let(_))));
+
+    let source = format!("{source}{{% set b %}}c{{% endset %}}");
+    let ast = Ast::from_str(&source, None, &syntax).unwrap();
+    assert_eq!(ast.nodes.len(), 41);
+    assert!(matches!(ast.nodes[39], Node::Let(_)));
+    assert!(matches!(ast.nodes[40], Node::LetBlock(_)));
+}
+
+#[test]
+fn test_parse_let_else() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            "{% let Some(user) = user else -%} guest {%- endlet %}",
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::Let(WithSpan::no_span(Let {
+            ws: Ws(None, Some(Whitespace::Suppress)),
+            var: Target::Tuple(vec!["Some"], vec![Target::Name("user")]),
+            val: Some(WithSpan::no_span(Expr::Var("user"))),
+            else_nodes: Some(vec![Node::Lit(WithSpan::no_span(Lit {
+                lws: " ",
+                val: "guest",
+                rws: " ",
+            }))]),
+            ws2: Ws(Some(Whitespace::Suppress), None),
+        }))],
+    );
+    assert!(Ast::from_str("{% set (a, b) = c else %}d{% endset %}", None, &syntax).is_ok());
+
+    // The end tag must match the opening keyword.
+    assert!(Ast::from_str("{% let Some(a) = b else %}c{% endset %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% let Some(a) = b else %}c", None, &syntax).is_err());
+    assert!(Ast::from_str("{% let a else %}c{% endlet %}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_with() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{% with a = 1, b = c %}{{ a }}{% endwith %}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::With(WithSpan::no_span(With {
+            ws1: Ws(None, None),
+            vars: vec![
+                (Target::Name("a"), WithSpan::no_span(Expr::NumLit("1"))),
+                (Target::Name("b"), WithSpan::no_span(Expr::Var("c"))),
+            ],
+            nodes: vec![Node::Expr(
+                Ws(None, None),
+                WithSpan::no_span(Expr::Var("a"))
+            )],
+            ws2: Ws(None, None),
+        }))],
+    );
+
+    assert!(Ast::from_str("{% with a = 1 %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% with a %}{% endwith %}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_include() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(r#"{% include "a.html" %}"#, None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Include(WithSpan::no_span(Include {
+            ws: Ws(None, None),
+            path: TemplatePath::Static("a.html"),
+            vars: vec![],
+            only: false,
+        }))],
+    );
+    assert_eq!(
+        Ast::from_str(
+            r#"{% include "a.html" with item = product, n = 1 only -%}"#,
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::Include(WithSpan::no_span(Include {
+            ws: Ws(None, Some(Whitespace::Suppress)),
+            path: TemplatePath::Static("a.html"),
+            vars: vec![
+                (
+                    Target::Name("item"),
+                    WithSpan::no_span(Expr::Var("product"))
+                ),
+                (Target::Name("n"), WithSpan::no_span(Expr::NumLit("1"))),
+            ],
+            only: true,
+        }))],
+    );
+    assert_eq!(
+        Ast::from_str(r#"{% include "a.html" only %}"#, None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Include(WithSpan::no_span(Include {
+            ws: Ws(None, None),
+            path: TemplatePath::Static("a.html"),
+            vars: vec![],
+            only: true,
+        }))],
+    );
+
+    assert_eq!(
+        Ast::from_str(r#"{% include partial with a = 1 %}"#, None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Include(WithSpan::no_span(Include {
+            ws: Ws(None, None),
+            path: TemplatePath::Dynamic(WithSpan::no_span(Expr::Var("partial"))),
+            vars: vec![(Target::Name("a"), WithSpan::no_span(Expr::NumLit("1")))],
+            only: false,
+        }))],
+    );
+
+    assert!(Ast::from_str(r#"{% include "a.html" with %}"#, None, &syntax).is_err());
+    assert!(Ast::from_str(r#"{% include "a.html" with a %}"#, None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_from_import() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            r#"{% from "macros.html" import input, button as btn -%}"#,
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::FromImport(WithSpan::no_span(FromImport {
+            ws: Ws(None, Some(Whitespace::Suppress)),
+            path: "macros.html",
+            names: vec![("input", None), ("button", Some("btn"))],
+        }))],
+    );
+
+    assert!(Ast::from_str(r#"{% from "macros.html" import %}"#, None, &syntax).is_err());
+    assert!(Ast::from_str(r#"{% from "macros.html" import a as %}"#, None, &syntax).is_err());
+    assert!(Ast::from_str(r#"{% from "macros.html" %}"#, None, &syntax).is_err());
+}
+
+#[test]
+fn test_parse_trans() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str(
+            r#"{% trans "hello" name = user.name, n = 1 %}Hi {{ name }}{% endtrans %}"#,
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        vec![Node::Trans(WithSpan::no_span(Trans {
+            ws1: Ws(None, None),
+            id: "hello",
+            args: vec![
+                (
+                    "name",
+                    WithSpan::no_span(Expr::Attr(
+                        Box::new(WithSpan::no_span(Expr::Var("user"))),
+                        "name"
+                    ))
+                ),
+                ("n", WithSpan::no_span(Expr::NumLit("1"))),
+            ],
+            nodes: vec![
+                Node::Lit(WithSpan::no_span(Lit {
+                    lws: "",
+                    val: "Hi",
+                    rws: " ",
+                })),
+                Node::Expr(Ws(None, None), WithSpan::no_span(Expr::Var("name"))),
+            ],
+            ws2: Ws(None, None),
+        }))],
+    );
+
+    assert!(Ast::from_str(r#"{% trans "a" %}"#, None, &syntax).is_err());
+    assert!(Ast::from_str("{% trans %}{% endtrans %}", None, &syntax).is_err());
+    assert!(Ast::from_str(r#"{% trans "a" b %}{% endtrans %}"#, None, &syntax).is_err());
+}
+
+#[test]
+fn test_placeholder_target() {
+    let syntax = Syntax::default();
+    assert_eq!(
+        Ast::from_str("{% let (_, Point { x, .. }) = a %}", None, &syntax)
+            .unwrap()
+            .nodes,
+        vec![Node::Let(WithSpan::no_span(Let {
+            ws: Ws(None, None),
+            var: Target::Tuple(
+                vec![],
+                vec![
+                    Target::Placeholder("_"),
+                    Target::Struct(
+                        vec!["Point"],
+                        vec![("x", Target::Name("x")), ("..", Target::Rest(".."))],
+                    ),
+                ],
+            ),
+            val: Some(WithSpan::no_span(Expr::Var("a"))),
+            else_nodes: None,
+            ws2: Ws(None, None),
+        }))],
+    );
+}
+
+#[test]
+fn test_span_positions() {
+    let source: Arc<str> = "a\n{% if x %}\n  ü{{ y }}\n{% endif %}".into();
+    let parsed = Parsed::new(Arc::clone(&source), None, &Syntax::default()).unwrap();
+    let Node::If(i) = &parsed.nodes()[1] else {
+        panic!("expected an `if` node");
+    };
+    let Node::Expr(_, y) = &i.branches[0].nodes[1] else {
+        panic!("expected an expression");
+    };
+    assert_eq!(i.offset(&source), 4);
+    assert_eq!(y.offset(&source), 20);
+    assert_eq!(i.line_column(&source), LineColumn { line: 1, column: 2 });
+    assert_eq!(y.line_column(&source), LineColumn { line: 2, column: 6 });
+
+    let source_map = parsed.source_map();
+    assert_eq!(source_map.line_count(), 4);
+    assert_eq!(source_map.line_start(3), Some(25));
+    assert_eq!(source_map.line_start(4), None);
+    assert_eq!(source_map.line_column(0), LineColumn { line: 0, column: 0 });
+    assert_eq!(source_map.line_column(2), LineColumn { line: 1, column: 0 });
+    assert_eq!(
+        source_map.line_column(source.len()),
+        LineColumn {
+            line: 3,
+            column: 11,
+        }
+    );
+    assert_eq!(
+        source_map.span_line_column(y),
+        y.line_column(parsed.source()),
+    );
+}
+
+#[test]
+#[should_panic(expected = "span is not a part of the source")]
+fn test_span_offset_other_source() {
+    let parsed = Parsed::new("{{ a }}".into(), None, &Syntax::default()).unwrap();
+    let Node::Expr(_, a) = &parsed.nodes()[0] else {
+        panic!("expected an expression");
+    };
+    a.offset("{{ a }}");
+}
+
+#[test]
+fn test_parse_recovering() {
+    let syntax = Syntax::default();
+    let parse = |src| {
+        let (ast, errors) = Ast::from_str_recovering(src, None, &syntax);
+        let errors: Vec<_> = errors
+            .into_iter()
+            .map(|err| match err {
+                ParseError::Details { offset, .. } => offset,
+                ParseError::Incomplete => panic!("unexpected incomplete error"),
+            })
+            .collect();
+        (ast.nodes, errors)
+    };
+
+    // Without errors, the result is the same as the one of `Ast::from_str()`.
+    let src = "a{% if x %}{{ b }}{% endif %}c";
+    assert_eq!(
+        parse(src),
+        (Ast::from_str(src, None, &syntax).unwrap().nodes, vec![])
+    );
+
+    // The broken `if` block is dropped, and its `endif` is skipped silently.
+    let (nodes, errors) = parse("a{% if x %}{{ b + }}{% endif %}c{{ d }}");
+    assert_eq!(errors, [18]);
+    assert_eq!(
+        nodes,
+        [
+            Node::Lit(WithSpan::no_span(Lit {
+                lws: "",
+                val: "a",
+                rws: "",
+            })),
+            Node::Lit(WithSpan::no_span(Lit {
+                lws: "",
+                val: "c",
+                rws: "",
+            })),
+            Node::Expr(Ws(None, None), WithSpan::no_span(Expr::Var("d"))),
+        ],
+    );
+
+    // Unknown tags are skipped, every one with an error.
+    let (nodes, errors) = parse("{% foo %}a{% endfor %}{{ b }}");
+    assert_eq!(errors, [0, 10]);
+    assert_eq!(nodes.len(), 2);
+
+    // Every broken block is reported.
+    let (nodes, errors) = parse("{% if %}{% endif %}{{ a }}{% for %}{% endfor %}");
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        nodes,
+        [Node::Expr(
+            Ws(None, None),
+            WithSpan::no_span(Expr::Var("a"))
+        )]
+    );
+
+    // An unclosed tag at the end.
+    let (nodes, errors) = parse("a{{ b");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(nodes.len(), 1);
+}
+
+#[test]
+fn test_line_statements() {
+    let default = Syntax::default();
+    let syntax = Syntax {
+        line_statement_prefix: Some("#"),
+        ..Syntax::default()
+    };
+    let same = |src: &str, expected: &str| {
+        assert_eq!(
+            Ast::from_str(src, None, &syntax).unwrap().nodes,
+            Ast::from_str(expected, None, &default).unwrap().nodes,
+            "{src:?}",
+        );
+    };
+
+    same(
+        "a:\n# for x in items\n  - {{ x }}\n# endfor\nb",
+        "a:\n{% for x in items %}  - {{ x }}\n{% endfor %}b",
+    );
+    // The leading white space and the line break belong to the tag.
+    same("  # if a\nb\n\t# endif  \n", "{% if a %}b\n{% endif %}");
+    same("# if a\r\nb\r\n# endif", "{% if a %}b\r\n{% endif %}");
+    // The expression of a line statement ends at the end of the line.
+    same(
+        "# for x in items\n[{{ x }}]\n# endfor",
+        "{% for x in items %}[{{ x }}]\n{% endfor %}",
+    );
+    same(
+        "# if c\n{{ a\n+ b }}\n# endif",
+        "{% if c %}{{ a\n+ b }}\n{% endif %}",
+    );
+    same("# if a\n- item\n# endif", "{% if a %}- item\n{% endif %}");
+    // Both kinds of tags can be mixed.
+    same("{% if a %}b\n# endif", "{% if a %}b\n{% endif %}");
+    // The prefix is only recognized at the start of a line.
+    same("a # b\n{{ c }}# d", "a # b\n{{ c }}# d");
+
+    assert_eq!(
+        Ast::from_str("# if a\nb\n# endif", None, &default)
+            .unwrap()
+            .nodes,
+        [Node::Lit(WithSpan::no_span(Lit {
+            lws: "",
+            val: "# if a\nb\n# endif",
+            rws: "",
+        }))],
+    );
+    assert!(Ast::from_str("# if a b\n# endif", None, &syntax).is_err());
+
+    // A parse that fails inside of a line statement does not affect the next one.
+    assert!(Ast::from_str("# for x in\n# endfor", None, &syntax).is_err());
+    same("{{ a\n+ b }}", "{{ a\n+ b }}");
+}
+
+#[test]
+fn test_extension_tags() {
+    let syntax = Syntax {
+        line_statement_prefix: Some("#"),
+        extension_tags: &["icon", "hr"],
+        ..Syntax::default()
+    };
+    let extension = |ws, name, args| {
+        Node::Extension(WithSpan::no_span(Extension {
+            ws,
+            name,
+            args,
+            body: None,
+        }))
+    };
+
+    assert_eq!(
+        Ast::from_str(
+            "{% icon \"home\" size=16 %}{%- hr -%}{%+ icon x|y(\"%\") ~%}",
+            None,
+            &syntax,
+        )
+        .unwrap()
+        .nodes,
+        [
+            extension(Ws(None, None), "icon", "\"home\" size=16"),
+            extension(
+                Ws(Some(Whitespace::Suppress), Some(Whitespace::Suppress)),
+                "hr",
+                "",
+            ),
+            extension(
+                Ws(Some(Whitespace::Preserve), Some(Whitespace::Minimize)),
+                "icon",
+                "x|y(\"%\")",
+            ),
+        ],
+    );
+    assert_eq!(
+        Ast::from_str("a\n  # icon star\n", None, &syntax)
+            .unwrap()
+            .nodes,
+        [
+            Node::Lit(WithSpan::no_span(Lit {
+                lws: "",
+                val: "a",
+                rws: "\n",
+            })),
+            extension(Ws(None, None), "icon", "star"),
+        ],
+    );
+
+    // Tags that are not registered are still unknown.
+    assert!(Ast::from_str("{% icon %}", None, &Syntax::default()).is_err());
+    assert!(Ast::from_str("{% icon", None, &syntax).is_err());
+}
+
+#[test]
+fn test_extension_block_tags() {
+    let syntax = Syntax {
+        line_statement_prefix: Some("#"),
+        extension_tags: &["icon", "markdown"],
+        extension_block_tags: &["markdown"],
+        ..Syntax::default()
+    };
+    let lit = |val| {
+        Node::Lit(WithSpan::no_span(Lit {
+            lws: "",
+            val,
+            rws: "",
+        }))
+    };
+
+    assert_eq!(
+        Ast::from_str(
+            "{% markdown safe %}# a{%- icon x %}{% endmarkdown -%}",
+            None,
+            &syntax,
+        )
+        .unwrap()
+        .nodes,
+        [Node::Extension(WithSpan::no_span(Extension {
+            ws: Ws(None, None),
+            name: "markdown",
+            args: "safe",
+            body: Some((
+                vec![
+                    lit("# a"),
+                    Node::Extension(WithSpan::no_span(Extension {
+                        ws: Ws(Some(Whitespace::Suppress), None),
+                        name: "icon",
+                        args: "x",
+                        body: None,
+                    })),
+                ],
+                Ws(None, Some(Whitespace::Suppress)),
+            )),
+        }))],
+    );
+    assert_eq!(
+        Ast::from_str(
+            "# markdown
+*a*
+# endmarkdown
+",
+            None,
+            &syntax
+        )
+        .unwrap()
+        .nodes,
+        [Node::Extension(WithSpan::no_span(Extension {
+            ws: Ws(None, None),
+            name: "markdown",
+            args: "",
+            body: Some((
+                vec![Node::Lit(WithSpan::no_span(Lit {
+                    lws: "",
+                    val: "*a*",
+                    rws: "\n",
+                }))],
+                Ws(None, None),
+            )),
+        }))],
+    );
+
+    assert!(Ast::from_str("{% markdown %}a", None, &syntax).is_err());
+    assert!(Ast::from_str("{% markdown %}a{% endicon %}", None, &syntax).is_err());
+    assert!(Ast::from_str("{% markdown %}a{% endmarkdown x %}", None, &syntax).is_err());
+}
+
+#[test]
+fn test_keyword_aliases() {
+    let syntax = Syntax {
+        keyword_aliases: &[("fi", "endif"), ("elsif", "elif")],
+        ..Syntax::default()
+    };
+    let aliased = Ast::from_str(
+        "{% if a %}1{% elsif b %}2{% else %}3{% fi %}{% for x in y %}{% endfor %}",
+        None,
+        &syntax,
+    )
+    .unwrap();
+    let builtin = Ast::from_str(
+        "{% if a %}1{% elif b %}2{% else %}3{% endif %}{% for x in y %}{% endfor %}",
+        None,
+        &syntax,
+    )
+    .unwrap();
+    assert_eq!(aliased.nodes, builtin.nodes);
+
+    // The aliases are only known to the syntax that defines them.
+    assert!(Ast::from_str("{% if a %}{% fi %}", None, &Syntax::default()).is_err());
+    // Other identifiers are not affected.
+    assert!(Ast::from_str("{{ fi }}{% let elsif = 1 %}", None, &syntax).is_ok());
+}
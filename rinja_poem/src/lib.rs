@@ -0,0 +1,38 @@
+#![forbid(unsafe_code)]
+#![deny(elided_lifetimes_in_paths)]
+#![deny(unreachable_pub)]
+
+#[doc(no_inline)]
+pub use poem;
+use poem::http::StatusCode;
+use poem::Response;
+#[doc(no_inline)]
+pub use rinja::*;
+
+/// Render a [`Template`] into a [`Response`], or render an error page.
+pub fn into_response<T: ?Sized + rinja::Template>(tmpl: &T) -> Response {
+    match try_into_response(tmpl) {
+        Ok(response) => response,
+        Err(err) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .content_type("text/plain; charset=utf-8")
+            .body(err.to_string()),
+    }
+}
+
+/// Try to render a [`Template`] into a [`Response`].
+pub fn try_into_response<T: ?Sized + rinja::Template>(tmpl: &T) -> Result<Response, Error> {
+    let value = tmpl.render()?;
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .content_type(T::MIME_TYPE)
+        .body(value))
+}
+
+/// Convert a rendering [`Error`] into a [`poem::Error`] with the status
+/// `500 Internal Server Error`.
+///
+/// This lets you use the `?` operator on [`Template::render()`] inside of a Poem handler.
+pub fn into_poem_error(err: Error) -> poem::Error {
+    poem::Error::new(err, StatusCode::INTERNAL_SERVER_ERROR)
+}
@@ -0,0 +1,40 @@
+use poem::test::TestClient;
+use poem::{get, handler, Route};
+use rinja_poem::Template;
+
+#[derive(Template)]
+#[template(path = "hello.html")]
+struct HelloTemplate<'a> {
+    name: &'a str,
+}
+
+#[handler]
+fn hello() -> HelloTemplate<'static> {
+    HelloTemplate { name: "world" }
+}
+
+#[handler]
+fn hello_string() -> poem::Result<String> {
+    HelloTemplate { name: "poem" }
+        .render()
+        .map_err(rinja_poem::into_poem_error)
+}
+
+#[tokio::test]
+async fn template_to_response() {
+    let app = Route::new().at("/", get(hello));
+
+    let res = TestClient::new(app).get("/").send().await;
+    res.assert_status_is_ok();
+    res.assert_content_type("text/html; charset=utf-8");
+    res.assert_text("Hello, world!").await;
+}
+
+#[tokio::test]
+async fn template_error_conversion() {
+    let app = Route::new().at("/", get(hello_string));
+
+    let res = TestClient::new(app).get("/").send().await;
+    res.assert_status_is_ok();
+    res.assert_text("Hello, poem!").await;
+}
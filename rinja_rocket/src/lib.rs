@@ -37,6 +37,9 @@ pub fn into_response<T: ?Sized + rinja::Template>(tmpl: &T) -> Response<'static>
 pub fn try_into_response<T: ?Sized + rinja::Template>(
     tmpl: &T,
 ) -> Result<Response<'static>, Error> {
+    #[cfg(feature = "buffer-pool")]
+    let value = rinja::buffer_pool::render(tmpl)?;
+    #[cfg(not(feature = "buffer-pool"))]
     let value = tmpl.render()?;
     Ok(Response::build()
         .header(rocket::http::Header::new("content-type", T::MIME_TYPE))
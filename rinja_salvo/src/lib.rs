@@ -0,0 +1,29 @@
+#![forbid(unsafe_code)]
+#![deny(elided_lifetimes_in_paths)]
+#![deny(unreachable_pub)]
+
+#[doc(no_inline)]
+pub use rinja::*;
+#[doc(no_inline)]
+pub use salvo_core;
+use salvo_core::http::header::{HeaderValue, CONTENT_TYPE};
+use salvo_core::http::{Response, StatusCode};
+
+/// Render a [`Template`] into a [`Response`], or set the status `500 Internal Server Error`.
+///
+/// In case of an error, the body is left empty, so a [`Catcher`](salvo_core::catcher::Catcher)
+/// can render the error page.
+pub fn render<T: ?Sized + rinja::Template>(tmpl: &T, res: &mut Response) {
+    if try_render(tmpl, res).is_err() {
+        res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
+
+/// Try to render a [`Template`] into a [`Response`].
+pub fn try_render<T: ?Sized + rinja::Template>(tmpl: &T, res: &mut Response) -> Result<(), Error> {
+    let value = tmpl.render()?;
+    res.headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(T::MIME_TYPE));
+    res.write_body(value)
+        .map_err(|err| Error::Custom(err.into()))
+}
@@ -0,0 +1,24 @@
+use rinja_salvo::Template;
+use salvo_core::prelude::*;
+use salvo_core::test::{ResponseExt, TestClient};
+
+#[derive(Template)]
+#[template(path = "hello.html")]
+struct HelloTemplate<'a> {
+    name: &'a str,
+}
+
+#[handler]
+async fn hello() -> HelloTemplate<'static> {
+    HelloTemplate { name: "world" }
+}
+
+#[tokio::test]
+async fn test_salvo() {
+    let service = Service::new(Router::new().get(hello));
+
+    let mut res = TestClient::get("http://127.0.0.1/").send(&service).await;
+    assert_eq!(res.status_code, Some(StatusCode::OK));
+    assert_eq!(res.headers()["Content-Type"], "text/html; charset=utf-8");
+    assert_eq!(res.take_string().await.unwrap(), "Hello, world!");
+}
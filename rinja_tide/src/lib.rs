@@ -0,0 +1,35 @@
+#![forbid(unsafe_code)]
+#![deny(elided_lifetimes_in_paths)]
+#![deny(unreachable_pub)]
+
+#[doc(no_inline)]
+pub use rinja::*;
+#[doc(no_inline)]
+pub use tide;
+use tide::{Body, Response, StatusCode};
+
+/// Render a [`Template`] into a [`Response`], or render an error page.
+///
+/// In case of an error, the status is `500 Internal Server Error`, and the error is attached
+/// to the response, so it can be inspected by a middleware.
+pub fn into_response<T: ?Sized + rinja::Template>(tmpl: &T) -> Response {
+    match try_into_body(tmpl) {
+        Ok(body) => {
+            let mut response = Response::new(StatusCode::Ok);
+            response.set_body(body);
+            response
+        }
+        Err(err) => {
+            let mut response = Response::new(StatusCode::InternalServerError);
+            response.set_error(err);
+            response
+        }
+    }
+}
+
+/// Try to render a [`Template`] into a [`Body`].
+pub fn try_into_body<T: ?Sized + rinja::Template>(tmpl: &T) -> Result<Body, Error> {
+    let mut body = Body::from_string(tmpl.render()?);
+    body.set_mime(T::MIME_TYPE);
+    Ok(body)
+}
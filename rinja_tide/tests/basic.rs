@@ -0,0 +1,30 @@
+use rinja_tide::Template;
+use tide::http::mime::HTML;
+use tide::http::{Method, Request, Response, Url};
+use tide::{Body, StatusCode};
+
+#[derive(Template)]
+#[template(path = "hello.html")]
+struct HelloTemplate<'a> {
+    name: &'a str,
+}
+
+#[async_std::test]
+async fn template_to_response() {
+    let mut app = tide::new();
+    app.at("/")
+        .get(|_| async { Ok(HelloTemplate { name: "world" }) });
+
+    let req = Request::new(Method::Get, Url::parse("http://localhost/").unwrap());
+    let mut res: Response = app.respond(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::Ok);
+    assert_eq!(res.content_type(), Some(HTML));
+    assert_eq!(res.body_string().await.unwrap(), "Hello, world!");
+}
+
+#[async_std::test]
+async fn template_to_body() {
+    let body: Body = HelloTemplate { name: "world" }.try_into().unwrap();
+    assert_eq!(body.mime(), &HTML);
+    assert_eq!(body.into_string().await.unwrap(), "Hello, world!");
+}
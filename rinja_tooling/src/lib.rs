@@ -0,0 +1,6 @@
+#![doc = include_str!("../README.md")]
+#![deny(elided_lifetimes_in_paths)]
+#![deny(unreachable_pub)]
+
+pub use parser;
+pub use rinja_derive_standalone::tooling::{analyze_template, Analysis, Definition, Diagnostic};
@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+
+use rinja_tooling::{analyze_template, Analysis, Definition};
+
+fn template(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("templates")
+        .join(name)
+}
+
+fn definitions<'a>(
+    analysis: &'a Analysis,
+    definitions: &'a [Definition],
+) -> Vec<(&'a str, &'a Path, &'a str)> {
+    definitions
+        .iter()
+        .map(|def| {
+            let source = analysis.templates[&def.path].source();
+            let tag = source[def.offset..].split("%}").next().unwrap();
+            (def.name.as_str(), def.path.as_path(), tag)
+        })
+        .collect()
+}
+
+#[test]
+fn test_analyze_valid() {
+    let analysis = analyze_template("index.html", None);
+    assert_eq!(analysis.diagnostics, []);
+    assert_eq!(analysis.path, Some(template("index.html")));
+    assert_eq!(
+        analysis.extends_chain,
+        [
+            template("index.html"),
+            template("layout.html"),
+            template("base.html")
+        ],
+    );
+
+    let mut templates = analysis.templates.keys().cloned().collect::<Vec<_>>();
+    templates.sort();
+    assert_eq!(
+        templates,
+        [
+            template("base.html"),
+            template("index.html"),
+            template("layout.html"),
+            template("macros.html")
+        ],
+    );
+    assert!(!analysis.templates[&template("index.html")]
+        .nodes()
+        .is_empty());
+
+    let (base, index, layout, macros) = (
+        template("base.html"),
+        template("index.html"),
+        template("layout.html"),
+        template("macros.html"),
+    );
+    assert_eq!(
+        definitions(&analysis, &analysis.blocks),
+        [
+            ("content", index.as_path(), " block content "),
+            ("title", layout.as_path(), " block title "),
+            ("subtitle", layout.as_path(), " block subtitle "),
+            ("title", base.as_path(), " block title "),
+            ("content", base.as_path(), " block content "),
+        ],
+    );
+    assert_eq!(
+        definitions(&analysis, &analysis.macros),
+        [
+            ("link", layout.as_path(), "- macro link(href) -"),
+            ("heading", macros.as_path(), " macro heading(text) "),
+        ],
+    );
+}
+
+#[test]
+fn test_analyze_syntax_error() {
+    let analysis = analyze_template("syntax.html", None);
+    assert_eq!(analysis.path, Some(template("syntax.html")));
+    assert_eq!(analysis.diagnostics.len(), 1);
+    let diagnostic = &analysis.diagnostics[0];
+    assert!(diagnostic
+        .message
+        .contains("failed to parse template source"));
+    assert_eq!(diagnostic.path, Some(template("syntax.html")));
+    assert_eq!(diagnostic.offset, Some(10));
+}
+
+#[test]
+fn test_analyze_missing_template() {
+    let analysis = analyze_template("missing-include.html", None);
+    assert_eq!(analysis.diagnostics.len(), 1);
+    let diagnostic = &analysis.diagnostics[0];
+    assert!(diagnostic
+        .message
+        .contains("template \"missing.html\" not found"));
+    assert_eq!(diagnostic.path, Some(template("missing-include.html")));
+    assert_eq!(diagnostic.offset, None);
+
+    let analysis = analyze_template("does-not-exist.html", None);
+    assert_eq!(analysis.path, None);
+    assert_eq!(analysis.diagnostics.len(), 1);
+    assert_eq!(analysis.diagnostics[0].path, None);
+}
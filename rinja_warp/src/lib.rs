@@ -25,6 +25,9 @@ pub fn into_response<T: ?Sized + rinja::Template>(tmpl: &T) -> Response {
 
 /// Try to render a [`Template`] into a [`Response`].
 pub fn try_into_response<T: ?Sized + rinja::Template>(tmpl: &T) -> Result<Response, Error> {
+    #[cfg(feature = "buffer-pool")]
+    let value = bytes::Bytes::from_owner(rinja::buffer_pool::render(tmpl)?).into();
+    #[cfg(not(feature = "buffer-pool"))]
     let value = tmpl.render()?.into();
     warp::http::Response::builder()
         .status(warp::http::StatusCode::OK)
@@ -0,0 +1,35 @@
+#![forbid(unsafe_code)]
+#![deny(elided_lifetimes_in_paths)]
+#![deny(unreachable_pub)]
+
+#[doc(no_inline)]
+pub use rinja::*;
+#[doc(no_inline)]
+pub use worker;
+use worker::Response;
+
+/// Render a [`Template`] into a [`Response`], or render an error page.
+///
+/// An error is only returned if the [`Response`] could not be created.
+pub fn into_response<T: ?Sized + rinja::Template>(tmpl: &T) -> worker::Result<Response> {
+    match try_into_response(tmpl) {
+        Ok(response) => Ok(response),
+        Err(err) => Response::error(err.to_string(), 500),
+    }
+}
+
+/// Try to render a [`Template`] into a [`Response`].
+pub fn try_into_response<T: ?Sized + rinja::Template>(tmpl: &T) -> Result<Response, Error> {
+    let value = tmpl.render()?;
+    let mut response = Response::ok(value).map_err(into_error)?;
+    response
+        .headers_mut()
+        .set("Content-Type", T::MIME_TYPE)
+        .map_err(into_error)?;
+    Ok(response)
+}
+
+// `worker::Error` can contain a `JsValue`, which is neither `Send` nor `Sync`.
+fn into_error(err: worker::Error) -> Error {
+    Error::Custom(err.to_string().into())
+}
@@ -0,0 +1,23 @@
+// The `worker` crate can only be used in a JavaScript runtime, so run this test with e.g.
+// `wasm-pack test --node`.
+#![cfg(target_arch = "wasm32")]
+
+use rinja_worker::Template;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[derive(Template)]
+#[template(path = "hello.html")]
+struct HelloTemplate<'a> {
+    name: &'a str,
+}
+
+#[wasm_bindgen_test]
+async fn template_to_response() {
+    let mut res = rinja_worker::into_response(&HelloTemplate { name: "world" }).unwrap();
+    assert_eq!(res.status_code(), 200);
+    assert_eq!(
+        res.headers().get("Content-Type").unwrap().as_deref(),
+        Some("text/html; charset=utf-8"),
+    );
+    assert_eq!(res.text().await.unwrap(), "Hello, world!");
+}
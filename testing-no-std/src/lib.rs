@@ -0,0 +1,41 @@
+//! Templates that are derived with rinja's default features, including `std`, disabled.
+//!
+//! The crate is `no_std`, so the generated code must only use `core` and `alloc`. It is tested
+//! with `cd testing-no-std && cargo test`, so other workspace members do not enable `std`.
+
+#![no_std]
+
+extern crate alloc;
+
+use core::fmt;
+
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = "Hello, {{ name }}! {% for n in numbers %}{{ n }},{% endfor %} {{ total()? }}",
+    ext = "html"
+)]
+pub struct HelloTemplate<'a> {
+    pub name: &'a str,
+    pub numbers: &'a [u32],
+}
+
+impl HelloTemplate<'_> {
+    fn total(&self) -> Result<u32, TooLarge> {
+        self.numbers
+            .iter()
+            .try_fold(0u32, |acc, &n| acc.checked_add(n))
+            .ok_or(TooLarge)
+    }
+}
+
+/// An error that is not a `std::error::Error`, raised with `?` in [`HelloTemplate`]
+#[derive(Debug, PartialEq)]
+pub struct TooLarge;
+
+impl fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the total is too large")
+    }
+}
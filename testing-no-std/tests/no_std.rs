@@ -0,0 +1,23 @@
+use rinja::Template;
+use rinja_testing_no_std::HelloTemplate;
+
+#[test]
+fn test_no_std() {
+    let t = HelloTemplate {
+        name: "<world>",
+        numbers: &[1, 2, 3],
+    };
+    assert_eq!(t.render().unwrap(), "Hello, &#60;world&#62;! 1,2,3, 6");
+}
+
+#[test]
+fn test_no_std_custom_error() {
+    let t = HelloTemplate {
+        name: "world",
+        numbers: &[u32::MAX, 1],
+    };
+    let err = t.render().unwrap_err();
+    assert_eq!(err.to_string(), "the total is too large");
+    // With `std` unified into the workspace, `TooLarge` is wrapped, so it cannot be downcast.
+    assert!(matches!(err, rinja::Error::Custom(_)));
+}
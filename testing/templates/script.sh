@@ -0,0 +1,3 @@
+#!/bin/sh
+mkdir -p -- {{ dir }}
+echo {{ greeting }} > {{ dir }}/hello.txt
@@ -0,0 +1,31 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = "<div class=\"card\">\n  <h1>{{ title }}</h1>\n</div>\n",
+    ext = "html"
+)]
+struct Card<'a> {
+    title: &'a str,
+}
+
+#[test]
+fn test_rendered_eq_ignoring_ws() {
+    rinja::assert_rendered_eq_ignoring_ws!(
+        Card { title: "Ferris" },
+        "<div class=\"card\"> <h1>Ferris</h1> </div>",
+    );
+    rinja::assert_rendered_eq_ignoring_ws!(
+        Card { title: "Ferris" },
+        "\n<div class=\"card\">\n\t<h1>Ferris</h1>\n</div>",
+    );
+}
+
+#[test]
+#[should_panic(expected = "rendered output does not match, ignoring whitespace")]
+fn test_rendered_eq_ignoring_ws_mismatch() {
+    rinja::assert_rendered_eq_ignoring_ws!(
+        Card { title: "Ferris" },
+        "<div class=\"card\"><h1>Ferris</h1></div>",
+    );
+}
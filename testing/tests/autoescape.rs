@@ -0,0 +1,42 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ s }}
+{% autoescape false %}{{ s }} {{ s|escape("html") }}{% endautoescape %}
+<script>let s = '{% autoescape "js" %}{{ s }}{% endautoescape %}';</script>
+{% autoescape false %}{% autoescape true %}{{ s }}{% endautoescape %}{% endautoescape %}"#,
+    ext = "html"
+)]
+struct AutoEscape<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_autoescape() {
+    let t = AutoEscape { s: "<'a'>" };
+    assert_eq!(
+        t.render().unwrap(),
+        "&#60;&#39;a&#39;&#62;
+<'a'> &#60;&#39;a&#39;&#62;
+<script>let s = '\\x3c\\x27a\\x27\\x3e';</script>
+&#60;&#39;a&#39;&#62;"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% macro m() %}[{{ s }}]{% endmacro -%}
+{% call m() %} {% autoescape "html" %}{% call m() %} {{ s|safe }}{% endautoescape %}"#,
+    ext = "txt"
+)]
+struct AutoEscapeMacro<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_autoescape_macro() {
+    // Macros that are called in the block use its escaper, too.
+    let t = AutoEscapeMacro { s: "a&b" };
+    assert_eq!(t.render().unwrap(), "[a&b] [a&#38;b] a&b");
+}
@@ -145,3 +145,76 @@ fn test_fragment_include() {
     let fragment_include = FragmentInclude { s: "world" };
     assert_eq!(fragment_include.render().unwrap(), "\nINCLUDED: world\n");
 }
+
+#[derive(Template)]
+#[template(path = "fragment-super.html", blocks = ["body", "other_body"])]
+struct FragmentBlocks<'a> {
+    name: &'a str,
+}
+
+/// Tests that `blocks = [...]` generates render methods for every listed block,
+/// while the whole template can still be rendered.
+#[test]
+fn test_fragment_blocks() {
+    let t = FragmentBlocks { name: "world" };
+
+    assert_eq!(
+        t.render_body().unwrap(),
+        "\n<p>Hello world!</p>\n\n<p>Parent body content</p>\n\n"
+    );
+    assert_eq!(
+        t.render_other_body().unwrap(),
+        "\n<p>Don't render me.</p>\n\n"
+    );
+
+    let mut buf = String::new();
+    t.render_body_into(&mut buf).unwrap();
+    assert_eq!(buf, t.render_body().unwrap());
+
+    assert!(t.render().unwrap().starts_with("<html>"));
+}
+
+#[derive(Template)]
+#[template(path = "blocks.txt", blocks = ["section"])]
+struct GenericBlocks<'a, T: std::fmt::Display> {
+    values: &'a [T],
+    s1: T,
+}
+
+#[test]
+fn test_fragment_blocks_generic() {
+    let t = GenericBlocks {
+        values: &[1, 2, 3],
+        s1: 4,
+    };
+    assert_eq!(t.render_section().unwrap(), "[123]");
+    assert_eq!(t.section_fragment().to_string(), "[123]");
+}
+
+#[derive(Template)]
+#[template(path = "fragment-super.html", fragments = true)]
+struct Fragments<'a> {
+    name: &'a str,
+}
+
+/// Tests that `fragments = true` generates fragments for every block of the template.
+#[test]
+fn test_fragments() {
+    fn render_fragment(tmpl: &impl Template) -> String {
+        tmpl.render().unwrap()
+    }
+
+    let t = Fragments { name: "world" };
+    assert_eq!(
+        render_fragment(&t.body_fragment()),
+        "\n<p>Hello world!</p>\n\n<p>Parent body content</p>\n\n"
+    );
+    assert_eq!(
+        t.other_body_fragment().to_string(),
+        "\n<p>Don't render me.</p>\n\n"
+    );
+    assert_eq!(
+        t.render_other_body().unwrap(),
+        "\n<p>Don't render me.</p>\n\n"
+    );
+}
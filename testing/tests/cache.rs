@@ -0,0 +1,140 @@
+use std::cell::Cell;
+
+use rinja::cache::{LruCache, RenderCache};
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = "<nav>
+{%- cache user -%}
+    {{ self.count() }}: {{ user }}
+{%- endcache -%}
+</nav> {% cache user %}{{ self.count() }}{% endcache %}",
+    ext = "html"
+)]
+struct Navigation<'a> {
+    user: &'a str,
+    renderings: &'a Cell<u32>,
+    cache: &'a LruCache,
+}
+
+impl Navigation<'_> {
+    fn count(&self) -> u32 {
+        self.renderings.set(self.renderings.get() + 1);
+        self.renderings.get()
+    }
+}
+
+impl RenderCache for Navigation<'_> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.cache.get(key)
+    }
+
+    fn insert(&self, key: String, value: String) {
+        self.cache.insert(key, value);
+    }
+}
+
+#[test]
+fn test_cache() {
+    let renderings = Cell::new(0);
+    let cache = LruCache::new(10);
+    let t = Navigation {
+        user: "<Ferris>",
+        renderings: &renderings,
+        cache: &cache,
+    };
+    // Both blocks have the same key, but their content is cached separately.
+    assert_eq!(t.render().unwrap(), "<nav>1: &#60;Ferris&#62;</nav> 2");
+    assert_eq!(t.render().unwrap(), "<nav>1: &#60;Ferris&#62;</nav> 2");
+    assert_eq!(renderings.get(), 2);
+
+    let t = Navigation {
+        user: "Ferris",
+        renderings: &renderings,
+        cache: &cache,
+    };
+    assert_eq!(t.render().unwrap(), "<nav>3: Ferris</nav> 4");
+
+    cache.clear();
+    assert_eq!(t.render().unwrap(), "<nav>5: Ferris</nav> 6");
+}
+
+// Templates of different modules, which have the same name, source and key, and share a cache.
+macro_rules! greeting {
+    ($module:ident) => {
+        mod $module {
+            use rinja::cache::{LruCache, RenderCache};
+            use rinja::Template;
+
+            #[derive(Template)]
+            #[template(source = "{% cache 1 %}{{ greeting }}{% endcache %}", ext = "html")]
+            pub struct Greeting<'a> {
+                pub greeting: &'a str,
+                pub cache: &'a LruCache,
+            }
+
+            impl RenderCache for Greeting<'_> {
+                fn get(&self, key: &str) -> Option<String> {
+                    self.cache.get(key)
+                }
+
+                fn insert(&self, key: String, value: String) {
+                    self.cache.insert(key, value);
+                }
+            }
+        }
+    };
+}
+
+greeting!(greeting_a);
+greeting!(greeting_b);
+
+#[test]
+fn test_cache_modules() {
+    let cache = LruCache::new(10);
+    let a = greeting_a::Greeting {
+        greeting: "<hi>",
+        cache: &cache,
+    };
+    let b = greeting_b::Greeting {
+        greeting: "hello",
+        cache: &cache,
+    };
+    assert_eq!(a.render().unwrap(), "&#60;hi&#62;");
+    assert_eq!(b.render().unwrap(), "hello");
+    assert_eq!(a.render().unwrap(), "&#60;hi&#62;");
+}
+
+// The same block is rendered with different escapers.
+#[derive(Template)]
+#[template(
+    source = "{% macro greet() %}{% cache 1 %}{{ greeting }}{% endcache %}{% endmacro -%}
+        {% call greet() %}|{% autoescape false %}{% call greet() %}{% endautoescape %}",
+    ext = "html"
+)]
+struct Escapers<'a> {
+    greeting: &'a str,
+    cache: &'a LruCache,
+}
+
+impl RenderCache for Escapers<'_> {
+    fn get(&self, key: &str) -> Option<String> {
+        self.cache.get(key)
+    }
+
+    fn insert(&self, key: String, value: String) {
+        self.cache.insert(key, value);
+    }
+}
+
+#[test]
+fn test_cache_escapers() {
+    let cache = LruCache::new(10);
+    let t = Escapers {
+        greeting: "<hi>",
+        cache: &cache,
+    };
+    assert_eq!(t.render().unwrap(), "&#60;hi&#62;|<hi>");
+    assert_eq!(t.render().unwrap(), "&#60;hi&#62;|<hi>");
+}
@@ -170,3 +170,65 @@ struct NotMethod;
 fn test_not_method() {
     assert_eq!(NotMethod.render().unwrap(), "a6");
 }
+
+struct Item {
+    active: bool,
+    price: u32,
+}
+
+#[derive(Template)]
+#[template(
+    source = "
+{{- items.iter().filter(|i| i.active).count() }}
+{{ items.iter().map(|item| item.price * factor).sum::<u32>() }}
+{{ pairs.iter().map(|(a, b)| a * b).max().unwrap() }}
+{{ (|| factor + 1)() -}}
+",
+    ext = "txt"
+)]
+struct Closures {
+    items: Vec<Item>,
+    pairs: Vec<(u32, u32)>,
+    factor: u32,
+}
+
+#[test]
+fn test_closures() {
+    let t = Closures {
+        items: vec![
+            Item {
+                active: true,
+                price: 1,
+            },
+            Item {
+                active: false,
+                price: 2,
+            },
+        ],
+        pairs: vec![(1, 2), (3, 4)],
+        factor: 10,
+    };
+    assert_eq!(t.render().unwrap(), "1\n30\n12\n11");
+}
+
+#[derive(Template)]
+#[template(
+    source = "
+{{- value.parse::<u64>().unwrap_or(0) }}
+{{ core::mem::size_of::<u32>() }}
+{{ Vec::<u8>::with_capacity(4).len() -}}
+",
+    ext = "txt"
+)]
+struct GenericArguments<'a> {
+    value: &'a str,
+}
+
+#[test]
+fn test_generic_arguments() {
+    assert_eq!(
+        GenericArguments { value: "12" }.render().unwrap(),
+        "12\n4\n0"
+    );
+    assert_eq!(GenericArguments { value: "x" }.render().unwrap(), "0\n4\n0");
+}
@@ -0,0 +1,64 @@
+use rinja::Template;
+
+pub const VERSION: &str = "1.2.3";
+pub static NAME: &str = "<app>";
+
+pub mod limits {
+    pub const MAX: usize = 10;
+
+    #[allow(non_upper_case_globals)]
+    pub static greeting: &str = "hello";
+}
+
+pub enum Level {
+    Low,
+}
+
+impl Level {
+    pub const COUNT: usize = 1;
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ crate::NAME }} {{ crate::VERSION }} {{ Level::COUNT }} {{ limits::MAX }} {{ u8::MAX }}",
+    ext = "html"
+)]
+struct Paths;
+
+#[test]
+fn test_const_paths() {
+    assert_eq!(Paths.render().unwrap(), "&#60;app&#62; 1.2.3 1 10 255");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ NAME }} {{ VERSION }}{% if items > MAX %} (too many){% endif %}",
+    ext = "html",
+    consts = ["crate::NAME"],
+    config = "test_constants.toml"
+)]
+struct Names {
+    items: usize,
+}
+
+#[test]
+fn test_const_names() {
+    assert_eq!(
+        Names { items: 11 }.render().unwrap(),
+        "&#60;app&#62; 1.2.3 (too many)"
+    );
+}
+
+// Local variables take precedence over constants.
+#[derive(Template)]
+#[template(
+    source = "{{ greeting }} {% for greeting in [\"hi\"] %}{{ greeting }}{% endfor %} {{ MAX }}",
+    ext = "txt",
+    consts = ["limits::greeting", "limits::MAX"]
+)]
+struct Shadowed;
+
+#[test]
+fn test_const_shadowed() {
+    assert_eq!(Shadowed.render().unwrap(), "hello hi 10");
+}
@@ -0,0 +1,65 @@
+#![cfg(debug_assertions)]
+
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"<p>{% include "included.html" %}</p>"#,
+    ext = "html",
+    debug_origins = true
+)]
+struct Include<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_debug_origins_include() {
+    let t = Include { s: "x" };
+    assert_eq!(
+        t.render().unwrap(),
+        "<!-- begin Include.html -->\
+         <p><!-- begin templates/included.html -->INCLUDED: x\
+         <!-- end templates/included.html --></p>\
+         <!-- end Include.html -->",
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% extends "base.html" %}{% block content %}content{% endblock %}"#,
+    ext = "html",
+    debug_origins = true
+)]
+struct Extends<'a> {
+    title: &'a str,
+}
+
+#[test]
+fn test_debug_origins_blocks() {
+    let t = Extends { title: "title" };
+    assert_eq!(
+        t.render().unwrap(),
+        "<!-- begin Extends.html --><!-- begin templates/base.html -->title\n\
+         <!-- begin Extends.html#block content -->content\
+         <!-- end Extends.html#block content -->\n\
+         <!-- begin templates/base.html#block foo -->Foo\
+         <!-- end templates/base.html#block foo -->\n\
+         Copyright 2017<!-- end templates/base.html --><!-- end Extends.html -->",
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% include "included.html" %}"#,
+    ext = "txt",
+    debug_origins = true
+)]
+struct Text<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_debug_origins_not_html() {
+    let t = Text { s: "x" };
+    assert_eq!(t.render().unwrap(), "INCLUDED: x");
+}
@@ -0,0 +1,45 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(path = "emails/welcome.html", config = "test_directories.toml")]
+struct Welcome<'a> {
+    name: &'a str,
+}
+
+#[derive(Template)]
+#[template(
+    path = "emails/welcome.html",
+    config = "test_directories.toml",
+    escape = "html",
+    whitespace = "preserve"
+)]
+struct WelcomeOverridden<'a> {
+    name: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "hello.html", config = "test_directories.toml")]
+struct Hello<'a> {
+    name: &'a str,
+}
+
+// The settings of a `[[directory]]` apply to the templates in it.
+#[test]
+fn test_directory_settings() {
+    let t = Welcome { name: "<world>" };
+    assert_eq!(t.render().unwrap(), "<p><world></p>");
+}
+
+// The arguments of the `template()` attribute have precedence.
+#[test]
+fn test_directory_settings_overridden() {
+    let t = WelcomeOverridden { name: "<world>" };
+    assert_eq!(t.render().unwrap(), "<p>\n  &#60;world&#62;\n</p>");
+}
+
+// Templates outside of the directory are not affected.
+#[test]
+fn test_directory_settings_other_dir() {
+    let t = Hello { name: "<world>" };
+    assert_eq!(t.render().unwrap(), "Hello, &#60;world&#62;!");
+}
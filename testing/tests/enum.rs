@@ -0,0 +1,132 @@
+use std::fmt::Display;
+
+use rinja::Template;
+
+#[derive(Template)]
+enum Page<'a> {
+    #[template(source = "Loading…", ext = "html")]
+    Loading,
+    #[template(source = "Error {{ code }}: {{ msg }}", ext = "html")]
+    Error { code: u16, msg: &'a str },
+    #[template(path = "hello.html")]
+    Ready { name: &'a str },
+}
+
+#[test]
+fn test_enum_per_variant() {
+    assert_eq!(Page::Loading.render().unwrap(), "Loading…");
+    assert_eq!(
+        Page::Error {
+            code: 404,
+            msg: "<not found>"
+        }
+        .render()
+        .unwrap(),
+        "Error 404: &#60;not found&#62;"
+    );
+    assert_eq!(Page::Ready { name: "world" }.to_string(), "Hello, world!");
+    assert_eq!(Page::EXTENSION, Some("html"));
+    assert_eq!(Page::MIME_TYPE, "text/html; charset=utf-8");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% match self %}{% when Self::Empty %}nothing{% else %}?{% endmatch %}"#,
+    ext = "txt"
+)]
+enum Fallback {
+    Empty,
+    #[template(source = "{{ self.0 }} + {{ self.1 }}", ext = "txt")]
+    Pair(u32, u32),
+    #[template(source = r#"{% if flag %}on{% else %}off{% endif %}"#, ext = "txt")]
+    Switch {
+        flag: bool,
+    },
+}
+
+#[test]
+fn test_enum_default_template() {
+    assert_eq!(Fallback::Empty.render().unwrap(), "nothing");
+    assert_eq!(Fallback::Pair(1, 2).render().unwrap(), "1 + 2");
+    assert_eq!(Fallback::Switch { flag: true }.render().unwrap(), "on");
+    assert_eq!(Fallback::Switch { flag: false }.render().unwrap(), "off");
+}
+
+#[derive(Template)]
+enum Generic<T: Display> {
+    #[template(source = "value: {{ value }}", ext = "txt")]
+    Value { value: T },
+    #[template(source = "{% for v in values %}{{ v }},{% endfor %}", ext = "txt")]
+    List { values: Vec<T> },
+}
+
+#[test]
+fn test_enum_generic() {
+    assert_eq!(Generic::Value { value: 5 }.render().unwrap(), "value: 5");
+    assert_eq!(
+        Generic::List {
+            values: vec!["a", "b"]
+        }
+        .render()
+        .unwrap(),
+        "a,b,"
+    );
+}
+
+#[test]
+fn test_enum_size_hint() {
+    assert!(Page::SIZE_HINT >= "Error : ".len());
+}
+
+#[derive(Template)]
+enum Cart {
+    #[template(
+        source = "{% if count > 5 %}many{% else %}{{ count }}{% endif %} {{ self.count + 1 }} \
+                  {{ label() }} {{ self.label() }}",
+        ext = "txt"
+    )]
+    Items { count: u32 },
+    #[template(source = "{{ self.0 * 2 }} {{ label() }}", ext = "txt")]
+    Weight(u32),
+    #[template(
+        source = r#"{{ name|upper }} {{ name.len() }} {{ tags|join(", ") }} {{ name == "ab" }}"#,
+        ext = "txt"
+    )]
+    Named { name: String, tags: Vec<String> },
+    #[template(source = "{{ label() }}", ext = "txt")]
+    Empty,
+}
+
+impl Cart {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Items { .. } => "items",
+            Self::Weight(_) => "weight",
+            Self::Named { .. } => "named",
+            Self::Empty => "empty",
+        }
+    }
+}
+
+#[test]
+fn test_enum_variant_fields() {
+    assert_eq!(
+        Cart::Items { count: 7 }.render().unwrap(),
+        "many 8 items items"
+    );
+    assert_eq!(
+        Cart::Items { count: 2 }.render().unwrap(),
+        "2 3 items items"
+    );
+    assert_eq!(Cart::Weight(3).render().unwrap(), "6 weight");
+    assert_eq!(
+        Cart::Named {
+            name: "ab".into(),
+            tags: vec!["x".into(), "y".into()],
+        }
+        .render()
+        .unwrap(),
+        "AB 2 x, y true"
+    );
+    assert_eq!(Cart::Empty.render().unwrap(), "empty");
+}
@@ -198,6 +198,8 @@ hello
     );
 }
 
+// The words of the included file are counted, so it is a fixture of its own that does not change
+// with e.g. the dependencies in `Cargo.toml`.
 #[derive(Template)]
 #[template(
     source = r#"{% extends "html-base.html" %}
@@ -207,7 +209,7 @@ hello
         {% set y = 12 %}
 
     {% filter wordcount %}
-        {%- include "../Cargo.toml" +%}
+        {%- include "wordcount.txt" +%}
         y is {{ y }}
     {% endfilter %}
 {%- endblock body %}
@@ -233,7 +235,7 @@ fn filter_block_include() {
     <body class=""><h1>Metadata</h1>
         
 
-    99</body>
+    22</body>
 </html>"#
     );
 }
@@ -330,3 +332,36 @@ fn filter_block_custom_errors() {
     };
     assert_eq!(template.render().unwrap_err().to_string(), "🐢");
 }
+
+// Filter blocks that only contain text are filtered and escaped at compile time. The output must
+// be the same as if the filters were applied at runtime.
+#[derive(Template)]
+#[template(
+    source = r#"
+    {%- filter title|trim -%}  héllo <WORLD> & 'ß' {# comment #} {% endfilter -%}
+    |{{ text|title|trim -}}
+    |{% filter lower|capfirst %}  CI & CD{% endfilter -%}
+    |{{ "  CI & CD"|lower|capfirst -}}
+    |{% filter upper %}{% filter capitalize %}rUST {{ x }}{% endfilter %} & go{% endfilter %}"#,
+    ext = "html"
+)]
+struct ConstFilterBlock<'a> {
+    text: &'a str,
+    x: u32,
+}
+
+#[test]
+fn filter_block_const() {
+    let template = ConstFilterBlock {
+        text: "  héllo <WORLD> & 'ß'  ",
+        x: 7,
+    };
+    assert_eq!(
+        template.render().unwrap(),
+        "Héllo &#60;world&#62; &#38; &#39;ß&#39;\
+         |Héllo &#60;world&#62; &#38; &#39;ß&#39;\
+         |  ci &#38; cd\
+         |  ci &#38; cd\
+         |RUST 7 &#38; GO",
+    );
+}
@@ -0,0 +1,62 @@
+use rinja::Template;
+
+mod global_filters {
+    pub fn shout(s: &str) -> ::rinja::Result<String> {
+        Ok(format!("{}!", s.to_uppercase()))
+    }
+
+    pub fn whisper(s: &str) -> ::rinja::Result<String> {
+        Ok(format!("{}...", s.to_lowercase()))
+    }
+}
+
+mod more_filters {
+    pub fn reverse(s: &str) -> ::rinja::Result<String> {
+        Ok(s.chars().rev().collect())
+    }
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ s|shout }} {{ s|reverse }} {{ s|upper }}",
+    ext = "txt",
+    config = "test_filter_modules.toml"
+)]
+struct GlobalFilters<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_global_filters() {
+    let t = GlobalFilters { s: "abc" };
+    assert_eq!(t.render().unwrap(), "ABC! cba ABC");
+}
+
+mod local {
+    use rinja::Template;
+
+    mod filters {
+        pub use crate::global_filters::whisper;
+
+        pub fn shout(s: &str) -> ::rinja::Result<String> {
+            Ok(format!("{s}?"))
+        }
+    }
+
+    #[derive(Template)]
+    #[template(
+        source = "{{ s|shout }} {{ s|whisper }}",
+        ext = "txt",
+        config = "test_filter_modules.toml"
+    )]
+    struct LocalFilters<'a> {
+        s: &'a str,
+    }
+
+    // A `filters` module next to the template takes precedence over the configured modules.
+    #[test]
+    fn test_local_filters() {
+        let t = LocalFilters { s: "Abc" };
+        assert_eq!(t.render().unwrap(), "Abc? abc...");
+    }
+}
@@ -24,6 +24,54 @@ fn filter_escape() {
     );
 }
 
+#[derive(Template)]
+#[template(path = "script.sh")]
+struct ShellEscapeTemplate<'a> {
+    dir: &'a str,
+    greeting: &'a str,
+}
+
+#[test]
+fn filter_escape_shell() {
+    let t = ShellEscapeTemplate {
+        dir: "/tmp/my dir",
+        greeting: "it's $(whoami)",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        r"#!/bin/sh
+mkdir -p -- '/tmp/my dir'
+echo 'it'\''s $(whoami)' > '/tmp/my dir'/hello.txt"
+    );
+}
+
+#[cfg(feature = "serde_json")]
+#[derive(Template)]
+#[template(path = "user.json")]
+struct JsonEscapeTemplate<'a> {
+    name: &'a str,
+    bio: &'a str,
+    tags: &'a [&'a str],
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn filter_escape_json() {
+    let t = JsonEscapeTemplate {
+        name: "Ferris \"the crab\"",
+        bio: "likes <rust>\\\n\tand\u{1}",
+        tags: &["a\"b"],
+    };
+    let rendered = t.render().unwrap();
+    assert_eq!(
+        rendered,
+        r#"{"name": "Ferris \"the crab\"", "bio": "likes <rust>\\\n\tand\u0001", "tags": ["a\"b"]}"#
+    );
+    let value: Value = serde_json::from_str(&rendered).unwrap();
+    assert_eq!(value["name"], "Ferris \"the crab\"");
+    assert_eq!(value["bio"], "likes <rust>\\\n\tand\u{1}");
+}
+
 #[derive(Template)]
 #[template(
     source = "{{ \"<h1 class=\\\"title\\\">Foo Bar</h1>\"|escape(\"none\") }}
@@ -74,6 +122,67 @@ fn filter_opt_escaper_html() {
     );
 }
 
+#[derive(Template)]
+#[template(
+    source = "{{ s|forceescape }} {{ s|safe|forceescape }} {{ s|forceescape(\"txt\") }}",
+    ext = "txt",
+    escape = "none"
+)]
+struct ForceEscapeNoneTemplate<'a> {
+    s: &'a str,
+}
+
+#[derive(Template)]
+#[template(source = "{{ s|safe|forceescape }} {{ s|safe }}", ext = "html")]
+struct ForceEscapeHtmlTemplate<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn filter_forceescape() {
+    let t = ForceEscapeNoneTemplate { s: "<b>" };
+    assert_eq!(t.render().unwrap(), "&#60;b&#62; &#60;b&#62; <b>");
+    let t = ForceEscapeHtmlTemplate { s: "<b>" };
+    assert_eq!(t.render().unwrap(), "&#60;b&#62; <b>");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ s|safe|upper|trim }} {{ s|upper }} {{ s|e|lower }}",
+    ext = "html"
+)]
+struct SafeUpperTemplate<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn filter_keeps_safe() {
+    let t = SafeUpperTemplate { s: " <b>&" };
+    assert_eq!(
+        t.render().unwrap(),
+        "<B>&  &#60;B&#62;&#38;  &#60;b&#62;&#38;"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "<script>let s = '{{ s|escape(\"js\") }}';</script><b title={{ s|escape(\"attr\") }}>",
+    ext = "html"
+)]
+struct ContextEscaperTemplate<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn filter_escape_context() {
+    let t = ContextEscaperTemplate { s: "</script> 'x'" };
+    assert_eq!(
+        t.render().unwrap(),
+        "<script>let s = '\\x3c\\x2fscript\\x3e \\x27x\\x27';</script>\
+         <b title=&#60;&#47;script&#62;&#32;&#39;x&#39;>",
+    );
+}
+
 #[derive(Template)]
 #[template(path = "format.html", escape = "none")]
 struct FormatTemplate<'a> {
@@ -123,9 +232,28 @@ mod filters {
         Ok(s.replace("oo", "aa"))
     }
     // for test_nested_filter_ref
-    pub fn mytrim(s: &dyn (::std::fmt::Display)) -> ::rinja::Result<String> {
+    pub fn mytrim(s: &dyn ::std::fmt::Display) -> ::rinja::Result<String> {
         Ok(s.to_string().trim().to_owned())
     }
+    pub fn apply(s: &str, f: impl Fn(&str) -> usize) -> ::rinja::Result<usize> {
+        Ok(f(s))
+    }
+}
+
+#[derive(Template)]
+#[template(source = "{{ s|apply(|s| s.len() + offset) }}", ext = "txt")]
+struct ClosureFilterTemplate<'a> {
+    s: &'a str,
+    offset: usize,
+}
+
+#[test]
+fn test_closure_filter_argument() {
+    let t = ClosureFilterTemplate {
+        s: "foo",
+        offset: 1,
+    };
+    assert_eq!(t.render().unwrap(), "4");
 }
 
 #[test]
@@ -441,3 +569,282 @@ fn test_linebreaks() {
         "<p>&#60;script&#62;<br/>alert(&#39;Hello, world!&#39;)<br/>&#60;/script&#62;</p>",
     );
 }
+
+struct User {
+    name: &'static str,
+    address: Address,
+}
+
+struct Address {
+    city: &'static str,
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for n in numbers|sort %}{{ n }},{% endfor %}
+{% for u in users|sort_by("name") %}{{ u.name }},{% endfor %}
+{% for u in users|sort_by("address.city") %}{{ u.name }},{% endfor %}
+{% for u in users|sort_by(|u| u.name.len()) %}{{ u.name }},{% endfor %}"#,
+    ext = "txt"
+)]
+struct SortFilter {
+    numbers: Vec<i32>,
+    users: Vec<User>,
+}
+
+#[test]
+fn test_sort_filter() {
+    let user = |name, city| User {
+        name,
+        address: Address { city },
+    };
+    let t = SortFilter {
+        numbers: vec![3, -1, 2, 2],
+        users: vec![
+            user("carol", "Berlin"),
+            user("alice", "Paris"),
+            user("bob", "Berlin"),
+        ],
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "-1,2,2,3,\nalice,bob,carol,\ncarol,bob,alice,\nbob,carol,alice,"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ users|map("name")|join(", ") }}
+{% for city in users|map("address.city") %}{{ city }},{% endfor %}
+{{ users[0]|attr("address")|attr("city") }} {{ users[1]|attr("address.city") }}
+{{ users|map(|u| u.name)|join(", ") }} {{ users|map(|u| u.address.city.len())|join(", ") }}"#,
+    ext = "txt"
+)]
+struct MapAttrFilter {
+    users: Vec<User>,
+}
+
+#[test]
+fn test_map_attr_filter() {
+    let user = |name, city| User {
+        name,
+        address: Address { city },
+    };
+    let t = MapAttrFilter {
+        users: vec![user("alice", "Berlin"), user("bob", "Paris")],
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "alice, bob\nBerlin,Paris,\nBerlin Paris\nalice, bob 6, 5"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for (city, users) in users|group_by("address.city") -%}
+    [{{ loop.index }}] {{ city }}: {{ users.len() }} {% for u in users %}{{ u.name }},{% endfor %}
+{% endfor %}
+{%- for (len, users) in users|group_by(|u| u.name.len()) -%}
+    {{ len }}: {% for u in users %}{{ u.name }},{% endfor %}
+{% endfor %}"#,
+    ext = "txt"
+)]
+struct GroupByFilter {
+    users: Vec<User>,
+}
+
+#[test]
+fn test_group_by_filter() {
+    let user = |name, city| User {
+        name,
+        address: Address { city },
+    };
+    let t = GroupByFilter {
+        users: vec![
+            user("carol", "Paris"),
+            user("alice", "Berlin"),
+            user("bob", "Paris"),
+        ],
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "[1] Berlin: 1 alice,\n[2] Paris: 2 carol,bob,\n3: bob,\n5: carol,alice,\n"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ nickname|default("anonymous") }} {{ bio|default("-") }} {{ bio|default("-", true) }}
+{% for tag in tags|default(fallback_tags, true) %}{{ tag }},{% endfor %}"#,
+    ext = "html"
+)]
+struct DefaultFilter<'a> {
+    nickname: Option<String>,
+    bio: &'a str,
+    tags: Vec<&'a str>,
+    fallback_tags: Vec<&'a str>,
+}
+
+#[test]
+fn test_default_filter() {
+    let t = DefaultFilter {
+        nickname: None,
+        bio: "",
+        tags: vec![],
+        fallback_tags: vec!["<none>"],
+    };
+    assert_eq!(t.render().unwrap(), "anonymous  -\n&#60;none&#62;,");
+
+    let t = DefaultFilter {
+        nickname: Some("rinja".to_owned()),
+        bio: "Templates",
+        tags: vec!["rust", "html"],
+        fallback_tags: vec!["<none>"],
+    };
+    assert_eq!(t.render().unwrap(), "rinja Templates Templates\nrust,html,");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for row in cells|batch(3) %}{{ row|join(",") }};{% endfor %}
+{% for row in cells|batch(3, "-") %}{% for c in row %}{{ c }}{% endfor %};{% endfor %}
+{% for col in cells|slice(3) %}{{ col|join(",") }};{% endfor %}
+{% for col in cells|slice(3, "-") %}{% for c in col %}{{ c }}{% endfor %};{% endfor %}"#,
+    ext = "txt"
+)]
+struct BatchSliceFilter<'a> {
+    cells: Vec<&'a str>,
+}
+
+#[test]
+fn test_batch_slice_filter() {
+    let t = BatchSliceFilter {
+        cells: vec!["a", "b", "c", "d", "e"],
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "a,b,c;d,e;\nabc;de-;\na,b;c,d;e;\nab;cd;e-;"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ chrono|date("%Y-%m-%d %H:%M") }} {{ time|date("%d.%m.%Y") }}"#,
+    ext = "html"
+)]
+struct DateFilter {
+    chrono: chrono::NaiveDateTime,
+    time: time::OffsetDateTime,
+}
+
+#[test]
+fn test_date_filter() {
+    let t = DateFilter {
+        chrono: chrono::NaiveDate::from_ymd_opt(2024, 7, 16)
+            .unwrap()
+            .and_hms_opt(8, 30, 0)
+            .unwrap(),
+        time: time::macros::datetime!(2000-01-02 03:04:05 UTC),
+    };
+    assert_eq!(t.render().unwrap(), "2024-07-16 08:30 02.01.2000");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% for (name, count) in rows %}{{ name|ljust(6, '.') }}{{ count|rjust(4) }}\n\
+              {% endfor %}",
+    ext = "txt"
+)]
+struct JustifyFilter<'a> {
+    rows: &'a [(&'a str, u32)],
+}
+
+#[test]
+fn test_justify_filter() {
+    let t = JustifyFilter {
+        rows: &[("apple", 3), ("kiwi", 120), ("banana", 7)],
+    };
+    assert_eq!(t.render().unwrap(), "apple.   3\nkiwi.. 120\nbanana   7\n");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ cents|currency("EUR") }} {{ price|currency(code, 0) }} {{ 5|currency("JPY") }}"#,
+    ext = "html"
+)]
+struct CurrencyFilter<'a> {
+    cents: i64,
+    price: f64,
+    code: &'a str,
+}
+
+#[test]
+fn test_currency_filter() {
+    let t = CurrencyFilter {
+        cents: -123_456,
+        price: 19.5,
+        code: "USD",
+    };
+    assert_eq!(t.render().unwrap(), "-€1,234.56 $20 ¥5");
+
+    let values: [(&str, String); 1] = [("locale", "fr-CA".to_owned())];
+    assert_eq!(
+        t.render_with_values(&values).unwrap(),
+        "-1\u{a0}234,56\u{a0}€ 20\u{a0}$ 5\u{a0}¥"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ system|reltime(now) }}, {{ chrono|reltime(now) }}, {{ time|reltime(clock) }}, \
+              {{ elapsed|reltime }}",
+    ext = "html"
+)]
+struct RelTimeFilter {
+    system: std::time::SystemTime,
+    chrono: chrono::DateTime<chrono::Utc>,
+    time: time::OffsetDateTime,
+    elapsed: std::time::Duration,
+    now: std::time::SystemTime,
+    clock: fn() -> std::time::SystemTime,
+}
+
+#[test]
+fn test_reltime_filter() {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    fn clock() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(946_782_245 - 3 * 24 * 60 * 60)
+    }
+
+    let now = UNIX_EPOCH + Duration::from_secs(1_721_118_600);
+    let t = RelTimeFilter {
+        system: now - Duration::from_secs(30),
+        chrono: chrono::DateTime::from_timestamp(1_721_118_600 + 2 * 60 * 60, 0).unwrap(),
+        time: time::macros::datetime!(2000-01-02 03:04:05 UTC),
+        elapsed: Duration::from_secs(5 * 60),
+        now,
+        clock,
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "30 seconds ago, in 2 hours, in 3 days, 5 minutes ago"
+    );
+}
+
+#[derive(Template)]
+#[template(source = "<article>{{ text|markdown }}</article>", ext = "html")]
+struct MarkdownFilter<'a> {
+    text: &'a str,
+}
+
+#[test]
+fn test_markdown_filter() {
+    let t = MarkdownFilter {
+        text: "Hello, *<world>*!",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "<article><p>Hello, <em>&lt;world&gt;</em>!</p>\n</article>"
+    );
+}
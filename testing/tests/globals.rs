@@ -0,0 +1,41 @@
+use rinja::Template;
+
+mod helpers {
+    pub fn now() -> &'static str {
+        "12:00"
+    }
+
+    pub fn greet(name: &str) -> String {
+        format!("Hello, {name}!")
+    }
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ now() }} {{ greet(name) }} {{ greet(\"world\")|upper }}",
+    ext = "txt",
+    config = "test_globals.toml"
+)]
+struct Globals<'a> {
+    name: &'a str,
+}
+
+#[test]
+fn test_globals() {
+    let t = Globals { name: "you" };
+    assert_eq!(t.render().unwrap(), "12:00 Hello, you! HELLO, WORLD!");
+}
+
+// Local variables take precedence over configured functions.
+#[derive(Template)]
+#[template(
+    source = "{% let now = || \"now\" %}{{ now() }}",
+    ext = "txt",
+    config = "test_globals.toml"
+)]
+struct Shadowed;
+
+#[test]
+fn test_globals_shadowed() {
+    assert_eq!(Shadowed.render().unwrap(), "now");
+}
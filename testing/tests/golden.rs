@@ -0,0 +1,21 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = "<div class=\"card\">\n  <h1>{{ title }}</h1>\n</div>\n",
+    ext = "html"
+)]
+struct Card<'a> {
+    title: &'a str,
+}
+
+#[test]
+fn test_golden_file() {
+    rinja::assert_rendered_matches_file!(Card { title: "Ferris" }, "tests/golden/card.html");
+}
+
+#[test]
+#[should_panic(expected = "-  <h1>Ferris</h1>\n+  <h1>Corro</h1>\n")]
+fn test_golden_file_mismatch() {
+    rinja::assert_rendered_matches_file!(Card { title: "Corro" }, "tests/golden/card.html");
+}
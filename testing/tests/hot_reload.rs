@@ -0,0 +1,111 @@
+#![cfg(debug_assertions)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rinja::filters::Html;
+use rinja::hot_reload::{render, Field, HotReloadField, Syntax, Whitespace};
+use rinja::observer::{set_observer, RenderObserver};
+use rinja::Template;
+
+#[derive(Template)]
+#[template(path = "hot-reload.html")]
+struct HotReloadTemplate<'a> {
+    name: &'a str,
+}
+
+#[test]
+fn test_hot_reload() {
+    let t = HotReloadTemplate { name: "<world>" };
+    assert_eq!(t.render().unwrap(), "Hello, &#60;world&#62;!");
+}
+
+// A copy of `templates/hot-reload.html` that the test can change, removed after the test.
+struct TempTemplate(PathBuf);
+
+impl TempTemplate {
+    fn new() -> Self {
+        let path =
+            std::env::temp_dir().join(format!("rinja-hot-reload-{}.html", std::process::id()));
+        let src = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("templates/hot-reload.html");
+        fs::copy(src, &path).unwrap();
+        Self(path)
+    }
+
+    // Renders the file like the code that is generated for `HotReloadTemplate`.
+    fn render(&self, name: &str) -> Option<rinja::Result<String>> {
+        let mut buf = String::new();
+        let result = render(
+            &mut buf,
+            self.0.to_str().unwrap(),
+            &Syntax::default(),
+            Whitespace::Preserve,
+            &[("name", (&&Field::new(&name, Html)).rinja_hot_reload_field())],
+        )?;
+        Some(result.map(|()| buf))
+    }
+}
+
+impl Drop for TempTemplate {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+// Collects the reasons why files could not be hot-reloaded.
+struct Fallbacks(Mutex<Vec<String>>);
+
+impl RenderObserver for Fallbacks {
+    fn rendered(&self, _: &'static str, _: Duration, _: usize) {}
+
+    fn hot_reload_fallback(&self, path: &str, reason: &str) {
+        if path.contains("rinja-hot-reload-") {
+            self.0.lock().unwrap().push(reason.to_owned());
+        }
+    }
+}
+
+static FALLBACKS: Fallbacks = Fallbacks(Mutex::new(Vec::new()));
+
+#[test]
+fn test_hot_reload_interpreter() {
+    assert!(set_observer(&FALLBACKS));
+    let tmpl = TempTemplate::new();
+    let render = |source: &str| {
+        fs::write(&tmpl.0, source).unwrap();
+        tmpl.render("<world>")
+    };
+    assert_eq!(
+        tmpl.render("<world>").unwrap().unwrap(),
+        "Hello, &#60;world&#62;!",
+    );
+
+    // Changes to the template file are visible without recompiling.
+    assert_eq!(
+        render("Goodbye, {{- name|safe }}{# comment #}.\n")
+            .unwrap()
+            .unwrap(),
+        "Goodbye,<world>.",
+    );
+
+    // Unsupported syntax falls back to the compiled template.
+    assert!(render("{% if true %}Bye, {{ name }}!{% endif %}\n").is_none());
+
+    // Syntax errors are reported.
+    assert!(render("Hello, {{ name !\n").unwrap().is_err());
+
+    // A file that cannot be read falls back to the compiled template, too.
+    fs::remove_file(&tmpl.0).unwrap();
+    assert!(tmpl.render("<world>").is_none());
+
+    // The observer is told about both fallbacks.
+    assert_eq!(
+        *FALLBACKS.0.lock().unwrap(),
+        [
+            "uses syntax that cannot be interpreted at runtime",
+            "could not be read",
+        ],
+    );
+}
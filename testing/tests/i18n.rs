@@ -0,0 +1,118 @@
+use std::fmt;
+
+use fluent_bundle::{FluentBundle, FluentResource};
+use rinja::i18n::Translate;
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"{% trans "title" %}Welcome{% endtrans %}
+{%- trans "greeting" name = user, count = 2 -%}
+    Hello, {{ name }}! You have {{ count }} messages.
+{%- endtrans %}
+{% trans "title" %}Welcome{% endtrans %}"#,
+    ext = "html"
+)]
+struct Greeting<'a> {
+    user: &'a str,
+    german: bool,
+}
+
+impl Translate for Greeting<'_> {
+    fn translate(&self, id: &str, args: &[(&str, &dyn fmt::Display)]) -> Option<String> {
+        if !self.german {
+            return None;
+        }
+        match (id, args) {
+            ("title", []) => Some("Willkommen <3".to_owned()),
+            ("greeting", [("name", name), ("count", count)]) => {
+                Some(format!("Hallo, {name}! Du hast {count} Nachrichten."))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_trans() {
+    let t = Greeting {
+        user: "<Ferris>",
+        german: false,
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "WelcomeHello, &#60;Ferris&#62;! You have 2 messages.\nWelcome"
+    );
+
+    let t = Greeting {
+        user: "<Ferris>",
+        german: true,
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "Willkommen &#60;3Hallo, &#60;Ferris&#62;! Du hast 2 Nachrichten.\nWillkommen &#60;3"
+    );
+}
+
+#[test]
+fn test_trans_message_ids() {
+    assert_eq!(Greeting::MESSAGE_IDS, &["greeting", "title"]);
+    assert!(<&Greeting<'_> as Template>::MESSAGE_IDS.contains(&"title"));
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% trans "emails" count = emails.len() %}{{ count }} emails{% endtrans %}"#,
+    ext = "txt"
+)]
+struct Inbox {
+    emails: Vec<&'static str>,
+    bundle: FluentBundle<FluentResource>,
+}
+
+impl Translate for Inbox {
+    fn translate(&self, id: &str, args: &[(&str, &dyn fmt::Display)]) -> Option<String> {
+        self.bundle.translate(id, args)
+    }
+}
+
+#[test]
+fn test_trans_fluent() {
+    let ftl = "\
+emails = { $count ->
+    [one] One new email
+   *[other] { $count } new emails
+}
+";
+    let new_inbox = |emails| {
+        let mut bundle = FluentBundle::new(vec!["en-US".parse().unwrap()]);
+        bundle.set_use_isolating(false);
+        bundle
+            .add_resource(FluentResource::try_new(ftl.to_owned()).unwrap())
+            .unwrap();
+        Inbox { emails, bundle }
+    };
+
+    assert_eq!(new_inbox(vec!["a"]).render().unwrap(), "One new email");
+    assert_eq!(new_inbox(vec!["a", "b"]).render().unwrap(), "2 new emails");
+}
+
+#[derive(Template)]
+#[template(source = r#"{% trans "missing" %}fallback{% endtrans %}"#, ext = "txt")]
+struct Fallback {
+    bundle: FluentBundle<FluentResource>,
+}
+
+impl Translate for Fallback {
+    fn translate(&self, id: &str, args: &[(&str, &dyn fmt::Display)]) -> Option<String> {
+        self.bundle.translate(id, args)
+    }
+}
+
+#[test]
+fn test_trans_fluent_fallback() {
+    let t = Fallback {
+        bundle: FluentBundle::default(),
+    };
+    assert_eq!(t.render().unwrap(), "fallback");
+}
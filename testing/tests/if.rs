@@ -24,3 +24,88 @@ fn test_if() {
     assert_eq!(If { s: "c" }.render().unwrap(), "c");
     assert_eq!(If { s: "d" }.render().unwrap(), "else");
 }
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ "yes" if cond else "no" }} {{ a if a > b else b }} {{ 1 if !cond else 2 if a == 0 else 3 }}
+{%- for s in ["<b>", "<i>"] if cond %} {{ s if loop.first else "-" }}{% endfor %}"#,
+    ext = "html"
+)]
+struct IfElseExpr {
+    cond: bool,
+    a: u32,
+    b: u32,
+}
+
+#[test]
+fn test_if_else_expr() {
+    let t = IfElseExpr {
+        cond: true,
+        a: 1,
+        b: 2,
+    };
+    assert_eq!(t.render().unwrap(), "yes 2 3 &#60;b&#62; -");
+    let t = IfElseExpr {
+        cond: false,
+        a: 3,
+        b: 2,
+    };
+    assert_eq!(t.render().unwrap(), "no 3 1");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{{ name if active else "anon" }} {{ "<i>"|safe if active else name }}"#,
+    ext = "html"
+)]
+struct IfElseMixedTypes {
+    name: String,
+    active: bool,
+}
+
+#[test]
+fn test_if_else_expr_mixed_types() {
+    let t = IfElseMixedTypes {
+        name: "<b>".to_owned(),
+        active: true,
+    };
+    assert_eq!(t.render().unwrap(), "&#60;b&#62; <i>");
+    let t = IfElseMixedTypes {
+        name: "<b>".to_owned(),
+        active: false,
+    };
+    assert_eq!(t.render().unwrap(), "anon &#60;b&#62;");
+}
+
+// The branches of `cfg()` conditions are only compiled if the predicate holds, so they can use
+// fields that only exist with a feature.
+#[derive(Template)]
+#[template(
+    source = r#"
+{%- if cfg(not(feature = "serde_json")) -%} {{ no_such_field }}
+{%- elif !cond %}off
+{%- elif cfg(test) -%} test {{ premium }}
+{%- else -%} {{ no_such_field }}
+{%- endif %}|{{ cfg(test) }} {{ cfg(not(test)) || cond }}
+{%- if cfg(all(test, not(feature = "serde_json"))) %} {{ no_such_field }}{% endif %}"#,
+    ext = "txt"
+)]
+struct IfCfg {
+    cond: bool,
+    #[cfg(test)]
+    premium: u32,
+}
+
+#[test]
+fn test_if_cfg() {
+    let t = IfCfg {
+        cond: true,
+        premium: 3,
+    };
+    assert_eq!(t.render().unwrap(), "test 3|true true");
+    let t = IfCfg {
+        cond: false,
+        premium: 3,
+    };
+    assert_eq!(t.render().unwrap(), "off|true false");
+}
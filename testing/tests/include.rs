@@ -53,3 +53,74 @@ fn test_include_macro() {
 
     assert_eq!(template.render().unwrap(), "Hello, Alice!\nHowdy, Bob!");
 }
+
+#[derive(Template)]
+#[template(
+    source = r#"{% for product in products %}{% include "include-card.html" with item = product %}{% endfor %}"#,
+    ext = "txt"
+)]
+struct IncludeWithTemplate<'a> {
+    title: &'a str,
+    products: &'a [&'a str],
+}
+
+#[test]
+fn test_include_with() {
+    let template = IncludeWithTemplate {
+        title: "Product",
+        products: &["apple", "pear"],
+    };
+    assert_eq!(
+        template.render().unwrap(),
+        "[Product: apple][Product: pear]"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% include "include-card.html" with title = "Sale", item = products[0] only %}
+{%- include "include-card.html" with title = item, item = title only %}"#,
+    ext = "txt"
+)]
+struct IncludeOnlyTemplate<'a> {
+    title: &'a str,
+    item: &'a str,
+    products: &'a [&'a str],
+}
+
+#[test]
+fn test_include_only() {
+    let template = IncludeOnlyTemplate {
+        title: "Product",
+        item: "apple",
+        products: &["pear"],
+    };
+    assert_eq!(template.render().unwrap(), "[Sale: pear][apple: Product]");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% include partial with item = "x" %}"#,
+    ext = "html",
+    includes = ["include-card.html", "include-bold.html"]
+)]
+struct IncludeDynamicTemplate<'a> {
+    title: &'a str,
+    partial: &'a str,
+}
+
+#[test]
+fn test_include_dynamic() {
+    let mut template = IncludeDynamicTemplate {
+        title: "Product",
+        partial: "include-card.html",
+    };
+    assert_eq!(template.render().unwrap(), "[Product: x]");
+
+    template.partial = "include-bold.html";
+    assert_eq!(template.render().unwrap(), "<b>x</b>");
+
+    template.partial = "included.html";
+    let err = template.render().unwrap_err();
+    assert!(matches!(err, rinja::Error::UnknownInclude(ref path) if path == "included.html"));
+}
@@ -354,3 +354,71 @@ fn test_named_end() {
     let n = NamedBlocks { title: "title" };
     assert_eq!(n.render().unwrap(), "title\n\ntadam\nCopyright 2017");
 }
+
+#[derive(Template)]
+#[template(
+    source = r#"{% extends "base.html" %}{% block foo %}<{{ super() }}>{% endblock %}"#,
+    ext = "html"
+)]
+struct SuperExprTemplate<'a> {
+    title: &'a str,
+}
+
+#[test]
+fn test_super_expr() {
+    let t = SuperExprTemplate { title: "Bar" };
+    assert_eq!(t.render().unwrap(), "Bar\n\n<Foo>\nCopyright 2017");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% extends layout %}{% block content %}<p>{{ title }}</p>{% endblock %}"#,
+    ext = "html",
+    extends = ["layout-full.html", "layout-modal.html"]
+)]
+struct DynamicExtendsTemplate<'a> {
+    layout: &'a str,
+    title: &'a str,
+}
+
+#[test]
+fn test_dynamic_extends() {
+    let mut t = DynamicExtendsTemplate {
+        layout: "layout-full.html",
+        title: "Hello",
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "<html><title>Hello</title><p>Hello</p></html>"
+    );
+
+    t.layout = "layout-modal.html";
+    assert_eq!(
+        t.render().unwrap(),
+        r#"<div class="modal"><p>Hello</p></div>"#
+    );
+
+    t.layout = "base.html";
+    assert!(matches!(
+        t.render().unwrap_err(),
+        rinja::Error::UnknownExtends(ref path) if path == "base.html"
+    ));
+}
+
+// Required blocks can be defined by any template that extends the template requiring them.
+#[derive(Template)]
+#[template(
+    source = r#"{% extends "required-layout.html" %}
+{%- block title %}{{ title }}{% endblock -%}
+{%- block text %}Hello{% endblock %}"#,
+    ext = "html"
+)]
+struct RequiredBlocks<'a> {
+    title: &'a str,
+}
+
+#[test]
+fn test_required_blocks() {
+    let t = RequiredBlocks { title: "Title" };
+    assert_eq!(t.render().unwrap(), "<h1>Title</h1><p>Hello</p>");
+}
@@ -0,0 +1,40 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(path = "inline/footer.html", config = "test_templates.toml")]
+struct Footer {
+    year: u32,
+}
+
+#[test]
+fn test_inline_template() {
+    assert_eq!(
+        Footer { year: 2024 }.render().unwrap(),
+        "<footer>2024</footer>"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% extends "inline/base.html" %}
+{%- import "inline/macros.html" as m -%}
+{%- block title %}{% call m::greet(name) %}{% endblock -%}"#,
+    ext = "html",
+    config = "test_templates.toml"
+)]
+struct Page<'a> {
+    name: &'a str,
+    year: u32,
+}
+
+#[test]
+fn test_inline_template_extends() {
+    let t = Page {
+        name: "<you>",
+        year: 2024,
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "<h1>Hello, &#60;you&#62;!</h1><footer>2024</footer>"
+    );
+}
@@ -373,6 +373,26 @@ fn test_loop_break_continue() {
     assert_eq!(t.render().unwrap(), "x1yx2yx3yx11x4yx5y");
 }
 
+#[derive(Template)]
+#[template(
+    source = "{% for v in values %}x{{v}}{% continue if v % 2 == 0 %}y{% break if *v >= 3 %}z{% endfor %}",
+    ext = "txt"
+)]
+struct BreakContinueIf<'a> {
+    values: &'a [i32],
+}
+
+#[test]
+fn test_loop_break_continue_if() {
+    let t = BreakContinueIf {
+        values: &[1, 2, 3, 4, 5],
+    };
+    assert_eq!(t.render().unwrap(), "x1yzx2x3y");
+
+    let t = BreakContinueIf { values: &[2, 4] };
+    assert_eq!(t.render().unwrap(), "x2x4");
+}
+
 #[derive(Template)]
 #[template(
     source = r#"{% for v in values %}{{loop.cycle(["r", "g", "b"])}}{{v}},{% endfor %}"#,
@@ -409,6 +429,21 @@ fn test_for_cycle_dynamic() {
     assert_eq!(t.render().unwrap(), "a1,b2,c3,d4,a5,b6,c7,d8,a9,");
 }
 
+#[derive(Template)]
+#[template(
+    source = r#"{% for v in values %}{{ loop.cycle("odd", "even") }}:{{ v }},{% endfor %}"#,
+    ext = "txt"
+)]
+struct ForCycleArgs<'a> {
+    values: &'a [u8],
+}
+
+#[test]
+fn test_for_cycle_args() {
+    let t = ForCycleArgs { values: &[1, 2, 3] };
+    assert_eq!(t.render().unwrap(), "odd:1,even:2,odd:3,");
+}
+
 #[test]
 fn test_for_cycle_empty() {
     let t = ForCycleDynamic {
@@ -435,3 +470,32 @@ fn test_for_in_if() {
     let t = ForInIf { limit: 1 };
     assert_eq!(t.render().unwrap(), ":(");
 }
+
+#[derive(Template)]
+#[template(
+    source = "
+{%- for i in 1..=n %}{{ i }}{{ loop.index }}{% if !loop.last %},{% endif %}{% endfor %}
+{% for i in (0..n).step_by(2) %}{{ i }}{{ loop.index0 }}{% if loop.last %}!{% endif %}{% endfor %}
+{% for i in (1..=n).rev() %}{% if loop.first %}^{% endif %}{{ i }}{% endfor %}
+{% for (i, item) in items.iter().enumerate().skip(1) %}{{ i }}{{ item }}{% endfor %}
+{% let end = n %}{% for i in 2..=end %}{{ i }}{% endfor %}
+{% for item in items %}[{% for i in 0..item %}{{ i }}{% endfor %}]{% endfor -%}
+",
+    ext = "txt"
+)]
+struct ForRangeAdapters {
+    n: u32,
+    items: Vec<u32>,
+}
+
+#[test]
+fn test_for_range_adapters() {
+    let t = ForRangeAdapters {
+        n: 3,
+        items: vec![1, 2],
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "11,22,33\n0021!\n^321\n12\n23\n[0][01]"
+    );
+}
@@ -170,3 +170,55 @@ struct TrailingComma;
 fn test_trailing_comma() {
     assert_eq!(TrailingComma.render().unwrap(), "hihihihihi");
 }
+
+#[derive(Template)]
+#[template(
+    source = "{%- macro badge(name) -%}<b>{{ name }}</b>{%- endmacro -%}
+{{ badge(user) }} {{ badge(user)|upper }} {{ badge(\"x\")|safe }}
+{%- let html = badge(user) %} {{ html }}",
+    ext = "html"
+)]
+struct MacroExprTemplate<'a> {
+    user: &'a str,
+}
+
+#[test]
+fn test_macro_expr() {
+    let t = MacroExprTemplate { user: "a&b" };
+    assert_eq!(
+        t.render().unwrap(),
+        "<b>a&#38;b</b> <B>A&#38;B</B> <b>x</b> <b>a&#38;b</b>",
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "{%- import \"macro.html\" as scope -%}[{{ scope::thrice(s) }}]",
+    ext = "html"
+)]
+struct ImportedMacroExprTemplate<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_imported_macro_expr() {
+    let t = ImportedMacroExprTemplate { s: "foo" };
+    assert_eq!(t.render().unwrap(), "[foo foo foo]");
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{%- from "macro.html" import thrice, twice as two -%}
+{%- from "nested-macro.html" import parent -%}
+{% call thrice(s) %}|{{ two(s) }}|{% call parent() %}"#,
+    ext = "txt"
+)]
+struct FromImportTemplate<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_from_import() {
+    let t = FromImportTemplate { s: "foo" };
+    assert_eq!(t.render().unwrap(), "foo foo foo|foo foo|foo");
+}
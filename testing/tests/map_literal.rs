@@ -0,0 +1,36 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"
+{%- let attrs = {"class": "btn", "id": id} -%}
+<button
+{%- for (name, value) in attrs %} {{ name }}="{{ value }}"{% endfor -%}
+>{{ attrs["class"] }} {{ attrs.len() }}</button>"#,
+    ext = "html"
+)]
+struct Attrs<'a> {
+    id: &'a str,
+}
+
+#[test]
+fn test_map_literal() {
+    assert_eq!(
+        Attrs { id: "submit" }.render().unwrap(),
+        r#"<button class="btn" id="submit">btn 2</button>"#,
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"
+{%- for (k, v) in {1: 'a', 2: 'b',} %}{{ k }}{{ v }}{% endfor %}
+{{- {"x": 1}["x"] }} {{ {"x": 1}.get("y").is_none() }}"#,
+    ext = "txt"
+)]
+struct Inline;
+
+#[test]
+fn test_map_literal_inline() {
+    assert_eq!(Inline.render().unwrap(), "1a2b1 true");
+}
@@ -224,3 +224,50 @@ fn test_match_enum_or() {
     };
     assert_eq!(template.render().unwrap(), "The card is red\n");
 }
+
+#[derive(Template)]
+#[template(
+    source = "{% if matches!(suit, Suit::Clubs | Suit::Spades) %}black{% else %}red{% endif %}",
+    ext = "txt"
+)]
+struct MatchesMacroTemplate {
+    suit: Suit,
+}
+
+#[test]
+fn test_matches_macro() {
+    let template = MatchesMacroTemplate { suit: Suit::Clubs };
+    assert_eq!(template.render().unwrap(), "black");
+    let template = MatchesMacroTemplate { suit: Suit::Spades };
+    assert_eq!(template.render().unwrap(), "black");
+    let template = MatchesMacroTemplate { suit: Suit::Hearts };
+    assert_eq!(template.render().unwrap(), "red");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% let limit = 2 %}\
+        {{ n }}: {{ matches!(item, Some(n) if *n > limit) }} {{ matches!(item, Some(1) or None) }}",
+    ext = "txt"
+)]
+struct MatchesMacroGuardTemplate<'a> {
+    item: &'a Option<u32>,
+    // Shadowed in the guard by the value bound by the pattern.
+    n: u32,
+}
+
+#[test]
+fn test_matches_macro_guard() {
+    let template = MatchesMacroGuardTemplate {
+        item: &Some(3),
+        n: 0,
+    };
+    assert_eq!(template.render().unwrap(), "0: true false");
+    let template = MatchesMacroGuardTemplate {
+        item: &Some(1),
+        n: 5,
+    };
+    assert_eq!(template.render().unwrap(), "5: false true");
+    let template = MatchesMacroGuardTemplate { item: &None, n: 5 };
+    assert_eq!(template.render().unwrap(), "5: false true");
+}
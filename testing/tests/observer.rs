@@ -0,0 +1,65 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rinja::observer::{set_observer, RenderObserver};
+use rinja::{DynTemplate, Error, Template};
+
+// Records the templates that were rendered, with the size of their output, or `None` if the
+// rendering failed.
+#[derive(Default)]
+struct Renderings(Mutex<Vec<(&'static str, Option<usize>)>>);
+
+impl RenderObserver for Renderings {
+    fn rendered(&self, template: &'static str, _: Duration, size: usize) {
+        self.0.lock().unwrap().push((template, Some(size)));
+    }
+
+    fn failed(&self, template: &'static str, _: Duration, _: &Error) {
+        self.0.lock().unwrap().push((template, None));
+    }
+}
+
+#[derive(Template)]
+#[template(source = "Hello, {{ name }}!", ext = "txt")]
+struct Hello<'a> {
+    name: &'a str,
+}
+
+#[derive(Template)]
+#[template(source = "{{ self.fail()? }}", ext = "txt")]
+struct Fail;
+
+impl Fail {
+    fn fail(&self) -> Result<&str, Error> {
+        Err(Error::Fmt)
+    }
+}
+
+#[test]
+fn test_observer() {
+    let renderings: &'static Renderings = Box::leak(Box::default());
+    assert!(set_observer(renderings));
+    assert!(!set_observer(Renderings::default()));
+
+    let hello = Hello { name: "world" };
+    assert_eq!(hello.render().unwrap(), "Hello, world!");
+    assert_eq!(rinja::buffer_pool::render(&hello).unwrap().as_str(), "Hello, world!");
+    let dyn_hello: &dyn DynTemplate = &hello;
+    assert_eq!(
+        dyn_hello.dyn_render_with_capacity(64).unwrap(),
+        "Hello, world!"
+    );
+    // Renderings into a writer are not observed.
+    assert_eq!(hello.to_string(), "Hello, world!");
+    assert!(Fail.render().is_err());
+
+    assert_eq!(
+        *renderings.0.lock().unwrap(),
+        [
+            ("observer::Hello<'_>", Some(13)),
+            ("observer::Hello<'_>", Some(13)),
+            ("observer::Hello<'_>", Some(13)),
+            ("observer::Fail", None),
+        ],
+    );
+}
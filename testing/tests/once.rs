@@ -0,0 +1,50 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"
+{%- macro button(label) -%}
+    {%- once %}<style>.btn {}</style>{% endonce -%}
+    <button class="btn">{{ label }}</button>
+{%- endmacro -%}
+{%- call button("a") -%}
+{%- call button("b") -%}"#,
+    ext = "html"
+)]
+struct Buttons;
+
+#[test]
+fn test_once() {
+    assert_eq!(
+        Buttons.render().unwrap(),
+        r#"<style>.btn {}</style><button class="btn">a</button><button class="btn">b</button>"#,
+    );
+    // The content is only rendered once per process.
+    assert_eq!(
+        Buttons.render().unwrap(),
+        r#"<button class="btn">a</button><button class="btn">b</button>"#,
+    );
+}
+
+// Templates of different modules, which have the same name and source.
+macro_rules! style {
+    ($module:ident) => {
+        mod $module {
+            use rinja::Template;
+
+            #[derive(Template)]
+            #[template(source = "{% once %}<style></style>{% endonce %}<p>", ext = "html")]
+            pub struct Style;
+        }
+    };
+}
+
+style!(style_a);
+style!(style_b);
+
+#[test]
+fn test_once_modules() {
+    assert_eq!(style_a::Style.render().unwrap(), "<style></style><p>");
+    assert_eq!(style_b::Style.render().unwrap(), "<style></style><p>");
+    assert_eq!(style_a::Style.render().unwrap(), "<p>");
+}
@@ -1,4 +1,4 @@
-use rinja::Template;
+use rinja::{PowError, Template};
 
 #[derive(Template)]
 #[template(path = "compare.html")]
@@ -61,3 +61,127 @@ fn test_short_circuit() {
     let t = ShortCircuitTemplate {};
     assert_eq!(t.render().unwrap(), "truetrue");
 }
+
+#[derive(Template)]
+#[template(
+    source = "{{ 1_000_000 }} {{ 0xFF }} {{ 0b1010 }} {{ 1.5e3 }} {{ half(3f32) }} \
+        {{ u64::MAX - 1_u64 }} {{ 255u8.checked_add(1).is_none() }}\
+        {% match byte %}{% when 0x7F %} del{% else %} other{% endmatch %}",
+    ext = "txt"
+)]
+struct NumberLiteralsTemplate {
+    byte: u8,
+}
+
+impl NumberLiteralsTemplate {
+    fn half(&self, value: f32) -> f32 {
+        value / 2.0
+    }
+}
+
+#[test]
+fn test_number_literals() {
+    let t = NumberLiteralsTemplate { byte: 127 };
+    assert_eq!(
+        t.render().unwrap(),
+        "1000000 255 10 1500 1.5 18446744073709551614 true del"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"
+{{- role in ["admin", "owner"] }} {{ role not in ["admin", "owner"] }}
+{{- " " }}{{ "ev" in name }} {{ 'x' in name }} {{ name in names }}
+{{- " " }}{{ role in scores }} {{ name not in scores }} {{ 3 in numbers }}
+{%- for n in numbers %} {{ n in [1, 2] }}{% endfor %}
+{{- " " }}{{ 1 + 1 in numbers && true }} {{ "a" in {"a": 1} }}"#,
+    ext = "txt"
+)]
+struct ContainsTemplate<'a> {
+    role: &'a str,
+    name: String,
+    names: Vec<String>,
+    scores: std::collections::HashMap<String, u32>,
+    numbers: std::collections::BTreeSet<u32>,
+}
+
+#[test]
+fn test_contains() {
+    let t = ContainsTemplate {
+        role: "owner",
+        name: "Steven".to_owned(),
+        names: vec!["Ferris".to_owned(), "Steven".to_owned()],
+        scores: [("owner".to_owned(), 1)].into_iter().collect(),
+        numbers: [1, 3].into_iter().collect(),
+    };
+    assert_eq!(
+        t.render().unwrap(),
+        "true false true false true true true true true false false true",
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ 2 ** 10 }} {{ 2 ** 3 ** 2 }} {{ 3 * 2 ** 2 }} {{ -2 ** 2 }} {{ base ** exp }} \
+              {{ price ** 2 }} {{ 4.0_f64 ** 0.5 }} {{ 2.0_f64 ** -1 }} {{ base.abs_diff(10) }}",
+    ext = "txt"
+)]
+struct PowerTemplate {
+    base: u64,
+    exp: u32,
+    price: f64,
+}
+
+#[test]
+fn test_power() {
+    let t = PowerTemplate {
+        base: 3,
+        exp: 4,
+        price: 1.5,
+    };
+    assert_eq!(t.render().unwrap(), "1024 512 12 4 81 2.25 2 0.5 7");
+}
+
+#[derive(Template)]
+#[template(source = "{{ base ** exp }}", ext = "txt")]
+struct PowerErrorTemplate {
+    base: i32,
+    exp: i64,
+}
+
+#[test]
+fn test_power_error() {
+    for (exp, expected) in [
+        (-1, PowError::NegativeExponent),
+        (31, PowError::Overflow),
+        (i64::from(u32::MAX) + 1, PowError::Overflow),
+    ] {
+        let t = PowerErrorTemplate { base: 2, exp };
+        match t.render() {
+            Err(rinja::Error::Custom(err)) => {
+                assert_eq!(err.downcast_ref::<PowError>(), Some(&expected), "{exp}");
+            }
+            result => panic!("unexpected result for {exp}: {result:?}"),
+        }
+    }
+    let t = PowerErrorTemplate { base: 2, exp: 30 };
+    assert_eq!(t.render().unwrap(), "1073741824");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ 60 * 60 * 24 }} {{ (7 - 10) / 2 }} {{ -7 % 3 }} {{ 0.1 + 0.2 }} {{ 1.0 / 0.0 }} \
+              {{ \"a<b\" }} {{ \"a\" < \"b\" }} {{ 'x' == 'y' || 3 >= 3 }} {{ 2_u8 ** 7 }} \
+              {{ 100_000 * 100_000_i64 }} {{ 1_u8 + 2 }}",
+    ext = "html"
+)]
+struct ConstExprTemplate;
+
+#[test]
+fn test_const_expr() {
+    assert_eq!(
+        ConstExprTemplate.render().unwrap(),
+        "86400 -1 -1 0.30000000000000004 inf a&#60;b true true 128 10000000000 3",
+    );
+}
@@ -0,0 +1,213 @@
+use std::cell::Cell;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use rinja::Template;
+
+#[derive(Template)]
+#[template(source = "Hello, {{ name }}!", ext = "html")]
+struct HelloTemplate<'a> {
+    name: &'a str,
+}
+
+#[tokio::test]
+async fn test_render_into_async() {
+    let t = HelloTemplate { name: "<world>" };
+    let mut buf = Vec::new();
+    t.render_into_async(&mut buf).await.unwrap();
+    assert_eq!(buf, b"Hello, &#60;world&#62;!");
+}
+
+#[tokio::test]
+async fn test_render_into_async_by_ref() {
+    let t = &HelloTemplate { name: "rinja" };
+    let mut buf = Vec::new();
+    t.render_into_async(&mut buf).await.unwrap();
+    assert_eq!(buf, b"Hello, rinja!");
+}
+
+#[derive(Template)]
+#[template(source = "{{ value? }}", ext = "txt")]
+struct FailingTemplate {
+    value: Result<u32, std::fmt::Error>,
+}
+
+#[tokio::test]
+async fn test_render_into_async_error() {
+    let t = FailingTemplate {
+        value: Err(std::fmt::Error),
+    };
+    let mut buf = Vec::new();
+    assert!(t.render_into_async(&mut buf).await.is_err());
+    assert!(buf.is_empty());
+}
+
+#[derive(Template)]
+#[template(
+    source = "<ul>{% for item in items %}<li>{{ item }}</li>{% endfor %}</ul>\
+              {% let n = items.len() %}{% let s %}{{ n }} items{% endlet %}\
+              {% macro m(x) %}[{{ x }}]{% endmacro %}{{ s }}{% call m(n) %}{{ m(n) }}\
+              {% filter upper %}{{ items.len() }} x{% endfilter %}",
+    ext = "html",
+    render_async = true
+)]
+struct StreamTemplate<'a> {
+    items: &'a [&'a str],
+}
+
+// Records every write, to check that the output is written piece by piece.
+#[derive(Default)]
+struct Chunks(Vec<String>);
+
+impl tokio::io::AsyncWrite for Chunks {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.0.push(String::from_utf8(buf.to_vec()).unwrap());
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn assert_send<T: Send>(value: T) -> T {
+    value
+}
+
+#[tokio::test]
+async fn test_render_async_incremental() {
+    let t = StreamTemplate {
+        items: &["a", "<b>"],
+    };
+    let mut chunks = Chunks::default();
+    assert_send(t.render_into_async(&mut chunks)).await.unwrap();
+    assert_eq!(chunks.0.concat(), t.render().unwrap());
+    assert_eq!(
+        chunks.0,
+        [
+            "<ul>",
+            "<li>a</li>",
+            "<li>&#60;b&#62;</li>",
+            "</ul>",
+            "2 items",
+            "[2]",
+            "[2]",
+            "2 X",
+        ],
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "start {% if true %}{{ value? }}{% endif %} end",
+    ext = "txt",
+    render_async = true
+)]
+struct FailingStreamTemplate {
+    value: Result<u32, std::fmt::Error>,
+}
+
+#[tokio::test]
+async fn test_render_async_incremental_error() {
+    let t = FailingStreamTemplate {
+        value: Err(std::fmt::Error),
+    };
+    let mut buf = Vec::new();
+    assert!(t.render_into_async(&mut buf).await.is_err());
+    assert_eq!(buf, b"start ");
+}
+
+// The async body is generated separately, so neither user code nor static text that looks like
+// the generated code is changed.
+#[derive(Template)]
+#[template(
+    source = "{{ span.entered() }} span.entered(); {{ \"x.entered()\" }}",
+    ext = "txt",
+    render_async = true
+)]
+struct EnteredTemplate {
+    span: Span,
+}
+
+struct Span;
+
+impl Span {
+    fn entered(&self) -> &'static str {
+        "entered"
+    }
+}
+
+#[tokio::test]
+async fn test_render_async_entered() {
+    let t = EnteredTemplate { span: Span };
+    let expected = "entered span.entered(); x.entered()";
+    assert_eq!(t.render().unwrap(), expected);
+    let mut buf = Vec::new();
+    t.render_into_async(&mut buf).await.unwrap();
+    assert_eq!(buf, expected.as_bytes());
+}
+
+// Neither the template nor its future have to be `Send` or `Sync`.
+#[derive(Template)]
+#[template(source = "{{ counter.get() }}", ext = "txt", render_async = true)]
+struct CellTemplate {
+    counter: Cell<u32>,
+}
+
+#[derive(Template)]
+#[template(source = "{{ counter.get() }}", ext = "txt")]
+struct BufferedCellTemplate {
+    counter: Cell<u32>,
+}
+
+#[tokio::test]
+async fn test_render_into_async_not_sync() {
+    let mut buf = Vec::new();
+    let t = CellTemplate {
+        counter: Cell::new(1),
+    };
+    t.render_into_async(&mut buf).await.unwrap();
+    let t = BufferedCellTemplate {
+        counter: Cell::new(2),
+    };
+    assert_send(t.render_into_async(&mut buf)).await.unwrap();
+    assert_eq!(buf, b"12");
+}
+
+struct FailingWriter;
+
+impl tokio::io::AsyncWrite for FailingWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        _: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(Err(std::io::ErrorKind::BrokenPipe.into()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn test_render_into_async_io_error() {
+    let t = StreamTemplate { items: &["a"] };
+    let err = t.render_into_async(&mut FailingWriter).await.unwrap_err();
+    assert!(matches!(err, rinja::Error::Io(err) if err.kind() == std::io::ErrorKind::BrokenPipe));
+    let t = HelloTemplate { name: "rinja" };
+    let err = t.render_into_async(&mut FailingWriter).await.unwrap_err();
+    assert!(matches!(err, rinja::Error::Io(err) if err.kind() == std::io::ErrorKind::BrokenPipe));
+}
@@ -0,0 +1,34 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(source = "Hello, {{ name }}!", ext = "html")]
+struct HelloTemplate<'a> {
+    name: &'a str,
+}
+
+#[test]
+fn test_render_bytes() {
+    let t = HelloTemplate { name: "<world>" };
+    let bytes = t.render_bytes().unwrap();
+    assert_eq!(bytes, "Hello, &#60;world&#62;!");
+}
+
+#[test]
+fn test_render_bytes_by_ref() {
+    let t = &HelloTemplate { name: "rinja" };
+    assert_eq!(t.render_bytes().unwrap(), "Hello, rinja!");
+}
+
+#[derive(Template)]
+#[template(source = "{{ value? }}", ext = "txt")]
+struct FailingTemplate {
+    value: Result<u32, std::fmt::Error>,
+}
+
+#[test]
+fn test_render_bytes_error() {
+    let t = FailingTemplate {
+        value: Err(std::fmt::Error),
+    };
+    assert!(t.render_bytes().is_err());
+}
@@ -0,0 +1,70 @@
+use std::borrow::Cow;
+
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = "<ul>{% for item in items %}<li>{{ item }}</li>{% endfor %}</ul>",
+    ext = "html"
+)]
+struct ListTemplate<'a> {
+    items: &'a [&'a str],
+}
+
+#[test]
+fn test_render_chunks() {
+    let t = ListTemplate {
+        items: &["a", "<b>"],
+    };
+    let chunks = t.render_chunks().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(chunks.concat(), t.render().unwrap());
+    assert_eq!(
+        chunks,
+        [
+            "<ul>",
+            "<li>",
+            "a",
+            "</li>",
+            "<li>",
+            "&#60;b&#62;",
+            "</li>",
+            "</ul>"
+        ],
+    );
+    assert!(matches!(chunks[0], Cow::Borrowed(_)));
+    assert!(matches!(chunks[2], Cow::Owned(_)));
+}
+
+#[test]
+fn test_render_chunks_by_ref() {
+    let t = &ListTemplate { items: &[] };
+    let chunks = t.render_chunks().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(chunks, ["<ul>", "</ul>"]);
+}
+
+#[derive(Template)]
+#[template(source = "start {{ value? }} end", ext = "txt")]
+struct FailingTemplate {
+    value: Result<u32, std::fmt::Error>,
+}
+
+#[test]
+fn test_render_chunks_error() {
+    let t = FailingTemplate {
+        value: Err(std::fmt::Error),
+    };
+    let mut chunks = t.render_chunks();
+    assert_eq!(chunks.next().unwrap().unwrap(), "start ");
+    assert!(chunks.next().unwrap().is_err());
+    assert!(chunks.next().is_none());
+}
+
+#[test]
+fn test_render_chunks_outlive_template() {
+    let chunks: Vec<Cow<'static, str>> = {
+        let items = vec!["a"];
+        let t = ListTemplate { items: &items };
+        t.render_chunks().collect::<Result<_, _>>().unwrap()
+    };
+    assert_eq!(chunks.concat(), "<ul><li>a</li></ul>");
+}
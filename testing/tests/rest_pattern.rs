@@ -76,3 +76,114 @@ fn c() {
 
     assert_eq!(Tmpl { abc: (1, 2, 3) }.to_string(), "-3-");
 }
+
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+struct Item {
+    name: &'static str,
+    price: u32,
+}
+
+#[test]
+fn struct_if_let() {
+    #[derive(Template)]
+    #[template(
+        source = "{% if let Point { x, .. } = p %}-{{x}}-{% endif %}{{x}}",
+        ext = "txt"
+    )]
+    struct Tmpl {
+        p: Point,
+        x: u32,
+    }
+
+    assert_eq!(
+        Tmpl {
+            p: Point { x: 1, y: 2 },
+            x: 3
+        }
+        .to_string(),
+        "-1-3"
+    );
+}
+
+#[test]
+fn struct_let() {
+    #[derive(Template)]
+    #[template(
+        source = "{% let Point { y, .. } = p %}{% let (_, Point { x: px, .. }) = t %}-{{y}}{{px}}-",
+        ext = "txt"
+    )]
+    struct Tmpl {
+        p: Point,
+        t: (u32, Point),
+    }
+
+    assert_eq!(
+        Tmpl {
+            p: Point { x: 1, y: 2 },
+            t: (3, Point { x: 4, y: 5 }),
+        }
+        .to_string(),
+        "-24-"
+    );
+}
+
+#[test]
+fn struct_for() {
+    #[derive(Template)]
+    #[template(
+        source = "{% for (i, Item { name, .. }) in items.iter().enumerate() if name.len() > 1 -%}
+            -{{i}}{{name}}-
+        {%- endfor %}",
+        ext = "txt"
+    )]
+    struct Tmpl {
+        items: Vec<Item>,
+        // Not used, the loop condition uses the bound `name`.
+        #[allow(dead_code)]
+        name: &'static str,
+    }
+
+    let items = vec![
+        Item {
+            name: "a",
+            price: 1,
+        },
+        Item {
+            name: "bc",
+            price: 2,
+        },
+    ];
+    assert_eq!(Tmpl { items, name: "xyz" }.to_string(), "-1bc-");
+}
+
+#[test]
+fn nested_struct_match() {
+    #[derive(Template)]
+    #[template(
+        source = "{% match t %}\
+            {% when (Item { price: 0, .. }, _) %}free\
+            {% when (Item { name, .. }, Point { x, .. }) %}{{name}}{{x}}\
+        {% endmatch %}",
+        ext = "txt"
+    )]
+    struct Tmpl {
+        t: (Item, Point),
+    }
+
+    let item = Item {
+        name: "a",
+        price: 0,
+    };
+    let t = (item, Point { x: 1, y: 2 });
+    assert_eq!(Tmpl { t }.to_string(), "free");
+    let item = Item {
+        name: "a",
+        price: 3,
+    };
+    let t = (item, Point { x: 1, y: 2 });
+    assert_eq!(Tmpl { t }.to_string(), "a1");
+}
@@ -0,0 +1,238 @@
+#![cfg(feature = "serde_json")]
+
+use std::path::PathBuf;
+
+use rinja::{Environment, Error};
+use serde::Serialize;
+use serde_json::json;
+
+#[derive(Serialize)]
+struct User<'a> {
+    name: &'a str,
+    active: bool,
+}
+
+#[derive(Serialize)]
+struct Page<'a> {
+    title: &'a str,
+    users: Vec<User<'a>>,
+    links: Vec<(&'a str, &'a str)>,
+}
+
+#[test]
+fn test_runtime_dir() {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("templates/runtime");
+    let env = Environment::from_dir(dir).unwrap();
+    assert!(env.contains_template("partials/nav.html"));
+
+    let mut page = Page {
+        title: "Team <A>",
+        users: vec![
+            User {
+                name: "ferris",
+                active: true,
+            },
+            User {
+                name: "corro",
+                active: false,
+            },
+            User {
+                name: "bob",
+                active: true,
+            },
+        ],
+        links: vec![("Home", "/"), ("About", "/about?a=1&b=2")],
+    };
+    assert_eq!(
+        env.render("page.html", &page).unwrap(),
+        "<title>Team &#60;A&#62; - Site</title>\
+         <nav><a href=\"/\">Home</a><a href=\"/about?a=1&#38;b=2\">About</a></nav>\n\
+         <main>1. Ferris,2. Bob</main>",
+    );
+
+    page.users.clear();
+    page.links.clear();
+    assert_eq!(
+        env.render("page.html", &page).unwrap(),
+        "<title>Team &#60;A&#62; - Site</title><nav></nav>\n<main>nobody</main>",
+    );
+}
+
+#[test]
+fn test_runtime_expressions() {
+    let mut env = Environment::new();
+    env.add_template(
+        "expr.txt",
+        r#"{{ 60 * 60 * 24 }} {{ 7 / 2 }} {{ 7.0 / 2 }} {{ 2 ** 10 }} {{ -n }}
+{{ "a" < "b" && !false }} {{ n in [1, 2, 3] }} {{ "ell" in "hello" }} {{ "x" not in map }}
+{{ map.key }} {{ map["key"]|upper }} {{ list[1] }} {{ list.len() }} {{ list|join(", ") }}
+{{ missing|default("none") }} {{ "yes" if list.is_empty() else "no" }}
+{%- let total = n * 10 %} {{ total }}
+{%- for i in 0..=n %}[{{ i }}]{% endfor %}
+{%- for (key, value) in map %} {{ key }}={{ value }}{% endfor %}
+{{ "<b>" }} {{ list|json }} {{ "\u{e9}t\u{e9}" }}"#,
+    )
+    .unwrap();
+    let ctx = json!({
+        "n": 2,
+        "list": ["a", "b", "c"],
+        "map": { "key": "value" },
+    });
+    assert_eq!(
+        env.render("expr.txt", &ctx).unwrap(),
+        "86400 3 3.5 1024 -2\n\
+         true true true true\n\
+         value VALUE b 3 a, b, c\n\
+         none no 20[0][1][2] key=value\n\
+         <b> [\"a\",\"b\",\"c\"] été",
+    );
+}
+
+#[test]
+fn test_runtime_whitespace_and_filters() {
+    let mut env = Environment::new();
+    env.add_template(
+        "ws.html",
+        "<ul>\n  {%- for item in items %}\n  <li>{{ item }}</li>\n  {%- endfor %}\n</ul>\n\
+         {% filter upper|trim %} x{{ tag }} {% endfilter %} {{ tag|safe }} {{ tag|e }}\n\
+         {%- raw %} {{ tag }}{% endraw %}{# comment #}",
+    )
+    .unwrap();
+    let ctx = json!({ "items": ["a", "b"], "tag": "<i>" });
+    assert_eq!(
+        env.render("ws.html", &ctx).unwrap(),
+        "<ul>\n  <li>a</li>\n  <li>b</li>\n</ul>\n\
+         X&#38;#60;I&#38;#62; <i> &#60;i&#62; {{ tag }}",
+    );
+}
+
+#[test]
+fn test_runtime_escaping() {
+    let mut env = Environment::new();
+    env.add_template(
+        "escape.html",
+        r#"{{ tag|e|upper }} {{ tag|escape("js") }} {{ tag|e("txt") }} {{ tag|safe|trim }}
+{{ tag|upper }} {{ tag|e|join(",") }} {{ [tag]|json }}"#,
+    )
+    .unwrap();
+    let ctx = json!({ "tag": "<i>'" });
+    assert_eq!(
+        env.render("escape.html", &ctx).unwrap(),
+        "&#60;I&#62;&#39; \\x3ci\\x3e\\x27 <i>' <i>'\n\
+         &#60;I&#62;&#39; &#38;#60;i&#38;#62;&#38;#39; [\"\\u003ci\\u003e\\u0027\"]",
+    );
+
+    env.add_template("unknown.html", r#"{{ tag|e("nope") }}"#)
+        .unwrap();
+    let err = env.render("unknown.html", &ctx).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        r#"invalid escaper "nope" for the filter `e`"#,
+    );
+}
+
+#[test]
+fn test_runtime_control_flow() {
+    let mut env = Environment::new();
+    env.add_template(
+        "loop.txt",
+        "{% for i in 1..10 %}{% continue if i % 2 == 0 %}{% if i > 6 %}{% break %}{% endif %}\
+         {{ i }}{% endfor %} {% if n == 1 %}one{% elif n == 2 %}two{% else %}many{% endif %}",
+    )
+    .unwrap();
+    assert_eq!(
+        env.render("loop.txt", &json!({ "n": 2 })).unwrap(),
+        "135 two"
+    );
+    assert_eq!(
+        env.render("loop.txt", &json!({ "n": 5 })).unwrap(),
+        "135 many"
+    );
+
+    env.add_template(
+        "include.txt",
+        r#"{% include "item.txt" %} {% include name %} {% include "item.txt" with x = 3 only %}"#,
+    )
+    .unwrap();
+    env.add_template("item.txt", "<{{ x }}>").unwrap();
+    assert_eq!(
+        env.render("include.txt", &json!({ "x": 1, "name": "item.txt" }))
+            .unwrap(),
+        "<1> <1> <3>",
+    );
+}
+
+#[test]
+fn test_runtime_errors() {
+    let mut env = Environment::new();
+    assert!(matches!(
+        env.add_template("bad.html", "{{ x"),
+        Err(Error::Custom(_))
+    ));
+
+    env.add_template("undefined.html", "{{ x }}").unwrap();
+    let err = env.render("undefined.html", &()).unwrap_err();
+    assert!(matches!(err, Error::Runtime(_)), "{err:?}");
+    assert_eq!(err.to_string(), "undefined variable `x`");
+
+    env.add_template("macro.html", "{% macro m() %}{% endmacro %}")
+        .unwrap();
+    let err = env.render("macro.html", &()).unwrap_err();
+    assert_eq!(err.to_string(), "`macro` is not supported by `Environment`");
+
+    env.add_template("filter.html", "{{ 1|reltime }}").unwrap();
+    let err = env.render("filter.html", &()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "the filter `reltime` is not supported by `Environment`",
+    );
+
+    env.add_template("overflow.html", "{{ 9223372036854775807 + 1 }}")
+        .unwrap();
+    assert!(env.render("overflow.html", &()).is_err());
+
+    // `default` only falls back on undefined variables and members.
+    env.add_template(
+        "default.html",
+        r#"{{ x|default("a") }} {{ x.y|default("b") }} {{ [1][2]|default("c") }}"#,
+    )
+    .unwrap();
+    assert_eq!(env.render("default.html", &()).unwrap(), "a b c");
+    env.add_template(
+        "default_overflow.html",
+        r#"{{ (9223372036854775807 + 1)|default("x") }}"#,
+    )
+    .unwrap();
+    assert!(env.render("default_overflow.html", &()).is_err());
+
+    env.add_template(
+        "range.html",
+        "{% for i in 0..9223372036854775807 %}{% endfor %}",
+    )
+    .unwrap();
+    let err = env.render("range.html", &()).unwrap_err();
+    assert_eq!(err.to_string(), "ranges cannot be longer than 100000 items");
+
+    env.add_template("include.html", r#"{% include "nope.html" %}"#)
+        .unwrap();
+    assert!(matches!(
+        env.render("include.html", &()),
+        Err(Error::UnknownInclude(name)) if name == "nope.html"
+    ));
+
+    env.add_template("self.html", r#"{% include "self.html" %}"#)
+        .unwrap();
+    assert!(matches!(
+        env.render("self.html", &()),
+        Err(Error::Runtime(_))
+    ));
+
+    assert!(matches!(
+        env.render("nope.html", &()),
+        Err(Error::Runtime(_))
+    ));
+    assert!(matches!(
+        env.render("undefined.html", &[1, 2]),
+        Err(Error::Runtime(_))
+    ));
+}
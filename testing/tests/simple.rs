@@ -407,6 +407,35 @@ fn test_index() {
     assert_eq!(t.render().unwrap(), "baz");
 }
 
+#[derive(Template)]
+#[template(
+    source = r#"
+{{- row[2] }} {{ row[idx] + 1 }} {{ grid[1][0] }} {{ names[0]|upper }}
+{%- if row[0] == 1 && names[0] != "b" %} cmp{% endif %}
+{%- let name = names[idx] %} {{ name }} {{ map["key"].len() }}
+{%- for cell in grid[1] %} {{ cell }}{% endfor %}"#,
+    ext = "txt"
+)]
+struct IndexBorrowTemplate {
+    row: Vec<u32>,
+    grid: Vec<Vec<u8>>,
+    names: Vec<String>,
+    map: HashMap<String, String>,
+    idx: usize,
+}
+
+#[test]
+fn test_index_borrow() {
+    let t = IndexBorrowTemplate {
+        row: vec![1, 2, 3],
+        grid: vec![vec![], vec![7, 8]],
+        names: vec!["a".into(), "bc".into()],
+        map: HashMap::from([("key".into(), "value".into())]),
+        idx: 1,
+    };
+    assert_eq!(t.render().unwrap(), "3 3 7 A cmp bc 5 7 8");
+}
+
 #[derive(Template)]
 #[template(source = "foo", ext = "txt")]
 struct Empty;
@@ -0,0 +1,105 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"<div id="app">{{ message }}</div>
+<p>[[ name ]]</p>{# kept #}"#,
+    ext = "html",
+    syntax_inline(
+        expr_start = "[[",
+        expr_end = "]]",
+        comment_start = "[#",
+        comment_end = "#]"
+    )
+)]
+struct Vue<'a> {
+    name: &'a str,
+}
+
+#[derive(Template)]
+#[template(
+    source = "<% for i in 0..3 %>{{ i }}<% endfor %>",
+    ext = "txt",
+    syntax_inline(block_start = "<%", block_end = "%>")
+)]
+struct BlockDelimiters;
+
+#[derive(Template)]
+#[template(
+    source = "services:
+# for (name, image) in services
+  {{ name }}:
+    image: {{ image }}
+    # if name == &\"db\"
+    restart: always
+    # endif
+# endfor
+",
+    ext = "yml",
+    syntax_inline(line_statement_prefix = "#")
+)]
+struct Compose<'a> {
+    services: &'a [(&'a str, &'a str)],
+}
+
+#[test]
+fn test_syntax_inline() {
+    assert_eq!(
+        Vue { name: "<Ferris>" }.render().unwrap(),
+        "<div id=\"app\">{{ message }}</div>\n<p>&#60;Ferris&#62;</p>{# kept #}"
+    );
+    assert_eq!(BlockDelimiters.render().unwrap(), "012");
+}
+
+#[test]
+fn test_line_statements() {
+    let compose = Compose {
+        services: &[("web", "nginx"), ("db", "postgres")],
+    };
+    assert_eq!(
+        compose.render().unwrap(),
+        "services:
+  web:
+    image: nginx
+  db:
+    image: postgres
+    restart: always
+",
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% for x in xs %}{% if *x == 1 %}one{% elif *x == 2 %}two\
+        {% else %}many{% fi %} {% done %}",
+    ext = "txt",
+    config = "test_keyword_aliases.toml",
+    syntax = "shell"
+)]
+struct KeywordAliases<'a> {
+    xs: &'a [u32],
+}
+
+#[test]
+fn test_keyword_aliases() {
+    assert_eq!(
+        KeywordAliases { xs: &[1, 2, 3] }.render().unwrap(),
+        "one two many "
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% var x %}{% if true %}{% var x = 1 %}\
+        {% elseif false %}{% var x = 2 %}{% else %}{% var x = 3 %}{% endif %}\
+        {% var y %}{{ x }}{% endvar %}{{ y }}",
+    ext = "txt",
+    config = "test_keyword_aliases.toml",
+    syntax = "twig"
+)]
+struct KeywordAliasesLet;
+
+#[test]
+fn test_keyword_aliases_let() {
+    assert_eq!(KeywordAliasesLet.render().unwrap(), "1");
+}
@@ -0,0 +1,102 @@
+use rinja::Template;
+
+// The handlers of the tags configured in `test_tags.toml` get the arguments as a string literal.
+#[macro_export]
+macro_rules! icon {
+    ($args:literal) => {
+        ::rinja::filters::Safe(::std::format!("<i class=\"icon-{}\"></i>", $args))
+    };
+}
+
+#[macro_export]
+macro_rules! shout {
+    ($args:literal) => {
+        $args.to_uppercase()
+    };
+}
+
+#[derive(Template)]
+#[template(
+    source = "<p>{% icon home %} {%- shout <b>hi</b> %}</p>{% if true %}{% icon star %}{% endif %}",
+    ext = "html",
+    config = "test_tags.toml"
+)]
+struct Tags;
+
+#[test]
+fn test_tags() {
+    assert_eq!(
+        Tags.render().unwrap(),
+        "<p><i class=\"icon-home\"></i>&#60;B&#62;HI&#60;/B&#62;</p><i class=\"icon-star\"></i>",
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "a\n  # icon home\nb",
+    ext = "txt",
+    config = "test_tags.toml",
+    syntax_inline(line_statement_prefix = "#")
+)]
+struct LineStatement;
+
+#[test]
+fn test_tags_line_statement() {
+    assert_eq!(
+        LineStatement.render().unwrap(),
+        "a\n<i class=\"icon-home\"></i>b"
+    );
+}
+
+// The handler of a block tag gets the rendered body as a second argument, which implements
+// `Display`.
+#[macro_export]
+macro_rules! card {
+    ($args:literal, $body:expr) => {
+        ::rinja::filters::Safe(::std::format!("<div class=\"{}\">{}</div>", $args, $body))
+    };
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% card note %}<b>{{ text }}</b>{% icon x %}{% endcard %}\n{%- card -%} a {%- endcard %}",
+    ext = "html",
+    config = "test_tags.toml"
+)]
+struct BlockTags<'a> {
+    text: &'a str,
+}
+
+#[test]
+fn test_block_tags() {
+    assert_eq!(
+        BlockTags { text: "1 < 2" }.render().unwrap(),
+        "<div class=\"note\"><b>1 &#60; 2</b><i class=\"icon-x\"></i></div><div class=\"\">a</div>",
+    );
+}
+
+// The handler of a filter gets the expressions of the arguments.
+#[macro_export]
+macro_rules! twice {
+    ($value:expr) => {
+        ::std::format!("{0}{0}", $value)
+    };
+    ($value:expr, $sep:expr) => {
+        ::std::format!("{0}{1}{0}", $value, $sep)
+    };
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ text|twice }} {{ text|twice(\" & \")|upper }}",
+    ext = "html",
+    config = "test_tags.toml"
+)]
+struct FilterMacros<'a> {
+    text: &'a str,
+}
+
+#[test]
+fn test_filter_macros() {
+    assert_eq!(FilterMacros { text: "a" }.render().unwrap(), "aa A &#38; A",);
+}
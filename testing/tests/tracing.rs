@@ -0,0 +1,66 @@
+use std::sync::{Arc, Mutex};
+
+use rinja::Template;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{subscriber, Event, Metadata, Subscriber};
+
+// Records the names of all spans that are entered.
+#[derive(Default, Clone)]
+struct Spans(Arc<Mutex<Vec<&'static str>>>);
+
+impl Subscriber for Spans {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut spans = self.0.lock().unwrap();
+        spans.push(span.metadata().name());
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+fn render_spans(template: &impl Template) -> Vec<&'static str> {
+    let spans = Spans::default();
+    subscriber::with_default(spans.clone(), || template.render().unwrap());
+    let spans = spans.0.lock().unwrap();
+    spans.clone()
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% extends "base.html" %}{% block content %}{% include "included.html" %}{% endblock %}"#,
+    ext = "html"
+)]
+struct Page<'a> {
+    title: &'a str,
+    s: &'a str,
+}
+
+#[test]
+fn test_tracing_spans() {
+    let page = Page {
+        title: "title",
+        s: "s",
+    };
+    assert_eq!(
+        render_spans(&page),
+        [
+            "Page.html",
+            "templates/base.html",
+            "Page.html#block content",
+            "templates/included.html",
+            "templates/base.html#block foo",
+        ],
+    );
+}
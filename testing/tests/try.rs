@@ -1,3 +1,5 @@
+use std::fmt;
+
 use rinja::Template;
 
 #[derive(Template)]
@@ -25,6 +27,30 @@ fn test_int_parser() {
     assert_eq!(template.render().unwrap(), "100=100");
 }
 
+#[derive(Template)]
+#[template(
+    source = "{% let v = s.parse::<i32>()? %}{{ v + 1 }} {{ s.parse::<u8>()? }}",
+    ext = "txt"
+)]
+struct TurbofishTemplate<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_turbofish() {
+    let template = TurbofishTemplate { s: "41" };
+    assert_eq!(template.render().unwrap(), "42 41");
+
+    let template = TurbofishTemplate { s: "-1" };
+    assert_eq!(
+        template.render().unwrap_err().to_string(),
+        "invalid digit found in string"
+    );
+
+    let template = TurbofishTemplate { s: "x" };
+    assert!(matches!(template.render(), Err(rinja::Error::Custom(_))));
+}
+
 #[derive(Template)]
 #[template(source = "{{ value()? }}", ext = "txt")]
 struct FailFmt {
@@ -81,3 +107,116 @@ fn fail_str() {
     let template = FailStr { value: true };
     assert_eq!(template.render().unwrap(), "hello world");
 }
+
+#[derive(Debug, PartialEq)]
+enum PageError {
+    NotFound(u32),
+    Render(String),
+}
+
+impl fmt::Display for PageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageError::NotFound(id) => write!(f, "page {id} not found"),
+            PageError::Render(err) => write!(f, "cannot render page: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for PageError {}
+
+impl From<rinja::Error> for PageError {
+    fn from(err: rinja::Error) -> Self {
+        PageError::Render(err.to_string())
+    }
+}
+
+impl From<std::num::ParseIntError> for PageError {
+    fn from(err: std::num::ParseIntError) -> Self {
+        PageError::Render(err.to_string())
+    }
+}
+
+mod filters {
+    pub fn checked(id: &u32) -> Result<u32, super::PageError> {
+        match *id {
+            0 => Err(super::PageError::NotFound(0)),
+            id => Ok(id),
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ self.load()? }} {{ id|checked }} {{ self.number()? }}",
+    ext = "txt",
+    error = "PageError"
+)]
+struct CustomError<'a> {
+    id: u32,
+    number: &'a str,
+}
+
+impl CustomError<'_> {
+    fn load(&self) -> Result<&'static str, PageError> {
+        match self.id {
+            404 => Err(PageError::NotFound(404)),
+            _ => Ok("page"),
+        }
+    }
+
+    fn number(&self) -> Result<u8, std::num::ParseIntError> {
+        self.number.parse()
+    }
+}
+
+#[test]
+fn test_custom_error() {
+    let template = CustomError { id: 1, number: "2" };
+    assert_eq!(template.try_render().unwrap(), "page 1 2");
+    let mut buf = String::new();
+    template.try_render_into(&mut buf).unwrap();
+    assert_eq!(buf, "page 1 2");
+
+    let template = CustomError {
+        id: 404,
+        number: "2",
+    };
+    assert_eq!(template.try_render(), Err(PageError::NotFound(404)));
+    // `Template::render()` still returns a `rinja::Error`.
+    assert!(matches!(template.render(), Err(rinja::Error::Custom(_))));
+
+    let template = CustomError { id: 0, number: "2" };
+    assert_eq!(template.try_render(), Err(PageError::NotFound(0)));
+
+    let template = CustomError { id: 1, number: "x" };
+    assert_eq!(
+        template.try_render(),
+        Err(PageError::Render("invalid digit found in string".into()))
+    );
+}
+
+#[derive(Template)]
+#[template(source = "{{ value }}", ext = "txt", error = "PageError")]
+struct CustomErrorFmt {
+    value: FailingDisplay,
+}
+
+struct FailingDisplay;
+
+impl fmt::Display for FailingDisplay {
+    fn fmt(&self, _: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Err(fmt::Error)
+    }
+}
+
+#[test]
+fn test_custom_error_from_rinja_error() {
+    let template = CustomErrorFmt {
+        value: FailingDisplay,
+    };
+    assert_eq!(
+        template.try_render(),
+        Err(PageError::Render("formatting error".into()))
+    );
+}
@@ -0,0 +1,33 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(source = r#"{{ user|attr("name; panic!()") }}"#, ext = "txt")]
+struct CodeInKey {
+    user: (String,),
+}
+
+#[derive(Template)]
+#[template(source = r#"{{ users|map("0..len") }}"#, ext = "txt")]
+struct EmptySegment {
+    users: Vec<(String,)>,
+}
+
+#[derive(Template)]
+#[template(source = r#"{{ users|sort_by("01")|length }}"#, ext = "txt")]
+struct LeadingZero {
+    users: Vec<(String,)>,
+}
+
+#[derive(Template)]
+#[template(source = r#"{{ users|map(|a, b| a)|length }}"#, ext = "txt")]
+struct ClosureArgs {
+    users: Vec<(String,)>,
+}
+
+#[derive(Template)]
+#[template(source = r#"{{ user|attr(|u| u.0) }}"#, ext = "txt")]
+struct AttrClosure {
+    user: (String,),
+}
+
+fn main() {}
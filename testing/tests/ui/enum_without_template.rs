@@ -0,0 +1,11 @@
+use rinja::Template;
+
+#[derive(Template)]
+enum Page {
+    #[template(source = "loading", ext = "txt")]
+    Loading,
+    Ready,
+}
+
+fn main() {
+}
@@ -0,0 +1,10 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(source = r#"{% extends layout %}"#, ext = "txt")]
+struct ExtendsDynamic<'a> {
+    layout: &'a str,
+}
+
+fn main() {
+}
@@ -0,0 +1,11 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"{% from "macro.html" import thrice, once %}{% call once("a") %}"#,
+    ext = "txt"
+)]
+struct FromImportUnknownMacro;
+
+fn main() {
+}
@@ -0,0 +1,10 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(source = r#"{% include partial %}"#, ext = "txt")]
+struct IncludeDynamic<'a> {
+    partial: &'a str,
+}
+
+fn main() {
+}
@@ -0,0 +1,14 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"{% include "include-card.html" with item = product only %}"#,
+    ext = "txt"
+)]
+struct IncludeOnly<'a> {
+    title: &'a str,
+    product: &'a str,
+}
+
+fn main() {
+}
@@ -0,0 +1,12 @@
+use rinja::Template;
+
+#[derive(Template)]
+#[template(source = "{% block a %}{% endblock %}", ext = "txt", blocks = "a")]
+struct NotAnArray;
+
+#[derive(Template)]
+#[template(source = "{% block a %}{% endblock %}", ext = "txt", blocks = ["a b"])]
+struct InvalidName;
+
+fn main() {
+}
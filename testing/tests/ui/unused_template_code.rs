@@ -0,0 +1,31 @@
+#![deny(deprecated)]
+
+use rinja::Template;
+
+#[derive(Template)]
+#[template(
+    source = r#"{% import "macro.html" as scope %}
+{% from "macro.html" import thrice %}
+{% macro greet(name) %}Hello, {{ name }}!{% endmacro %}
+{% let unused = 1 %}
+{% let (used, unused2) = (2, 3) %}
+{{ used }}"#,
+    ext = "txt",
+    warn_unused = true
+)]
+struct UnusedCode;
+
+// Unused code is allowed like other lints.
+#[allow(deprecated)]
+#[derive(Template)]
+#[template(source = r#"{% let unused = 1 %}"#, ext = "txt", warn_unused = true)]
+struct AllowedUnusedCode;
+
+// Unused code is only reported if `warn_unused` is enabled.
+#[derive(Template)]
+#[template(source = r#"{% let unused = 1 %}"#, ext = "txt")]
+struct UncheckedUnusedCode;
+
+fn main() {
+    let _ = (UnusedCode, AllowedUnusedCode, UncheckedUnusedCode);
+}
@@ -0,0 +1,78 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use rinja::{DynTemplate, Template};
+
+#[derive(Template)]
+#[template(
+    source = r#"
+{%- macro locale() -%}
+    {{ rinja::get_value::<String>("locale")? }}
+{%- endmacro -%}
+{{ greeting }} ({% call locale() %})
+{%- if let Ok(user) = rinja::get_value::<&str>("user") %} {{ user }}{% endif %}"#,
+    ext = "html"
+)]
+struct Page<'a> {
+    greeting: &'a str,
+}
+
+#[test]
+fn test_values() {
+    let mut values: HashMap<&str, Box<dyn Any>> = HashMap::new();
+    values.insert("locale", Box::new("en-US".to_owned()));
+    let t = Page { greeting: "Hello" };
+    assert_eq!(t.render_with_values(&values).unwrap(), "Hello (en-US)");
+
+    values.insert("user", Box::new("<Ferris>"));
+    assert_eq!(
+        t.render_with_values(&values).unwrap(),
+        "Hello (en-US) &#60;Ferris&#62;"
+    );
+    let t: &dyn DynTemplate = &t;
+    assert_eq!(
+        t.dyn_render_with_values(&values).unwrap(),
+        "Hello (en-US) &#60;Ferris&#62;"
+    );
+}
+
+#[test]
+fn test_values_errors() {
+    let t = Page { greeting: "Hello" };
+    // Errors of macros are wrapped into `Error::Custom`.
+    assert_eq!(
+        t.render().unwrap_err().to_string(),
+        r#"no value for key "locale""#,
+    );
+    assert!(matches!(
+        Choice::A { key: "m" }.render(),
+        Err(rinja::Error::ValueMissing(key)) if key == "m",
+    ));
+    assert!(matches!(
+        Choice::A { key: "n" }.render_with_values(&[("n", "1")]),
+        Err(rinja::Error::ValueType(key)) if key == "n",
+    ));
+}
+
+#[derive(Template)]
+#[template(
+    source = r#"{% if let Ok(n) = rinja::get_value::<u32>("n") %}{{ n }}{% endif %}"#,
+    ext = "txt"
+)]
+enum Choice {
+    #[template(source = "a{{ rinja::get_value::<u32>(key)? }}", ext = "txt")]
+    A {
+        key: &'static str,
+    },
+    B,
+}
+
+#[test]
+fn test_values_enum() {
+    let values = [("n", 1u32), ("m", 2)];
+    assert_eq!(
+        Choice::A { key: "m" }.render_with_values(&values).unwrap(),
+        "a2"
+    );
+    assert_eq!(Choice::B.render_with_values(&values).unwrap(), "1");
+}
@@ -151,3 +151,169 @@ fn test_not_moving_fields_in_var() {
     };
     assert_eq!(x.render().unwrap(), "a/a");
 }
+
+#[derive(Template)]
+#[template(
+    source = "
+{%- set body -%}
+    <b>{{ name }}</b>
+{%- endset -%}
+[{{ body }}] [{{ body|upper|safe }}]",
+    ext = "html"
+)]
+struct SetBlock<'a> {
+    name: &'a str,
+}
+
+#[test]
+fn test_set_block() {
+    let t = SetBlock { name: "a&b" };
+    assert_eq!(t.render().unwrap(), "[<b>a&#38;b</b>] [<B>A&#38;B</B>]");
+}
+
+#[derive(Template)]
+#[template(
+    source = "
+{%- macro wrap(content) -%}
+    <p>{{ content }}</p>
+{%- endmacro -%}
+{%- let items -%}
+{%- if list.is_empty() -%}
+    {%- let items %}none{% endlet -%}
+{%- else -%}
+    {%- let items -%}
+        {% for i in list %}{{ i }};{% endfor %}
+    {%- endlet -%}
+{%- endif -%}
+{%- call wrap(items) -%}",
+    ext = "html"
+)]
+struct LetBlockDecl<'a> {
+    list: &'a [u32],
+}
+
+#[test]
+fn test_let_block_decl() {
+    let t = LetBlockDecl { list: &[] };
+    assert_eq!(t.render().unwrap(), "<p>none</p>");
+    let t = LetBlockDecl { list: &[1, 2] };
+    assert_eq!(t.render().unwrap(), "<p>1;2;</p>");
+}
+
+#[derive(Template)]
+#[template(
+    source = "
+{%- with a = s, b = a.len(), s = 1 -%}
+    {{ a }}:{{ b }}:{{ s }}
+{%- endwith -%}
+/{{ s }}",
+    ext = "txt"
+)]
+struct WithTemplate<'a> {
+    s: &'a str,
+}
+
+#[test]
+fn test_with() {
+    let t = WithTemplate { s: "foo" };
+    assert_eq!(t.render().unwrap(), "foo:3:1/foo");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% let a = 1 %}{% with a = a + 1, (b, c) = t %}{{ a }}{{ b }}{{ c }}{% endwith %}{{ a }}",
+    ext = "txt"
+)]
+struct WithShadowTemplate {
+    t: (u32, u32),
+}
+
+#[test]
+fn test_with_shadow() {
+    let t = WithShadowTemplate { t: (3, 4) };
+    assert_eq!(t.render().unwrap(), "2341");
+}
+
+#[derive(Template)]
+#[template(
+    source = "<
+{%- for user in users -%}
+    {%- let Some(name) = user else -%}
+        guest
+    {%- endlet -%}
+    {{ name }}
+{%- endfor -%}
+>",
+    ext = "txt"
+)]
+struct LetElseTemplate<'a> {
+    users: &'a [Option<&'a str>],
+}
+
+#[test]
+fn test_let_else() {
+    let t = LetElseTemplate {
+        users: &[Some("alice"), None, Some("bob")],
+    };
+    assert_eq!(t.render().unwrap(), "<aliceguestbob>");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% let (Some(a), b) = t else %}no a{% endlet %}{{ a }}{{ b }}",
+    ext = "txt"
+)]
+struct LetElseTopLevelTemplate {
+    t: (Option<u32>, u32),
+}
+
+#[test]
+fn test_let_else_top_level() {
+    let t = LetElseTopLevelTemplate { t: (Some(1), 2) };
+    assert_eq!(t.render().unwrap(), "12");
+    let t = LetElseTopLevelTemplate { t: (None, 2) };
+    assert_eq!(t.render().unwrap(), "no a");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{{ counter.get() }}
+{%- do counter.set(counter.get() + 1) -%}
+{{ counter.get() }}
+{%- let items = std::cell::RefCell::new(Vec::new()) -%}
+{%- for name in [\"a\", \"b\"] -%}
+    {%- do items.borrow_mut().push(name.to_uppercase()) -%}
+{%- endfor %} {{ items.borrow().join(\",\") }}",
+    ext = "txt"
+)]
+struct DoTemplate {
+    counter: std::cell::Cell<u32>,
+}
+
+#[test]
+fn test_do() {
+    let t = DoTemplate {
+        counter: std::cell::Cell::new(1),
+    };
+    assert_eq!(t.render().unwrap(), "12 A,B");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% let 挨拶 = \"こんにちは\" %}{{ 挨拶 }}、{{ 名前 }}！\
+        {% for größe in größen %} {{ größe }}{% endfor %}",
+    ext = "txt"
+)]
+struct NonAsciiTemplate<'a> {
+    名前: &'a str,
+    größen: &'a [u32],
+}
+
+#[test]
+fn test_non_ascii_identifiers() {
+    let t = NonAsciiTemplate {
+        名前: "フェリス",
+        größen: &[1, 2],
+    };
+    assert_eq!(t.render().unwrap(), "こんにちは、フェリス！ 1 2");
+}
@@ -143,3 +143,98 @@ fn test_template_whitespace_config() {
     );
     test_template_ws_config!("test_minimize.toml", "suppress", "\n1{# #}\n\n\n2", "\n12");
 }
+
+#[derive(Template)]
+#[template(
+    source = "<ul>\n  {% for i in list %}\n    <li>{{ i }}</li>\n  {% endfor %}\n</ul>\n",
+    ext = "html",
+    trim_blocks = true,
+    lstrip_blocks = true
+)]
+struct TrimLstripBlocks<'a> {
+    list: &'a [u32],
+}
+
+#[test]
+fn test_trim_lstrip_blocks() {
+    let t = TrimLstripBlocks { list: &[1, 2] };
+    assert_eq!(
+        t.render().unwrap(),
+        "<ul>\n    <li>1</li>\n    <li>2</li>\n</ul>\n"
+    );
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% if true %}\n  {{ a }}\n{% endif %}\n{# comment #}\n  {%+ if true +%}\nb{%- endif %}",
+    ext = "txt",
+    trim_blocks = true
+)]
+struct TrimBlocks {
+    a: u32,
+}
+
+#[test]
+fn test_trim_blocks() {
+    // Only the newline directly after a block tag or comment is removed, but not after an
+    // expression, or if the tag uses a whitespace control character.
+    assert_eq!(TrimBlocks { a: 1 }.render().unwrap(), "  1\n  \nb");
+}
+
+#[derive(Template)]
+#[template(
+    source = "  {% if true %}x{% endif %}\n\t{# c #}y {% if true %}z{% endif %}",
+    ext = "txt",
+    lstrip_blocks = true
+)]
+struct LstripBlocks;
+
+#[test]
+fn test_lstrip_blocks() {
+    // Only the indentation in front of a tag is removed.
+    assert_eq!(LstripBlocks.render().unwrap(), "x\ny z");
+}
+
+#[derive(Template)]
+#[template(
+    source = "{% if true %}\n  {%- if true %}a{% endif %}\n{% endif %}\n",
+    ext = "txt",
+    config = "test_trim_blocks.toml"
+)]
+struct TrimBlocksConfig;
+
+#[test]
+fn test_trim_blocks_config() {
+    assert_eq!(TrimBlocksConfig.render().unwrap(), "a");
+}
+
+#[derive(Template)]
+#[template(
+    source = "<p>\n  {% if true %}\n    a\n  {% endif %}\n</p>\n\
+              {% whitespace suppress %}\n  {% if true %}\n    b\n  {% endif %}\n{% endwhitespace %}\n",
+    ext = "txt"
+)]
+struct WhitespaceBlock;
+
+#[derive(Template)]
+#[template(
+    source = "{% macro m() %} m {% endmacro %}\
+              [ {% call m() %} ]{% whitespace preserve %} [ {% call m() %} ] {% endwhitespace %}\
+              [ {%- if true +%} x {%- endif %} ]",
+    ext = "txt",
+    whitespace = "suppress"
+)]
+struct WhitespaceBlockPreserve;
+
+#[test]
+fn test_whitespace_block() {
+    assert_eq!(
+        WhitespaceBlock.render().unwrap(),
+        "<p>\n  \n    a\n  \n</p>\nb\n"
+    );
+    // Macros that are called in the block use its handling, too.
+    assert_eq!(
+        WhitespaceBlockPreserve.render().unwrap(),
+        "[m] [  m  ] [ x]"
+    );
+}
@@ -0,0 +1,59 @@
+use std::io;
+
+use rinja::Template;
+
+#[derive(Template)]
+#[template(source = "Hello, {{ name }}! ünïcödé", ext = "html")]
+struct HelloTemplate<'a> {
+    name: &'a str,
+}
+
+#[test]
+fn test_write_into() {
+    let t = HelloTemplate { name: "<world>" };
+    let mut buf = Vec::new();
+    t.write_into(&mut buf).unwrap();
+    assert_eq!(buf, "Hello, &#60;world&#62;! ünïcödé".as_bytes());
+}
+
+#[test]
+fn test_write_into_by_ref() {
+    let t = &HelloTemplate { name: "rinja" };
+    let mut buf = Vec::new();
+    t.write_into(&mut buf).unwrap();
+    assert_eq!(buf, "Hello, rinja! ünïcödé".as_bytes());
+}
+
+struct BrokenPipe;
+
+impl io::Write for BrokenPipe {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_write_into_io_error() {
+    let t = HelloTemplate { name: "rinja" };
+    let err = t.write_into(&mut BrokenPipe).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+}
+
+#[derive(Template)]
+#[template(source = "{{ value? }}", ext = "txt")]
+struct FailingTemplate {
+    value: Result<u32, std::fmt::Error>,
+}
+
+#[test]
+fn test_write_into_render_error() {
+    let t = FailingTemplate {
+        value: Err(std::fmt::Error),
+    };
+    let err = t.write_into(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Other);
+}